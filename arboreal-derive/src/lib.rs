@@ -0,0 +1,117 @@
+//! `#[derive(Nodal)]` and `#[derive(DirEdge)]` for arboreal, re-exported by it behind the
+//! `derive` feature; see the docs on each macro.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident};
+
+/// Implements `Nodal` for a struct with named fields, one of them marked `#[arboreal(id)]`:
+///
+/// ```ignore
+/// #[derive(Clone, PartialEq, Default, Serialize, Deserialize, Nodal)]
+/// struct Passage {
+///     #[arboreal(id)]
+///     id: Id,
+///     text: String,
+/// }
+/// ```
+///
+/// `bare(id)` fills every other field from `Default`.
+#[proc_macro_derive(Nodal, attributes(arboreal))]
+pub fn derive_nodal(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_nodal(&input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+/// Implements `DirEdge` for a struct with named fields, two of them marked `#[arboreal(start)]`
+/// and `#[arboreal(end)]`:
+///
+/// ```ignore
+/// #[derive(Clone, PartialEq, Default, Serialize, Deserialize, DirEdge)]
+/// struct Choice {
+///     #[arboreal(start)]
+///     from: Id,
+///     #[arboreal(end)]
+///     to: Id,
+///     label: String,
+/// }
+/// ```
+///
+/// `bare(start, end)` fills every other field from `Default`.
+#[proc_macro_derive(DirEdge, attributes(arboreal))]
+pub fn derive_dir_edge(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_dir_edge(&input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+fn expand_nodal(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let id = marked_field(input, "id")?;
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::arboreal::graph_base::graph_components::Nodal for #name #type_generics #where_clause {
+            fn bare(id: ::arboreal::graph_base::graph_components::Id) -> Self {
+                Self { #id: id, ..::core::default::Default::default() }
+            }
+            fn node_id(&self) -> ::arboreal::graph_base::graph_components::Id {
+                self.#id
+            }
+            fn change_id(&mut self, new_id: ::arboreal::graph_base::graph_components::Id) {
+                self.#id = new_id;
+            }
+        }
+    })
+}
+
+fn expand_dir_edge(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let start = marked_field(input, "start")?;
+    let end = marked_field(input, "end")?;
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::arboreal::graph_base::graph_components::DirEdge for #name #type_generics #where_clause {
+            fn bare(start: ::arboreal::graph_base::graph_components::Id, end: ::arboreal::graph_base::graph_components::Id) -> Self {
+                Self { #start: start, #end: end, ..::core::default::Default::default() }
+            }
+            fn terminal_ids(&self) -> (::arboreal::graph_base::graph_components::Id, ::arboreal::graph_base::graph_components::Id) {
+                (self.#start, self.#end)
+            }
+            fn change_start(&mut self, new_start: ::arboreal::graph_base::graph_components::Id) {
+                self.#start = new_start;
+            }
+            fn change_end(&mut self, new_end: ::arboreal::graph_base::graph_components::Id) {
+                self.#end = new_end;
+            }
+        }
+    })
+}
+
+/// The one named field marked `#[arboreal(<role>)]`
+fn marked_field<'a>(input: &'a DeriveInput, role: &str) -> syn::Result<&'a Ident> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(&input.ident, "only structs can be derived"));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(&input.ident, "only structs with named fields can be derived"));
+    };
+    let mut found = None;
+    for field in fields.named.iter() {
+        for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("arboreal")) {
+            let mut marked = false;
+            attr.parse_nested_meta(|meta| {
+                match ["id", "start", "end"].iter().any(|known| meta.path.is_ident(known)) {
+                    true => marked |= meta.path.is_ident(role),
+                    false => return Err(meta.error("expected `id`, `start` or `end`")),
+                }
+                Ok(())
+            })?;
+            if marked && found.replace(field).is_some() {
+                return Err(syn::Error::new_spanned(attr, format!("more than one field marked `#[arboreal({role})]`")));
+            }
+        }
+    }
+    found
+        .and_then(|field| field.ident.as_ref())
+        .ok_or_else(|| syn::Error::new_spanned(&input.ident, format!("no field marked `#[arboreal({role})]`")))
+}
@@ -0,0 +1,85 @@
+//! Edge lookups and removals, which lean on the per-node incidence index.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+use arboreal::digraph::DiGraph;
+use arboreal::graph_base::graph_components::{BareEdge, BareNode, Id};
+
+type Graph = DiGraph<BareNode, BareEdge>;
+
+const NODES: Id = 50_000;
+const EDGES_PER_NODE: Id = 4;
+
+/// Same pseudo-random edges on every run
+fn edge_pairs() -> Vec<(Id, Id)> {
+    let mut state: u32 = 0x9e37_79b9;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        (state % NODES as u32) as Id
+    };
+    let mut pairs: Vec<(Id, Id)> = (0..NODES)
+        .flat_map(|start| (0..EDGES_PER_NODE).map(move |_| start))
+        .map(|start| (start, next()))
+        .collect();
+    pairs.sort_unstable();
+    pairs.dedup();
+    pairs
+}
+
+fn construction(c: &mut Criterion) {
+    let pairs = edge_pairs();
+    let mut group = c.benchmark_group("construction");
+    group.sample_size(10);
+    group.bench_function("from_terminal_pairs", |b| {
+        b.iter(|| Graph::from_terminal_pairs(black_box(pairs.clone())))
+    });
+    group.finish();
+}
+
+fn lookup(c: &mut Criterion) {
+    let pairs = edge_pairs();
+    let graph = Graph::from_terminal_pairs(pairs.clone());
+    c.bench_function("get_edge (every edge)", |b| {
+        b.iter(|| {
+            for &(start_id, end_id) in pairs.iter() {
+                black_box(graph.get_edge(start_id, end_id));
+            }
+        })
+    });
+}
+
+fn removal(c: &mut Criterion) {
+    let pairs = edge_pairs();
+    let mut group = c.benchmark_group("removal");
+    group.sample_size(10);
+    group.bench_function("remove_edge (every 10th)", |b| {
+        b.iter_batched_ref(
+            || Graph::from_terminal_pairs(pairs.clone()),
+            |graph| {
+                for &(start_id, end_id) in pairs.iter().step_by(10) {
+                    graph.remove_edge(start_id, end_id).unwrap();
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("remove_node (every 10th)", |b| {
+        b.iter_batched_ref(
+            || Graph::from_terminal_pairs(pairs.clone()),
+            |graph| {
+                for node_id in (0..NODES).step_by(10) {
+                    black_box(graph.remove_node(node_id).unwrap());
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, construction, lookup, removal);
+criterion_main!(benches);
@@ -0,0 +1,110 @@
+//! Behavior-tree execution over a `DiGraph`.
+//!
+//! Nodes say what they do through `Behavior::role()`; edges run from a composite node to its
+//! children, which are ticked in `DiGraph::ordered_successors()` order. Composites are reactive: every tick starts
+//! again from the first child, so a `Running` leaf is re-ticked only if everything before it
+//! still succeeds (Sequence) or still fails (Selector).
+
+use crate::digraph::DiGraph;
+use crate::graph_base::graph_components::{DirEdge, GraphError, Id, IdSet, Nodal};
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Status {
+    Success,
+    Failure,
+    Running,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Role {
+    /// Ticks children in order until one does not succeed
+    Sequence,
+    /// Ticks children in order until one does not fail
+    Selector,
+    /// Does the actual work in `Behavior::tick_leaf()`; any children are ignored
+    Leaf,
+}
+
+pub trait Behavior: Nodal {
+    /// State shared by every node during a tick (blackboard, world handle, ...)
+    type Context;
+
+    fn role(&self) -> Role;
+
+    /// Called when a `Leaf` node is ticked
+    fn tick_leaf(&mut self, ctx: &mut Self::Context) -> Status;
+}
+
+pub struct BehaviorTree<N, E> {
+    graph: DiGraph<N, E>,
+    root: Id,
+}
+
+impl<N: Behavior, E: DirEdge> BehaviorTree<N, E> {
+
+    /// Wraps `graph`, to be ticked from `root`.
+    ///
+    /// If `root` is not found among the nodes, or a cycle is reachable from it, an error is returned.
+    pub fn new(graph: DiGraph<N, E>, root: Id) -> Result<Self, GraphError> {
+        if graph.get_node(root).is_none() {
+            return Err("Root not found in graph.".into());
+        }
+        let tree = Self { graph, root };
+        if tree.has_cycle_from(root, &mut IdSet::new(), &mut IdSet::new()) {
+            return Err("Behavior graph contains a cycle.".into());
+        }
+        Ok(tree)
+    }
+
+    pub fn root(&self) -> Id {
+        self.root
+    }
+    pub fn graph(&self) -> &DiGraph<N, E> {
+        &self.graph
+    }
+    pub fn into_graph(self) -> DiGraph<N, E> {
+        self.graph
+    }
+
+    /// Runs one tick from the root, returning the root's status
+    pub fn tick(&mut self, ctx: &mut N::Context) -> Status {
+        self.tick_node(self.root, ctx)
+    }
+
+    fn children(&self, node_id: Id) -> Vec<Id> {
+        self.graph.ordered_successors(node_id).unwrap_or_default()
+    }
+
+    fn tick_node(&mut self, node_id: Id, ctx: &mut N::Context) -> Status {
+        let role = self.graph.get_node(node_id).unwrap().role();
+        let (keep_going, exhausted) = match role {
+            Role::Leaf => return self.graph.get_node_mut(node_id).unwrap().tick_leaf(ctx),
+            Role::Sequence => (Status::Success, Status::Success),
+            Role::Selector => (Status::Failure, Status::Failure),
+        };
+        for child_id in self.children(node_id) {
+            let status = self.tick_node(child_id, ctx);
+            if status != keep_going {
+                return status;
+            }
+        }
+        exhausted
+    }
+
+    fn has_cycle_from(&self, node_id: Id, on_path: &mut IdSet, done: &mut IdSet) -> bool {
+        if done.contains(node_id) {
+            return false;
+        }
+        if !on_path.insert(node_id) {
+            return true;
+        }
+        for child_id in self.children(node_id) {
+            if self.has_cycle_from(child_id, on_path, done) {
+                return true;
+            }
+        }
+        on_path.remove(node_id);
+        done.insert(node_id);
+        false
+    }
+}
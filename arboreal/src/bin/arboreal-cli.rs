@@ -0,0 +1,276 @@
+//! Inspects saved `DiGraph` files (`.ron`, optionally compressed) without knowing their node
+//! or edge types. Build with `cargo run --features cli --bin arboreal-cli -- <command>`.
+//!
+//! Edges may be saved as a `"start->end"` map (format v2) or as a sequence (older files).
+//! Terminals are taken from the map key, or else found by field name (`start`/`end`, `source`/`target`, `from`/`to`,
+//! `start_id`/`end_id`), or else as the first field holding an `(id, id)` pair.
+
+use std::collections::{BTreeMap, HashMap};
+use std::process::ExitCode;
+
+use ron::Value;
+use serde::{Deserialize, Serialize};
+
+use arboreal::digraph::{DiGraph, FileIO};
+use arboreal::graph_base::graph_components::{BareEdge, BareNode, Id};
+
+const USAGE: &str = "\
+Usage: arboreal-cli <command>
+
+Commands:
+    stats <file>                          Counts, sources, sinks and cycles
+    validate <file>                       Reports edges that can't be loaded; fails if any
+    dot <file>                            Prints the graph in Graphviz DOT format
+    paths <file> --from <id> --to <id>    Prints a shortest path between two nodes
+    diff <left> <right>                   Reports differences in structure and payloads";
+
+const TERMINAL_NAMES: [(&str, &str); 4] = [("start", "end"), ("source", "target"), ("from", "to"), ("start_id", "end_id")];
+
+/// Any saved `DiGraph`, with payloads kept as RON values
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "DiGraph")]
+struct RawGraph {
+    name: Option<String>,
+    nodes: BTreeMap<Id, Value>,
+    /// A map keyed by `"start->end"`, or a sequence before format v2
+    edges: Value,
+}
+impl Default for RawGraph {
+    fn default() -> Self {
+        Self { name: None, nodes: BTreeMap::new(), edges: Value::Seq(Vec::new()) }
+    }
+}
+impl FileIO for RawGraph {}
+
+/// A loaded file: its structure, the payloads behind it, and anything that had to be dropped
+struct Loaded {
+    structure: DiGraph<BareNode, BareEdge>,
+    node_payloads: BTreeMap<Id, Value>,
+    edge_payloads: HashMap<(Id, Id), Vec<Value>>,
+    problems: Vec<String>,
+}
+
+fn value_to_id(value: &Value) -> Option<Id> {
+    value.clone().into_rust().ok()
+}
+
+fn pair_to_ids(value: &Value) -> Option<(Id, Id)> {
+    match value {
+        Value::Seq(items) if items.len() == 2 => Some((value_to_id(&items[0])?, value_to_id(&items[1])?)),
+        _ => None,
+    }
+}
+
+fn edge_terminals(edge: &Value) -> Option<(Id, Id)> {
+    match edge {
+        Value::Map(fields) => {
+            let field = |name: &str| fields.get(&Value::String(name.to_string()));
+            for (start, end) in TERMINAL_NAMES {
+                if let (Some(start_id), Some(end_id)) = (field(start).and_then(value_to_id), field(end).and_then(value_to_id)) {
+                    return Some((start_id, end_id));
+                }
+            }
+            fields.values().find_map(pair_to_ids)
+        },
+        // Tuple structs, e.g. Edge(1, 2, "label")
+        Value::Seq(items) if items.len() >= 2 => Some((value_to_id(&items[0])?, value_to_id(&items[1])?)),
+        _ => None,
+    }
+}
+
+fn parse_edge_key(key: &Value) -> Option<(Id, Id)> {
+    let Value::String(key) = key else {
+        return None;
+    };
+    let (start, end) = key.split_once("->")?;
+    Some((start.trim().parse().ok()?, end.trim().parse().ok()?))
+}
+
+/// A saved edge, with the terminals its key gives (format v2) if any
+type RawEdge = (Option<(Id, Id)>, Value);
+
+fn raw_edges(edges: Value) -> Option<Vec<RawEdge>> {
+    match edges {
+        Value::Map(entries) => Some(entries.into_iter().map(|(key, edge)| (parse_edge_key(&key), edge)).collect()),
+        Value::Seq(items) => Some(items.into_iter().map(|edge| (None, edge)).collect()),
+        _ => None,
+    }
+}
+
+fn load(path: &str) -> Result<Loaded, String> {
+    let raw = RawGraph::load_from_file(path).ok_or(format!("{path}: not a readable DiGraph file"))?;
+    let mut structure = DiGraph::new();
+    structure.name = raw.name;
+    for &node_id in raw.nodes.keys() {
+        structure.insert_node(BareNode { id: node_id }).map_err(|e| format!("{path}: {e}"))?;
+    }
+    let mut edge_payloads: HashMap<(Id, Id), Vec<Value>> = HashMap::new();
+    let mut problems = Vec::new();
+    let edges = raw_edges(raw.edges).ok_or(format!("{path}: edges are neither a map nor a sequence"))?;
+    for (index, (key_terminals, edge)) in edges.into_iter().enumerate() {
+        let Some((start_id, end_id)) = key_terminals.or_else(|| edge_terminals(&edge)) else {
+            problems.push(format!("Edge {index}: no terminals found"));
+            continue;
+        };
+        let missing: Vec<Id> = [start_id, end_id].into_iter().filter(|&id| !raw.nodes.contains_key(&id)).collect();
+        if !missing.is_empty() {
+            problems.push(format!("Edge {index} ({start_id}->{end_id}): missing node(s) {missing:?}"));
+            continue;
+        }
+        if structure.insert_edge(BareEdge { start: start_id, end: end_id }).is_err() {
+            problems.push(format!("Edge {index} ({start_id}->{end_id}): duplicate"));
+        }
+        edge_payloads.entry((start_id, end_id)).or_default().push(edge);
+    }
+    Ok(Loaded { structure, node_payloads: raw.nodes, edge_payloads, problems })
+}
+
+fn stats(loaded: &Loaded) {
+    let graph = &loaded.structure;
+    let node_ids = graph.all_node_ids();
+    let with_degree = |degree: fn(&DiGraph<BareNode, BareEdge>, Id) -> Option<usize>| -> Vec<Id> {
+        node_ids.iter().copied().filter(|&id| degree(graph, id) == Some(0)).collect()
+    };
+    println!("Name: {}", graph.name.as_deref().unwrap_or("(none)"));
+    println!("Nodes: {}", node_ids.len());
+    println!("Edges: {}", graph.all_edge_pairs().len());
+    println!("Sources: {:?}", with_degree(DiGraph::in_degree));
+    println!("Sinks: {:?}", with_degree(DiGraph::out_degree));
+    println!("Acyclic: {}", graph.topological_order().is_some());
+    println!("Reciprocity: {:.3}", graph.reciprocity());
+    if !loaded.problems.is_empty() {
+        println!("Edges dropped: {} (see `validate`)", loaded.problems.len());
+    }
+}
+
+fn validate(loaded: &Loaded) -> ExitCode {
+    if loaded.problems.is_empty() {
+        println!("OK");
+        return ExitCode::SUCCESS;
+    }
+    for problem in loaded.problems.iter() {
+        println!("{problem}");
+    }
+    ExitCode::FAILURE
+}
+
+fn dot(loaded: &Loaded) {
+    let graph = &loaded.structure;
+    println!("digraph {:?} {{", graph.name.as_deref().unwrap_or("G"));
+    for node_id in graph.all_node_ids() {
+        println!("    {node_id};");
+    }
+    for (start_id, end_id) in graph.all_edge_pairs() {
+        println!("    {start_id} -> {end_id};");
+    }
+    println!("}}");
+}
+
+fn paths(loaded: &Loaded, from: Id, to: Id) -> ExitCode {
+    match loaded.structure.shortest_path(from, to) {
+        Some(path) => {
+            let ids: Vec<String> = path.iter().map(Id::to_string).collect();
+            println!("{}", ids.join(" -> "));
+            ExitCode::SUCCESS
+        },
+        None => {
+            println!("No path from {from} to {to}");
+            ExitCode::FAILURE
+        },
+    }
+}
+
+fn payload_text<T: Serialize>(payload: &T) -> String {
+    ron::to_string(payload).unwrap_or("<unserializable>".to_string())
+}
+
+fn diff(left: &Loaded, right: &Loaded) -> ExitCode {
+    let mut report = left.structure.explain_difference(&right.structure).unwrap_or_default();
+    for (node_id, left_payload) in left.node_payloads.iter() {
+        if let Some(right_payload) = right.node_payloads.get(node_id)
+            && left_payload != right_payload
+        {
+            report += &format!("Node {node_id} payload differs:\n\tleft:  {}\n\tright: {}\n",
+                payload_text(left_payload), payload_text(right_payload));
+        }
+    }
+    let mut shared_pairs: Vec<&(Id, Id)> = left.edge_payloads.keys().filter(|pair| right.edge_payloads.contains_key(pair)).collect();
+    shared_pairs.sort();
+    for pair in shared_pairs {
+        if left.edge_payloads[pair] != right.edge_payloads[pair] {
+            report += &format!("Edge {}->{} payload differs:\n\tleft:  {}\n\tright: {}\n",
+                pair.0, pair.1, payload_text(&left.edge_payloads[pair]), payload_text(&right.edge_payloads[pair]));
+        }
+    }
+    if report.is_empty() {
+        println!("No differences");
+        return ExitCode::SUCCESS;
+    }
+    print!("{report}");
+    ExitCode::FAILURE
+}
+
+fn flag_id(args: &[String], flag: &str) -> Result<Id, String> {
+    let position = args.iter().position(|arg| arg == flag).ok_or(format!("missing {flag}"))?;
+    let value = args.get(position + 1).ok_or(format!("missing value for {flag}"))?;
+    value.parse().map_err(|_| format!("{flag}: '{value}' is not a node id"))
+}
+
+fn run(args: &[String]) -> Result<ExitCode, String> {
+    let path = args.get(1).ok_or(USAGE)?;
+    let code = match args[0].as_str() {
+        "stats" => {
+            stats(&load(path)?);
+            ExitCode::SUCCESS
+        },
+        "validate" => validate(&load(path)?),
+        "dot" => {
+            dot(&load(path)?);
+            ExitCode::SUCCESS
+        },
+        "paths" => paths(&load(path)?, flag_id(args, "--from")?, flag_id(args, "--to")?),
+        "diff" => diff(&load(path)?, &load(args.get(2).ok_or(USAGE)?)?),
+        _ => return Err(USAGE.to_string()),
+    };
+    Ok(code)
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    }
+    run(&args).unwrap_or_else(|message| {
+        eprintln!("{message}");
+        ExitCode::FAILURE
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_saved_graph() {
+        let dir = std::env::temp_dir().join(format!("arboreal_cli_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (current, before_v2) = (dir.join("current.ron"), dir.join("before_v2.ron"));
+        let saved: DiGraph<BareNode, BareEdge> = DiGraph::from_terminal_pairs(vec![(1,2), (2,3), (3,1)]);
+        saved.save_to_file(&current).unwrap();
+        std::fs::write(&before_v2, "DiGraph(name: None, nodes: {1: (id: 1), 2: (id: 2)}, edges: [(start: 1, end: 2)])").unwrap();
+
+        let loaded = load(current.to_str().unwrap()).unwrap();
+        assert!(loaded.problems.is_empty());
+        assert_eq!(loaded.structure.all_node_ids(), saved.all_node_ids());
+        let mut pairs = loaded.structure.all_edge_pairs();
+        pairs.sort();
+        assert_eq!(pairs, vec![(1,2), (2,3), (3,1)]);
+        assert_eq!(loaded.edge_payloads.len(), 3);
+
+        let loaded = load(before_v2.to_str().unwrap()).unwrap();
+        assert!(loaded.problems.is_empty());
+        assert_eq!(loaded.structure.all_edge_pairs(), vec![(1,2)]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
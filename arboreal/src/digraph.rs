@@ -1,29 +1,193 @@
 
 mod file;
+mod storage;
+mod analysis;
+mod archive;
+mod bare;
 mod cache;
+mod canonical;
+mod clipboard;
+mod compare;
+mod compress;
+mod constraints;
+mod coverage;
+#[cfg(feature = "crdt")]
+mod crdt;
+#[cfg(feature = "json")]
+mod cytoscape;
+mod dag;
+mod delta;
 mod digraph_impl;
+mod dirty;
+mod edit;
+mod edit_distance;
+mod edge_map;
+mod endpoints;
+mod extras;
+#[cfg(feature = "arbitrary")]
+mod fuzz;
+mod group;
+mod hash;
+mod history;
+mod ids;
+mod keyed;
+mod labels;
+mod limits;
+mod memory;
+mod metrics;
+mod newick;
+mod ordering;
+mod outline;
+mod parts;
+mod ported;
+mod rollback;
+mod route;
+mod sampling;
+#[cfg(feature = "schema")]
+mod schema;
+mod speculative;
+mod strict;
+mod tags;
+mod traversal;
+mod tree;
+mod view;
 
+pub use analysis::MAX_SUBGRAPH_MATCHES;
+pub use archive::GraphArchive;
+pub use bare::BareNodePolicy;
+pub use cache::CoalesceWindow;
+pub use clipboard::{GraphClipboard, PasteIds};
+pub use compress::Compression;
+pub use constraints::{Acyclic, ForbiddenEdges, GraphConstraint, MaxNodes, SingleSource, UniqueEdgeLabels};
+pub use coverage::CoverageReport;
+#[cfg(feature = "crdt")]
+pub use crdt::Stamp;
+pub use delta::{ApplyError, DeltaConflict, DeltaOp, GraphDelta};
+pub use edit_distance::{EditLimits, EditOp};
 pub use file::FileIO;
+pub use ids::IdReuse;
+pub use memory::MemoryReport;
+pub use metrics::MutationMetrics;
+#[cfg(feature = "arbitrary")]
+pub use fuzz::{GraphOp, ARBITRARY_ID_MAX};
+pub use route::PathToken;
+pub use speculative::SpeculativeGraph;
+#[cfg(all(feature = "web", target_arch = "wasm32"))]
+pub use storage::LocalStorage;
+pub use storage::{Storage, FileStorage, MemoryStorage};
+pub use tree::{LcaIndex, Tree};
+pub use view::{FilteredView, FrozenDiGraph};
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
 
 use crate::graph_base::{graph_components::*, graph_ref};
-use cache::{ChangeCache, HistoryDeque};
+pub(crate) use cache::ChangeCache;
+use cache::HistoryDeque;
+use constraints::ConstraintSet;
+
+/// Positions in `DiGraph::edges`, kept inline like `NeighborIds`
+type EdgeIndices = SmallVec<[usize; 4]>;
+
+/// Writes a map in key order, so that equal graphs save to identical text
+fn serialize_sorted<K, V, S>(map: &HashMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    K: Ord + Serialize,
+    V: Serialize,
+    S: serde::Serializer,
+{
+    map.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+}
 
 #[derive(PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(bound = "N: Nodal, E: DirEdge")]
 pub struct DiGraph<N, E> {
+    /// See `edge_map`
+    #[serde(default)]
+    format_version: edge_map::FormatVersion,
     pub name: Option<String>,
-    
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    metadata: BTreeMap<String, String>,
+
+    #[serde(serialize_with = "serialize_sorted")]
     nodes: HashMap<Id, N>,
+    /// Saved as a map keyed by terminals; see `edge_map`
+    #[serde(serialize_with = "edge_map::serialize_edges", deserialize_with = "edge_map::deserialize_edges")]
+    #[cfg_attr(feature = "schema", schemars(with = "BTreeMap<String, E>"))]
     edges: Vec<E>,
 
     #[serde(skip)]
-    neighbors_before: HashMap<Id, Vec<Id>>,
+    neighbors_before: HashMap<Id, NeighborIds>,
     #[serde(skip)]
-    neighbors_after: HashMap<Id, Vec<Id>>,
+    neighbors_after: HashMap<Id, NeighborIds>,
+    /// Indices into `edges` of each node's incoming and outgoing edges, ascending
+    /// (a self-loop is listed once), so that edge lookups never scan every edge
     #[serde(skip)]
+    incident_edges: HashMap<Id, EdgeIndices>,
+    #[serde(default, skip_serializing_if = "HistoryDeque::is_transient")]
     undo_history: HistoryDeque<N, E>,
+    #[serde(skip)]
+    reserved_ids: HashSet<Id>,
+    /// Author-set successor order per node; see `set_successor_order()`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty", serialize_with = "serialize_sorted")]
+    successor_order: HashMap<Id, Vec<Id>>,
+    /// Removal count per id, for `NodeHandle`s; ids never removed are absent
+    #[serde(default, skip_serializing_if = "HashMap::is_empty", serialize_with = "serialize_sorted")]
+    generations: HashMap<Id, u32>,
+    /// Ids whose node was removed and that no node has taken since, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    freed_ids: Vec<Id>,
+    /// See `set_id_reuse()`
+    #[serde(default, skip_serializing_if = "IdReuse::is_default")]
+    id_reuse: IdReuse,
+    /// See `set_entry()`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    entry: Option<Id>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    exit: Option<Id>,
+    /// Ids refused by `remove_node()`; see `pin_node()`
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pinned: BTreeSet<Id>,
+    /// Hidden nodes per supernode id; see `group_nodes()`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    groups: BTreeMap<Id, NodeGroup<N, E>>,
+    /// Kept apart from payloads; see `tag_node()`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    node_tags: BTreeMap<Id, BTreeSet<String>>,
+    /// Keyed by start id, then end id
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    edge_tags: BTreeMap<Id, BTreeMap<Id, BTreeSet<String>>>,
+    /// Per-node degree limits; see `set_max_out_degree()`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    max_out_degrees: BTreeMap<Id, usize>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    max_in_degrees: BTreeMap<Id, usize>,
+    #[serde(skip)]
+    constraints: ConstraintSet<N, E>,
+    /// See `set_bare_node_policy()`
+    #[serde(skip)]
+    bare_node_policy: bare::BarePolicy<N>,
+    /// See `set_strict()`
+    #[serde(skip)]
+    strict: strict::StrictMode,
+    /// See `metrics()`
+    #[serde(skip)]
+    metrics: metrics::Metrics,
+    /// Kept while DAG mode is on; see `enforce_acyclic()`
+    #[serde(skip)]
+    acyclic_order: dag::DagMode,
+    /// Flagged by `mark_dirty()`
+    #[serde(skip)]
+    dirty: dirty::DirtyFlags,
+    /// See `begin_change()`
+    #[serde(skip)]
+    rollback: rollback::RollbackLog,
+    /// Merge stamps; see `enable_crdt()`
+    #[cfg(feature = "crdt")]
+    #[serde(default, skip_serializing_if = "crdt::CrdtState::is_unused")]
+    crdt: crdt::CrdtState,
 }
 
 impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
@@ -43,6 +207,25 @@ impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
         instance
     }
 
+    /// Returns the metadata value stored under `key` (e.g. "version", "author")
+    pub fn metadata(&self, key: &str) -> Option<&str> {
+        self.metadata.get(key).map(|value| value.as_str())
+    }
+
+    /// Stores `value` under `key`, returning the previous value if there was one
+    pub fn set_metadata<K: Into<String>, V: Into<String>>(&mut self, key: K, value: V) -> Option<String> {
+        self.metadata.insert(key.into(), value.into())
+    }
+
+    pub fn remove_metadata(&mut self, key: &str) -> Option<String> {
+        self.metadata.remove(key)
+    }
+
+    /// All metadata entries, ordered by key
+    pub fn metadata_entries(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+
     pub fn all_node_ids(&self) -> Vec<Id> {
         let mut node_ids: Vec<Id> = self.nodes
             .keys()
@@ -68,6 +251,10 @@ impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
     pub fn get_node_mut(&mut self, node_id: Id) -> Option<&mut N> {
         self.nodes.get_mut(&node_id)
     }
+    /// Every node, in no particular order
+    pub fn nodes_mut(&mut self) -> impl Iterator<Item = &mut N> {
+        self.nodes.values_mut()
+    }
 
     pub fn get_edge(&self, start_id: Id, end_id: Id) -> Option<&E> {
         if let Some(index) = self.edge_index(start_id, end_id) {
@@ -85,50 +272,82 @@ impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
     /// Inserts `node` into graph, with no edges.
     /// 
     /// If the node's id is already in use, an error is returned.
-    pub fn insert_node(&mut self, node: N) -> Result<(), &'static str> {
+    pub fn insert_node(&mut self, node: N) -> Result<(), GraphError> {
+        trace_span!("insert_node", node_id = node.node_id());
         let change = 
             graph_ref::check_add_node::<N, E>(&self.nodes, node);
         let new_node = change.try_get_node()?;
+        self.begin_change();
         self.insert_node_unregistered(new_node);
-        self.register_change(change);
+        self.commit_change(change)?;
         Ok(())
     }
 
     /// Removes and returns node (as Ok(N)) with input id, breaking any edges incident on it.
     /// 
-    /// If no node with that id is present in the graph, an error is returned.
-    pub fn remove_node(&mut self, node_id: Id) -> Result<N, &'static str> {
+    /// If no node with that id is present in the graph, an error is returned;
+    /// if the node is pinned, the error is `GraphError::NodePinned`.
+    pub fn remove_node(&mut self, node_id: Id) -> Result<N, GraphError> {
+        trace_span!("remove_node", node_id);
+        if self.pinned.contains(&node_id) {
+            return Err(GraphError::NodePinned(node_id));
+        }
         let change =
-            graph_ref::check_remove_node::<N, E>(&self.nodes, &self.edges, node_id);
+            graph_ref::check_remove_node::<N, E>(&self.nodes, self.edges_incident_on(node_id), node_id);
         let out_node_id = change.try_get_node()?.node_id();
         // let out_edges = change.try_get_edge_vec()?;
+        self.begin_change();
         let removed_node = self.remove_node_unregistered(out_node_id);
-        self.register_change(change);
+        self.commit_change(change)?;
         Ok(removed_node)
     }
 
+    /// Puts `new_node` in place of the node with id `old_id`, and returns the old node,
+    /// as a single undoable change.
+    ///
+    /// If `new_node` has a different id, the node moves to that id, taking its incident edges
+    /// (and any successor orders naming it, its pin and its degree limits) along; handles to `old_id` go stale.
+    ///
+    /// If `old_id` is not found, or `new_node`'s id is already used by another node, an error is returned.
+    pub fn replace_node(&mut self, old_id: Id, new_node: N) -> Result<N, GraphError> {
+        trace_span!("replace_node", old_id, new_id = new_node.node_id());
+        let change =
+            graph_ref::check_replace_node::<N, E>(&self.nodes, old_id, &new_node);
+        let _old_node = change.try_get_node()?;
+        self.begin_change();
+        let replaced_node = self.replace_node_unregistered(old_id, new_node);
+        self.commit_change(change)?;
+        Ok(replaced_node)
+    }
+
     /// Inserts `edge` into graph.
     /// 
     /// If the edge's terminal nodes are not present in the graph,
     /// or an edge with these same terminals is already present in the graph,
-    /// an error is returned.
-    pub fn insert_edge(&mut self, edge: E) -> Result<(), &'static str> {
+    /// an error is returned; if it would break a degree limit (see `set_max_out_degree()`),
+    /// the error is `GraphError::OutDegreeLimit` or `GraphError::InDegreeLimit`.
+    pub fn insert_edge(&mut self, edge: E) -> Result<(), GraphError> {
+        trace_span!("insert_edge", start_id = edge.start_id(), end_id = edge.end_id());
         let change =
-            graph_ref::check_add_edge::<N, E>(&self.nodes, &self.edges, edge);
+            graph_ref::check_add_edge::<N, E>(&self.nodes, self.edges_incident_on(edge.start_id()), edge);
         let new_edge = change.try_get_edge()?;
+        self.check_edge_limits(new_edge.start_id(), new_edge.end_id())?;
+        self.begin_change();
         self.insert_edge_unregistered(new_edge);
-        self.register_change(change);
+        self.commit_change(change)?;
         Ok(())
     }
 
     /// Doc TODO
-    pub fn remove_edge(&mut self, start_id: Id, end_id: Id) -> Result<(), &'static str> {
+    pub fn remove_edge(&mut self, start_id: Id, end_id: Id) -> Result<(), GraphError> {
+        trace_span!("remove_edge", start_id, end_id);
         let change = 
-            graph_ref::check_remove_edge::<N, E>(&self.edges, start_id, end_id);
+            graph_ref::check_remove_edge::<N, E>(self.edges_incident_on(start_id), start_id, end_id);
         let _out_edge = change.try_get_edge()?;
         let edge_index = self.edge_index(start_id, end_id).unwrap();
+        self.begin_change();
         self.remove_edge_unregistered(edge_index);
-        self.register_change(change);
+        self.commit_change(change)?;
         Ok(())
     }
 
@@ -137,41 +356,50 @@ impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
     /// 
     /// That edge's data is moved to the new edge from `id_before` to `new_id`.
     /// 
-    /// If the old edge does not exist, or `new_id` is already in use, an error is returned.
-    pub fn insert_node_along(&mut self, new_id: Id, id_before: Id, id_after: Id) -> Result<(), &'static str> {
+    /// If the old edge does not exist, `new_id` is already in use, or `BareNodePolicy::Deny`
+    /// is set, an error is returned.
+    pub fn insert_node_along(&mut self, new_id: Id, id_before: Id, id_after: Id) -> Result<(), GraphError> {
+        trace_span!("insert_node_along", new_id, id_before, id_after);
         let new_node =
-            graph_ref::check_add_node::<N, E>(&self.nodes, N::bare(new_id))
+            graph_ref::check_add_node::<N, E>(&self.nodes, self.make_bare_node(new_id)?)
             .try_get_node()?;
         let old_edge =
-            graph_ref::check_remove_edge::<N, E>(&self.edges, id_before, id_after)
+            graph_ref::check_remove_edge::<N, E>(self.edges_incident_on(id_before), id_before, id_after)
             .try_get_edge()?;
         let edge_index = self.edge_index(id_before, id_after).unwrap();
         let mut edge_before = old_edge.clone();
         edge_before.change_end(new_id);
         let edge_after = E::bare(new_id, id_after);
+        self.begin_change();
         self.insert_node_unregistered(new_node.clone());
         self.remove_edge_unregistered(edge_index);
         self.insert_edge_unregistered(edge_before);
         self.insert_edge_unregistered(edge_after);
-        self.register_change(GraphChange::InsertNodeAlongEdge(new_node, old_edge));
+        self.commit_change(GraphChange::InsertNodeAlongEdge(new_node, old_edge))?;
         Ok(())
     }
 
-    /// Inserts a bare `Edge` with provided terminals, creating bare nodes at those terminals if needed.
+    /// Inserts a bare `Edge` with provided terminals, creating bare nodes at those terminals if needed
+    /// (see `set_bare_node_policy()`).
     /// 
-    /// If an edge with these terminals already exists, an error is returned.
-    pub fn insert_edge_with_nodes(&mut self, id_in: Id, id_out: Id) -> Result<(), &'static str> {
+    /// If an edge with these terminals already exists, it would break a degree limit
+    /// (see `set_max_out_degree()`), or a node is needed but `BareNodePolicy::Deny` is set,
+    /// an error is returned.
+    pub fn insert_edge_with_nodes(&mut self, id_in: Id, id_out: Id) -> Result<(), GraphError> {
+        trace_span!("insert_edge_with_nodes", id_in, id_out);
         let change =
-            graph_ref::check_add_edge_with_nodes::<N, E>(&self.nodes, &self.edges, id_in, id_out);
+            graph_ref::check_add_edge_with_nodes::<N, E>(&self.nodes, self.edges_incident_on(id_in), id_in, id_out);
         let (new_edge, new_in, new_out) = change.try_get_edge_with_nodes()?;
-        if let Some(new_id) = new_in {
-            self.insert_node_unregistered(N::bare(new_id));
-        }
-        if let Some(new_id) = new_out {
-            self.insert_node_unregistered(N::bare(new_id));
+        self.check_edge_limits(id_in, id_out)?;
+        let new_nodes = new_in.into_iter().chain(new_out)
+            .map(|new_id| self.make_bare_node(new_id))
+            .collect::<Result<Vec<N>, GraphError>>()?;
+        self.begin_change();
+        for node in new_nodes {
+            self.insert_node_unregistered(node);
         }
         self.insert_edge_unregistered(new_edge);
-        self.register_change(change);
+        self.commit_change(change)?;
         Ok(())
     }
 
@@ -195,7 +423,31 @@ impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
         None
     }
 
-    pub fn get_source(&self) -> Result<&N, &'static str> {
+    /// Returns `Some(ids)` of nodes reached by an edge starting at `node_id`, ascending and without repeats
+    ///
+    /// Or `None` if the provided id is not found among the nodes
+    pub fn successors_sorted(&self, node_id: Id) -> Option<Vec<Id>> {
+        let mut ids_after = self.neighbors_after.get(&node_id)?.to_vec();
+        ids_after.dedup();
+        Some(ids_after)
+    }
+
+    /// Returns `Some(ids)` of nodes with an edge ending at `node_id`, ascending and without repeats
+    ///
+    /// Or `None` if the provided id is not found among the nodes
+    pub fn predecessors_sorted(&self, node_id: Id) -> Option<Vec<Id>> {
+        let mut ids_before = self.neighbors_before.get(&node_id)?.to_vec();
+        ids_before.dedup();
+        Some(ids_before)
+    }
+
+    /// Returns the entry node if one is designated (see `set_entry()`), or else the only source.
+    ///
+    /// If there is no entry node and there are no sources or several, an error is returned.
+    pub fn get_source(&self) -> Result<&N, GraphError> {
+        if let Some(entry_id) = self.entry() {
+            return Ok(&self.nodes[&entry_id]);
+        }
         let mut source_ids = self.source_node_ids();
         match source_ids.len() {
             1 => {
@@ -203,17 +455,44 @@ impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
                 let source_node = self.nodes.get(&index).unwrap();
                 Ok(source_node)
             }
-            0 => Err("No sources in graph."),
-            _ => Err("Multiple sources in graph.")
+            0 => Err("No sources in graph.".into()),
+            _ => Err("Multiple sources in graph.".into())
         }
     }
 
+    /// Returns every node with no incoming edges, by ascending id (e.g. to let a user pick
+    /// the start when `get_source()` finds several)
+    pub fn get_sources(&self) -> Vec<&N> {
+        self.source_node_ids().iter().map(|id| &self.nodes[id]).collect()
+    }
+
+    /// Returns every node with no outgoing edges, by ascending id
+    pub fn get_sinks(&self) -> Vec<&N> {
+        self.sink_node_ids().iter().map(|id| &self.nodes[id]).collect()
+    }
+
+    /// Returns ids of nodes with no path from `starting_point`, ascending
+    /// (every node, if `starting_point` is not found among the nodes)
     pub fn nodes_unreachable_from(&self, starting_point: Id) -> Vec<Id> {
-        let mut lost_nodes: Vec<Id> = self.all_node_ids();
-        let mut census = Vec::with_capacity(self.nodes.len());
+        let mut census = IdSet::new();
         graph_ref::collect_reachable_neighbors(&mut census, starting_point, &self.neighbors_after);
-        lost_nodes.retain(|&id| !census.contains(&id));
+        let mut lost_nodes: Vec<Id> = self.nodes
+            .keys()
+            .copied()
+            .filter(|&id| !census.contains(id))
+            .collect();
+        lost_nodes.sort_unstable();
         lost_nodes
     }
 
+    /// Same as `nodes_unreachable_from().len()`, without building the list
+    pub fn unreachable_count_from(&self, starting_point: Id) -> usize {
+        if !self.nodes.contains_key(&starting_point) {
+            return self.nodes.len();
+        }
+        let mut census = IdSet::new();
+        let reached = graph_ref::collect_reachable_neighbors(&mut census, starting_point, &self.neighbors_after);
+        self.nodes.len() - reached
+    }
+
 }
@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+
+use crate::graph_base::{
+    graph_algo::{self, ProgressFn},
+    graph_components::{BareEdge, BareNode, GraphError, Id, TreeViolation},
+};
+use super::{Nodal, DirEdge, DiGraph};
+
+/// Default cap on the mappings returned by `find_subgraph_isomorphisms()`
+pub const MAX_SUBGRAPH_MATCHES: usize = 10_000;
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+
+    /// Returns ids of nodes whose removal would split their (undirected) component in two, ascending.
+    ///
+    /// Edge directions are ignored; an edge pair in both directions counts as two links.
+    pub fn articulation_points(&self) -> Vec<Id> {
+        graph_algo::cut_vertices_and_bridges(self).0
+    }
+
+    /// Returns (start, end) of every edge whose removal would split its (undirected) component, ascending.
+    ///
+    /// Edge directions are ignored; an edge pair in both directions counts as two links,
+    /// so neither of them is a bridge.
+    pub fn bridges(&self) -> Vec<(Id, Id)> {
+        graph_algo::cut_vertices_and_bridges(self).1
+    }
+
+    /// Returns (start, end) of every edge whose removal alone would leave `end_id` unreachable from
+    /// `start_id` (single points of failure, e.g. in quest progression), in the order a path meets them.
+    ///
+    /// Unlike `bridges()`, edge directions count. Empty if `end_id` is unreachable already,
+    /// or either id is not found among the nodes.
+    pub fn critical_edges(&self, start_id: Id, end_id: Id) -> Vec<(Id, Id)> {
+        graph_algo::critical_edges(self, start_id, end_id)
+    }
+
+    /// Same as `critical_edges()`, reporting each edge of a shortest path tried; see
+    /// `graph_algo::Progress`.
+    ///
+    /// If `progress` cancels, `GraphError::Cancelled` is returned.
+    pub fn critical_edges_with_progress(&self, start_id: Id, end_id: Id, progress: ProgressFn) -> Result<Vec<(Id, Id)>, GraphError> {
+        graph_algo::critical_edges_with_progress(self, start_id, end_id, progress)
+    }
+
+    /// Same as `critical_edges()`, for the nodes between `start_id` and `end_id`
+    pub fn critical_nodes(&self, start_id: Id, end_id: Id) -> Vec<Id> {
+        graph_algo::critical_nodes(self, start_id, end_id)
+    }
+
+    /// Returns `Some((left, right))` splitting all node ids so that every edge joins the two sides
+    /// (edge directions ignored), or `None` if the graph is not bipartite.
+    ///
+    /// Both sides are ascending; in each connected component, the smallest id is on the left.
+    pub fn is_bipartite(&self) -> Option<(Vec<Id>, Vec<Id>)> {
+        graph_algo::bipartition(self)
+    }
+
+    /// Greedily assigns each node a color in `0..k` such that no edge joins two nodes
+    /// of the same color (edge directions ignored).
+    ///
+    /// Returns `None` if the greedy pass runs out of colors; a smarter search
+    /// might still succeed with `k` colors.
+    pub fn color_nodes(&self, k: usize) -> Option<HashMap<Id, usize>> {
+        graph_algo::greedy_coloring(self, k)
+    }
+
+    /// Returns `Some(edges)`, as (start, end) pairs in walking order, of a path that uses every edge
+    /// exactly once, or `None` if there is no such path.
+    ///
+    /// If a circuit exists, the path is closed (it ends where it started).
+    /// A graph without edges gives `Some` empty path.
+    pub fn eulerian_path(&self) -> Option<Vec<(Id, Id)>> {
+        let walk = graph_algo::eulerian_walk(self)?;
+        Some(walk.windows(2).map(|pair| (pair[0], pair[1])).collect())
+    }
+
+    /// Returns `true` if a closed walk exists that uses every edge exactly once
+    pub fn has_eulerian_circuit(&self) -> bool {
+        let balanced = self.nodes
+            .keys()
+            .all(|&id| self.in_degree(id) == self.out_degree(id));
+        balanced && graph_algo::eulerian_walk(self).is_some()
+    }
+
+    /// Clusters nodes by label propagation over the undirected view, returning each node's
+    /// community number (numbered from 0, in order of each community's smallest id).
+    ///
+    /// At most `max_iters` passes are made; results are deterministic between runs.
+    pub fn communities_label_propagation(&self, max_iters: usize) -> HashMap<Id, usize> {
+        graph_algo::label_propagation(self, max_iters)
+    }
+
+    /// Same as `communities_label_propagation()`, reporting each pass; see `graph_algo::Progress`.
+    ///
+    /// If `progress` cancels, `GraphError::Cancelled` is returned.
+    pub fn communities_label_propagation_with_progress(&self, max_iters: usize, progress: ProgressFn) -> Result<HashMap<Id, usize>, GraphError> {
+        graph_algo::label_propagation_with_progress(self, max_iters, progress)
+    }
+
+    /// Number of edges from `start_id` to `end_id` (0 if either node is missing).
+    ///
+    /// For edges in either direction, add `edge_count_between(end_id, start_id)`.
+    pub fn edge_count_between(&self, start_id: Id, end_id: Id) -> usize {
+        match self.neighbors_after.get(&start_id) {
+            Some(ids_after) => ids_after.iter().filter(|&&id| id == end_id).count(),
+            None => 0,
+        }
+    }
+
+    /// Returns pairs `(a, b)` with `a < b` that have edges both from `a` to `b` and from `b` to `a`, ascending
+    pub fn mutual_edges(&self) -> Vec<(Id, Id)> {
+        graph_algo::mutual_pairs(self)
+    }
+
+    /// Returns the fraction of edges (self-loops aside) whose reverse edge is also present,
+    /// or 0.0 if there are no such edges.
+    pub fn reciprocity(&self) -> f64 {
+        graph_algo::reciprocity(self)
+    }
+
+    /// Returns every mapping (pattern id -> id in this graph) under which `pattern` appears here,
+    /// with each pattern edge matched by an edge of this graph. Other edges between the matched
+    /// nodes are allowed.
+    ///
+    /// Symmetric patterns give one mapping per symmetry. At most `MAX_SUBGRAPH_MATCHES` mappings
+    /// are returned; see `find_subgraph_isomorphisms_limited()` to choose the cap.
+    pub fn find_subgraph_isomorphisms(&self, pattern: &DiGraph<BareNode, BareEdge>) -> Vec<HashMap<Id, Id>> {
+        self.find_subgraph_isomorphisms_limited(pattern, MAX_SUBGRAPH_MATCHES)
+    }
+
+    /// Same as `find_subgraph_isomorphisms()`, stopping after `limit` mappings
+    pub fn find_subgraph_isomorphisms_limited(&self, pattern: &DiGraph<BareNode, BareEdge>, limit: usize) -> Vec<HashMap<Id, Id>> {
+        graph_algo::subgraph_matches(self, pattern, limit)
+    }
+
+    /// Same as `find_subgraph_isomorphisms_limited()`, reporting each graph node tried for the
+    /// first pattern node; see `graph_algo::Progress`.
+    ///
+    /// If `progress` cancels, `GraphError::Cancelled` is returned.
+    pub fn find_subgraph_isomorphisms_with_progress(
+        &self,
+        pattern: &DiGraph<BareNode, BareEdge>,
+        limit: usize,
+        progress: ProgressFn,
+    ) -> Result<Vec<HashMap<Id, Id>>, GraphError> {
+        graph_algo::subgraph_matches_with_progress(self, pattern, limit, progress)
+    }
+
+    /// Whether the graph is a tree with every edge pointing away from `root`: the root has no
+    /// incoming edge, every other node exactly one, and all nodes are reachable from the root
+    /// (which rules out cycles).
+    ///
+    /// `tree_violations(Some(root))` lists what is wrong otherwise.
+    pub fn is_arborescence(&self, root: Id) -> bool {
+        self.nodes.contains_key(&root) && self.tree_violations(Some(root)).is_empty()
+    }
+
+    /// Whether the graph is a set of arborescences: no node has more than one incoming edge,
+    /// and there are no cycles. An empty graph is a forest.
+    ///
+    /// `tree_violations(None)` lists what is wrong otherwise.
+    pub fn is_forest(&self) -> bool {
+        self.tree_violations(None).is_empty()
+    }
+
+    /// Returns (id, problem) for every node that keeps the graph from being an arborescence
+    /// rooted at `root`, or a forest if `root` is `None`, ascending by id.
+    pub fn tree_violations(&self, root: Option<Id>) -> Vec<(Id, TreeViolation)> {
+        graph_algo::tree_violations(self, root)
+    }
+
+    /// Returns the lowest common ancestors of `a` and `b`, ascending: nodes from which both
+    /// can be reached (each node reaches itself), and from which no other such node can.
+    /// Meant for DAGs, where there may be several; on a `Tree`, see `Tree::lca()`.
+    ///
+    /// Empty if either id is not found, or no node reaches both.
+    pub fn lowest_common_ancestors(&self, a: Id, b: Id) -> Vec<Id> {
+        graph_algo::lowest_common_ancestors(self, a, b)
+    }
+}
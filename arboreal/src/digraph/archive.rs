@@ -0,0 +1,59 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use ron::Result as RonResult;
+
+use super::{Nodal, DirEdge, DiGraph, FileIO};
+
+/// Named collection of graphs saved together as one file (or `Storage` entry).
+///
+/// Each graph is kept as its own RON text and only parsed by `get()`, so opening an archive
+/// is cheap however many graphs it holds. Graphs in one archive may have different node and
+/// edge types.
+#[derive(PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct GraphArchive {
+    entries: BTreeMap<String, String>,
+}
+
+impl GraphArchive {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores a copy of `graph` under `name`, replacing any graph already stored there
+    pub fn insert<N: Nodal, E: DirEdge>(&mut self, name: impl Into<String>, graph: &DiGraph<N, E>) -> RonResult<()> {
+        let text = ron::to_string(graph)?;
+        self.entries.insert(name.into(), text);
+        Ok(())
+    }
+
+    /// Parses and returns the graph stored under `name`.
+    ///
+    /// Returns `None` if there is no such entry, or it does not hold a `DiGraph<N, E>`.
+    pub fn get<N: Nodal, E: DirEdge>(&self, name: &str) -> Option<DiGraph<N, E>> {
+        DiGraph::from_ron_str(self.entries.get(name)?)
+    }
+
+    /// Returns whether an entry was removed
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.entries.remove(name).is_some()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.contains_key(name)
+    }
+
+    /// Entry names, in ascending order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(|name| name.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl FileIO for GraphArchive {}
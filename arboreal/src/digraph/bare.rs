@@ -0,0 +1,68 @@
+use crate::graph_base::graph_components::{GraphError, Id};
+use super::{Nodal, DirEdge, DiGraph};
+
+/// How a graph makes the nodes that edits create on their own, such as missing ends in
+/// `insert_edge_with_nodes()`; see `DiGraph::set_bare_node_policy()`.
+pub enum BareNodePolicy<N> {
+    /// `Nodal::bare()` (the default)
+    Allow,
+    /// Refuse the edit with an error
+    Deny,
+    /// Build the node from its id; the id is corrected if the node comes back with another
+    Custom(fn(Id) -> N),
+}
+
+impl<N> Clone for BareNodePolicy<N> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<N> Copy for BareNodePolicy<N> {}
+
+/// Kept out of `DiGraph` equality, like constraints
+pub(super) struct BarePolicy<N>(pub(super) BareNodePolicy<N>);
+
+impl<N> Default for BarePolicy<N> {
+    fn default() -> Self {
+        Self(BareNodePolicy::Allow)
+    }
+}
+
+impl<N> PartialEq for BarePolicy<N> {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+
+    /// Chooses how nodes that edits create on their own are made: the missing ends in
+    /// `insert_edge_with_nodes()` and `extend_from_edges()`, the node in `insert_node_along()`,
+    /// `insert_bare_node()`'s node, and `group_nodes()`'s supernode. With `Deny`, those edits
+    /// fail instead (nodes passed in by the caller are unaffected).
+    ///
+    /// The policy is not saved with the graph, nor copied by `clone()`.
+    pub fn set_bare_node_policy(&mut self, policy: BareNodePolicy<N>) {
+        self.bare_node_policy = BarePolicy(policy);
+    }
+
+    pub fn bare_node_policy(&self) -> BareNodePolicy<N> {
+        self.bare_node_policy.0
+    }
+
+    /// Node with id `node_id` as the policy makes it, or an error if the policy is `Deny`
+    pub(super) fn make_bare_node(&self, node_id: Id) -> Result<N, GraphError> {
+        match self.bare_node_policy.0 {
+            BareNodePolicy::Allow => Ok(N::bare(node_id)),
+            BareNodePolicy::Deny => Err("Creating bare nodes is denied by the graph's policy.".into()),
+            BareNodePolicy::Custom(make) => {
+                let mut node = make(node_id);
+                if node.node_id() != node_id {
+                    node.change_id(node_id);
+                }
+                Ok(node)
+            },
+        }
+    }
+}
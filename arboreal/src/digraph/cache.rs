@@ -1,18 +1,327 @@
 
 use std::fmt::Debug;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use fixed_deque::Deque;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
-use super::{DirEdge, GraphChange, Nodal, DiGraph};
+use super::{ChangeRecord, DirEdge, GraphChange, GraphError, Nodal, DiGraph, UndoneChange};
 
 const UNDO_HISTORY_LIMIT: usize = 100;
 
-#[derive(PartialEq)]
-pub struct HistoryDeque<N, E> (Deque<GraphChange<N, E>>);
+/// Bumped whenever the serialized form of `GraphChange` changes
+const HISTORY_FORMAT_VERSION: u8 = 2;
+
+pub struct HistoryDeque<N, E> {
+    changes: Deque<GraphChange<N, E>>,
+    /// One per entry in `changes`, in the same order
+    records: Deque<ChangeRecord>,
+    /// When false (the default), the history is left out of serialized graphs
+    persist: bool,
+    /// Window for merging each new change into the newest entry, if any
+    auto_coalesce: Option<CoalesceWindow>,
+    /// Changes merged into the newest entry while coalescing automatically;
+    /// 0 when nothing may be merged into it (e.g. it was there before the mode was turned on)
+    newest_run: usize,
+    /// One per entry in `changes`, in the same order
+    links: Deque<StepLinks<N, E>>,
+    /// Undone changes that can be made again from the current state, oldest undone first
+    branches: Vec<Branch<N, E>>,
+    next_step_id: usize,
+}
+
+/// Where an entry in the history sits in the undo tree
+struct StepLinks<N, E> {
+    id: usize,
+    /// Branches off the state before the entry's change, left behind when it was made
+    siblings: Vec<Branch<N, E>>,
+}
+
+/// Undone change hanging off the state it was undone to, which `DiGraph::redo()` or
+/// `DiGraph::switch_branch()` can make again
+struct Branch<N, E> {
+    id: usize,
+    change: GraphChange<N, E>,
+    /// Reversing this makes `change` again
+    redo: GraphChange<N, E>,
+    record: ChangeRecord,
+    /// Branches off the state right after `change`
+    branches: Vec<Branch<N, E>>,
+}
+
+/// Ids of the branches from one in `branches` down to the one with id `id`, if any
+fn branch_path<N, E>(branches: &[Branch<N, E>], id: usize) -> Option<Vec<usize>> {
+    branches.iter().find_map(|branch| match branch.id == id {
+        true => Some(vec![id]),
+        false => branch_path(&branch.branches, id).map(|mut path| {
+            path.insert(0, branch.id);
+            path
+        }),
+    })
+}
+
+/// Limits on which consecutive changes are merged into one undo step, by
+/// `DiGraph::coalesce_history()` or `DiGraph::set_history_coalescing()`.
+///
+/// Only changes describing the same nodes and edges (same `ChangeRecord` apart from the
+/// timestamp) are merged, e.g. repeated `replace_node()` calls on one node while a slider is
+/// dragged.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct CoalesceWindow {
+    /// Most milliseconds between two changes merged, or `None` for no limit.
+    /// Changes made without a clock are never kept apart by this.
+    pub max_gap_ms: Option<u64>,
+    /// Most changes merged into one step
+    pub max_changes: usize,
+}
+
+impl CoalesceWindow {
+    /// Whether a change recorded as `later` may be merged into the step recorded as `earlier`
+    fn admits(&self, earlier: &ChangeRecord, later: &ChangeRecord) -> bool {
+        let close = match (self.max_gap_ms, earlier.timestamp_ms, later.timestamp_ms) {
+            (Some(max_gap_ms), Some(earlier_ms), Some(later_ms)) => later_ms.saturating_sub(earlier_ms) <= max_gap_ms,
+            _ => true,
+        };
+        close && earlier.kind == later.kind && earlier.node_ids == later.node_ids && earlier.edges == later.edges
+    }
+}
+
+impl Default for CoalesceWindow {
+    /// Half a second between changes, with no limit on their number
+    fn default() -> Self {
+        Self { max_gap_ms: Some(500), max_changes: usize::MAX }
+    }
+}
+
+/// One change with the effect of `earlier` followed by `later`, which describe the same nodes and edges
+fn merge_changes<N, E>(earlier: GraphChange<N, E>, later: GraphChange<N, E>) -> GraphChange<N, E> {
+    match (earlier, later) {
+        // Undoing only needs the state from before the first
+        (GraphChange::ReplaceNode(node, _), GraphChange::ReplaceNode(_, new_id)) => GraphChange::ReplaceNode(node, new_id),
+        (earlier @ GraphChange::ReorderSuccessors(..), GraphChange::ReorderSuccessors(..)) => earlier,
+        (GraphChange::Batch(mut changes), GraphChange::Batch(later_changes)) => {
+            changes.extend(later_changes);
+            GraphChange::Batch(changes)
+        },
+        (earlier, later) => GraphChange::Batch(vec![earlier, later]),
+    }
+}
 
 impl<N, E> HistoryDeque<N, E> {
     pub fn new(limit: usize) -> Self {
-        Self(Deque::new(limit))
+        Self {
+            changes: Deque::new(limit),
+            records: Deque::new(limit),
+            persist: false,
+            auto_coalesce: None,
+            newest_run: 0,
+            links: Deque::new(limit),
+            branches: Vec::new(),
+            next_step_id: 0,
+        }
+    }
+
+    /// History with no length limit, so that nothing is dropped (e.g. for `SpeculativeGraph`)
+    pub(super) fn unbounded() -> Self {
+        Self {
+            changes: Deque::from_vec(vec![], usize::MAX),
+            records: Deque::from_vec(vec![], usize::MAX),
+            links: Deque::from_vec(vec![], usize::MAX),
+            ..Self::new(0)
+        }
+    }
+
+    /// Removes and returns every change, oldest first
+    pub(super) fn take_changes(&mut self) -> Vec<GraphChange<N, E>> {
+        self.records.clear();
+        self.newest_run = 0;
+        self.links.clear();
+        self.branches.clear();
+        std::iter::from_fn(|| self.changes.pop_front()).collect()
+    }
+
+    pub(super) fn is_transient(&self) -> bool {
+        !self.persist
+    }
+
+    /// Registered changes, oldest first
+    pub(super) fn iter_changes(&self) -> std::collections::vec_deque::Iter<'_, GraphChange<N, E>> {
+        self.changes.iter()
+    }
+
+    /// Turns the entry just popped off the history, whose change was reversed, into a branch
+    /// off the state it left
+    fn branch_off(&mut self, change: GraphChange<N, E>, redo: GraphChange<N, E>, record: ChangeRecord) {
+        let Some(links) = self.links.pop_back() else {
+            return;
+        };
+        let branches = std::mem::replace(&mut self.branches, links.siblings);
+        self.branches.push(Branch { id: links.id, change, redo, record, branches });
+    }
+
+    fn new_links(&mut self, siblings: Vec<Branch<N, E>>) -> StepLinks<N, E> {
+        self.next_step_id += 1;
+        StepLinks { id: self.next_step_id - 1, siblings }
+    }
+}
+
+// How a graph came to be, not what it is, so that a `clone()` (which starts with an empty
+// history) equals the original
+impl<N, E> PartialEq for HistoryDeque<N, E> {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+#[derive(Serialize)]
+struct SavedHistoryRef<'a, N, E> {
+    version: u8,
+    limit: usize,
+    changes: Vec<&'a GraphChange<N, E>>,
+    records: Vec<&'a ChangeRecord>,
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+struct SavedHistory<N, E> {
+    version: u8,
+    limit: usize,
+    changes: Vec<GraphChange<N, E>>,
+    /// Missing before v2
+    #[serde(default)]
+    records: Vec<ChangeRecord>,
+}
+
+impl<N: Serialize, E: Serialize> Serialize for HistoryDeque<N, E> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SavedHistoryRef {
+            version: HISTORY_FORMAT_VERSION,
+            limit: self.changes.capacity(),
+            changes: self.changes.iter().collect(),
+            records: self.records.iter().collect(),
+        }.serialize(serializer)
+    }
+}
+
+impl<'de, N: Deserialize<'de>, E: Deserialize<'de>> Deserialize<'de> for HistoryDeque<N, E> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let saved = SavedHistory::deserialize(deserializer)?;
+        if saved.version > HISTORY_FORMAT_VERSION {
+            return Err(de::Error::custom(format!(
+                "undo history format v{} is newer than supported (v{HISTORY_FORMAT_VERSION})", saved.version
+            )));
+        }
+        // Histories saved without records get them from `fill_missing_records()` after loading
+        let records = match saved.records.len() == saved.changes.len() {
+            true => saved.records,
+            false => vec![],
+        };
+        // Branches are not saved, so every entry starts a fresh undo tree with no side branches
+        let count = saved.changes.len();
+        let links = (0..count).map(|id| StepLinks { id, siblings: Vec::new() }).collect();
+        // Anything read back was persisted on purpose, so keep persisting it
+        Ok(Self {
+            changes: Deque::from_vec(saved.changes, saved.limit),
+            records: Deque::from_vec(records, saved.limit),
+            persist: true,
+            auto_coalesce: None,
+            newest_run: 0,
+            links: Deque::from_vec(links, saved.limit),
+            branches: Vec::new(),
+            next_step_id: count,
+        })
+    }
+}
+
+impl<N: Nodal, E: DirEdge> HistoryDeque<N, E> {
+    pub(super) fn fill_missing_records(&mut self) {
+        if self.records.len() != self.changes.len() {
+            let records = self.changes.iter().map(|change| change.record(None)).collect();
+            self.records = Deque::from_vec(records, self.changes.capacity());
+        }
+    }
+
+    /// Appends `change`, or merges it into the newest entry if automatic coalescing admits it;
+    /// returns the oldest entry if it was dropped to make room
+    fn push(&mut self, change: GraphChange<N, E>, record: ChangeRecord) -> Option<GraphChange<N, E>> {
+        if self.merges(&record)
+            && let Some(newest) = self.records.back_mut()
+        {
+            newest.timestamp_ms = record.timestamp_ms;
+            let earlier = self.changes.pop_back().unwrap();
+            self.changes.push_back(merge_changes(earlier, change));
+            self.newest_run += 1;
+            return None;
+        }
+        self.records.push_back(record);
+        self.newest_run = 1;
+        let siblings = std::mem::take(&mut self.branches);
+        let links = self.new_links(siblings);
+        self.links.push_back(links);
+        self.changes.push_back(change)
+    }
+
+    /// Whether `push()` would merge a change described by `record` into the newest entry
+    fn merges(&self, record: &ChangeRecord) -> bool {
+        self.auto_coalesce.is_some_and(|window| {
+            (1..window.max_changes).contains(&self.newest_run)
+                && self.branches.is_empty()
+                && self.records.back().is_some_and(|newest| window.admits(newest, record))
+        })
+    }
+
+    /// Whether registering `change` now would drop the oldest entry to make room
+    pub(super) fn drops_oldest(&self, change: &GraphChange<N, E>) -> bool {
+        self.changes.len() == self.changes.capacity() && !self.merges(&change.record(now_ms()))
+    }
+
+    /// Merges each run of consecutive changes `window` admits into one entry, which keeps the
+    /// undo tree id of its first change and takes the timestamp of its last. A change with side
+    /// branches in the undo tree is never merged into the one before it.
+    /// Returns how many entries were merged away.
+    pub fn coalesce(&mut self, window: CoalesceWindow) -> usize {
+        self.fill_missing_records();
+        let limit = self.changes.capacity();
+        let count = self.changes.len();
+        let mut changes: Vec<GraphChange<N, E>> = Vec::with_capacity(count);
+        let mut records: Vec<ChangeRecord> = Vec::with_capacity(count);
+        let mut links: Vec<StepLinks<N, E>> = Vec::with_capacity(count);
+        let mut run = 0;
+        while let (Some(change), Some(record), Some(step_links)) = (self.changes.pop_front(), self.records.pop_front(), self.links.pop_front()) {
+            if let Some(newest) = records.last_mut()
+                && run < window.max_changes
+                && step_links.siblings.is_empty()
+                && window.admits(newest, &record)
+            {
+                newest.timestamp_ms = record.timestamp_ms;
+                let earlier = changes.pop().unwrap();
+                changes.push(merge_changes(earlier, change));
+                run += 1;
+            } else {
+                changes.push(change);
+                records.push(record);
+                links.push(step_links);
+                run = 1;
+            }
+        }
+        let merged = count - changes.len();
+        self.changes = Deque::from_vec(changes, limit);
+        self.records = Deque::from_vec(records, limit);
+        self.links = Deque::from_vec(links, limit);
+        self.newest_run = 0;
+        merged
+    }
+}
+
+// Saved in the same form as `SavedHistory`
+#[cfg(feature = "schema")]
+impl<N: schemars::JsonSchema, E: schemars::JsonSchema> schemars::JsonSchema for HistoryDeque<N, E> {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        SavedHistory::<N, E>::schema_name()
+    }
+    fn json_schema(generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        SavedHistory::<N, E>::json_schema(generator)
     }
 }
 
@@ -28,11 +337,31 @@ impl<N, E> Debug for HistoryDeque<N, E> {
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+fn now_ms() -> Option<u64> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|elapsed| elapsed.as_millis() as u64)
+}
+
+#[cfg(all(feature = "web", target_arch = "wasm32"))]
+fn now_ms() -> Option<u64> {
+    let performance = web_sys::window()?.performance()?;
+    Some((performance.time_origin() + performance.now()) as u64)
+}
+
+// SystemTime::now() panics on wasm32, and without web-sys there is no other clock
+#[cfg(all(not(feature = "web"), target_arch = "wasm32"))]
+fn now_ms() -> Option<u64> {
+    None
+}
+
 /// Trait for implementing optional cache usage.
 /// 
 /// Trait is required for DiGraph, but default implementation does nothing
 /// if mut_history() returns None.
-pub(super) trait ChangeCache<N: Nodal, E: DirEdge> {
+pub(crate) trait ChangeCache<N: Nodal, E: DirEdge> {
 
     /// Points to deque of GraphChange variants, for ease of undoing operations
     /// 
@@ -42,23 +371,32 @@ pub(super) trait ChangeCache<N: Nodal, E: DirEdge> {
     /// Erases all entries but keeps the same max length
     fn clear_history(&mut self) {
         if let Some(hist_deq) = self.mut_history() {
-            hist_deq.0.clear();
+            hist_deq.changes.clear();
+            hist_deq.records.clear();
+            hist_deq.newest_run = 0;
+            hist_deq.links.clear();
+            hist_deq.branches.clear();
         }
     }
 
     /// Adds change variant to HistoryDeque; returns oldest item in HistoryDeque if at capacity
     fn register_change(&mut self, change: GraphChange<N, E>) -> Option<GraphChange<N, E>> {
         if let Some(hist_deq) = self.mut_history() {
-            let first_in = hist_deq.0.push_back(change);
-            return first_in;
+            let record = change.record(now_ms());
+            return hist_deq.push(change, record);
         }
         None
     }
 
-    /// Removes and returns most recent change variant, or None if HistoryDeque is empty
-    fn pop_change(&mut self) -> Option<GraphChange<N, E>> {
+    /// Removes and returns most recent change variant with its record, or None if HistoryDeque is empty
+    fn pop_change(&mut self) -> Option<(GraphChange<N, E>, ChangeRecord)> {
         if let Some(hist_deq) = self.mut_history() {
-            return hist_deq.0.pop_back();
+            let change = hist_deq.changes.pop_back()?;
+            hist_deq.newest_run = 0;
+            let record = hist_deq.records
+                .pop_back()
+                .unwrap_or_else(|| change.record(None));
+            return Some((change, record));
         }
         None
     }
@@ -72,45 +410,241 @@ impl<N: Nodal, E: DirEdge> ChangeCache<N, E> for DiGraph<N, E> {
 }
 
 impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
-    pub fn undo(&mut self) -> Result<(), &'static str> {
-        if let Some(change_to_reverse) = self.pop_change() {
-            match change_to_reverse {
-                GraphChange::AddNode(node) => {
-                    self.remove_node_unregistered(node.node_id());
-                },
-                GraphChange::RemoveNode(node, edges) => {
-                    self.insert_node_unregistered(node);
-                    for edge in edges.into_iter() {
-                        self.insert_edge_unregistered(edge);
-                    }
-                },
-                GraphChange::AddEdge(edge) => {
-                    let edge_index = self.edge_index(edge.start_id(), edge.end_id()).unwrap();
-                    self.remove_edge_unregistered(edge_index);
-                },
-                GraphChange::AddEdgeWith(edge, new_start, new_end) => {
-                    if let Some(node_id) = new_start {
-                        self.remove_node_unregistered(node_id);
-                    }
-                    if let Some(node_id) = new_end {
-                        self.remove_node_unregistered(node_id);
-                    }
-                    if let Some(edge_index) = self.edge_index(edge.start_id(), edge.end_id()) {
-                        // This should not trigger if either new_start or new_end is Some(node_id)
-                        self.remove_edge_unregistered(edge_index);
-                    }
-                },
-                GraphChange::RemoveEdge(edge) => {
-                    self.insert_edge_unregistered(edge);
-                },
-                GraphChange::InsertNodeAlongEdge(node, edge) => {
-                    self.remove_node_unregistered(node.node_id());
-                    self.insert_edge_unregistered(edge);
-                },
-                GraphChange::Failure(msg) => return Err(msg), // should be impossible with how mut_history is set up.
+
+    /// Chooses whether the (bounded) undo history is saved along with the graph.
+    ///
+    /// Off by default. Graphs loaded with a saved history keep this setting on.
+    pub fn set_history_persistence(&mut self, persist: bool) {
+        self.undo_history.persist = persist;
+    }
+
+    pub fn persists_history(&self) -> bool {
+        self.undo_history.persist
+    }
+
+    /// Forgets every change in the undo history and every branch of the undo tree, so that
+    /// nothing can be undone or redone (e.g. once the graph is saved); the history keeps its
+    /// length limit and other settings.
+    pub fn clear_undo_history(&mut self) {
+        self.clear_history();
+    }
+
+    /// Merges runs of consecutive changes to the same nodes and edges in the undo history into
+    /// single steps, as `window` allows, and returns how many steps were merged away.
+    pub fn coalesce_history(&mut self, window: CoalesceWindow) -> usize {
+        self.undo_history.coalesce(window)
+    }
+
+    /// Chooses whether each new change is merged into the previous undo step when `window`
+    /// allows (see `coalesce_history()`), e.g. to undo a whole drag at once. Off by default.
+    ///
+    /// Steps already in the history are left alone, as is the step before an `undo()`.
+    /// The setting is not saved with the graph.
+    pub fn set_history_coalescing(&mut self, window: Option<CoalesceWindow>) {
+        self.undo_history.auto_coalesce = window;
+        self.undo_history.newest_run = 0;
+    }
+
+    pub fn history_coalescing(&self) -> Option<CoalesceWindow> {
+        self.undo_history.auto_coalesce
+    }
+
+    /// Describes the change the next `undo()` would reverse, if any
+    pub fn last_change(&self) -> Option<&ChangeRecord> {
+        self.undo_history.records.back()
+    }
+
+    /// Describes every change in the undo history, oldest first
+    pub fn history_iter(&self) -> impl DoubleEndedIterator<Item = &ChangeRecord> {
+        self.undo_history.records.iter()
+    }
+
+    /// Reverses the most recent change, and returns it. The change stays in the undo tree as a
+    /// branch off the state it leaves, which `redo()` or `switch_branch()` can make again.
+    ///
+    /// Returns `Ok(None)` if there is nothing to undo, or an error in strict mode (see `set_strict()`).
+    pub fn undo(&mut self) -> Result<Option<UndoneChange<N, E>>, GraphError> {
+        trace_span!("undo");
+        let Some((change, record)) = self.pop_change() else {
+            if self.strict.0 {
+                return Err("Nothing to undo.".into());
             }
+            return Ok(None);
+        };
+        let redo = self.reverse_change(change.clone())?;
+        // Undoing an addition removes what was added
+        let redo = self.forget_removed(redo);
+        self.undo_history.branch_off(change.clone(), redo, record.clone());
+        self.refresh_acyclic_order();
+        self.count_undo();
+        #[cfg(feature = "crdt")]
+        self.stamp_change(&change);
+        Ok(Some(UndoneChange { change, record }))
+    }
+
+    /// Makes the most recently undone change again (the last of `redo_branches()`), and
+    /// describes it. Redone changes are checked and counted like new edits (DAG mode,
+    /// constraints, metrics), but keep their undo tree id.
+    ///
+    /// Returns `Ok(None)` if there is nothing to redo, or an error in strict mode (see `set_strict()`).
+    pub fn redo(&mut self) -> Result<Option<ChangeRecord>, GraphError> {
+        trace_span!("redo");
+        if self.undo_history.branches.is_empty() {
+            if self.strict.0 {
+                return Err("Nothing to redo.".into());
+            }
+            return Ok(None);
+        }
+        self.redo_branch(self.undo_history.branches.len() - 1)?;
+        Ok(self.last_change().cloned())
+    }
+
+    /// Undo tree id and description of each change that `redo()` or `switch_branch()` can make
+    /// again from the current state, oldest undone first. Undoing a change and then making
+    /// another keeps both here once the new one is undone too.
+    pub fn redo_branches(&self) -> Vec<(usize, &ChangeRecord)> {
+        self.undo_history.branches.iter().map(|branch| (branch.id, &branch.record)).collect()
+    }
+
+    /// Undo tree id of the change the next `undo()` would reverse, if any
+    pub fn last_change_id(&self) -> Option<usize> {
+        self.undo_history.links.back().map(|links| links.id)
+    }
+
+    /// Moves the graph to the state right after the change with undo tree id `id`, undoing
+    /// and redoing as needed, e.g. to bring back edits abandoned by undoing and trying
+    /// something else. Ids stay valid until the change drops out of the history.
+    ///
+    /// If no change in the undo tree has this id, an error is returned. If a redone change is
+    /// refused (e.g. by a constraint added since), the graph is left at the state before it
+    /// and the error is returned.
+    pub fn switch_branch(&mut self, id: usize) -> Result<(), GraphError> {
+        trace_span!("switch_branch", id);
+        let history = &self.undo_history;
+        let count = history.links.len();
+        let (undos, path) = if let Some(position) = history.links.iter().position(|links| links.id == id) {
+            (count - 1 - position, Vec::new())
+        } else if let Some(path) = branch_path(&history.branches, id) {
+            (0, path)
+        } else if let Some((position, path)) = history.links.iter().enumerate().rev()
+            .find_map(|(position, links)| Some((position, branch_path(&links.siblings, id)?)))
+        {
+            (count - position, path)
+        } else {
+            return Err("No change in the undo tree with this id.".into());
+        };
+        for _ in 0..undos {
+            self.undo()?;
+        }
+        for branch_id in path {
+            let position = self.undo_history.branches.iter().position(|branch| branch.id == branch_id).unwrap();
+            self.redo_branch(position)?;
+        }
+        Ok(())
+    }
+
+    /// Makes the change of the branch at `position` among the current ones again, registered
+    /// under its old id; on failure the branch is put back
+    fn redo_branch(&mut self, position: usize) -> Result<(), GraphError> {
+        let branch = self.undo_history.branches.remove(position);
+        self.begin_change();
+        if let Err(error) = self.reverse_change(branch.redo.clone()) {
+            self.end_change();
+            self.undo_history.branches.insert(position, branch);
+            return Err(error);
+        }
+        if let Err(error) = self.commit_change(branch.change.clone()) {
+            self.undo_history.branches.insert(position, branch);
+            return Err(error);
         }
+        let history = &mut self.undo_history;
+        history.newest_run = 0;
+        if let Some(links) = history.links.back_mut() {
+            links.id = branch.id;
+        }
+        history.branches = branch.branches;
         Ok(())
     }
 
+    /// Applies the inverse of `change`, without registering anything in the history, and
+    /// returns a change whose inverse makes `change` again
+    pub(super) fn reverse_change(&mut self, change: GraphChange<N, E>) -> Result<GraphChange<N, E>, GraphError> {
+        let redo = match change {
+            GraphChange::AddNode(node) => {
+                GraphChange::RemoveNode(self.remove_node_unregistered(node.node_id()), Vec::new())
+            },
+            GraphChange::RemoveNode(node, edges) => {
+                self.insert_node_unregistered(node.clone());
+                for edge in edges.into_iter() {
+                    self.insert_edge_unregistered(edge);
+                }
+                GraphChange::AddNode(node)
+            },
+            GraphChange::AddEdge(edge) => {
+                let edge_index = self.edge_index(edge.start_id(), edge.end_id()).unwrap();
+                self.remove_edge_unregistered(edge_index);
+                GraphChange::RemoveEdge(edge)
+            },
+            GraphChange::AddEdgeWith(edge, new_start, new_end) => {
+                // Undone last to first, so that the edge comes back after its new nodes
+                let mut redo = vec![GraphChange::RemoveEdge(edge.clone())];
+                if let Some(node_id) = new_start {
+                    redo.push(GraphChange::RemoveNode(self.remove_node_unregistered(node_id), Vec::new()));
+                }
+                if let Some(node_id) = new_end {
+                    redo.push(GraphChange::RemoveNode(self.remove_node_unregistered(node_id), Vec::new()));
+                }
+                if let Some(edge_index) = self.edge_index(edge.start_id(), edge.end_id()) {
+                    // This should not trigger if either new_start or new_end is Some(node_id)
+                    self.remove_edge_unregistered(edge_index);
+                }
+                GraphChange::Batch(redo)
+            },
+            GraphChange::RemoveEdge(edge) => {
+                self.insert_edge_unregistered(edge.clone());
+                GraphChange::AddEdge(edge)
+            },
+            GraphChange::InsertNodeAlongEdge(node, edge) => {
+                let new_edges = self.edges_incident_on(node.node_id()).cloned().collect();
+                let new_node = self.remove_node_unregistered(node.node_id());
+                self.insert_edge_unregistered(edge.clone());
+                GraphChange::Batch(vec![GraphChange::AddEdge(edge), GraphChange::RemoveNode(new_node, new_edges)])
+            },
+            GraphChange::ReplaceNode(node, new_id) => {
+                let old_id = node.node_id();
+                GraphChange::ReplaceNode(self.replace_node_unregistered(new_id, node), old_id)
+            },
+            GraphChange::ReverseEdge(start_id, end_id) => {
+                let edge_index = self.edge_index(end_id, start_id).unwrap();
+                self.flip_edge_unregistered(edge_index);
+                GraphChange::ReverseEdge(end_id, start_id)
+            },
+            GraphChange::ReverseAllEdges => {
+                self.flip_all_edges_unregistered();
+                GraphChange::ReverseAllEdges
+            },
+            GraphChange::ReorderSuccessors(node_id, previous_order) => {
+                GraphChange::ReorderSuccessors(node_id, self.set_successor_order_unregistered(node_id, previous_order))
+            },
+            GraphChange::SetGroup(group_id, previous_group) => {
+                GraphChange::SetGroup(group_id, self.set_group_unregistered(group_id, previous_group))
+            },
+            GraphChange::SetNodeExtras(node_id, previous_extras) => {
+                GraphChange::SetNodeExtras(node_id, self.set_node_extras_unregistered(node_id, previous_extras))
+            },
+            GraphChange::SetEdgeTags(start_id, end_id, previous_tags) => {
+                GraphChange::SetEdgeTags(start_id, end_id, self.set_edge_tags_unregistered(start_id, end_id, previous_tags))
+            },
+            GraphChange::Batch(changes) => {
+                let redo = changes.into_iter()
+                    .rev()
+                    .map(|change| self.reverse_change(change))
+                    .collect::<Result<Vec<_>, _>>()?;
+                GraphChange::Batch(redo)
+            },
+            GraphChange::Failure(msg) => return Err(msg.into()), // should be impossible with how mut_history is set up.
+        };
+        Ok(redo)
+    }
+
 }
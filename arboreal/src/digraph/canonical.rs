@@ -0,0 +1,133 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use crate::graph_base::graph_components::{GraphError, Id, IdSet};
+use super::{Nodal, DirEdge, DiGraph, ChangeCache};
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+
+    /// Renumbers the nodes `0, 1, 2...` in breadth-first order, and sorts the edges, so that graphs
+    /// differing only in ids, insertion order or history save to byte-identical files (e.g. for
+    /// content hashing). Returns each old id mapped to its new id.
+    ///
+    /// The search starts from the sources, then from any nodes left over (e.g. in a cycle with
+    /// no way in). Successors are visited in the order set by `set_successor_order()` if there
+    /// is one; otherwise, and among sources, nodes are taken in order of their payloads, with
+    /// their old ids breaking the remaining ties.
+    ///
+    /// Pins, degree limits, successor orders, tags, the entry and exit, and dirty flags follow their
+    /// nodes. The undo history and reserved ids are cleared, and handles made before the call must
+    /// not be used after it.
+    ///
+    /// If a constraint refuses the new ids (see `add_constraint()`), CRDT tracking is enabled,
+    /// or there are grouped nodes (see `group_nodes()`), nothing is renumbered and an error is returned.
+    pub fn canonicalize(&mut self) -> Result<HashMap<Id, Id>, GraphError> {
+        trace_span!("canonicalize", nodes = self.nodes.len(), edges = self.edges.len());
+        #[cfg(feature = "crdt")]
+        if self.crdt_replica().is_some() {
+            return Err("Renumbering would break merges; CRDT tracking is enabled.".into());
+        }
+        if !self.groups.is_empty() {
+            return Err("Renumbering would break groups; ungroup the nodes first.".into());
+        }
+        let id_map = self.canonical_id_map();
+        self.renumber_unregistered(&id_map);
+        if let Some(name) = self.constraints.first_violated(self) {
+            let inverse_map = id_map.iter().map(|(&old_id, &new_id)| (new_id, old_id)).collect();
+            self.renumber_unregistered(&inverse_map);
+            return Err(GraphError::ConstraintViolated(name));
+        }
+        self.edges.sort_by_key(|edge| edge.terminal_ids());
+        self.rebuild_neighbors();
+        // Written out in full, leaving out the orders that are just ascending
+        let ordered_ids: Vec<Id> = self.successor_order.keys().copied().collect();
+        for node_id in ordered_ids {
+            let order = self.ordered_successors(node_id).unwrap();
+            match order.is_sorted() {
+                true => self.successor_order.remove(&node_id),
+                false => self.successor_order.insert(node_id, order),
+            };
+        }
+        self.refresh_acyclic_order();
+        self.generations.clear();
+        self.freed_ids.clear();
+        self.reserved_ids.clear();
+        self.clear_history();
+        Ok(id_map)
+    }
+
+    fn canonical_id_map(&self) -> HashMap<Id, Id> {
+        // Payload text with the id blanked out, so that ids only matter when payloads tie
+        let keys: HashMap<Id, (String, Id)> = self.nodes.iter()
+            .map(|(&node_id, node)| {
+                let mut node = node.clone();
+                node.change_id(0);
+                (node_id, (ron::to_string(&node).unwrap_or_default(), node_id))
+            })
+            .collect();
+        let by_key = |ids: &mut Vec<Id>| ids.sort_by(|a, b| keys[a].cmp(&keys[b]));
+
+        let mut sources = self.source_node_ids();
+        by_key(&mut sources);
+        let mut starts = self.all_node_ids();
+        by_key(&mut starts);
+
+        let mut id_map = HashMap::with_capacity(self.nodes.len());
+        let mut visited = IdSet::new();
+        let mut queue = VecDeque::new();
+        for start_id in sources.into_iter().chain(starts) {
+            if !visited.insert(start_id) {
+                continue;
+            }
+            queue.push_back(start_id);
+            while let Some(node_id) = queue.pop_front() {
+                id_map.insert(node_id, id_map.len() as Id);
+                let mut successor_ids = self.ordered_successors(node_id).unwrap();
+                if !self.has_successor_order(node_id) {
+                    by_key(&mut successor_ids);
+                }
+                for successor_id in successor_ids {
+                    if visited.insert(successor_id) {
+                        queue.push_back(successor_id);
+                    }
+                }
+            }
+        }
+        id_map
+    }
+
+    /// Gives every node and edge the new id `id_map` holds for its old one, along with
+    /// everything kept per id (entries for ids no longer in use are dropped).
+    /// Edge order is untouched, so applying the inverse map undoes it.
+    fn renumber_unregistered(&mut self, id_map: &HashMap<Id, Id>) {
+        let renumbered = |id: &Id| id_map[id];
+        let renumbered_if_used = |id: &Id| id_map.get(id).copied();
+        self.nodes = std::mem::take(&mut self.nodes).into_values()
+            .map(|mut node| {
+                node.change_id(renumbered(&node.node_id()));
+                (node.node_id(), node)
+            })
+            .collect();
+        for edge in self.edges.iter_mut() {
+            let (start_id, end_id) = edge.terminal_ids();
+            edge.change_start(renumbered(&start_id));
+            edge.change_end(renumbered(&end_id));
+        }
+        self.rebuild_neighbors();
+        self.successor_order = std::mem::take(&mut self.successor_order).into_iter()
+            .filter_map(|(node_id, order)| {
+                Some((renumbered_if_used(&node_id)?, order.iter().filter_map(renumbered_if_used).collect()))
+            })
+            .collect();
+        self.pinned = self.pinned.iter().filter_map(renumbered_if_used).collect();
+        *self.dirty = self.dirty.iter().filter_map(renumbered_if_used).collect();
+        let renumber_limits = |limits: &BTreeMap<Id, usize>| -> BTreeMap<Id, usize> {
+            limits.iter()
+                .filter_map(|(node_id, &limit)| Some((renumbered_if_used(node_id)?, limit)))
+                .collect()
+        };
+        self.max_out_degrees = renumber_limits(&self.max_out_degrees);
+        self.max_in_degrees = renumber_limits(&self.max_in_degrees);
+        self.rename_endpoints(|id| renumbered_if_used(&id));
+        self.renumber_tags(id_map, false);
+    }
+}
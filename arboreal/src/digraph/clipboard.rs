@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::graph_base::graph_components::{GraphChange, GraphError, Id, ID_MAX};
+use super::{Nodal, DirEdge, DiGraph};
+
+/// Copies of some nodes and the edges between them, from `DiGraph::copy_subgraph()`.
+///
+/// Serializable, so it can travel through a system clipboard as text.
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct GraphClipboard<N, E> {
+    nodes: Vec<N>,
+    edges: Vec<E>,
+}
+
+impl<N: Nodal, E: DirEdge> GraphClipboard<N, E> {
+    /// Copied nodes, ordered by their original id
+    pub fn nodes(&self) -> &[N] {
+        &self.nodes
+    }
+    /// Copied edges, with their original terminals
+    pub fn edges(&self) -> &[E] {
+        &self.edges
+    }
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+/// How `DiGraph::paste()` picks ids for the pasted nodes
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum PasteIds {
+    /// Lowest ids neither in use nor reserved, handed out in the order of the original ids
+    Auto,
+    /// Original id plus this offset
+    Offset(Id),
+}
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+
+    /// Copies the nodes in `node_ids` (unknown ids are skipped) and every edge between them.
+    /// Edges to nodes left out are not copied.
+    pub fn copy_subgraph(&self, node_ids: &[Id]) -> GraphClipboard<N, E> {
+        let mut ids: Vec<Id> = node_ids.iter()
+            .copied()
+            .filter(|id| self.nodes.contains_key(id))
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        let edges = self.edges.iter()
+            .filter(|edge| ids.binary_search(&edge.start_id()).is_ok() && ids.binary_search(&edge.end_id()).is_ok())
+            .cloned()
+            .collect();
+        let nodes = ids.iter().map(|id| self.nodes[id].clone()).collect();
+        GraphClipboard { nodes, edges }
+    }
+
+    /// Inserts fresh copies of the clipboard's nodes under new ids (see `PasteIds`), with their
+    /// edges rewired to the copies, as a single undoable change. Returns each original id
+    /// mapped to the id of its copy.
+    ///
+    /// If an offset id is out of range or already used, or an edge would break a degree limit,
+    /// an error is returned and nothing is pasted; with `PasteIds::Auto`, running out of ids
+    /// gives `GraphError::IdSpaceExhausted`.
+    pub fn paste(&mut self, clipboard: &GraphClipboard<N, E>, ids: PasteIds) -> Result<HashMap<Id, Id>, GraphError> {
+        trace_span!("paste", nodes = clipboard.nodes.len(), edges = clipboard.edges.len());
+        let new_ids: Vec<Id> = match ids {
+            PasteIds::Auto => {
+                let free_ids: Vec<Id> = (0..=ID_MAX)
+                    .filter(|&id| self.id_available(id))
+                    .take(clipboard.nodes.len())
+                    .collect();
+                if free_ids.len() < clipboard.nodes.len() {
+                    return Err(GraphError::IdSpaceExhausted);
+                }
+                free_ids
+            },
+            PasteIds::Offset(offset) => {
+                let mut new_ids = Vec::with_capacity(clipboard.nodes.len());
+                for node in clipboard.nodes.iter() {
+                    let new_id = node.node_id()
+                        .checked_add(offset)
+                        .ok_or(GraphError::Invalid("Pasted id would exceed ID_MAX."))?;
+                    if self.nodes.contains_key(&new_id) {
+                        return Err("Node with this id already exists.".into());
+                    }
+                    new_ids.push(new_id);
+                }
+                new_ids
+            },
+        };
+        let id_map: HashMap<Id, Id> = clipboard.nodes.iter()
+            .map(|node| node.node_id())
+            .zip(new_ids)
+            .collect();
+        let mut edges = Vec::with_capacity(clipboard.edges.len());
+        for edge in clipboard.edges.iter() {
+            let (start_id, end_id) = edge.terminal_ids();
+            let (Some(&new_start), Some(&new_end)) = (id_map.get(&start_id), id_map.get(&end_id)) else {
+                return Err("Clipboard edge refers to a node not in the clipboard.".into());
+            };
+            let mut edge = edge.clone();
+            edge.change_start(new_start);
+            edge.change_end(new_end);
+            edges.push(edge);
+        }
+        let new_terminals: Vec<(Id, Id)> = edges.iter().map(|edge| edge.terminal_ids()).collect();
+        self.check_moved_edge_limits(&[], &new_terminals)?;
+
+        let mut changes = Vec::with_capacity(clipboard.nodes.len() + edges.len());
+        self.begin_change();
+        for node in clipboard.nodes.iter() {
+            let mut node = node.clone();
+            node.change_id(id_map[&node.node_id()]);
+            self.insert_node_unregistered(node.clone());
+            changes.push(GraphChange::AddNode(node));
+        }
+        for edge in edges {
+            self.insert_edge_unregistered(edge.clone());
+            changes.push(GraphChange::AddEdge(edge));
+        }
+        if !changes.is_empty() {
+            self.commit_change(GraphChange::Batch(changes))?;
+        }
+        Ok(id_map)
+    }
+}
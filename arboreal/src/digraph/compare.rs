@@ -0,0 +1,155 @@
+use std::collections::BTreeSet;
+use std::fmt::{Debug, Write};
+
+use crate::graph_base::graph_components::{Id, NodeGroup};
+use super::{Nodal, DirEdge, DiGraph};
+
+/// Panics with `DiGraph::explain_difference()`'s report if two graphs differ.
+///
+/// Unlike `assert_eq!`, undo history and the ordering of internal collections are ignored;
+/// see `explain_difference()` for what is compared.
+#[macro_export]
+macro_rules! assert_graph_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        if let Some(report) = $left.explain_difference(&$right) {
+            panic!("assertion `left == right` failed for graphs\n{}", report);
+        }
+    };
+}
+
+pub(super) fn payload_text<T: serde::Serialize>(payload: &T) -> String {
+    ron::to_string(payload).unwrap_or("<unserializable>".to_string())
+}
+
+/// Pairs in `pairs` but not in `others`, counting repeats (both must be sorted)
+fn sorted_difference(pairs: &[(Id, Id)], others: &[(Id, Id)]) -> Vec<(Id, Id)> {
+    let mut difference = Vec::new();
+    let mut others = others.iter().peekable();
+    for pair in pairs {
+        while others.next_if(|other| *other < pair).is_some() {}
+        if others.next_if_eq(&pair).is_none() {
+            difference.push(*pair);
+        }
+    }
+    difference
+}
+
+/// Reports, for each of `keys` (ascending), whether `what` differs between `left` and `right`
+fn compare_by_key<K: Ord + Debug, V: PartialEq + Debug>(
+    report: &mut String, what: &str, keys: BTreeSet<K>, left: impl Fn(&K) -> V, right: impl Fn(&K) -> V,
+) {
+    for key in keys {
+        let (left, right) = (left(&key), right(&key));
+        if left != right {
+            writeln!(report, "{what} {key:?} differ: {left:?} vs {right:?}").unwrap();
+        }
+    }
+}
+
+fn format_pairs(pairs: &[(Id, Id)]) -> String {
+    let pairs: Vec<String> = pairs
+        .iter()
+        .map(|(start_id, end_id)| format!("{start_id}->{end_id}"))
+        .collect();
+    format!("[{}]", pairs.join(", "))
+}
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+
+    /// Whether both graphs have the same node ids and the same edges (counting parallel edges),
+    /// whatever their payloads, names or metadata. `other` may hold other payload types,
+    /// e.g. a graph from `load_structure_only()`.
+    pub fn same_topology<N2: Nodal, E2: DirEdge>(&self, other: &DiGraph<N2, E2>) -> bool {
+        self.all_node_ids() == other.all_node_ids() && self.all_edge_pairs() == other.all_edge_pairs()
+    }
+
+    /// Returns `None` if both graphs hold the same name, metadata, nodes and edges, and the same
+    /// entry and exit, pins, tags, groups, successor orders and degree limits, or else a readable
+    /// report of every difference (`self` is "left", `other` is "right").
+    ///
+    /// Payloads are compared with `PartialEq` and shown as RON. The undo history, constraints,
+    /// DAG mode, dirty flags, id bookkeeping and CRDT stamps are not compared.
+    pub fn explain_difference(&self, other: &Self) -> Option<String> {
+        let mut report = String::new();
+        if self.name != other.name {
+            writeln!(report, "Names differ: {:?} vs {:?}", self.name, other.name).unwrap();
+        }
+        if self.metadata != other.metadata {
+            writeln!(report, "Metadata differs: {:?} vs {:?}", self.metadata, other.metadata).unwrap();
+        }
+
+        let left_ids = self.all_node_ids();
+        let right_ids = other.all_node_ids();
+        let only_left: Vec<Id> = left_ids.iter().copied().filter(|id| !other.nodes.contains_key(id)).collect();
+        let only_right: Vec<Id> = right_ids.iter().copied().filter(|id| !self.nodes.contains_key(id)).collect();
+        if !only_left.is_empty() {
+            writeln!(report, "Nodes only in left: {only_left:?}").unwrap();
+        }
+        if !only_right.is_empty() {
+            writeln!(report, "Nodes only in right: {only_right:?}").unwrap();
+        }
+        for id in left_ids.iter() {
+            if let (Some(left), Some(right)) = (self.nodes.get(id), other.nodes.get(id))
+                && left != right
+            {
+                writeln!(report, "Node {id} payload differs:\n\tleft:  {}\n\tright: {}",
+                    payload_text(left), payload_text(right)).unwrap();
+            }
+        }
+
+        let left_pairs = self.all_edge_pairs();
+        let right_pairs = other.all_edge_pairs();
+        let only_left = sorted_difference(&left_pairs, &right_pairs);
+        let only_right = sorted_difference(&right_pairs, &left_pairs);
+        if !only_left.is_empty() {
+            writeln!(report, "Edges only in left: {}", format_pairs(&only_left)).unwrap();
+        }
+        if !only_right.is_empty() {
+            writeln!(report, "Edges only in right: {}", format_pairs(&only_right)).unwrap();
+        }
+        for &(start_id, end_id) in left_pairs.iter() {
+            if let (Some(left), Some(right)) = (self.get_edge(start_id, end_id), other.get_edge(start_id, end_id))
+                && left != right
+            {
+                writeln!(report, "Edge {start_id}->{end_id} payload differs:\n\tleft:  {}\n\tright: {}",
+                    payload_text(left), payload_text(right)).unwrap();
+            }
+        }
+
+        if (self.entry, self.exit) != (other.entry, other.exit) {
+            writeln!(report, "Entry and exit differ: {:?} vs {:?}", (self.entry, self.exit), (other.entry, other.exit)).unwrap();
+        }
+        if self.pinned != other.pinned {
+            writeln!(report, "Pinned nodes differ: {:?} vs {:?}", self.pinned, other.pinned).unwrap();
+        }
+        let both = |left: Vec<&Id>, right: Vec<&Id>| left.into_iter().chain(right).copied().collect::<BTreeSet<Id>>();
+        compare_by_key(&mut report, "Tags of node", both(self.node_tags.keys().collect(), other.node_tags.keys().collect()),
+            |id| self.node_tags.get(id), |id| other.node_tags.get(id));
+        let tagged_pairs = |graph: &Self| graph.edge_tags.iter()
+            .flat_map(|(&start_id, ends)| ends.keys().map(move |&end_id| (start_id, end_id)))
+            .collect::<BTreeSet<(Id, Id)>>();
+        compare_by_key(&mut report, "Tags of edge", &tagged_pairs(self) | &tagged_pairs(other),
+            |&(start_id, end_id)| self.edge_tags(start_id, end_id), |&(start_id, end_id)| other.edge_tags(start_id, end_id));
+        compare_by_key(&mut report, "Successor orders of node",
+            both(self.successor_order.keys().collect(), other.successor_order.keys().collect()),
+            |id| self.successor_order.get(id), |id| other.successor_order.get(id));
+        compare_by_key(&mut report, "Out-degree limits of node",
+            both(self.max_out_degrees.keys().collect(), other.max_out_degrees.keys().collect()),
+            |id| self.max_out_degrees.get(id), |id| other.max_out_degrees.get(id));
+        compare_by_key(&mut report, "In-degree limits of node",
+            both(self.max_in_degrees.keys().collect(), other.max_in_degrees.keys().collect()),
+            |id| self.max_in_degrees.get(id), |id| other.max_in_degrees.get(id));
+        for id in both(self.groups.keys().collect(), other.groups.keys().collect()) {
+            let (left, right) = (self.groups.get(&id), other.groups.get(&id));
+            if left != right {
+                let members = |group: Option<&NodeGroup<N, E>>| group.map(NodeGroup::member_ids);
+                writeln!(report, "Group behind supernode {id} differs: members {:?} vs {:?}", members(left), members(right)).unwrap();
+            }
+        }
+
+        match report.is_empty() {
+            true => None,
+            false => Some(report),
+        }
+    }
+}
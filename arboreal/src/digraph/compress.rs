@@ -0,0 +1,70 @@
+use std::borrow::Cow;
+use std::io;
+#[cfg(feature = "gzip")]
+use std::io::{Read, Write};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Formats for `FileIO::save_to_file_compressed()`; each needs the cargo feature of the same name
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Compression {
+    #[cfg(feature = "gzip")]
+    Gzip,
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+pub(super) fn compress(bytes: &[u8], compression: Compression) -> io::Result<Vec<u8>> {
+    match compression {
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => {
+            let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(bytes)?;
+            encoder.finish()
+        },
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => zstd::encode_all(bytes, 0),
+    }
+}
+
+/// Returns `bytes` unpacked if they start with the magic bytes of a supported format,
+/// or unchanged otherwise. Compressed input without the matching feature gives an error.
+pub(super) fn decompress_if_needed(bytes: &[u8]) -> io::Result<Cow<'_, [u8]>> {
+    if bytes.starts_with(&GZIP_MAGIC) {
+        return gunzip(bytes).map(Cow::Owned);
+    }
+    if bytes.starts_with(&ZSTD_MAGIC) {
+        return unzstd(bytes).map(Cow::Owned);
+    }
+    Ok(Cow::Borrowed(bytes))
+}
+
+#[cfg(not(all(feature = "gzip", feature = "zstd")))]
+fn missing_feature(format: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("Input is {format}-compressed; enable the \"{format}\" feature to read it."),
+    )
+}
+
+#[cfg(feature = "gzip")]
+fn gunzip(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    let mut unpacked = Vec::new();
+    flate2::read::GzDecoder::new(bytes).read_to_end(&mut unpacked)?;
+    Ok(unpacked)
+}
+#[cfg(not(feature = "gzip"))]
+fn gunzip(_bytes: &[u8]) -> io::Result<Vec<u8>> {
+    Err(missing_feature("gzip"))
+}
+
+#[cfg(feature = "zstd")]
+fn unzstd(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    zstd::decode_all(bytes)
+}
+#[cfg(not(feature = "zstd"))]
+fn unzstd(_bytes: &[u8]) -> io::Result<Vec<u8>> {
+    Err(missing_feature("zstd"))
+}
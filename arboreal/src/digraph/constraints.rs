@@ -0,0 +1,162 @@
+use std::collections::HashSet;
+
+use crate::graph_base::graph_components::{GraphChange, GraphError, Id, LabeledEdge};
+use super::{Nodal, DirEdge, DiGraph};
+
+/// Graph-level invariant, checked on every edit; see `DiGraph::add_constraint()`.
+///
+/// `is_satisfied()` sees the graph as it would be after the edit, and the edit is refused
+/// with `GraphError::ConstraintViolated(name())` if it returns false.
+pub trait GraphConstraint<N, E>: Send + Sync {
+    /// Short name, returned in `GraphError::ConstraintViolated`
+    fn name(&self) -> &'static str;
+    fn is_satisfied(&self, graph: &DiGraph<N, E>) -> bool;
+}
+
+/// No cycles, self-loops included
+pub struct Acyclic;
+
+impl<N: Nodal, E: DirEdge> GraphConstraint<N, E> for Acyclic {
+    fn name(&self) -> &'static str {
+        "acyclic"
+    }
+    fn is_satisfied(&self, graph: &DiGraph<N, E>) -> bool {
+        graph.topological_order().is_some()
+    }
+}
+
+/// At most one node with no incoming edges (so an empty graph passes)
+pub struct SingleSource;
+
+impl<N: Nodal, E: DirEdge> GraphConstraint<N, E> for SingleSource {
+    fn name(&self) -> &'static str {
+        "single source"
+    }
+    fn is_satisfied(&self, graph: &DiGraph<N, E>) -> bool {
+        graph.source_node_ids().len() <= 1
+    }
+}
+
+/// At most this many nodes
+pub struct MaxNodes(pub usize);
+
+impl<N: Nodal, E: DirEdge> GraphConstraint<N, E> for MaxNodes {
+    fn name(&self) -> &'static str {
+        "max nodes"
+    }
+    fn is_satisfied(&self, graph: &DiGraph<N, E>) -> bool {
+        graph.nodes.len() <= self.0
+    }
+}
+
+/// No edge from the first id of any pair to the second
+pub struct ForbiddenEdges(pub Vec<(Id, Id)>);
+
+impl<N: Nodal, E: DirEdge> GraphConstraint<N, E> for ForbiddenEdges {
+    fn name(&self) -> &'static str {
+        "forbidden edges"
+    }
+    fn is_satisfied(&self, graph: &DiGraph<N, E>) -> bool {
+        !self.0.iter().any(|&(start_id, end_id)| graph.edge_index(start_id, end_id).is_some())
+    }
+}
+
+/// No two edges leaving the same node share a label, so that transitions are deterministic
+pub struct UniqueEdgeLabels;
+
+impl<N: Nodal, E: DirEdge + LabeledEdge> GraphConstraint<N, E> for UniqueEdgeLabels {
+    fn name(&self) -> &'static str {
+        "unique edge labels"
+    }
+    fn is_satisfied(&self, graph: &DiGraph<N, E>) -> bool {
+        let mut labels: HashSet<(Id, &str)> = HashSet::with_capacity(graph.edges.len());
+        graph.edges.iter().all(|edge| labels.insert((edge.start_id(), edge.label())))
+    }
+}
+
+/// Constraints held by a graph. They are not data, so they are never saved or compared.
+pub(super) struct ConstraintSet<N, E>(Vec<Box<dyn GraphConstraint<N, E>>>);
+
+impl<N, E> ConstraintSet<N, E> {
+    pub(super) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Name of the first constraint `graph` breaks, if any
+    pub(super) fn first_violated(&self, graph: &DiGraph<N, E>) -> Option<&'static str> {
+        self.0.iter()
+            .find(|constraint| !constraint.is_satisfied(graph))
+            .map(|constraint| constraint.name())
+    }
+}
+
+impl<N, E> Default for ConstraintSet<N, E> {
+    fn default() -> Self {
+        Self(Vec::new())
+    }
+}
+
+impl<N, E> PartialEq for ConstraintSet<N, E> {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+
+    /// Adds an invariant that every later edit must keep (e.g. `Acyclic`, `MaxNodes(50)`);
+    /// an edit that would break it is rolled back and returns `GraphError::ConstraintViolated`.
+    ///
+    /// Constraints are not saved with the graph, and `undo()` does not check them.
+    /// A refused edit leaves the graph as it was, so handles to a node whose removal was
+    /// refused stay live.
+    ///
+    /// If the graph already breaks `constraint`, it is not added and an error is returned.
+    pub fn add_constraint(&mut self, constraint: Box<dyn GraphConstraint<N, E>>) -> Result<(), GraphError> {
+        if !constraint.is_satisfied(self) {
+            return Err(GraphError::ConstraintViolated(constraint.name()));
+        }
+        self.constraints.0.push(constraint);
+        Ok(())
+    }
+
+    /// Removes every constraint with this name, returning whether there were any
+    pub fn remove_constraint(&mut self, name: &str) -> bool {
+        let count = self.constraints.0.len();
+        self.constraints.0.retain(|constraint| constraint.name() != name);
+        self.constraints.0.len() != count
+    }
+
+    /// Names of the graph's constraints, in the order they were added
+    pub fn constraint_names(&self) -> Vec<&'static str> {
+        self.constraints.0.iter().map(|constraint| constraint.name()).collect()
+    }
+
+    /// Registers `change`, already applied, unless it closes a cycle in DAG mode, breaks
+    /// a constraint, or (in strict mode) would push the oldest change out of a full undo
+    /// history; in that case it is rolled back instead (exactly, if `begin_change()` was
+    /// called before applying it) and an error is returned.
+    pub(super) fn commit_change(&mut self, change: GraphChange<N, E>) -> Result<(), GraphError> {
+        if let Err(error) = self.update_acyclic_order(&change) {
+            self.roll_back(change)?;
+            return Err(error);
+        }
+        if let Some(name) = self.constraints.first_violated(self) {
+            self.roll_back(change)?;
+            self.refresh_acyclic_order();
+            return Err(GraphError::ConstraintViolated(name));
+        }
+        if self.strict.0 && self.undo_history.drops_oldest(&change) {
+            self.roll_back(change)?;
+            self.refresh_acyclic_order();
+            return Err("Undo history full; clear_undo_history() makes room.".into());
+        }
+        self.end_change();
+        let change = self.forget_removed(change);
+        #[cfg(feature = "crdt")]
+        self.stamp_change(&change);
+        self.count_mutation(&change);
+        self.keep_in_history(change);
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+use std::collections::HashSet;
+
+use crate::graph_base::{graph_algo::{self, ProgressFn}, graph_components::{GraphError, Id}};
+use super::{Nodal, DirEdge, DiGraph};
+
+/// Nodes and edges that none of a set of paths went through; see `DiGraph::path_coverage()`
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct CoverageReport {
+    /// Ascending
+    pub unvisited_nodes: Vec<Id>,
+    /// (start, end), ascending
+    pub unvisited_edges: Vec<(Id, Id)>,
+    pub node_count: usize,
+    /// Parallel edges count once, as one path step covers them all
+    pub edge_count: usize,
+}
+
+impl CoverageReport {
+    /// Whether every node and edge was visited
+    pub fn is_complete(&self) -> bool {
+        self.unvisited_nodes.is_empty() && self.unvisited_edges.is_empty()
+    }
+
+    /// Share of nodes visited, from 0.0 to 1.0 (1.0 for an empty graph)
+    pub fn node_fraction(&self) -> f64 {
+        match self.node_count {
+            0 => 1.0,
+            count => (count - self.unvisited_nodes.len()) as f64 / count as f64,
+        }
+    }
+
+    /// Same as `node_fraction()`, for edges
+    pub fn edge_fraction(&self) -> f64 {
+        match self.edge_count {
+            0 => 1.0,
+            count => (count - self.unvisited_edges.len()) as f64 / count as f64,
+        }
+    }
+}
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+
+    /// Up to `limit` paths from a node with no incoming edges to one with no outgoing edges
+    /// (e.g. every playthrough of a story from an opening to an ending), each visiting a node at
+    /// most once; see `graph_algo::source_to_sink_paths()` for the order.
+    pub fn source_to_sink_paths(&self, limit: usize) -> Vec<Vec<Id>> {
+        graph_algo::source_to_sink_paths(self, limit)
+    }
+
+    /// Same as `source_to_sink_paths()`, reporting each source whose paths are done, and checking
+    /// for cancellation at each path found; see `graph_algo::Progress`.
+    ///
+    /// If `progress` cancels, `GraphError::Cancelled` is returned.
+    pub fn source_to_sink_paths_with_progress(&self, limit: usize, progress: ProgressFn) -> Result<Vec<Vec<Id>>, GraphError> {
+        graph_algo::source_to_sink_paths_with_progress(self, limit, progress)
+    }
+
+    /// Reports the nodes and edges that no path in `visited` went through, e.g. dialogue lines
+    /// no playtest has reached. Each path is a list of node ids, with an edge between each pair
+    /// of neighbors; ids and steps that are not in the graph are ignored.
+    pub fn path_coverage(&self, visited: &[Vec<Id>]) -> CoverageReport {
+        let nodes: HashSet<Id> = visited.iter().flatten().copied().collect();
+        let edges: HashSet<(Id, Id)> = visited.iter()
+            .flat_map(|path| path.windows(2).map(|step| (step[0], step[1])))
+            .collect();
+        let mut unvisited_edges = self.all_edge_pairs();
+        unvisited_edges.dedup();
+        let edge_count = unvisited_edges.len();
+        unvisited_edges.retain(|edge| !edges.contains(edge));
+        CoverageReport {
+            unvisited_nodes: self.all_node_ids().into_iter().filter(|id| !nodes.contains(id)).collect(),
+            unvisited_edges,
+            node_count: self.nodes.len(),
+            edge_count,
+        }
+    }
+}
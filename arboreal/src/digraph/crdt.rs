@@ -0,0 +1,207 @@
+//! Last-writer-wins tracking of node and edge presence, so that replicas of a graph
+//! edited concurrently converge when merged; see `DiGraph::merge_concurrent()`.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::graph_base::graph_components::{GraphChange, GraphError, Id};
+use super::{Nodal, DirEdge, DiGraph, ChangeCache};
+
+/// When, and on which replica, a node or edge last changed. Later stamps win a merge;
+/// Lamport time decides first, then the replica id.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Stamp {
+    pub lamport: u64,
+    pub replica: u32,
+}
+
+/// Stamps for every node id and edge (start, end) pair ever tracked, removed ones included
+#[derive(PartialEq, Debug, Default, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub(super) struct CrdtState {
+    replica: Option<u32>,
+    clock: u64,
+    nodes: BTreeMap<Id, Stamp>,
+    /// Keyed by start id, then end id
+    edges: BTreeMap<Id, BTreeMap<Id, Stamp>>,
+}
+
+impl CrdtState {
+    pub(super) fn is_unused(&self) -> bool {
+        self.replica.is_none()
+    }
+
+    fn edge_stamp(&self, (start_id, end_id): (Id, Id)) -> Option<Stamp> {
+        self.edges.get(&start_id)?.get(&end_id).copied()
+    }
+
+    fn set_edge_stamp(&mut self, (start_id, end_id): (Id, Id), stamp: Stamp) {
+        self.edges.entry(start_id).or_default().insert(end_id, stamp);
+    }
+}
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+
+    /// Starts stamping every registered edit (and undo) for `merge_concurrent()`, as replica `replica`.
+    /// Each copy of the graph being edited concurrently needs its own replica id.
+    ///
+    /// Nodes and edges already in the graph get the stamp `(0, 0)`, so replicas should be
+    /// copies of one starting graph.
+    pub fn enable_crdt(&mut self, replica: u32) {
+        let base = Stamp { lamport: 0, replica: 0 };
+        for &node_id in self.nodes.keys() {
+            self.crdt.nodes.entry(node_id).or_insert(base);
+        }
+        for edge in self.edges.iter() {
+            if self.crdt.edge_stamp(edge.terminal_ids()).is_none() {
+                self.crdt.set_edge_stamp(edge.terminal_ids(), base);
+            }
+        }
+        self.crdt.replica = Some(replica);
+    }
+
+    /// Replica id given to `enable_crdt()`, if enabled
+    pub fn crdt_replica(&self) -> Option<u32> {
+        self.crdt.replica
+    }
+
+    /// Brings in concurrent edits from `other`, another replica of the same graph: for each
+    /// node id, and each edge (start, end) pair, whichever side changed it last wins. Merging
+    /// in either direction, or repeatedly, gives the same nodes and edges.
+    ///
+    /// Edges whose terminals lost are dropped. Tags, entry and exit designations, successor
+    /// orders and degree limits of nodes and edges the merge removes are dropped with them,
+    /// as with a registered removal. Payload edits count only when registered
+    /// (e.g. `replace_node()`, not `get_node_mut()`). Pins, degree limits and constraints
+    /// can't refuse a merge (a merge that closes a cycle turns DAG mode off), and the merge
+    /// clears the undo history, since earlier changes may no longer be reversible.
+    ///
+    /// If `enable_crdt()` has not been called on this graph, an error is returned.
+    pub fn merge_concurrent(&mut self, other: &DiGraph<N, E>) -> Result<(), GraphError> {
+        trace_span!("merge_concurrent", nodes = other.nodes.len(), edges = other.edges.len());
+        if self.crdt.replica.is_none() {
+            return Err("CRDT tracking not enabled; see enable_crdt().".into());
+        }
+        let my_edges = std::mem::take(&mut self.edges);
+        // Nodes come and go below with no edges in place
+        self.rebuild_neighbors();
+        let mut removed = my_edges.iter().cloned().map(GraphChange::RemoveEdge).collect::<Vec<_>>();
+        for (&node_id, &their_stamp) in other.crdt.nodes.iter() {
+            if self.crdt.nodes.get(&node_id).is_some_and(|&my_stamp| my_stamp >= their_stamp) {
+                continue;
+            }
+            self.crdt.nodes.insert(node_id, their_stamp);
+            match (other.nodes.get(&node_id), self.nodes.contains_key(&node_id)) {
+                (Some(node), true) => {
+                    self.nodes.insert(node_id, node.clone());
+                },
+                (Some(node), false) => self.insert_node_unregistered(node.clone()),
+                (None, true) => {
+                    removed.push(GraphChange::RemoveNode(self.remove_node_unregistered(node_id), Vec::new()));
+                },
+                (None, false) => {},
+            }
+        }
+
+        let other_wins = |terminals: (Id, Id)| other.crdt.edge_stamp(terminals) > self.crdt.edge_stamp(terminals);
+        let mut edges: Vec<E> = my_edges.into_iter()
+            .filter(|edge| !other_wins(edge.terminal_ids()))
+            .chain(other.edges.iter().filter(|edge| other_wins(edge.terminal_ids())).cloned())
+            .filter(|edge| self.nodes.contains_key(&edge.start_id()) && self.nodes.contains_key(&edge.end_id()))
+            .collect();
+        edges.sort_by_key(|edge| edge.terminal_ids());
+        for (&start_id, stamps) in other.crdt.edges.iter() {
+            for (&end_id, &their_stamp) in stamps.iter() {
+                if Some(their_stamp) > self.crdt.edge_stamp((start_id, end_id)) {
+                    self.crdt.set_edge_stamp((start_id, end_id), their_stamp);
+                }
+            }
+        }
+        self.edges = edges;
+        self.rebuild_neighbors();
+        // Same cleanup as a registered removal, for lost nodes and edges (those still present
+        // are left alone); there is no undoing a merge, so the record of it is dropped
+        self.forget_removed(GraphChange::Batch(removed));
+        self.refresh_acyclic_order();
+        self.crdt.clock = self.crdt.clock.max(other.crdt.clock);
+        self.clear_history();
+        Ok(())
+    }
+
+    /// Gives every node and edge `change` touched a fresh stamp (nothing, unless enabled)
+    pub(super) fn stamp_change(&mut self, change: &GraphChange<N, E>) {
+        let Some(replica) = self.crdt.replica else {
+            return;
+        };
+        let mut node_ids = BTreeSet::new();
+        let mut edge_pairs = BTreeSet::new();
+        self.touched_by(change, &mut node_ids, &mut edge_pairs);
+        self.crdt.clock += 1;
+        let stamp = Stamp { lamport: self.crdt.clock, replica };
+        for node_id in node_ids {
+            self.crdt.nodes.insert(node_id, stamp);
+        }
+        for terminals in edge_pairs {
+            self.crdt.set_edge_stamp(terminals, stamp);
+        }
+    }
+
+    /// Collects ids and (start, end) pairs that `change` (or its reversal) may have added or removed
+    fn touched_by(&self, change: &GraphChange<N, E>, node_ids: &mut BTreeSet<Id>, edge_pairs: &mut BTreeSet<(Id, Id)>) {
+        match change {
+            GraphChange::AddNode(node) => {
+                node_ids.insert(node.node_id());
+            },
+            GraphChange::RemoveNode(node, edges) => {
+                node_ids.insert(node.node_id());
+                edge_pairs.extend(edges.iter().map(|edge| edge.terminal_ids()));
+            },
+            GraphChange::AddEdge(edge) | GraphChange::RemoveEdge(edge) => {
+                edge_pairs.insert(edge.terminal_ids());
+            },
+            GraphChange::AddEdgeWith(edge, new_start, new_end) => {
+                edge_pairs.insert(edge.terminal_ids());
+                node_ids.extend(new_start.iter().chain(new_end.iter()));
+            },
+            GraphChange::InsertNodeAlongEdge(node, edge) => {
+                let (start_id, end_id) = edge.terminal_ids();
+                node_ids.insert(node.node_id());
+                edge_pairs.extend([(start_id, end_id), (start_id, node.node_id()), (node.node_id(), end_id)]);
+            },
+            GraphChange::ReplaceNode(node, new_id) => {
+                let old_id = node.node_id();
+                node_ids.extend([old_id, *new_id]);
+                // The edges moved one way or the other, so both ids' pairs changed
+                let swapped = |id: Id| match id {
+                    id if id == old_id => *new_id,
+                    id if id == *new_id => old_id,
+                    id => id,
+                };
+                for edge in self.edges.iter() {
+                    let (start_id, end_id) = edge.terminal_ids();
+                    if [start_id, end_id].iter().any(|id| *id == old_id || id == new_id) {
+                        edge_pairs.extend([(start_id, end_id), (swapped(start_id), swapped(end_id))]);
+                    }
+                }
+            },
+            GraphChange::ReverseEdge(start_id, end_id) => {
+                edge_pairs.extend([(*start_id, *end_id), (*end_id, *start_id)]);
+            },
+            GraphChange::ReverseAllEdges => {
+                for edge in self.edges.iter() {
+                    let (start_id, end_id) = edge.terminal_ids();
+                    edge_pairs.extend([(start_id, end_id), (end_id, start_id)]);
+                }
+            },
+            GraphChange::Batch(changes) => {
+                for change in changes.iter() {
+                    self.touched_by(change, node_ids, edge_pairs);
+                }
+            },
+            GraphChange::ReorderSuccessors(_, _) | GraphChange::SetGroup(_, _) | GraphChange::SetNodeExtras(_, _)
+                | GraphChange::SetEdgeTags(_, _, _) | GraphChange::Failure(_) => {},
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::graph_base::graph_components::Id;
+use super::{Nodal, DirEdge, DiGraph};
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+
+    /// Returns the graph as a Cytoscape.js elements array, ready for `cytoscape({ elements })`.
+    ///
+    /// Nodes come first (ascending id), then edges (ascending terminals). Each element's `data`
+    /// holds the fields of its node or edge payload, plus `id` (the node id as a string, or
+    /// `"start-end"` for edges, with `-1`, `-2`, ... appended for parallel edges) and, for edges,
+    /// `source` and `target`. Payloads that don't serialize to a map go under `data.payload`.
+    pub fn to_cytoscape_json(&self) -> serde_json::Result<String> {
+        let mut elements = Vec::with_capacity(self.nodes.len() + self.edges.len());
+        for node_id in self.all_node_ids() {
+            let data = payload_data(&self.nodes[&node_id], json!({ "id": node_id.to_string() }))?;
+            elements.push(json!({ "group": "nodes", "data": data }));
+        }
+        let mut edges: Vec<&E> = self.edges.iter().collect();
+        edges.sort_by_key(|edge| edge.terminal_ids());
+        let mut repeats: HashMap<(Id, Id), usize> = HashMap::new();
+        for edge in edges {
+            let (start_id, end_id) = edge.terminal_ids();
+            let repeat = repeats.entry((start_id, end_id)).or_default();
+            let edge_id = match *repeat {
+                0 => format!("{start_id}-{end_id}"),
+                n => format!("{start_id}-{end_id}-{n}"),
+            };
+            *repeat += 1;
+            let required = json!({ "id": edge_id, "source": start_id.to_string(), "target": end_id.to_string() });
+            elements.push(json!({ "group": "edges", "data": payload_data(edge, required)? }));
+        }
+        serde_json::to_string(&elements)
+    }
+}
+
+/// Merges `payload`'s fields into `required`, which wins wherever a key appears in both
+fn payload_data<T: Serialize>(payload: &T, required: Value) -> serde_json::Result<Value> {
+    let mut data = match serde_json::to_value(payload)? {
+        Value::Object(fields) => fields,
+        Value::Null => Map::new(),
+        other => Map::from_iter([("payload".to_string(), other)]),
+    };
+    if let Value::Object(required) = required {
+        data.extend(required);
+    }
+    Ok(Value::Object(data))
+}
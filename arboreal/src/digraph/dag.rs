@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+
+use crate::graph_base::{graph_algo, graph_components::{GraphChange, GraphError, Id, IdSet, NeighborIds}};
+use super::{Nodal, DirEdge, DiGraph};
+
+/// Position of every node in a topological order, kept up to date edit by edit
+/// (Pearce and Kelly's algorithm) while `DiGraph::enforce_acyclic()` is on.
+///
+/// Positions only ever get compared, so removed nodes keep theirs, leaving gaps that are
+/// closed once they make up half of the order.
+pub(super) struct AcyclicOrder {
+    positions: HashMap<Id, usize>,
+    /// Id at each position, the inverse of `positions`
+    slots: Vec<Id>,
+}
+
+impl AcyclicOrder {
+    fn new(node_ids: Vec<Id>) -> Self {
+        Self {
+            positions: node_ids.iter().enumerate().map(|(position, &node_id)| (node_id, position)).collect(),
+            slots: node_ids,
+        }
+    }
+
+    fn place(&mut self, node_id: Id) {
+        if !self.positions.contains_key(&node_id) {
+            self.positions.insert(node_id, self.slots.len());
+            self.slots.push(node_id);
+        }
+    }
+
+    fn move_to(&mut self, node_id: Id, position: usize) {
+        self.positions.insert(node_id, position);
+        self.slots[position] = node_id;
+    }
+
+    /// Drops the positions of removed nodes, renumbering the rest
+    fn compact<N>(&mut self, nodes: &HashMap<Id, N>) {
+        self.slots.retain(|node_id| nodes.contains_key(node_id));
+        *self = Self::new(std::mem::take(&mut self.slots));
+    }
+}
+
+/// The order, if DAG mode is on. A mode is not data, so it is never compared.
+#[derive(Default)]
+pub(super) struct DagMode(Option<AcyclicOrder>);
+
+impl PartialEq for DagMode {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Deref for DagMode {
+    type Target = Option<AcyclicOrder>;
+
+    fn deref(&self) -> &Option<AcyclicOrder> {
+        &self.0
+    }
+}
+
+impl DerefMut for DagMode {
+    fn deref_mut(&mut self) -> &mut Option<AcyclicOrder> {
+        &mut self.0
+    }
+}
+
+/// What an edit added, for updating the order
+#[derive(Default)]
+struct Additions {
+    node_ids: Vec<Id>,
+    edges: Vec<(Id, Id)>,
+    /// Too much changed to update the order edge by edge
+    needs_rebuild: bool,
+}
+
+impl Additions {
+    fn collect<N: Nodal, E: DirEdge>(&mut self, change: &GraphChange<N, E>) {
+        match change {
+            GraphChange::AddNode(node) => self.node_ids.push(node.node_id()),
+            GraphChange::AddEdge(edge) | GraphChange::AddEdgeWith(edge, _, _) => {
+                self.node_ids.extend([edge.start_id(), edge.end_id()]);
+                self.edges.push(edge.terminal_ids());
+            },
+            GraphChange::InsertNodeAlongEdge(node, edge) => {
+                let (start_id, end_id) = edge.terminal_ids();
+                self.node_ids.push(node.node_id());
+                self.edges.extend([(start_id, node.node_id()), (node.node_id(), end_id)]);
+            },
+            GraphChange::ReverseEdge(start_id, end_id) => self.edges.push((*end_id, *start_id)),
+            GraphChange::ReplaceNode(node, new_id) => self.needs_rebuild |= node.node_id() != *new_id,
+            GraphChange::ReverseAllEdges => self.needs_rebuild = true,
+            GraphChange::Batch(changes) => {
+                for change in changes.iter() {
+                    self.collect(change);
+                }
+                // Edges still waiting their turn could mislead the search for the first ones
+                self.needs_rebuild |= self.edges.len() > 1;
+            },
+            GraphChange::RemoveNode(_, _) | GraphChange::RemoveEdge(_) | GraphChange::ReorderSuccessors(_, _)
+                | GraphChange::SetGroup(_, _) | GraphChange::SetNodeExtras(_, _) | GraphChange::SetEdgeTags(_, _, _)
+                | GraphChange::Failure(_) => {},
+        }
+    }
+}
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+
+    /// Turns DAG mode on or off. While on, every edit that would close a cycle is refused with
+    /// `GraphError::ConstraintViolated("acyclic")`, checked incrementally so that adding an edge
+    /// only looks at the nodes between its terminals in a topological order kept by the graph
+    /// (see `cached_topological_order()`), rather than at the whole graph as `Acyclic` does.
+    ///
+    /// The mode is not saved with the graph. Like constraints, it is not checked by `undo()`:
+    /// undoing edits made before it was turned on may bring back a cycle, which turns it off.
+    ///
+    /// If the graph has a cycle, the mode stays off and an error is returned.
+    pub fn enforce_acyclic(&mut self, enabled: bool) -> Result<(), GraphError> {
+        *self.acyclic_order = None;
+        if enabled {
+            *self.acyclic_order = Some(self.full_acyclic_order().ok_or(GraphError::ConstraintViolated("acyclic"))?);
+        }
+        Ok(())
+    }
+
+    pub fn is_acyclic_enforced(&self) -> bool {
+        self.acyclic_order.is_some()
+    }
+
+    /// The topological order kept in DAG mode (not necessarily `topological_order()`'s),
+    /// or `None` if the mode is off
+    pub fn cached_topological_order(&self) -> Option<Vec<Id>> {
+        self.acyclic_order.as_ref()?;
+        Some(self.iter_topological().collect())
+    }
+
+    /// Ids in the order kept in DAG mode, without sorting anything (none if the mode is off)
+    pub fn iter_topological(&self) -> impl Iterator<Item = Id> + '_ {
+        self.acyclic_order.iter()
+            .flat_map(|order| order.slots.iter().copied())
+            .filter(|node_id| self.nodes.contains_key(node_id))
+    }
+
+    /// Position of the node with id `node_id` in the order kept in DAG mode, or `None` if it
+    /// is not found or the mode is off. Along every edge, the end's index is greater than the
+    /// start's. Indexes change as edges are added, and may skip numbers left by removed nodes.
+    pub fn topo_index(&self, node_id: Id) -> Option<usize> {
+        let order = self.acyclic_order.as_ref()?;
+        self.nodes.contains_key(&node_id).then(|| order.positions[&node_id])
+    }
+
+    fn full_acyclic_order(&self) -> Option<AcyclicOrder> {
+        graph_algo::topological_order_by_key(self, |_| ()).map(AcyclicOrder::new)
+    }
+
+    /// Brings the order up to date with `change`, already applied, unless it closed a cycle;
+    /// nothing to do outside DAG mode
+    pub(super) fn update_acyclic_order(&mut self, change: &GraphChange<N, E>) -> Result<(), GraphError> {
+        let Some(mut order) = self.acyclic_order.take() else {
+            return Ok(());
+        };
+        let mut additions = Additions::default();
+        additions.collect(change);
+        let result = match additions.needs_rebuild {
+            true => self.full_acyclic_order().map(|full_order| order = full_order).ok_or(()),
+            false => {
+                for node_id in additions.node_ids {
+                    order.place(node_id);
+                }
+                additions.edges.into_iter().try_for_each(|(start_id, end_id)| self.reorder_for_edge(&mut order, start_id, end_id))
+            },
+        };
+        // Not after a refusal, as reversing the change may bring removed nodes back
+        if result.is_ok() && order.slots.len() > 2 * self.nodes.len() {
+            order.compact(&self.nodes);
+        }
+        // Even after a refusal, the order still fits every edge that will be left once the change is reversed
+        *self.acyclic_order = Some(order);
+        result.map_err(|_| GraphError::ConstraintViolated("acyclic"))
+    }
+
+    /// After `undo()` and other edits made outside `commit_change()`: rebuilds the order,
+    /// or turns DAG mode off if the graph now has a cycle
+    pub(super) fn refresh_acyclic_order(&mut self) {
+        if self.acyclic_order.is_some() {
+            *self.acyclic_order = self.full_acyclic_order();
+        }
+    }
+
+    /// Moves nodes so that `start_id` comes before `end_id`, given an order that fits every
+    /// other edge; fails if `end_id` leads back to `start_id`
+    fn reorder_for_edge(&self, order: &mut AcyclicOrder, start_id: Id, end_id: Id) -> Result<(), ()> {
+        let upper = order.positions[&start_id];
+        let lower = order.positions[&end_id];
+        if lower > upper {
+            return Ok(());
+        }
+        if start_id == end_id {
+            return Err(());
+        }
+        // Only nodes placed between the two terminals can be in the way
+        let mut forward = self.reach_between(end_id, order, |position| position < upper, &self.neighbors_after, Some(start_id))?;
+        let mut backward = self.reach_between(start_id, order, |position| position > lower, &self.neighbors_before, None)?;
+        forward.sort_by_key(|id| order.positions[id]);
+        backward.sort_by_key(|id| order.positions[id]);
+        let mut free_positions: Vec<usize> = forward.iter().chain(backward.iter()).map(|id| order.positions[id]).collect();
+        free_positions.sort_unstable();
+        for (node_id, position) in backward.into_iter().chain(forward).zip(free_positions) {
+            order.move_to(node_id, position);
+        }
+        Ok(())
+    }
+
+    /// Nodes reachable from `from_id` through `neighbors` without leaving positions for which
+    /// `in_bounds` holds, `from_id` included; fails on reaching `forbidden_id`
+    fn reach_between<F: Fn(usize) -> bool>(
+        &self,
+        from_id: Id,
+        order: &AcyclicOrder,
+        in_bounds: F,
+        neighbors: &HashMap<Id, NeighborIds>,
+        forbidden_id: Option<Id>,
+    ) -> Result<Vec<Id>, ()> {
+        let mut reached = vec![from_id];
+        let mut visited = IdSet::new();
+        visited.insert(from_id);
+        let mut stack = vec![from_id];
+        while let Some(node_id) = stack.pop() {
+            for &neighbor_id in neighbors[&node_id].iter() {
+                if Some(neighbor_id) == forbidden_id {
+                    return Err(());
+                }
+                if in_bounds(order.positions[&neighbor_id]) && visited.insert(neighbor_id) {
+                    reached.push(neighbor_id);
+                    stack.push(neighbor_id);
+                }
+            }
+        }
+        Ok(reached)
+    }
+}
@@ -0,0 +1,197 @@
+use std::fmt::{self, Display};
+
+use serde::{Deserialize, Serialize};
+
+use crate::graph_base::{graph_components::{GraphChange, GraphError, Id}, graph_ref};
+use super::{Nodal, DirEdge, DiGraph};
+
+/// One edit in a `GraphDelta`. Removals and updates carry the value they expect to find,
+/// so that edits made against a different version of the graph are caught as conflicts.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum DeltaOp<N, E> {
+    AddNode(N),
+    /// Also removes the node's edges
+    RemoveNode(N),
+    /// Both nodes must have the same id
+    UpdateNode { old: N, new: N },
+    AddEdge(E),
+    RemoveEdge(E),
+    /// Both edges must have the same terminals
+    UpdateEdge { old: E, new: E },
+}
+
+/// Serializable list of edits, e.g. received from a collaboration server or built on
+/// another thread, applied all-or-nothing by `DiGraph::apply_delta()`
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GraphDelta<N, E> {
+    pub ops: Vec<DeltaOp<N, E>>,
+}
+
+impl<N, E> GraphDelta<N, E> {
+    pub fn new() -> Self {
+        Self { ops: Vec::new() }
+    }
+    pub fn push(&mut self, op: DeltaOp<N, E>) {
+        self.ops.push(op);
+    }
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+}
+
+impl<N, E> Default for GraphDelta<N, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How the graph disagrees with what a `DeltaOp` expected
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum DeltaConflict {
+    NodeExists(Id),
+    NodeMissing(Id),
+    /// The node differs from the one the op expected
+    NodeChanged(Id),
+    EdgeExists(Id, Id),
+    EdgeMissing(Id, Id),
+    /// The edge differs from the one the op expected
+    EdgeChanged(Id, Id),
+    /// The state matched, but the graph refused the edit (pinned node, degree limit, constraint...)
+    Refused(GraphError),
+}
+
+/// Why `DiGraph::apply_delta()` applied nothing: the op at `index` conflicted
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct ApplyError {
+    pub index: usize,
+    pub conflict: DeltaConflict,
+}
+
+impl Display for ApplyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Delta op {} conflicts with the graph: ", self.index)?;
+        match &self.conflict {
+            DeltaConflict::NodeExists(id) => write!(f, "node {id} already exists."),
+            DeltaConflict::NodeMissing(id) => write!(f, "node {id} not found."),
+            DeltaConflict::NodeChanged(id) => write!(f, "node {id} has changed."),
+            DeltaConflict::EdgeExists(start_id, end_id) => write!(f, "edge {start_id} -> {end_id} already exists."),
+            DeltaConflict::EdgeMissing(start_id, end_id) => write!(f, "edge {start_id} -> {end_id} not found."),
+            DeltaConflict::EdgeChanged(start_id, end_id) => write!(f, "edge {start_id} -> {end_id} has changed."),
+            DeltaConflict::Refused(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for ApplyError {}
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+
+    /// Applies every op of `delta` in order, registered as a single change, or none of them.
+    ///
+    /// Each op is checked against the graph as left by the ops before it; on the first
+    /// conflict, those ops are rolled back, leaving the graph exactly as it was (handles to
+    /// nodes they removed included), and the conflict is returned with the op's index.
+    /// A delta that breaks a constraint (see `add_constraint()`) is refused after its last op.
+    pub fn apply_delta(&mut self, delta: &GraphDelta<N, E>) -> Result<(), ApplyError> {
+        trace_span!("apply_delta", ops = delta.ops.len());
+        let mut changes = Vec::with_capacity(delta.ops.len());
+        self.begin_tentative_change();
+        for (index, op) in delta.ops.iter().enumerate() {
+            if let Err(conflict) = self.apply_delta_op(op, &mut changes) {
+                // Nothing registered yet, so rolling back leaves no trace in the history
+                self.roll_back(GraphChange::Batch(changes))
+                    .map_err(|error| ApplyError { index, conflict: DeltaConflict::Refused(error) })?;
+                return Err(ApplyError { index, conflict });
+            }
+        }
+        if changes.is_empty() {
+            self.end_change();
+            return Ok(());
+        }
+        self.commit_change(GraphChange::Batch(changes))
+            .map_err(|error| ApplyError { index: delta.ops.len() - 1, conflict: DeltaConflict::Refused(error) })
+    }
+
+    fn apply_delta_op(&mut self, op: &DeltaOp<N, E>, changes: &mut Vec<GraphChange<N, E>>) -> Result<(), DeltaConflict> {
+        match op {
+            DeltaOp::AddNode(node) => {
+                let node_id = node.node_id();
+                if self.nodes.contains_key(&node_id) {
+                    return Err(DeltaConflict::NodeExists(node_id));
+                }
+                self.insert_node_unregistered(node.clone());
+                changes.push(GraphChange::AddNode(node.clone()));
+            },
+            DeltaOp::RemoveNode(node) => {
+                let node_id = node.node_id();
+                self.expect_node(node)?;
+                if self.pinned.contains(&node_id) {
+                    return Err(DeltaConflict::Refused(GraphError::NodePinned(node_id)));
+                }
+                let change = graph_ref::check_remove_node(&self.nodes, self.edges_incident_on(node_id), node_id);
+                self.remove_node_unregistered(node_id);
+                changes.push(change);
+            },
+            DeltaOp::UpdateNode { old, new } => {
+                if old.node_id() != new.node_id() {
+                    return Err(DeltaConflict::Refused("Node update may not change the id.".into()));
+                }
+                self.expect_node(old)?;
+                self.replace_node_unregistered(old.node_id(), new.clone());
+                changes.push(GraphChange::ReplaceNode(old.clone(), new.node_id()));
+            },
+            DeltaOp::AddEdge(edge) => {
+                let (start_id, end_id) = edge.terminal_ids();
+                for node_id in [start_id, end_id] {
+                    if !self.nodes.contains_key(&node_id) {
+                        return Err(DeltaConflict::NodeMissing(node_id));
+                    }
+                }
+                if self.edge_index(start_id, end_id).is_some() {
+                    return Err(DeltaConflict::EdgeExists(start_id, end_id));
+                }
+                self.check_edge_limits(start_id, end_id).map_err(DeltaConflict::Refused)?;
+                self.insert_edge_unregistered(edge.clone());
+                changes.push(GraphChange::AddEdge(edge.clone()));
+            },
+            DeltaOp::RemoveEdge(edge) => {
+                let edge_index = self.expect_edge(edge)?;
+                self.remove_edge_unregistered(edge_index);
+                changes.push(GraphChange::RemoveEdge(edge.clone()));
+            },
+            DeltaOp::UpdateEdge { old, new } => {
+                if old.terminal_ids() != new.terminal_ids() {
+                    return Err(DeltaConflict::Refused("Edge update may not change the terminals.".into()));
+                }
+                let edge_index = self.expect_edge(old)?;
+                self.remove_edge_unregistered(edge_index);
+                self.insert_edge_unregistered(new.clone());
+                changes.push(GraphChange::RemoveEdge(old.clone()));
+                changes.push(GraphChange::AddEdge(new.clone()));
+            },
+        }
+        Ok(())
+    }
+
+    fn expect_node(&self, node: &N) -> Result<(), DeltaConflict> {
+        let node_id = node.node_id();
+        match self.nodes.get(&node_id) {
+            None => Err(DeltaConflict::NodeMissing(node_id)),
+            Some(current) if current != node => Err(DeltaConflict::NodeChanged(node_id)),
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Returns the index of the edge equal to `edge`
+    fn expect_edge(&self, edge: &E) -> Result<usize, DeltaConflict> {
+        let (start_id, end_id) = edge.terminal_ids();
+        let edge_index = self.edge_index(start_id, end_id)
+            .ok_or(DeltaConflict::EdgeMissing(start_id, end_id))?;
+        match &self.edges[edge_index] == edge {
+            true => Ok(edge_index),
+            false => Err(DeltaConflict::EdgeChanged(start_id, end_id)),
+        }
+    }
+}
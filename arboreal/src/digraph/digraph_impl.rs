@@ -1,72 +1,207 @@
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fmt::{self, Display};
 
-use crate::graph_base::graph_components::Id;
-use crate::graph_base::graph_ref;
-use super::{Nodal, DirEdge, DiGraph, HistoryDeque};
+use smallvec::SmallVec;
+
+use crate::graph_base::graph_components::{Id, NeighborIds};
+use super::{edge_map, Nodal, DirEdge, DiGraph, ConstraintSet, EdgeIndices, HistoryDeque, IdReuse};
 
 const DEFAULT_NODE_PREALLOCATION: usize = 20;
 const EXPECTED_EDGES_PER_NODE: usize = 3;
 
+/// Neighbor lists are kept sorted, holding one entry per edge (so parallel edges repeat an id)
+fn insert_sorted(neighbor_ids: &mut NeighborIds, id: Id) {
+    let position = neighbor_ids.partition_point(|&x| x <= id);
+    neighbor_ids.insert(position, id);
+}
+
+fn insert_sorted_index(edge_indices: &mut EdgeIndices, edge_index: usize) {
+    let position = edge_indices.partition_point(|&x| x < edge_index);
+    edge_indices.insert(position, edge_index);
+}
+
+/// Removes a single entry for `id`, leaving entries for any parallel edges in place
+fn remove_one_sorted(neighbor_ids: &mut NeighborIds, id: Id) {
+    if let Ok(position) = neighbor_ids.binary_search(&id) {
+        neighbor_ids.remove(position);
+    }
+}
+
 impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
 
     pub(super) fn edge_index(&self, start_id: Id, end_id: Id) -> Option<usize> {
-        for (index, edge) in self.edges.iter().enumerate() {
-            if edge.terminal_ids() == (start_id, end_id) {
-                return Some(index);
-            }
-        }
-        None
+        self.incident_edges.get(&start_id)?
+            .iter()
+            .copied()
+            .find(|&index| self.edges[index].terminal_ids() == (start_id, end_id))
+    }
+
+    /// Edges with `node_id` as a terminal, in `edges` order (none if the node is not found)
+    pub(super) fn edges_incident_on(&self, node_id: Id) -> impl Iterator<Item = &E> + Clone {
+        self.incident_edges.get(&node_id)
+            .into_iter()
+            .flatten()
+            .map(|&index| &self.edges[index])
     }
 
     pub(super) fn insert_node_unregistered(&mut self, node: N) {
         let node_id = node.node_id();
+        if self.reserved_ids.remove(&node_id) {
+            self.rollback.unreserved(node_id);
+        }
+        if let Some(position) = self.freed_ids.iter().position(|&id| id == node_id) {
+            self.rollback.freed_ids_changing(&self.freed_ids);
+            self.freed_ids.remove(position);
+        }
         self.nodes.insert(node_id, node);
-        self.neighbors_before.insert(node_id, Vec::with_capacity(5));
-        self.neighbors_after.insert(node_id, Vec::with_capacity(5));
+        self.neighbors_before.insert(node_id, SmallVec::new());
+        self.neighbors_after.insert(node_id, SmallVec::new());
+        self.incident_edges.insert(node_id, SmallVec::new());
     }
 
     pub(super) fn remove_node_unregistered(&mut self, node_id: Id) -> N {
+        // Ids repeat once per parallel edge, and a self-loop shows up in both lists,
+        // so an edge may already be gone by the time its id comes up again
         for id_before in self.neighbors_before.get(&node_id).unwrap().to_owned() {
-            let edge_index = self.edge_index(id_before, node_id).unwrap();
-            self.remove_edge_unregistered(edge_index);
+            if let Some(edge_index) = self.edge_index(id_before, node_id) {
+                self.remove_edge_unregistered(edge_index);
+            }
         }
         for id_after in self.neighbors_after.get(&node_id).unwrap().to_owned() {
-            let edge_index = self.edge_index(node_id, id_after).unwrap();
-            self.remove_edge_unregistered(edge_index);
+            if let Some(edge_index) = self.edge_index(node_id, id_after) {
+                self.remove_edge_unregistered(edge_index);
+            }
         }
+        self.neighbors_before.remove(&node_id);
+        self.neighbors_after.remove(&node_id);
+        self.incident_edges.remove(&node_id);
+        self.rollback.generation_bumped(node_id, self.generations.get(&node_id).copied());
+        *self.generations.entry(node_id).or_default() += 1;
+        self.rollback.freed_ids_changing(&self.freed_ids);
+        self.freed_ids.push(node_id);
+        // Only undo() gets here with a pinned node, which it may remove
+        self.pinned.remove(&node_id);
         self.nodes
             .remove(&node_id)
             .unwrap()
     }
 
+    /// Puts `node` in place of the node at `old_id`, returning the old node. If `node` has
+    /// another id, incident edges (and successor orders) are moved over to it.
+    pub(super) fn replace_node_unregistered(&mut self, old_id: Id, node: N) -> N {
+        let new_id = node.node_id();
+        if new_id == old_id {
+            return self.nodes.insert(old_id, node).unwrap();
+        }
+        let renamed = |id: Id| if id == old_id { new_id } else { id };
+        let incident_edges: Vec<E> = self.edges_incident_on(old_id).cloned().collect();
+        let was_pinned = self.pinned.contains(&old_id);
+        let old_node = self.remove_node_unregistered(old_id);
+        self.insert_node_unregistered(node);
+        if was_pinned {
+            self.pinned.insert(new_id);
+        }
+        if let Some(max) = self.max_out_degrees.remove(&old_id) {
+            self.max_out_degrees.insert(new_id, max);
+        }
+        if let Some(max) = self.max_in_degrees.remove(&old_id) {
+            self.max_in_degrees.insert(new_id, max);
+        }
+        for mut edge in incident_edges {
+            let (start_id, end_id) = edge.terminal_ids();
+            edge.change_start(renamed(start_id));
+            edge.change_end(renamed(end_id));
+            self.insert_edge_unregistered(edge);
+        }
+        self.rename_tagged(old_id, new_id);
+        self.rename_endpoints(|id| match id {
+            _ if id == old_id => Some(new_id),
+            _ if id == new_id => None,
+            _ => Some(id),
+        });
+        if let Some(order) = self.successor_order.remove(&old_id) {
+            self.successor_order.insert(new_id, order);
+        }
+        for order in self.successor_order.values_mut() {
+            for id in order.iter_mut() {
+                *id = renamed(*id);
+            }
+        }
+        old_node
+    }
+
     pub(super) fn insert_edge_unregistered(&mut self, edge: E) {
         let (start_id, end_id) = edge.terminal_ids();
+        self.index_edge(self.edges.len(), start_id, end_id);
         self.edges.push(edge);
+        self.rollback.edge_pushed();
         // Register end node's id as start node's after-neighbor
-        self.neighbors_after
-            .get_mut(&start_id)
-            .unwrap()
-            .push(end_id);
+        insert_sorted(self.neighbors_after.get_mut(&start_id).unwrap(), end_id);
         // Register start node's id as end node's before-neighbor
-        self.neighbors_before
-            .get_mut(&end_id)
-            .unwrap()
-            .push(start_id);
+        insert_sorted(self.neighbors_before.get_mut(&end_id).unwrap(), start_id);
     }
 
     pub(super) fn remove_edge_unregistered(&mut self, edge_index: usize) {
+        let last_index = self.edges.len() - 1;
         let dropped_edge = self.edges.swap_remove(edge_index);
         let (start_id, end_id) = dropped_edge.terminal_ids();
-        self.neighbors_before
-            .get_mut(&end_id)
-            .unwrap()
-            .retain(|&x| x != start_id);
-        self.neighbors_after
-            .get_mut(&start_id)
-            .unwrap()
-            .retain(|&x| x != end_id);
+        self.rollback.edge_swap_removed(edge_index, (start_id, end_id));
+        remove_one_sorted(self.neighbors_before.get_mut(&end_id).unwrap(), start_id);
+        remove_one_sorted(self.neighbors_after.get_mut(&start_id).unwrap(), end_id);
+        self.unindex_edge(edge_index, start_id, end_id);
+        if edge_index != last_index {
+            // The last edge took the dropped edge's place
+            let (moved_start, moved_end) = self.edges[edge_index].terminal_ids();
+            self.unindex_edge(last_index, moved_start, moved_end);
+            self.index_edge(edge_index, moved_start, moved_end);
+        }
+    }
+
+    fn index_edge(&mut self, edge_index: usize, start_id: Id, end_id: Id) {
+        insert_sorted_index(self.incident_edges.get_mut(&start_id).unwrap(), edge_index);
+        if end_id != start_id {
+            insert_sorted_index(self.incident_edges.get_mut(&end_id).unwrap(), edge_index);
+        }
+    }
+
+    fn unindex_edge(&mut self, edge_index: usize, start_id: Id, end_id: Id) {
+        for node_id in [start_id, end_id] {
+            let indices = self.incident_edges.get_mut(&node_id).unwrap();
+            if let Ok(position) = indices.binary_search(&edge_index) {
+                indices.remove(position);
+            }
+        }
+    }
+
+    /// Recreates `neighbors_before`, `neighbors_after` and `incident_edges` from `nodes` and `edges`
+    /// (these maps are skipped by serde, so this runs after every load)
+    pub(super) fn rebuild_neighbors(&mut self) {
+        self.neighbors_before.clear();
+        self.neighbors_after.clear();
+        self.incident_edges.clear();
+        for &node_id in self.nodes.keys() {
+            self.neighbors_before.insert(node_id, SmallVec::new());
+            self.neighbors_after.insert(node_id, SmallVec::new());
+            self.incident_edges.insert(node_id, SmallVec::new());
+        }
+        for (edge_index, edge) in self.edges.iter().enumerate() {
+            let (start_id, end_id) = edge.terminal_ids();
+            if let Some(ids_after) = self.neighbors_after.get_mut(&start_id) {
+                insert_sorted(ids_after, end_id);
+            }
+            if let Some(ids_before) = self.neighbors_before.get_mut(&end_id) {
+                insert_sorted(ids_before, start_id);
+            }
+            // Ascending, since edges are visited in order
+            for node_id in [start_id, end_id] {
+                if let Some(indices) = self.incident_edges.get_mut(&node_id)
+                    && indices.last() != Some(&edge_index)
+                {
+                    indices.push(edge_index);
+                }
+            }
+        }
     }
 
     /// Returns vec of `node_id` for which `in_degree(node_id) == Some(0)`
@@ -93,17 +228,20 @@ impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
     //     todo!()
     // }
 
+    #[allow(dead_code)]
     pub(super) fn is_connected(&self) -> bool {
         let mut source_ids = self.source_node_ids();
         if source_ids.len() != 1 {
             return false;
         }
         let starting_point = source_ids.pop().unwrap();
-        self.nodes_unreachable_from(starting_point).is_empty()
+        self.unreachable_count_from(starting_point) == 0
     }
+    #[allow(dead_code)]
     pub(super) fn is_terminable(&self) -> bool {
         todo!()
     }
+    #[allow(dead_code)]
     pub(super) fn is_valid(&self) -> bool {
         self.is_connected() && self.is_terminable()
     }
@@ -114,12 +252,41 @@ impl<N: Nodal, E: DirEdge> Default for DiGraph<N, E> {
         let n = DEFAULT_NODE_PREALLOCATION;
         let e = EXPECTED_EDGES_PER_NODE * n;
         let name = None;
+        let metadata = BTreeMap::new();
         let nodes = HashMap::with_capacity(n);
         let edges = Vec::with_capacity(e);
         let neighbors_before = HashMap::with_capacity(n);
         let neighbors_after = HashMap::with_capacity(n);
+        let incident_edges = HashMap::with_capacity(n);
         let undo_history = HistoryDeque::default();
-        Self { name, nodes, edges, neighbors_before, neighbors_after, undo_history }
+        let reserved_ids = HashSet::new();
+        let successor_order = HashMap::new();
+        let generations = HashMap::new();
+        let (entry, exit) = (None, None);
+        let pinned = BTreeSet::new();
+        let groups = BTreeMap::new();
+        let node_tags = BTreeMap::new();
+        let edge_tags = BTreeMap::new();
+        let max_out_degrees = BTreeMap::new();
+        let max_in_degrees = BTreeMap::new();
+        let constraints = ConstraintSet::default();
+        Self {
+            format_version: edge_map::FormatVersion,
+            name, metadata, nodes, edges, neighbors_before, neighbors_after, incident_edges, undo_history,
+            reserved_ids, successor_order, generations,
+            freed_ids: Vec::new(),
+            id_reuse: IdReuse::default(),
+            entry, exit, pinned, groups, node_tags, edge_tags, max_out_degrees,
+            max_in_degrees, constraints,
+            bare_node_policy: Default::default(),
+            strict: Default::default(),
+            metrics: Default::default(),
+            acyclic_order: Default::default(),
+            dirty: Default::default(),
+            rollback: Default::default(),
+            #[cfg(feature = "crdt")]
+            crdt: Default::default(),
+        }
     }
 }
 
@@ -130,9 +297,9 @@ impl<N: Nodal, E: DirEdge> Display for DiGraph<N, E> {
             None => "DiGraph".to_string()
         };
         let edges_per_line: u8 = 4;
-        write!(f, "{name}\n")?;
-        write!(f, "\tNode Ids: {:?}\n", self.all_node_ids())?;
-        if self.edges.len() > 0 {
+        writeln!(f, "{name}")?;
+        writeln!(f, "\tNode Ids: {:?}", self.all_node_ids())?;
+        if !self.edges.is_empty() {
             let edge_vec = self.all_edge_pairs();
             let mut reset_count = 0;
             for edge_pair in edge_vec.into_iter() {
@@ -145,7 +312,7 @@ impl<N: Nodal, E: DirEdge> Display for DiGraph<N, E> {
                 reset_count += 1;
                 if reset_count == edges_per_line {
                     reset_count = 0;
-                    write!(f, "\n")?;
+                    writeln!(f)?;
                 }
             }
         } else {
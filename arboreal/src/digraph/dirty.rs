@@ -0,0 +1,57 @@
+use std::collections::BTreeSet;
+use std::ops::{Deref, DerefMut};
+
+use crate::graph_base::{graph_algo, graph_components::{GraphError, Id}};
+use super::{Nodal, DirEdge, DiGraph};
+
+/// Ids flagged by `mark_dirty()`. Runtime bookkeeping, never saved, so it is never compared.
+#[derive(Default, Clone)]
+pub(super) struct DirtyFlags(BTreeSet<Id>);
+
+impl PartialEq for DirtyFlags {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Deref for DirtyFlags {
+    type Target = BTreeSet<Id>;
+
+    fn deref(&self) -> &BTreeSet<Id> {
+        &self.0
+    }
+}
+
+impl DerefMut for DirtyFlags {
+    fn deref_mut(&mut self) -> &mut BTreeSet<Id> {
+        &mut self.0
+    }
+}
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+
+    /// Flags `node_id` as changed, for `propagate_dirty()`. Flags are not saved with the graph.
+    ///
+    /// If `node_id` is not found, an error is returned.
+    pub fn mark_dirty(&mut self, node_id: Id) -> Result<(), GraphError> {
+        if !self.nodes.contains_key(&node_id) {
+            return Err("Node with this id not found.".into());
+        }
+        self.dirty.insert(node_id);
+        Ok(())
+    }
+
+    pub fn is_dirty(&self, node_id: Id) -> bool {
+        self.dirty.contains(&node_id)
+    }
+
+    /// Returns every dirty node and everything downstream of one, each node after all of its
+    /// predecessors in the set (so in a dataflow graph, in an order safe to recompute),
+    /// and clears the flags. Nodes removed since being flagged are left out.
+    ///
+    /// Ties go to the smallest id; nodes on a cycle are ordered as in `graph_algo::downstream_order()`.
+    pub fn propagate_dirty(&mut self) -> Vec<Id> {
+        let dirty_ids: Vec<Id> = std::mem::take(&mut *self.dirty).into_iter().collect();
+        graph_algo::downstream_order(self, &dirty_ids)
+    }
+}
@@ -0,0 +1,173 @@
+//! Saved form of `DiGraph::edges`: a map from `"start->end"` to the edge, so that a hand edit
+//! or a merge touches one entry per edge. Graphs saved before format v2 list their
+//! edges in a plain sequence instead, which still loads.
+//!
+//! `deserialize_terminals()` reads just the terminals of saved edges of any type, for
+//! `DiGraph::load_structure_only()`.
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use ron::Value;
+use serde::{de::{self, IgnoredAny, MapAccess, SeqAccess, Visitor}, ser::SerializeMap, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::graph_base::graph_components::Id;
+use super::DirEdge;
+
+/// Field names that hold an edge's terminals in a sequence saved before v2, tried in order
+const TERMINAL_NAMES: [(&str, &str); 4] = [("start", "end"), ("source", "target"), ("from", "to"), ("start_id", "end_id")];
+
+/// Bumped whenever the serialized form of `DiGraph` changes in a way older versions can't read
+const GRAPH_FORMAT_VERSION: u8 = 2;
+
+/// Format a graph is saved in: always written as the current version, missing from graphs
+/// saved before v2, and refused on load if newer. Not data, so it never makes graphs unequal.
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct FormatVersion;
+
+impl PartialEq for FormatVersion {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Serialize for FormatVersion {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        GRAPH_FORMAT_VERSION.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for FormatVersion {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let version = u8::deserialize(deserializer)?;
+        if version > GRAPH_FORMAT_VERSION {
+            return Err(de::Error::custom(format!(
+                "graph format v{version} is newer than supported (v{GRAPH_FORMAT_VERSION})"
+            )));
+        }
+        Ok(Self)
+    }
+}
+
+// Saved as a plain version number
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for FormatVersion {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        u8::schema_name()
+    }
+    fn json_schema(generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        u8::json_schema(generator)
+    }
+}
+
+fn edge_key((start_id, end_id): (Id, Id)) -> String {
+    format!("{start_id}->{end_id}")
+}
+
+fn parse_edge_key(key: &str) -> Option<(Id, Id)> {
+    let (start, end) = key.split_once("->")?;
+    Some((start.trim().parse().ok()?, end.trim().parse().ok()?))
+}
+
+/// Writes `edges` as a map keyed by terminals, in the graph's edge order (which edge indices
+/// depend on)
+pub(super) fn serialize_edges<E: DirEdge, S: Serializer>(edges: &[E], serializer: S) -> Result<S::Ok, S::Error> {
+    let mut map = serializer.serialize_map(Some(edges.len()))?;
+    for edge in edges {
+        map.serialize_entry(&edge_key(edge.terminal_ids()), edge)?;
+    }
+    map.end()
+}
+
+/// Reads edges saved either way: as a map keyed by terminals, or (before v2) as a sequence
+pub(super) fn deserialize_edges<'de, E: DirEdge, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<E>, D::Error> {
+    deserializer.deserialize_any(EdgesVisitor(PhantomData))
+}
+
+struct EdgesVisitor<E>(PhantomData<E>);
+
+impl<'de, E: DirEdge> Visitor<'de> for EdgesVisitor<E> {
+    type Value = Vec<E>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map from \"start->end\" to edges, or a sequence of edges")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<E>, A::Error> {
+        let mut edges = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(edge) = seq.next_element()? {
+            edges.push(edge);
+        }
+        Ok(edges)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Vec<E>, A::Error> {
+        let mut edges = Vec::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some((key, edge)) = map.next_entry::<String, E>()? {
+            let terminals = parse_edge_key(&key)
+                .ok_or_else(|| de::Error::custom(format!("edge key \"{key}\" is not of the form \"start->end\"")))?;
+            // Both are hand-editable, so neither is trusted over the other
+            if terminals != edge.terminal_ids() {
+                return Err(de::Error::custom(format!("edge key \"{key}\" does not match the edge's terminals")));
+            }
+            edges.push(edge);
+        }
+        Ok(edges)
+    }
+}
+
+/// Reads the terminals of edges saved either way, without knowing their type: from the keys of
+/// a map, or (before v2) from each edge's fields, named as in `TERMINAL_NAMES` or else the
+/// first holding an `(id, id)` pair
+pub(super) fn deserialize_terminals<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<(Id, Id)>, D::Error> {
+    deserializer.deserialize_any(TerminalsVisitor)
+}
+
+struct TerminalsVisitor;
+
+impl<'de> Visitor<'de> for TerminalsVisitor {
+    type Value = Vec<(Id, Id)>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map from \"start->end\" to edges, or a sequence of edges")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<(Id, Id)>, A::Error> {
+        let mut terminals = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(edge) = seq.next_element::<Value>()? {
+            terminals.push(value_terminals(&edge).ok_or_else(|| de::Error::custom("edge terminals not found"))?);
+        }
+        Ok(terminals)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Vec<(Id, Id)>, A::Error> {
+        let mut terminals = Vec::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some((key, IgnoredAny)) = map.next_entry::<String, IgnoredAny>()? {
+            terminals.push(parse_edge_key(&key)
+                .ok_or_else(|| de::Error::custom(format!("edge key \"{key}\" is not of the form \"start->end\"")))?);
+        }
+        Ok(terminals)
+    }
+}
+
+fn value_to_id(value: &Value) -> Option<Id> {
+    value.clone().into_rust().ok()
+}
+
+fn value_terminals(edge: &Value) -> Option<(Id, Id)> {
+    let pair = |items: &[Value]| Some((value_to_id(items.first()?)?, value_to_id(items.get(1)?)?));
+    match edge {
+        Value::Map(fields) => {
+            let field = |name: &str| fields.get(&Value::String(name.to_string())).and_then(value_to_id);
+            TERMINAL_NAMES.iter()
+                .find_map(|&(start, end)| Some((field(start)?, field(end)?)))
+                .or_else(|| fields.values().find_map(|value| match value {
+                    Value::Seq(items) if items.len() == 2 => pair(items),
+                    _ => None,
+                }))
+        },
+        // Tuple structs, e.g. Edge(1, 2, "label")
+        Value::Seq(items) => pair(items),
+        _ => None,
+    }
+}
@@ -0,0 +1,292 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::graph_base::graph_components::{GraphChange, GraphError, Id};
+use super::{Nodal, DirEdge, DiGraph, ChangeCache};
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+
+    /// Returns ids of nodes with no edges at all, in ascending order
+    pub fn isolated_node_ids(&self) -> Vec<Id> {
+        let mut ids = self.all_node_ids();
+        ids.retain(|&id| self.in_degree(id) == Some(0) && self.out_degree(id) == Some(0));
+        ids
+    }
+
+    /// Removes and returns every node with zero in- and out-degree, ordered by id.
+    ///
+    /// All removals are registered as a single change, so one `undo()` restores them.
+    /// If a constraint refuses the removals (see `add_constraint()`), none are made and an error is returned.
+    pub fn prune_isolated_nodes(&mut self) -> Result<Vec<N>, GraphError> {
+        self.prune_isolated_nodes_except(&[])
+    }
+
+    /// Same as `prune_isolated_nodes()`, but nodes in `keep` (e.g. a root that has
+    /// no edges yet) are left in place. Pinned nodes are always left in place.
+    pub fn prune_isolated_nodes_except(&mut self, keep: &[Id]) -> Result<Vec<N>, GraphError> {
+        trace_span!("prune_isolated_nodes");
+        let mut doomed_ids = self.isolated_node_ids();
+        doomed_ids.retain(|id| !keep.contains(id) && !self.pinned.contains(id));
+        let mut removed_nodes = Vec::with_capacity(doomed_ids.len());
+        let mut changes = Vec::with_capacity(doomed_ids.len());
+        self.begin_change();
+        for node_id in doomed_ids {
+            let removed_node = self.remove_node_unregistered(node_id);
+            changes.push(GraphChange::RemoveNode(removed_node.clone(), vec![]));
+            removed_nodes.push(removed_node);
+        }
+        if !changes.is_empty() {
+            self.commit_change(GraphChange::Batch(changes))?;
+        }
+        Ok(removed_nodes)
+    }
+
+    /// Protects the node with id `node_id` from `remove_node()`, which will return
+    /// `GraphError::NodePinned` instead (e.g. for a story's start and exit nodes).
+    /// Pins are saved with the graph, and follow the node if `replace_node()` gives it a new id.
+    ///
+    /// If `node_id` is not found, an error is returned.
+    pub fn pin_node(&mut self, node_id: Id) -> Result<(), GraphError> {
+        if !self.nodes.contains_key(&node_id) {
+            return Err("Node with this id not found.".into());
+        }
+        self.pinned.insert(node_id);
+        Ok(())
+    }
+
+    /// Returns whether the node was pinned
+    pub fn unpin_node(&mut self, node_id: Id) -> bool {
+        self.pinned.remove(&node_id)
+    }
+
+    pub fn is_pinned(&self, node_id: Id) -> bool {
+        self.pinned.contains(&node_id)
+    }
+
+    /// Ids of pinned nodes, ascending
+    pub fn pinned_node_ids(&self) -> Vec<Id> {
+        self.pinned.iter().copied().collect()
+    }
+
+    pub(super) fn flip_edge_unregistered(&mut self, edge_index: usize) {
+        let mut edge = self.edges[edge_index].clone();
+        let (start_id, end_id) = edge.terminal_ids();
+        self.remove_edge_unregistered(edge_index);
+        edge.change_start(end_id);
+        edge.change_end(start_id);
+        self.insert_edge_unregistered(edge);
+        self.flip_edge_tags(start_id, end_id);
+    }
+
+    pub(super) fn flip_all_edges_unregistered(&mut self) {
+        for edge in self.edges.iter_mut() {
+            let (start_id, end_id) = edge.terminal_ids();
+            edge.change_start(end_id);
+            edge.change_end(start_id);
+        }
+        std::mem::swap(&mut self.neighbors_before, &mut self.neighbors_after);
+        self.flip_all_edge_tags();
+    }
+
+    /// Flips the edge from `start_id` to `end_id` so that it runs from `end_id` to `start_id`,
+    /// keeping its data.
+    ///
+    /// If the edge does not exist, or an edge from `end_id` to `start_id` already exists,
+    /// an error is returned.
+    pub fn reverse_edge(&mut self, start_id: Id, end_id: Id) -> Result<(), GraphError> {
+        trace_span!("reverse_edge", start_id, end_id);
+        let edge_index = self.edge_index(start_id, end_id)
+            .ok_or(GraphError::Invalid("Edge not found in graph."))?;
+        if start_id != end_id && self.edge_index(end_id, start_id).is_some() {
+            return Err("Edge with these terminals already exists.".into());
+        }
+        self.begin_change();
+        self.flip_edge_unregistered(edge_index);
+        self.commit_change(GraphChange::ReverseEdge(start_id, end_id))?;
+        Ok(())
+    }
+
+    /// Flips every edge in the graph, as a single undoable change.
+    ///
+    /// If a constraint refuses the result (see `add_constraint()`), nothing is flipped and an error is returned.
+    pub fn reverse_all_edges(&mut self) -> Result<(), GraphError> {
+        trace_span!("reverse_all_edges", edges = self.edges.len());
+        self.begin_change();
+        self.flip_all_edges_unregistered();
+        self.commit_change(GraphChange::ReverseAllEdges)
+    }
+
+    /// Moves every edge for which `rule` returns new terminals `(start, end)`, keeping its data,
+    /// and returns how many edges moved. All moves are registered as a single change, so one
+    /// `undo()` puts every edge back.
+    ///
+    /// If any new terminal is not found among the nodes, two edges would end up with the
+    /// same terminals, or a degree limit would be broken, an error is returned and nothing is moved.
+    pub fn rewire<F: FnMut(&E) -> Option<(Id, Id)>>(&mut self, mut rule: F) -> Result<usize, GraphError> {
+        trace_span!("rewire", edges = self.edges.len());
+        let mut moves = Vec::new();
+        for (edge_index, edge) in self.edges.iter().enumerate() {
+            if let Some(new_terminals) = rule(edge)
+                && new_terminals != edge.terminal_ids()
+            {
+                moves.push((edge_index, new_terminals));
+            }
+        }
+        if moves.iter().any(|(_, (start_id, end_id))| !self.nodes.contains_key(start_id) || !self.nodes.contains_key(end_id)) {
+            return Err("Edge terminal not found in graph.".into());
+        }
+        let mut final_counts: HashMap<(Id, Id), usize> = HashMap::new();
+        let mut moved = moves.iter().map(|&(edge_index, _)| edge_index).peekable();
+        for (edge_index, edge) in self.edges.iter().enumerate() {
+            if moved.next_if_eq(&edge_index).is_none() {
+                *final_counts.entry(edge.terminal_ids()).or_default() += 1;
+            }
+        }
+        for (_, new_terminals) in moves.iter() {
+            *final_counts.entry(*new_terminals).or_default() += 1;
+        }
+        if moves.iter().any(|(_, new_terminals)| final_counts[new_terminals] > 1) {
+            return Err("Edge with these terminals already exists.".into());
+        }
+        let old_terminals: Vec<(Id, Id)> = moves.iter()
+            .map(|&(edge_index, _)| self.edges[edge_index].terminal_ids())
+            .collect();
+        let new_terminals: Vec<(Id, Id)> = moves.iter().map(|&(_, new_terminals)| new_terminals).collect();
+        self.check_moved_edge_limits(&old_terminals, &new_terminals)?;
+
+        let mut changes = Vec::with_capacity(2 * moves.len());
+        let mut additions = Vec::with_capacity(moves.len());
+        self.begin_change();
+        // Descending, so that swap_remove() never moves an edge still waiting to be taken out
+        for &(edge_index, (start_id, end_id)) in moves.iter().rev() {
+            let mut edge = self.edges[edge_index].clone();
+            self.remove_edge_unregistered(edge_index);
+            changes.push(GraphChange::RemoveEdge(edge.clone()));
+            edge.change_start(start_id);
+            edge.change_end(end_id);
+            additions.push(edge);
+        }
+        for edge in additions {
+            self.insert_edge_unregistered(edge.clone());
+            changes.push(GraphChange::AddEdge(edge));
+        }
+        // Tags go with their edges, all taken off before any is put back, as moves may swap terminals
+        let mut moved_tags = Vec::new();
+        for (&(start_id, end_id), &new_terminals) in old_terminals.iter().zip(new_terminals.iter()) {
+            let tags = self.set_edge_tags_unregistered(start_id, end_id, BTreeSet::new());
+            if !tags.is_empty() {
+                changes.push(GraphChange::SetEdgeTags(start_id, end_id, tags.clone()));
+                moved_tags.push((new_terminals, tags));
+            }
+        }
+        for ((start_id, end_id), tags) in moved_tags {
+            let previous_tags = self.set_edge_tags_unregistered(start_id, end_id, tags);
+            changes.push(GraphChange::SetEdgeTags(start_id, end_id, previous_tags));
+        }
+        if !changes.is_empty() {
+            self.commit_change(GraphChange::Batch(changes))?;
+        }
+        Ok(moves.len())
+    }
+
+    /// Inserts a bare edge for each (start, end) pair in `edges`, and a bare node for each id
+    /// not found, then clears the undo history like `from_terminal_pairs()`. Returns how many
+    /// edges were inserted.
+    ///
+    /// Much faster than `insert_edge_with_nodes()` one pair at a time on large inputs: space
+    /// is reserved up front and nothing is registered per edge. A pair matching an existing edge,
+    /// or an earlier pair, is skipped if `dedupe` is true and an error otherwise.
+    ///
+    /// If a duplicate is refused, nodes are needed but `BareNodePolicy::Deny` is set, a degree
+    /// limit would be broken, or the result closes a cycle
+    /// in DAG mode or breaks a constraint, an error is returned and nothing is inserted
+    /// (the history is then left as it was).
+    pub fn extend_from_edges<I: IntoIterator<Item = (Id, Id)>>(&mut self, edges: I, dedupe: bool) -> Result<usize, GraphError> {
+        trace_span!("extend_from_edges");
+        let edges = edges.into_iter();
+        let mut seen: HashSet<(Id, Id)> = HashSet::with_capacity(edges.size_hint().0);
+        let mut added = Vec::with_capacity(edges.size_hint().0);
+        for (start_id, end_id) in edges {
+            if self.edge_index(start_id, end_id).is_some() || !seen.insert((start_id, end_id)) {
+                match dedupe {
+                    true => continue,
+                    false => return Err("Edge with these terminals already exists.".into()),
+                }
+            }
+            added.push((start_id, end_id));
+        }
+        self.check_moved_edge_limits(&[], &added)?;
+        let new_ids: HashSet<Id> = added.iter()
+            .flat_map(|&(start_id, end_id)| [start_id, end_id])
+            .filter(|node_id| !self.nodes.contains_key(node_id))
+            .collect();
+        // Only `BareNodePolicy::Deny` fails, and for any id alike
+        if let Some(&node_id) = new_ids.iter().next() {
+            self.make_bare_node(node_id)?;
+        }
+        let new_count = new_ids.len();
+        self.nodes.reserve(new_count);
+        self.neighbors_before.reserve(new_count);
+        self.neighbors_after.reserve(new_count);
+        self.incident_edges.reserve(new_count);
+        self.edges.reserve(added.len());
+
+        self.begin_change();
+        let mut changes = Vec::with_capacity(added.len());
+        for &(start_id, end_id) in added.iter() {
+            let new_start = (!self.nodes.contains_key(&start_id)).then_some(start_id);
+            let new_end = (!self.nodes.contains_key(&end_id) && end_id != start_id).then_some(end_id);
+            for node_id in new_start.into_iter().chain(new_end) {
+                self.insert_node_unregistered(self.make_bare_node(node_id)?);
+            }
+            let edge = E::bare(start_id, end_id);
+            self.insert_edge_unregistered(edge.clone());
+            changes.push(GraphChange::AddEdgeWith(edge, new_start, new_end));
+        }
+        if !changes.is_empty() {
+            self.commit_change(GraphChange::Batch(changes))?;
+            self.clear_history();
+        }
+        Ok(added.len())
+    }
+
+    /// Removes every node with exactly one incoming and one outgoing edge (other than a
+    /// self-loop), joining its two edges into one, so that each chain of such nodes becomes
+    /// a single edge. `combine` builds that edge from the incoming edge, the node and the
+    /// outgoing edge; its terminals are then set to the chain's ends. Returns the removed
+    /// ids, in the order they were removed.
+    ///
+    /// Pinned nodes, and nodes whose predecessor already has an edge to their successor,
+    /// are kept. All removals are registered as a single change, so one `undo()` restores them.
+    /// If a constraint refuses the result (see `add_constraint()`), nothing is removed and an error is returned.
+    pub fn simplify_chains<F: FnMut(&E, &N, &E) -> E>(&mut self, mut combine: F) -> Result<Vec<Id>, GraphError> {
+        trace_span!("simplify_chains", nodes = self.nodes.len());
+        let mut collapsed_ids = Vec::new();
+        let mut changes = Vec::new();
+        self.begin_change();
+        for node_id in self.all_node_ids() {
+            let ids_before = self.neighbors_before[&node_id].as_slice();
+            let ids_after = self.neighbors_after[&node_id].as_slice();
+            let (&[id_before], &[id_after]) = (ids_before, ids_after) else {
+                continue;
+            };
+            if id_before == node_id || self.pinned.contains(&node_id) || self.edge_index(id_before, id_after).is_some() {
+                continue;
+            }
+            let edge_in = &self.edges[self.edge_index(id_before, node_id).unwrap()];
+            let edge_out = &self.edges[self.edge_index(node_id, id_after).unwrap()];
+            let mut joined_edge = combine(edge_in, &self.nodes[&node_id], edge_out);
+            joined_edge.change_start(id_before);
+            joined_edge.change_end(id_after);
+            let removed_edges = vec![edge_in.clone(), edge_out.clone()];
+            let removed_node = self.remove_node_unregistered(node_id);
+            changes.push(GraphChange::RemoveNode(removed_node, removed_edges));
+            self.insert_edge_unregistered(joined_edge.clone());
+            changes.push(GraphChange::AddEdge(joined_edge));
+            collapsed_ids.push(node_id);
+        }
+        if !changes.is_empty() {
+            self.commit_change(GraphChange::Batch(changes))?;
+        }
+        Ok(collapsed_ids)
+    }
+}
@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+
+use crate::graph_base::{graph_algo::{never_cancel, report, ProgressFn}, graph_components::{GraphError, Id, IdSet}};
+use super::{Nodal, DirEdge, DiGraph, compare::payload_text};
+
+/// Bounds on `DiGraph::edit_distance()`'s search
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct EditLimits {
+    /// Scripts with more ops than this are not looked for
+    pub max_distance: usize,
+    /// Alternative node pairings tried, beyond the first for each node, before settling
+    /// for the shortest script found so far
+    pub max_steps: usize,
+}
+
+impl Default for EditLimits {
+    fn default() -> Self {
+        Self { max_distance: usize::MAX, max_steps: 100_000 }
+    }
+}
+
+/// One step of an edit script from `DiGraph::edit_distance()`, with payloads as found in
+/// the graph edited (`old`) and in the graph it should become (`new`)
+#[derive(PartialEq, Debug, Clone)]
+pub enum EditOp<N, E> {
+    RemoveEdge(E),
+    RemoveNode(N),
+    /// Same id with another payload, or the node renumbered; its edges follow it
+    ReplaceNode { old: N, new: N },
+    InsertNode(N),
+    InsertEdge(E),
+    /// Same edge (once its terminals' nodes are replaced) with another payload
+    ReplaceEdge { old: E, new: E },
+}
+
+impl<N: Nodal, E: DirEdge> Display for EditOp<N, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RemoveEdge(edge) => write!(f, "Remove edge {}->{}", edge.start_id(), edge.end_id()),
+            Self::RemoveNode(node) => write!(f, "Remove node {}", node.node_id()),
+            Self::ReplaceNode { old, new } if old.node_id() == new.node_id() => {
+                write!(f, "Change node {}: {} -> {}", old.node_id(), payload_text(old), payload_text(new))
+            },
+            Self::ReplaceNode { old, new } => {
+                write!(f, "Replace node {} with node {}: {}", old.node_id(), new.node_id(), payload_text(new))
+            },
+            Self::InsertNode(node) => write!(f, "Insert node {}: {}", node.node_id(), payload_text(node)),
+            Self::InsertEdge(edge) => write!(f, "Insert edge {}->{}", edge.start_id(), edge.end_id()),
+            Self::ReplaceEdge { old, new } => {
+                write!(f, "Change edge {}->{}: {} -> {}", new.start_id(), new.end_id(), payload_text(old), payload_text(new))
+            },
+        }
+    }
+}
+
+/// Length of an edit script, and the script
+type Distance<N, E> = (usize, Vec<EditOp<N, E>>);
+
+/// Whether two edges differ other than in their terminals
+fn edge_payloads_differ<E: DirEdge>(old: &E, new: &E) -> bool {
+    let mut moved = old.clone();
+    moved.change_start(new.start_id());
+    moved.change_end(new.end_id());
+    moved != *new
+}
+
+/// Depth-first search over pairings of `old`'s nodes (in ascending id order) with `new`'s
+/// nodes or with removal, pruned by the cheapest complete pairing found so far
+struct Search<'a, N, E> {
+    old: &'a DiGraph<N, E>,
+    new: &'a DiGraph<N, E>,
+    old_ids: Vec<Id>,
+    new_ids: Vec<Id>,
+    /// New id paired with each of `old_ids` so far (`None` for removal)
+    pairing: Vec<Option<Id>>,
+    /// Old id paired with each paired new id
+    paired_with: HashMap<Id, Id>,
+    used: IdSet,
+    max_steps: usize,
+    steps_left: usize,
+    best: Option<(usize, Vec<Option<Id>>)>,
+    progress: ProgressFn<'a>,
+    cancelled: bool,
+}
+
+impl<N: Nodal, E: DirEdge> Search<'_, N, E> {
+    /// Where `old_id`, already paired, went (`None` if removed or not yet paired)
+    fn image(&self, old_id: Id) -> Option<Id> {
+        let index = self.old_ids.binary_search(&old_id).ok()?;
+        *self.pairing.get(index)?
+    }
+
+    /// Ops needed for the node at `self.pairing.len() - 1`, and for edges between it and
+    /// nodes paired before it
+    fn added_cost(&self, old_id: Id, new_id: Option<Id>) -> usize {
+        let mut cost = match new_id {
+            Some(new_id) => (self.old.nodes[&old_id] != self.new.nodes[&new_id]) as usize,
+            None => 1,
+        };
+        let index = self.pairing.len() - 1;
+        let is_paired = |id: Id| self.old_ids.binary_search(&id).is_ok_and(|i| i <= index);
+        for edge in self.old.edges_incident_on(old_id) {
+            let (start_id, end_id) = edge.terminal_ids();
+            if !(is_paired(start_id) && is_paired(end_id)) {
+                continue;
+            }
+            cost += match (self.image(start_id), self.image(end_id)) {
+                (Some(new_start), Some(new_end)) => match self.new.get_edge(new_start, new_end) {
+                    Some(new_edge) => edge_payloads_differ(edge, new_edge) as usize,
+                    None => 1,
+                },
+                _ => 1,
+            };
+        }
+        if let Some(new_id) = new_id {
+            for edge in self.new.edges_incident_on(new_id) {
+                let terminals = (self.paired_with.get(&edge.start_id()), self.paired_with.get(&edge.end_id()));
+                let (Some(&old_start), Some(&old_end)) = terminals else {
+                    continue;
+                };
+                cost += self.old.get_edge(old_start, old_end).is_none() as usize;
+            }
+        }
+        cost
+    }
+
+    /// Ops for the new nodes left unpaired, and their edges
+    fn closing_cost(&self) -> usize {
+        let unpaired_nodes = self.new_ids.len() - self.paired_with.len();
+        let unpaired_edges = self.new.edges.iter()
+            .filter(|edge| !self.used.contains(edge.start_id()) || !self.used.contains(edge.end_id()))
+            .count();
+        unpaired_nodes + unpaired_edges
+    }
+
+    fn bound(&self) -> usize {
+        self.best.as_ref().map_or(usize::MAX, |(cost, _)| *cost)
+    }
+
+    fn extend(&mut self, cost: usize) {
+        if self.cancelled {
+            return;
+        }
+        let index = self.pairing.len();
+        let Some(&old_id) = self.old_ids.get(index) else {
+            let total = cost + self.closing_cost();
+            if total < self.bound() {
+                self.best = Some((total, self.pairing.clone()));
+            }
+            return;
+        };
+        // New nodes that can't all be paired with the old nodes left must be inserted
+        let to_insert = (self.new_ids.len() - self.paired_with.len()).saturating_sub(self.old_ids.len() - index);
+        if cost + to_insert >= self.bound() {
+            return;
+        }
+        // Same id first, so that the first complete pairing is usually already a good bound
+        let same_id = self.new.nodes.contains_key(&old_id).then_some(old_id);
+        let others = self.new_ids.iter().copied().filter(|&id| id != old_id);
+        let candidates: Vec<Option<Id>> = same_id.into_iter().chain(others).map(Some).chain([None]).collect();
+        let mut first = true;
+        for new_id in candidates {
+            if new_id.is_some_and(|id| self.used.contains(id)) {
+                continue;
+            }
+            if !std::mem::take(&mut first) {
+                if self.steps_left == 0 {
+                    return;
+                }
+                self.steps_left -= 1;
+                if report(self.progress, self.max_steps - self.steps_left, self.max_steps).is_err() {
+                    self.cancelled = true;
+                    return;
+                }
+            }
+            self.pairing.push(new_id);
+            if let Some(new_id) = new_id {
+                self.used.insert(new_id);
+                self.paired_with.insert(new_id, old_id);
+            }
+            let cost = cost + self.added_cost(old_id, new_id);
+            if cost < self.bound() {
+                self.extend(cost);
+            }
+            if let Some(new_id) = new_id {
+                self.used.remove(new_id);
+                self.paired_with.remove(&new_id);
+            }
+            self.pairing.pop();
+        }
+    }
+}
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+
+    /// Returns the length of the shortest edit script turning `self` into `other` (counting one
+    /// for each node or edge removed, inserted, or replaced) and the script itself, e.g. to show
+    /// what changed between two versions of a story as a list of readable steps (each `EditOp`
+    /// implements `Display`).
+    ///
+    /// Nodes may be paired with nodes under other ids, at the cost of a `ReplaceNode`. Ops are
+    /// listed removals first, then replacements and insertions; they describe the difference and,
+    /// when nodes are renumbered, are not meant to be applied one by one.
+    ///
+    /// The search is exponential in the worst case. If it takes more than `limits.max_steps`
+    /// steps, the shortest script found by then is returned, which may not be the shortest there
+    /// is. Returns `None` if no script of at most `limits.max_distance` ops was found.
+    pub fn edit_distance(&self, other: &Self, limits: EditLimits) -> Option<(usize, Vec<EditOp<N, E>>)> {
+        self.edit_distance_with_progress(other, limits, &never_cancel).unwrap()
+    }
+
+    /// Same as `edit_distance()`, reporting each step (of `limits.max_steps`); see
+    /// `graph_algo::Progress`. The search may end before `limits.max_steps` steps are taken.
+    ///
+    /// If `progress` cancels, `GraphError::Cancelled` is returned.
+    pub fn edit_distance_with_progress(
+        &self,
+        other: &Self,
+        limits: EditLimits,
+        progress: ProgressFn,
+    ) -> Result<Option<Distance<N, E>>, GraphError> {
+        trace_span!("edit_distance", nodes = self.nodes.len(), other_nodes = other.nodes.len());
+        let mut search = Search {
+            old: self,
+            new: other,
+            old_ids: self.all_node_ids(),
+            new_ids: other.all_node_ids(),
+            pairing: Vec::with_capacity(self.nodes.len()),
+            paired_with: HashMap::with_capacity(other.nodes.len()),
+            used: IdSet::new(),
+            max_steps: limits.max_steps,
+            steps_left: limits.max_steps,
+            best: None,
+            progress,
+            cancelled: false,
+        };
+        if limits.max_distance < usize::MAX {
+            search.best = Some((limits.max_distance + 1, Vec::new()));
+        }
+        search.extend(0);
+        if search.cancelled {
+            return Err(GraphError::Cancelled);
+        }
+        let Some((distance, pairing)) = search.best.filter(|(distance, _)| *distance <= limits.max_distance) else {
+            return Ok(None);
+        };
+        let ops = self.edit_script(other, &search.old_ids, &pairing);
+        debug_assert_eq!(ops.len(), distance);
+        Ok(Some((distance, ops)))
+    }
+
+    fn edit_script(&self, other: &Self, old_ids: &[Id], pairing: &[Option<Id>]) -> Vec<EditOp<N, E>> {
+        let image: HashMap<Id, Option<Id>> = old_ids.iter().copied().zip(pairing.iter().copied()).collect();
+        let paired_with: HashMap<Id, Id> = image.iter()
+            .filter_map(|(&old_id, &new_id)| Some((new_id?, old_id)))
+            .collect();
+        let mut removals = Vec::new();
+        let mut edge_changes = Vec::new();
+        let mut old_edges: Vec<&E> = self.edges.iter().collect();
+        old_edges.sort_by_key(|edge| edge.terminal_ids());
+        for edge in old_edges {
+            let (start_id, end_id) = edge.terminal_ids();
+            let new_edge = match (image[&start_id], image[&end_id]) {
+                (Some(new_start), Some(new_end)) => other.get_edge(new_start, new_end),
+                _ => None,
+            };
+            match new_edge {
+                None => removals.push(EditOp::RemoveEdge(edge.clone())),
+                Some(new_edge) if edge_payloads_differ(edge, new_edge) => {
+                    edge_changes.push(EditOp::ReplaceEdge { old: edge.clone(), new: new_edge.clone() });
+                },
+                Some(_) => {},
+            }
+        }
+        let mut node_ops = Vec::new();
+        for (&old_id, &new_id) in old_ids.iter().zip(pairing) {
+            let old = &self.nodes[&old_id];
+            match new_id.map(|new_id| &other.nodes[&new_id]) {
+                None => removals.push(EditOp::RemoveNode(old.clone())),
+                Some(new) if new != old => node_ops.push(EditOp::ReplaceNode { old: old.clone(), new: new.clone() }),
+                Some(_) => {},
+            }
+        }
+        for new_id in other.all_node_ids() {
+            if !paired_with.contains_key(&new_id) {
+                node_ops.push(EditOp::InsertNode(other.nodes[&new_id].clone()));
+            }
+        }
+        let mut new_edges: Vec<&E> = other.edges.iter().collect();
+        new_edges.sort_by_key(|edge| edge.terminal_ids());
+        for edge in new_edges {
+            let old_edge = match (paired_with.get(&edge.start_id()), paired_with.get(&edge.end_id())) {
+                (Some(&old_start), Some(&old_end)) => self.get_edge(old_start, old_end),
+                _ => None,
+            };
+            if old_edge.is_none() {
+                node_ops.push(EditOp::InsertEdge(edge.clone()));
+            }
+        }
+        removals.into_iter().chain(node_ops).chain(edge_changes).collect()
+    }
+}
@@ -0,0 +1,57 @@
+use crate::graph_base::graph_components::{GraphError, Id};
+use super::{Nodal, DirEdge, DiGraph};
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+
+    /// Designates the node with id `node_id` as the graph's entry (e.g. a story's start), for
+    /// graphs where it can't be told from the sources alone. `get_source()` returns it from then on.
+    ///
+    /// The entry is saved with the graph, and follows the node if `replace_node()` or
+    /// `canonicalize()` gives it a new id. If the node is removed, the designation is cleared
+    /// (so a node that later takes the id doesn't inherit it), and undoing the removal brings
+    /// it back. A node hidden by `group_nodes()` keeps it, but isn't reported until `ungroup()`.
+    ///
+    /// If `node_id` is not found, an error is returned.
+    pub fn set_entry(&mut self, node_id: Id) -> Result<(), GraphError> {
+        if !self.nodes.contains_key(&node_id) {
+            return Err("Node with this id not found.".into());
+        }
+        self.entry = Some(node_id);
+        Ok(())
+    }
+
+    /// Same as `set_entry()`, for the graph's exit (e.g. a story's ending)
+    pub fn set_exit(&mut self, node_id: Id) -> Result<(), GraphError> {
+        if !self.nodes.contains_key(&node_id) {
+            return Err("Node with this id not found.".into());
+        }
+        self.exit = Some(node_id);
+        Ok(())
+    }
+
+    /// Id of the designated entry node, or `None` if there is none or it is hidden in a group
+    pub fn entry(&self) -> Option<Id> {
+        self.entry.filter(|node_id| self.nodes.contains_key(node_id))
+    }
+
+    /// Id of the designated exit node, or `None` if there is none or it is hidden in a group
+    pub fn exit(&self) -> Option<Id> {
+        self.exit.filter(|node_id| self.nodes.contains_key(node_id))
+    }
+
+    /// Returns the id that was designated, if any
+    pub fn clear_entry(&mut self) -> Option<Id> {
+        self.entry.take()
+    }
+
+    /// Returns the id that was designated, if any
+    pub fn clear_exit(&mut self) -> Option<Id> {
+        self.exit.take()
+    }
+
+    /// Renumbers the entry and exit designations, dropping those `renamed` maps to `None`
+    pub(super) fn rename_endpoints<F: Fn(Id) -> Option<Id>>(&mut self, renamed: F) {
+        self.entry = self.entry.and_then(&renamed);
+        self.exit = self.exit.and_then(&renamed);
+    }
+}
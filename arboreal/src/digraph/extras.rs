@@ -0,0 +1,95 @@
+use std::collections::{BTreeSet, HashSet};
+
+use crate::graph_base::graph_components::{GraphChange, Id, NodeExtras};
+use super::{Nodal, DirEdge, DiGraph};
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+
+    /// Replaces what is kept for the node with id `node_id` apart from its payload, and returns
+    /// what was
+    pub(super) fn set_node_extras_unregistered(&mut self, node_id: Id, extras: NodeExtras) -> NodeExtras {
+        let NodeExtras { tags, successor_order, is_entry, is_exit, max_out_degree, max_in_degree } = extras;
+        let previous = NodeExtras {
+            tags: self.set_node_tags_unregistered(node_id, tags),
+            successor_order: self.set_successor_order_unregistered(node_id, successor_order),
+            is_entry: self.entry == Some(node_id),
+            is_exit: self.exit == Some(node_id),
+            max_out_degree: set_limit(&mut self.max_out_degrees, node_id, max_out_degree),
+            max_in_degree: set_limit(&mut self.max_in_degrees, node_id, max_in_degree),
+        };
+        match (is_entry, previous.is_entry) {
+            (true, _) => self.entry = Some(node_id),
+            (false, true) => self.entry = None,
+            (false, false) => {},
+        }
+        match (is_exit, previous.is_exit) {
+            (true, _) => self.exit = Some(node_id),
+            (false, true) => self.exit = None,
+            (false, false) => {},
+        }
+        previous
+    }
+
+    /// Drops what was kept for the nodes and edges `change` (just made) removed, so that none
+    /// of it passes to a node or edge that later takes the same id or terminals, and returns
+    /// `change` batched with changes that put it back when undone. Nodes hidden in a group,
+    /// and their edges, keep everything for `ungroup()`.
+    pub(super) fn forget_removed(&mut self, change: GraphChange<N, E>) -> GraphChange<N, E> {
+        let mut node_ids = Vec::new();
+        let mut edge_pairs = Vec::new();
+        removed_by(&change, &mut node_ids, &mut edge_pairs);
+        if node_ids.is_empty() && edge_pairs.is_empty() {
+            return change;
+        }
+        let hidden: HashSet<Id> = self.groups.values()
+            .flat_map(|group| group.nodes().iter().map(Nodal::node_id))
+            .collect();
+        let mut changes = vec![change];
+        for node_id in node_ids {
+            if !self.nodes.contains_key(&node_id) && !hidden.contains(&node_id) {
+                let extras = self.set_node_extras_unregistered(node_id, NodeExtras::default());
+                if !extras.is_empty() {
+                    changes.push(GraphChange::SetNodeExtras(node_id, extras));
+                }
+            }
+        }
+        for (start_id, end_id) in edge_pairs {
+            if self.edge_index(start_id, end_id).is_none() && !hidden.contains(&start_id) && !hidden.contains(&end_id) {
+                let tags = self.set_edge_tags_unregistered(start_id, end_id, BTreeSet::new());
+                if !tags.is_empty() {
+                    changes.push(GraphChange::SetEdgeTags(start_id, end_id, tags));
+                }
+            }
+        }
+        match changes.len() {
+            1 => changes.pop().unwrap(),
+            _ => GraphChange::Batch(changes),
+        }
+    }
+}
+
+/// Sets or lifts the limit of `node_id` in `limits`, returning the one it had
+fn set_limit(limits: &mut std::collections::BTreeMap<Id, usize>, node_id: Id, limit: Option<usize>) -> Option<usize> {
+    match limit {
+        Some(limit) => limits.insert(node_id, limit),
+        None => limits.remove(&node_id),
+    }
+}
+
+/// Ids of nodes, and terminals of edges, that `change` removes
+fn removed_by<N: Nodal, E: DirEdge>(change: &GraphChange<N, E>, node_ids: &mut Vec<Id>, edge_pairs: &mut Vec<(Id, Id)>) {
+    match change {
+        GraphChange::RemoveNode(node, edges) => {
+            node_ids.push(node.node_id());
+            edge_pairs.extend(edges.iter().map(DirEdge::terminal_ids));
+        },
+        // Split in two
+        GraphChange::RemoveEdge(edge) | GraphChange::InsertNodeAlongEdge(_, edge) => edge_pairs.push(edge.terminal_ids()),
+        GraphChange::Batch(changes) => {
+            for change in changes.iter() {
+                removed_by(change, node_ids, edge_pairs);
+            }
+        },
+        _ => {},
+    }
+}
@@ -1,12 +1,14 @@
-
-use std::fs::File;
-use std::io::Read;
+use std::collections::HashMap;
+use std::io;
 use std::path::Path;
 
-use serde::{Serialize, Deserialize};
-use ron::{ser::PrettyConfig, de::from_bytes as ron_reader, Options as ron_writer, Result as RonResult};
+use serde::{de::IgnoredAny, Serialize, Deserialize};
+use ron::{ser::PrettyConfig, de::{from_bytes as ron_reader, from_str as ron_str_reader}, Options as ron_writer, Result as RonResult};
 
-use super::{Nodal, DirEdge, DiGraph};
+use crate::graph_base::graph_components::{BareEdge, BareNode, Id};
+use super::{compress, edge_map, Nodal, DirEdge, DiGraph, FileStorage, Storage};
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+use super::Compression;
 
 pub trait FileIO: Default + Serialize + for<'a> Deserialize<'a>
 {
@@ -15,25 +17,103 @@ pub trait FileIO: Default + Serialize + for<'a> Deserialize<'a>
             .depth_limit(4)
             .indentor("\t")
     }
+    /// Called on every freshly deserialized item, to rebuild anything serde skipped
+    fn after_load(&mut self) {}
+
+    /// Loads `path`, or returns the default if that fails for any reason, including a corrupt file
+    /// (logged as a warning with feature `trace`); see `load_or_default_strict()`
     fn load_or_default<P: AsRef<Path>>(path: P) -> Self {
-        Self::load_from_file(path).unwrap_or(Self::default())
+        let path = path.as_ref();
+        Self::load_from_file(path).unwrap_or_else(|| {
+            #[cfg(feature = "trace")]
+            if FileStorage::for_file(path).is_ok_and(|(storage, key)| storage.contains(&key)) {
+                tracing::warn!(path = %path.display(), "could not load file; using the default instead");
+            }
+            Self::default()
+        })
+    }
+    /// Same as `load_or_default()`, but only a missing file gives the default; a file that can't
+    /// be read or parsed is an error instead of being masked
+    fn load_or_default_strict<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let (storage, key) = FileStorage::for_file(path.as_ref())?;
+        let bytes = match storage.read(&key) {
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            result => result?,
+        };
+        Self::load_from_bytes(&bytes)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "File could not be parsed."))
     }
+    /// Loads `path` through a `FileStorage` (as do all the path-based methods here), or returns
+    /// `None` if there is no such file or it can't be loaded
     fn load_from_file<P: AsRef<Path>>(path: P) -> Option<Self> {
-        if let Ok(mut file) = File::open(path) {
-            let mut buf = vec![];
-            if file.read_to_end(&mut buf).is_ok() {
-                if let Ok(loaded_item) = ron_reader(&buf[..]) {
-                    return Some(loaded_item);
-                }
-            }
-        }
-        // No file, or failure to load
-        None
+        trace_span!("load_from_file");
+        let (storage, key) = FileStorage::for_file(path.as_ref()).ok()?;
+        Self::load_from_storage(&storage, &key)
+    }
+    /// Parses RON text, unpacking it first if it was saved with `save_to_file_compressed()`
+    fn load_from_bytes(bytes: &[u8]) -> Option<Self> {
+        trace_span!("load_from_bytes", bytes = bytes.len());
+        let bytes = compress::decompress_if_needed(bytes).ok()?;
+        let mut loaded_item: Self = ron_reader(&bytes).ok()?;
+        loaded_item.after_load();
+        Some(loaded_item)
     }
     fn save_to_file<P: AsRef<Path>>(&self, path: P) -> RonResult<()> {
-        let f = File::create(path)?;
+        trace_span!("save_to_file");
+        let (mut storage, key) = FileStorage::for_file(path.as_ref())?;
+        self.save_to_storage(&mut storage, &key)
+    }
+
+    /// Same as `save_to_file()`, but compressed. Every `load_*` method detects
+    /// and unpacks compressed input by itself.
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    fn save_to_file_compressed<P: AsRef<Path>>(&self, path: P, compression: Compression) -> RonResult<()> {
+        trace_span!("save_to_file_compressed");
+        let packed = compress::compress(self.to_ron_string()?.as_bytes(), compression)?;
+        let (mut storage, key) = FileStorage::for_file(path.as_ref())?;
+        storage.write(&key, &packed)?;
+        Ok(())
+    }
+
+    /// Same as `save_to_file()`, but returns the text instead of writing it anywhere
+    fn to_ron_string(&self) -> RonResult<String> {
+        trace_span!("to_ron_string");
         ron_writer::default()
-            .to_io_writer_pretty(f, self, Self::config())?;
+            .to_string_pretty(self, Self::config())
+    }
+    /// Same as `load_from_file()`, but parses `text` directly, e.g. a graph
+    /// embedded in the binary with `include_str!`
+    fn from_ron_str(text: &str) -> Option<Self> {
+        trace_span!("from_ron_str", bytes = text.len());
+        let mut loaded_item: Self = ron_str_reader(text).ok()?;
+        loaded_item.after_load();
+        Some(loaded_item)
+    }
+
+    #[cfg(feature = "json")]
+    fn to_json_string(&self) -> serde_json::Result<String> {
+        trace_span!("to_json_string");
+        serde_json::to_string_pretty(self)
+    }
+    #[cfg(feature = "json")]
+    fn from_json_str(text: &str) -> Option<Self> {
+        trace_span!("from_json_str", bytes = text.len());
+        let mut loaded_item: Self = serde_json::from_str(text).ok()?;
+        loaded_item.after_load();
+        Some(loaded_item)
+    }
+
+    /// Same as `load_from_file()`, but reads through any `Storage` backend
+    fn load_from_storage<S: Storage + ?Sized>(storage: &S, key: &str) -> Option<Self> {
+        trace_span!("load_from_storage", key);
+        let buf = storage.read(key).ok()?;
+        Self::load_from_bytes(&buf)
+    }
+    /// Same as `save_to_file()`, but writes through any `Storage` backend
+    fn save_to_storage<S: Storage + ?Sized>(&self, storage: &mut S, key: &str) -> RonResult<()> {
+        trace_span!("save_to_storage", key);
+        let text = self.to_ron_string()?;
+        storage.write(key, text.as_bytes())?;
         Ok(())
     }
 }
@@ -43,5 +123,57 @@ impl<N: Nodal, E: DirEdge> FileIO for DiGraph<N, E> {
     //  - config()
     //  - load_or_default()
     //  - load_from_file()
+    //  - load_from_bytes()
     //  - save_to_file()
+    //  - save_to_file_compressed(), with feature "gzip" or "zstd"
+    //  - to_ron_string()
+    //  - from_ron_str()
+    //  - to_json_string() and from_json_str(), with feature "json"
+    //  - load_from_storage()
+    //  - save_to_storage()
+
+    fn after_load(&mut self) {
+        self.rebuild_neighbors();
+        self.undo_history.fill_missing_records();
+    }
+}
+
+/// The parts of a saved `DiGraph` needed for its structure; payloads are skipped unparsed
+#[derive(Deserialize)]
+#[serde(rename = "DiGraph")]
+struct SavedStructure {
+    name: Option<String>,
+    nodes: HashMap<Id, IgnoredAny>,
+    #[serde(deserialize_with = "edge_map::deserialize_terminals")]
+    edges: Vec<(Id, Id)>,
+}
+
+impl DiGraph<BareNode, BareEdge> {
+
+    /// Same as `load_from_file()`, but keeps only the name, node ids and edge terminals of a
+    /// graph saved with any node and edge types, for tools that need just the topology
+    /// (validators, layout, ...).
+    ///
+    /// Payloads are skipped without being parsed; edge terminals come from the keys edges are
+    /// saved under. Files saved before format v2 list their edges without keys, so their
+    /// terminals are looked for in fields named `start`/`end` (or `source`/`target`,
+    /// `from`/`to`, `start_id`/`end_id`), or else the first field holding an `(id, id)` pair.
+    pub fn load_structure_only<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let (storage, key) = FileStorage::for_file(path.as_ref()).ok()?;
+        let buf = storage.read(&key).ok()?;
+        let bytes = compress::decompress_if_needed(&buf).ok()?;
+        let saved: SavedStructure = ron_reader(&bytes).ok()?;
+        let mut structure = DiGraph::new();
+        structure.name = saved.name;
+        for node_id in saved.nodes.into_keys() {
+            structure.insert_node_unregistered(BareNode::bare(node_id));
+        }
+        for (start_id, end_id) in saved.edges {
+            // A hand-edited file could have edges to missing nodes; those are dropped
+            if structure.nodes.contains_key(&start_id) && structure.nodes.contains_key(&end_id) {
+                structure.insert_edge_unregistered(BareEdge::bare(start_id, end_id));
+            }
+        }
+        Some(structure)
+    }
 }
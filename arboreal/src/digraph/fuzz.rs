@@ -0,0 +1,121 @@
+//! `arbitrary` support, for fuzzing code that handles graphs.
+
+use arbitrary::{Arbitrary, Unstructured};
+
+use crate::graph_base::graph_components::{GraphError, Id};
+use super::{Nodal, DirEdge, DiGraph};
+
+/// Arbitrary graphs and `GraphOp`s use ids up to this, so that random ops mostly
+/// name nodes that exist
+pub const ARBITRARY_ID_MAX: Id = 63;
+
+fn arbitrary_id(u: &mut Unstructured<'_>) -> arbitrary::Result<Id> {
+    u.int_in_range(0..=ARBITRARY_ID_MAX)
+}
+
+/// Builds a valid graph (distinct node ids, edges only between existing nodes, no two edges
+/// with the same terminals) with an empty undo history. Node and edge data come from
+/// `N::arbitrary()` and `E::arbitrary()`, with ids and terminals overwritten.
+impl<'a, N, E> Arbitrary<'a> for DiGraph<N, E>
+where
+    N: Nodal + Arbitrary<'a>,
+    E: DirEdge + Arbitrary<'a>,
+{
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let mut graph = Self::new();
+        let mut node_ids = Vec::new();
+        for _ in 0..u.int_in_range(0..=ARBITRARY_ID_MAX)? {
+            let mut node = N::arbitrary(u)?;
+            let node_id = arbitrary_id(u)?;
+            if graph.nodes.contains_key(&node_id) {
+                continue;
+            }
+            node.change_id(node_id);
+            graph.insert_node_unregistered(node);
+            node_ids.push(node_id);
+        }
+        if node_ids.is_empty() {
+            return Ok(graph);
+        }
+        for _ in 0..u.arbitrary_len::<(Id, Id)>()?.min(4 * node_ids.len()) {
+            let mut edge = E::arbitrary(u)?;
+            let (start_id, end_id) = (*u.choose(&node_ids)?, *u.choose(&node_ids)?);
+            if graph.edge_index(start_id, end_id).is_some() {
+                continue;
+            }
+            edge.change_start(start_id);
+            edge.change_end(end_id);
+            graph.insert_edge_unregistered(edge);
+        }
+        Ok(graph)
+    }
+}
+
+/// One public edit, for fuzzing sequences of edits and undos; see `DiGraph::apply_op()`
+#[derive(PartialEq, Debug, Clone)]
+pub enum GraphOp<N, E> {
+    InsertNode(N),
+    RemoveNode(Id),
+    ReplaceNode(Id, N),
+    /// Terminals come from the edge
+    InsertEdge(E),
+    RemoveEdge(Id, Id),
+    InsertEdgeWithNodes(Id, Id),
+    /// New id, then the terminals of the edge to split
+    InsertNodeAlong(Id, Id, Id),
+    ReverseEdge(Id, Id),
+    Undo,
+}
+
+impl<'a, N, E> Arbitrary<'a> for GraphOp<N, E>
+where
+    N: Nodal + Arbitrary<'a>,
+    E: DirEdge + Arbitrary<'a>,
+{
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(match u.int_in_range(0..=8)? {
+            0 => {
+                let mut node = N::arbitrary(u)?;
+                node.change_id(arbitrary_id(u)?);
+                Self::InsertNode(node)
+            },
+            1 => Self::RemoveNode(arbitrary_id(u)?),
+            2 => {
+                let old_id = arbitrary_id(u)?;
+                let mut node = N::arbitrary(u)?;
+                node.change_id(arbitrary_id(u)?);
+                Self::ReplaceNode(old_id, node)
+            },
+            3 => {
+                let mut edge = E::arbitrary(u)?;
+                edge.change_start(arbitrary_id(u)?);
+                edge.change_end(arbitrary_id(u)?);
+                Self::InsertEdge(edge)
+            },
+            4 => Self::RemoveEdge(arbitrary_id(u)?, arbitrary_id(u)?),
+            5 => Self::InsertEdgeWithNodes(arbitrary_id(u)?, arbitrary_id(u)?),
+            6 => Self::InsertNodeAlong(arbitrary_id(u)?, arbitrary_id(u)?, arbitrary_id(u)?),
+            7 => Self::ReverseEdge(arbitrary_id(u)?, arbitrary_id(u)?),
+            _ => Self::Undo,
+        })
+    }
+}
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+
+    /// Applies `op` through the matching public method, returning its error if it was refused
+    /// (`Undo` with nothing to undo is not an error)
+    pub fn apply_op(&mut self, op: GraphOp<N, E>) -> Result<(), GraphError> {
+        match op {
+            GraphOp::InsertNode(node) => self.insert_node(node),
+            GraphOp::RemoveNode(node_id) => self.remove_node(node_id).map(|_| ()),
+            GraphOp::ReplaceNode(old_id, node) => self.replace_node(old_id, node).map(|_| ()),
+            GraphOp::InsertEdge(edge) => self.insert_edge(edge),
+            GraphOp::RemoveEdge(start_id, end_id) => self.remove_edge(start_id, end_id),
+            GraphOp::InsertEdgeWithNodes(start_id, end_id) => self.insert_edge_with_nodes(start_id, end_id),
+            GraphOp::InsertNodeAlong(new_id, id_before, id_after) => self.insert_node_along(new_id, id_before, id_after),
+            GraphOp::ReverseEdge(start_id, end_id) => self.reverse_edge(start_id, end_id),
+            GraphOp::Undo => self.undo().map(|_| ()),
+        }
+    }
+}
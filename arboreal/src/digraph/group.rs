@@ -0,0 +1,146 @@
+use std::collections::BTreeMap;
+
+use crate::graph_base::{graph_components::{GraphChange, GraphError, Id, NodeGroup}, graph_ref};
+use super::{Nodal, DirEdge, DiGraph};
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+
+    /// Replaces the nodes in `node_ids` with a bare supernode with id `group_id` (e.g. to
+    /// collapse a cluster for display), as a single undoable change. Each outside node with
+    /// edges to or from the group gets one bare edge to or from the supernode, whose number of
+    /// hidden edges is kept in the group; see `group()` and `NodeGroup::hidden_edge_count()`.
+    ///
+    /// `group_id` may be one of the grouped ids. Groups may be nested.
+    ///
+    /// If `node_ids` is empty or has an id not found among the nodes, `group_id` is used by a
+    /// node outside the group or by another group, a grouped node is pinned, a degree limit
+    /// or constraint would be broken, or `BareNodePolicy::Deny` is set, an error is returned
+    /// and nothing is grouped.
+    pub fn group_nodes(&mut self, node_ids: &[Id], group_id: Id) -> Result<(), GraphError> {
+        trace_span!("group_nodes", nodes = node_ids.len(), group_id);
+        let mut member_ids = node_ids.to_vec();
+        member_ids.sort_unstable();
+        member_ids.dedup();
+        if member_ids.is_empty() {
+            return Err("No nodes to group.".into());
+        }
+        if let Some(&node_id) = member_ids.iter().find(|id| !self.nodes.contains_key(id)) {
+            return Err(GraphError::Invalid(match node_id == group_id {
+                true => "Group id not found among the nodes to group.",
+                false => "Node with this id not found.",
+            }));
+        }
+        if self.nodes.contains_key(&group_id) && member_ids.binary_search(&group_id).is_err() {
+            return Err("Node with this id already exists.".into());
+        }
+        let hidden_in_group = self.groups.values().any(|group| group.nodes.iter().any(|node| node.node_id() == group_id));
+        if self.group(group_id).is_some() || hidden_in_group {
+            return Err("Group with this id already exists.".into());
+        }
+        if let Some(&node_id) = member_ids.iter().find(|id| self.pinned.contains(id)) {
+            return Err(GraphError::NodePinned(node_id));
+        }
+
+        let is_member = |id: &Id| member_ids.binary_search(id).is_ok();
+        let mut hidden_edges = Vec::new();
+        let mut edge_counts: BTreeMap<(Id, Id), usize> = BTreeMap::new();
+        for edge in self.edges.iter() {
+            let (start_id, end_id) = edge.terminal_ids();
+            let collapsed = match (is_member(&start_id), is_member(&end_id)) {
+                (false, false) => continue,
+                (true, true) => None,
+                (true, false) => Some((group_id, end_id)),
+                (false, true) => Some((start_id, group_id)),
+            };
+            hidden_edges.push(edge.clone());
+            if let Some(terminals) = collapsed {
+                *edge_counts.entry(terminals).or_default() += 1;
+            }
+        }
+        let removed_terminals: Vec<(Id, Id)> = hidden_edges.iter().map(|edge| edge.terminal_ids()).collect();
+        let added_terminals: Vec<(Id, Id)> = edge_counts.keys().copied().collect();
+        self.check_moved_edge_limits(&removed_terminals, &added_terminals)?;
+        let supernode = self.make_bare_node(group_id)?;
+
+        let mut changes = Vec::with_capacity(member_ids.len() + added_terminals.len() + 2);
+        let mut members = Vec::with_capacity(member_ids.len());
+        self.begin_change();
+        for &node_id in member_ids.iter() {
+            let change = graph_ref::check_remove_node(&self.nodes, self.edges_incident_on(node_id), node_id);
+            members.push(self.remove_node_unregistered(node_id));
+            changes.push(change);
+        }
+        self.insert_node_unregistered(supernode.clone());
+        changes.push(GraphChange::AddNode(supernode));
+        for &(start_id, end_id) in added_terminals.iter() {
+            self.insert_edge_unregistered(E::bare(start_id, end_id));
+            changes.push(GraphChange::AddEdge(E::bare(start_id, end_id)));
+        }
+        let group = NodeGroup {
+            nodes: members,
+            edges: hidden_edges,
+            edge_counts: edge_counts.into_iter().map(|((start_id, end_id), count)| (start_id, end_id, count)).collect(),
+        };
+        let previous_group = self.set_group_unregistered(group_id, Some(group));
+        changes.push(GraphChange::SetGroup(group_id, previous_group));
+        self.commit_change(GraphChange::Batch(changes))
+    }
+
+    /// Removes the supernode `group_id` and its edges, and puts back the nodes it hid along
+    /// with their edges, as a single undoable change. Hidden edges to nodes removed since are
+    /// left out. Returns the restored ids, ascending.
+    ///
+    /// If `group_id` is not a group's supernode, the supernode is pinned, a hidden id has been
+    /// taken by another node since, or a degree limit or constraint would be broken, an error
+    /// is returned and nothing is changed.
+    pub fn ungroup(&mut self, group_id: Id) -> Result<Vec<Id>, GraphError> {
+        trace_span!("ungroup", group_id);
+        let Some(group) = self.groups.get(&group_id).filter(|_| self.nodes.contains_key(&group_id)) else {
+            return Err("Group with this id not found.".into());
+        };
+        if self.pinned.contains(&group_id) {
+            return Err(GraphError::NodePinned(group_id));
+        }
+        let member_ids = group.member_ids();
+        if member_ids.iter().any(|&id| id != group_id && self.nodes.contains_key(&id)) {
+            return Err("Node with this id already exists.".into());
+        }
+        let is_present = |id: Id| member_ids.binary_search(&id).is_ok() || (id != group_id && self.nodes.contains_key(&id));
+        let restored_edges: Vec<E> = group.edges.iter()
+            .filter(|edge| is_present(edge.start_id()) && is_present(edge.end_id()))
+            .cloned()
+            .collect();
+        let removed_terminals: Vec<(Id, Id)> = self.edges_incident_on(group_id).map(|edge| edge.terminal_ids()).collect();
+        let added_terminals: Vec<(Id, Id)> = restored_edges.iter().map(|edge| edge.terminal_ids()).collect();
+        self.check_moved_edge_limits(&removed_terminals, &added_terminals)?;
+
+        self.begin_change();
+        let group = self.set_group_unregistered(group_id, None).unwrap();
+        let mut changes = Vec::with_capacity(group.nodes.len() + restored_edges.len() + 2);
+        changes.push(GraphChange::SetGroup(group_id, Some(group.clone())));
+        changes.push(graph_ref::check_remove_node(&self.nodes, self.edges_incident_on(group_id), group_id));
+        self.remove_node_unregistered(group_id);
+        for node in group.nodes {
+            self.insert_node_unregistered(node.clone());
+            changes.push(GraphChange::AddNode(node));
+        }
+        for edge in restored_edges {
+            self.insert_edge_unregistered(edge.clone());
+            changes.push(GraphChange::AddEdge(edge));
+        }
+        self.commit_change(GraphChange::Batch(changes))?;
+        Ok(member_ids)
+    }
+
+    /// Nodes hidden behind the supernode `group_id`, or `None` if it is not a group's supernode
+    pub fn group(&self, group_id: Id) -> Option<&NodeGroup<N, E>> {
+        self.groups.get(&group_id).filter(|_| self.nodes.contains_key(&group_id))
+    }
+
+    pub(super) fn set_group_unregistered(&mut self, group_id: Id, group: Option<NodeGroup<N, E>>) -> Option<NodeGroup<N, E>> {
+        match group {
+            Some(group) => self.groups.insert(group_id, group),
+            None => self.groups.remove(&group_id),
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use crate::graph_base::graph_components::Id;
+use super::{Nodal, DirEdge, DiGraph};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// 64-bit FNV-1a, used instead of `DefaultHasher` so hashes stay the same
+/// across Rust releases and platforms (they may be stored as cache keys)
+struct StableHasher(u64);
+
+impl StableHasher {
+    fn new() -> Self {
+        Self(FNV_OFFSET_BASIS)
+    }
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+    fn write_id(&mut self, id: Id) {
+        self.write(&id.to_le_bytes());
+    }
+    fn write_payload<T: serde::Serialize>(&mut self, payload: &T) {
+        // Payloads are only required to be Serialize, so hash their RON text
+        let text = ron::to_string(payload).unwrap_or_default();
+        self.write(&(text.len() as u64).to_le_bytes());
+        self.write(text.as_bytes());
+    }
+}
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+
+    /// Hash of node ids and edge terminals only.
+    ///
+    /// Independent of insertion order and of the graph's history, and stable
+    /// between runs, so it can be compared across saved files before diffing them.
+    pub fn structure_hash(&self) -> u64 {
+        self.stable_hash(false)
+    }
+
+    /// Same as `structure_hash()`, but node and edge payloads also contribute
+    pub fn structure_hash_with_payloads(&self) -> u64 {
+        self.stable_hash(true)
+    }
+
+    fn stable_hash(&self, include_payloads: bool) -> u64 {
+        let mut hasher = StableHasher::new();
+        let node_ids = self.all_node_ids();
+        hasher.write(&(node_ids.len() as u64).to_le_bytes());
+        for node_id in node_ids {
+            hasher.write_id(node_id);
+            if include_payloads {
+                hasher.write_payload(&self.nodes[&node_id]);
+            }
+        }
+        let edge_pairs = self.all_edge_pairs();
+        hasher.write(&(edge_pairs.len() as u64).to_le_bytes());
+        for (start_id, end_id) in edge_pairs {
+            hasher.write_id(start_id);
+            hasher.write_id(end_id);
+            if include_payloads {
+                hasher.write_payload(self.get_edge(start_id, end_id).unwrap());
+            }
+        }
+        hasher.0
+    }
+}
@@ -0,0 +1,78 @@
+use super::{Nodal, DirEdge, DiGraph};
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+
+    /// Copy of the graph's current contents, with an empty undo history
+    pub(super) fn snapshot(&self) -> Self {
+        let mut copy = Self::new();
+        copy.name = self.name.clone();
+        copy.metadata = self.metadata.clone();
+        copy.nodes = self.nodes.clone();
+        copy.edges = self.edges.clone();
+        copy.successor_order = self.successor_order.clone();
+        copy.generations = self.generations.clone();
+        copy.freed_ids = self.freed_ids.clone();
+        copy.id_reuse = self.id_reuse;
+        copy.entry = self.entry;
+        copy.exit = self.exit;
+        copy.pinned = self.pinned.clone();
+        copy.groups = self.groups.clone();
+        copy.node_tags = self.node_tags.clone();
+        copy.edge_tags = self.edge_tags.clone();
+        copy.max_out_degrees = self.max_out_degrees.clone();
+        copy.max_in_degrees = self.max_in_degrees.clone();
+        copy.rebuild_neighbors();
+        copy
+    }
+
+    /// Copy of just the name, nodes and edges, with neighbor lists rebuilt and nothing else
+    /// (no history, metadata, pins, tags, successor orders, limits or constraints). Cheaper
+    /// and more predictable than `clone()` when only the structure and payloads matter.
+    pub fn clone_data(&self) -> Self {
+        let mut copy = Self::new();
+        copy.name = self.name.clone();
+        copy.nodes = self.nodes.clone();
+        copy.edges = self.edges.clone();
+        copy.rebuild_neighbors();
+        copy
+    }
+
+    /// Returns the graph as it was `steps_back` changes ago (`state_at(0)` is a copy of the
+    /// current graph), or `None` if the undo history holds fewer changes than that.
+    ///
+    /// The returned graph has an empty undo history; `self` is left untouched.
+    pub fn state_at(&self, steps_back: usize) -> Option<Self> {
+        self.history_states().nth(steps_back)
+    }
+
+    /// Iterates over past versions of the graph, from the current state back to the state
+    /// before the oldest change in the undo history (e.g. to animate edits, or to find
+    /// the change after which some check first fails).
+    ///
+    /// Each version is built by reversing one more change on a copy, so `self` is left untouched.
+    pub fn history_states(&self) -> impl Iterator<Item = Self> + '_ {
+        let mut state = self.snapshot();
+        let current = std::iter::once(state.snapshot());
+        let past = self.undo_history.iter_changes().rev().map(move |change| {
+            state.reverse_change(change.clone()).unwrap();
+            state.snapshot()
+        });
+        current.chain(past)
+    }
+}
+
+/// Copies everything saved with the graph except the undo history (which starts empty), i.e.
+/// also metadata, pins, tags, groups, successor orders, entry and exit, degree limits and
+/// CRDT tracking, as well as reserved ids. Constraints, DAG mode, strict mode and dirty flags
+/// are not copied. The copy equals the original, as `==` leaves out the history and such settings.
+impl<N: Nodal, E: DirEdge> Clone for DiGraph<N, E> {
+    fn clone(&self) -> Self {
+        let mut copy = self.snapshot();
+        copy.reserved_ids = self.reserved_ids.clone();
+        #[cfg(feature = "crdt")]
+        {
+            copy.crdt = self.crdt.clone();
+        }
+        copy
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::graph_base::graph_components::{GraphError, Id, ID_MAX, NodeHandle};
+use super::{Nodal, DirEdge, DiGraph};
+
+/// Total number of distinct ids, `0..=ID_MAX`
+const ID_SPACE: usize = ID_MAX as usize + 1;
+
+/// Which ids auto-allocation (`next_free_id()`, `insert_bare_node()`, `reserve_ids()`) hands
+/// out once their node has been removed; see `DiGraph::set_id_reuse()`
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum IdReuse {
+    /// Never, e.g. while external systems may still refer to removed nodes by id
+    Never,
+    /// Most recently freed first, then the smallest id never used
+    Lifo,
+    /// Smallest unused id, freed or not
+    #[default]
+    Smallest,
+}
+
+impl IdReuse {
+    pub(super) fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+
+    pub(super) fn id_available(&self, id: Id) -> bool {
+        !self.nodes.contains_key(&id) && !self.reserved_ids.contains(&id)
+    }
+
+    /// Ids auto-allocation may hand out, in the order the `IdReuse` setting prefers them
+    fn allocatable_ids(&self) -> impl Iterator<Item = Id> + '_ {
+        let skipped: HashSet<Id> = match self.id_reuse {
+            IdReuse::Smallest => HashSet::new(),
+            IdReuse::Never | IdReuse::Lifo => self.freed_ids.iter().copied().collect(),
+        };
+        let recently_freed: &[Id] = match self.id_reuse {
+            IdReuse::Lifo => &self.freed_ids,
+            IdReuse::Never | IdReuse::Smallest => &[],
+        };
+        recently_freed.iter()
+            .rev()
+            .copied()
+            .filter(|&id| self.id_available(id))
+            .chain((0..=ID_MAX).filter(move |&id| self.id_available(id) && !skipped.contains(&id)))
+    }
+
+    /// Chooses which ids auto-allocation hands out again after their node is removed.
+    /// `IdReuse::Smallest` by default. The setting and the freed ids are saved with the graph.
+    ///
+    /// Ids are freed by any removal, including by `undo()` (e.g. of an insertion), and taken
+    /// back by any insertion, including by `undo()` of a removal.
+    pub fn set_id_reuse(&mut self, id_reuse: IdReuse) {
+        self.id_reuse = id_reuse;
+    }
+
+    pub fn id_reuse(&self) -> IdReuse {
+        self.id_reuse
+    }
+
+    /// Ids whose node was removed and that no node has taken since, oldest first
+    pub fn freed_ids(&self) -> &[Id] {
+        &self.freed_ids
+    }
+
+    /// Number of ids auto-allocation can still hand out: neither used by a node nor reserved,
+    /// nor freed under `IdReuse::Never`
+    pub fn remaining_id_capacity(&self) -> usize {
+        let retired = match self.id_reuse {
+            IdReuse::Never => self.freed_ids.iter().filter(|id| !self.reserved_ids.contains(id)).count(),
+            IdReuse::Lifo | IdReuse::Smallest => 0,
+        };
+        ID_SPACE - self.nodes.len() - self.reserved_ids.len() - retired
+    }
+
+    /// Returns the id auto-allocation would hand out next: the smallest id that is neither
+    /// in use nor reserved, unless `set_id_reuse()` says otherwise.
+    ///
+    /// If no id is left, `GraphError::IdSpaceExhausted` is returned.
+    pub fn next_free_id(&self) -> Result<Id, GraphError> {
+        self.allocatable_ids()
+            .next()
+            .ok_or(GraphError::IdSpaceExhausted)
+    }
+
+    /// Inserts a bare node (see `set_bare_node_policy()`) under an automatically allocated id,
+    /// and returns that id.
+    ///
+    /// If no id is available, `GraphError::IdSpaceExhausted` is returned; if
+    /// `BareNodePolicy::Deny` is set, an error is returned.
+    pub fn insert_bare_node(&mut self) -> Result<Id, GraphError> {
+        let new_id = self.next_free_id()?;
+        self.insert_node(self.make_bare_node(new_id)?)?;
+        Ok(new_id)
+    }
+
+    /// Sets aside `n` unused ids, chosen as `next_free_id()` would, so that auto-allocation will
+    /// not hand them out.
+    ///
+    /// A reservation ends when a node with that id is inserted, or on `release_ids()`.
+    /// Either all `n` ids are reserved, or (if fewer remain) none are and
+    /// `GraphError::IdSpaceExhausted` is returned.
+    pub fn reserve_ids(&mut self, n: usize) -> Result<Vec<Id>, GraphError> {
+        if n > self.remaining_id_capacity() {
+            return Err(GraphError::IdSpaceExhausted);
+        }
+        let new_ids: Vec<Id> = self.allocatable_ids()
+            .take(n)
+            .collect();
+        self.reserved_ids.extend(new_ids.iter().copied());
+        Ok(new_ids)
+    }
+
+    /// Returns reserved ids to the pool of ids available for auto-allocation
+    pub fn release_ids(&mut self, ids: &[Id]) {
+        for id in ids {
+            self.reserved_ids.remove(id);
+        }
+    }
+
+    fn generation(&self, id: Id) -> u32 {
+        self.generations.get(&id).copied().unwrap_or(0)
+    }
+
+    /// Returns a handle to the node with id `node_id`, or `None` if there is no such node.
+    ///
+    /// The handle goes stale when that node is removed, including by `undo()`;
+    /// restoring the node (e.g. undoing its removal) does not revive old handles.
+    pub fn node_handle(&self, node_id: Id) -> Option<NodeHandle> {
+        self.nodes.contains_key(&node_id)
+            .then(|| NodeHandle { id: node_id, generation: self.generation(node_id) })
+    }
+
+    pub fn is_handle_live(&self, handle: NodeHandle) -> bool {
+        self.nodes.contains_key(&handle.id) && self.generation(handle.id) == handle.generation
+    }
+
+    /// Returns the id `handle` refers to.
+    ///
+    /// If its node has been removed since the handle was made, `GraphError::StaleHandle` is returned.
+    pub fn resolve_handle(&self, handle: NodeHandle) -> Result<Id, GraphError> {
+        match self.is_handle_live(handle) {
+            true => Ok(handle.id),
+            false => Err(GraphError::StaleHandle(handle)),
+        }
+    }
+
+    pub fn get_node_by_handle(&self, handle: NodeHandle) -> Result<&N, GraphError> {
+        let node_id = self.resolve_handle(handle)?;
+        Ok(&self.nodes[&node_id])
+    }
+    pub fn get_node_by_handle_mut(&mut self, handle: NodeHandle) -> Result<&mut N, GraphError> {
+        let node_id = self.resolve_handle(handle)?;
+        Ok(self.nodes.get_mut(&node_id).unwrap())
+    }
+}
@@ -0,0 +1,33 @@
+use crate::graph_base::graph_components::{EdgeKeyed, GraphChange, GraphError};
+use super::{Nodal, DiGraph};
+
+impl<N: Nodal, E: EdgeKeyed> DiGraph<N, E> {
+
+    fn edge_index_by_key(&self, key: u32) -> Option<usize> {
+        self.edges
+            .iter()
+            .position(|edge| edge.edge_key() == key)
+    }
+
+    pub fn get_edge_by_key(&self, key: u32) -> Option<&E> {
+        let index = self.edge_index_by_key(key)?;
+        self.edges.get(index)
+    }
+    pub fn get_edge_by_key_mut(&mut self, key: u32) -> Option<&mut E> {
+        let index = self.edge_index_by_key(key)?;
+        self.edges.get_mut(index)
+    }
+
+    /// Removes and returns the edge whose `edge_key()` equals `key`.
+    ///
+    /// If no such edge is present in the graph, an error is returned.
+    pub fn remove_edge_by_key(&mut self, key: u32) -> Result<E, GraphError> {
+        let edge_index = self.edge_index_by_key(key)
+            .ok_or(GraphError::Invalid("Edge with this key not found."))?;
+        let removed_edge = self.edges[edge_index].clone();
+        self.begin_change();
+        self.remove_edge_unregistered(edge_index);
+        self.commit_change(GraphChange::RemoveEdge(removed_edge.clone()))?;
+        Ok(removed_edge)
+    }
+}
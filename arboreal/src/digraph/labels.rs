@@ -0,0 +1,46 @@
+use crate::graph_base::{graph_algo::{never_cancel, ProgressFn}, graph_components::{GraphError, Id, LabeledEdge}, label_path::{self, LabelPattern}};
+use super::{Nodal, DirEdge, DiGraph};
+
+impl<N: Nodal, E: DirEdge + LabeledEdge> DiGraph<N, E> {
+
+    /// Id at the end of the edge leaving `node_id` with label `label`, or `None` if there is none.
+    ///
+    /// If several edges share the label, the one with the smallest end id is returned;
+    /// add the `UniqueEdgeLabels` constraint to rule that out.
+    pub fn successor_by_label(&self, node_id: Id, label: &str) -> Option<Id> {
+        self.edges_incident_on(node_id)
+            .filter(|edge| edge.start_id() == node_id && edge.label() == label)
+            .map(|edge| edge.end_id())
+            .min()
+    }
+
+    /// For each node where a path from `start_id` whose edge labels match `pattern` ends,
+    /// one such path with the fewest edges (ids from `start_id` on), ordered by end id;
+    /// see `graph_base::label_path` for the pattern syntax.
+    ///
+    /// Empty if `start_id` is not found among the nodes.
+    pub fn match_label_path(&self, start_id: Id, pattern: &LabelPattern) -> Vec<Vec<Id>> {
+        self.match_label_path_with_progress(start_id, pattern, &never_cancel).unwrap()
+    }
+
+    /// Same as `match_label_path()`, reporting each (node, pattern state) pair searched;
+    /// see `graph_algo::Progress`.
+    ///
+    /// If `progress` cancels, `GraphError::Cancelled` is returned.
+    pub fn match_label_path_with_progress(
+        &self,
+        start_id: Id,
+        pattern: &LabelPattern,
+        progress: ProgressFn,
+    ) -> Result<Vec<Vec<Id>>, GraphError> {
+        if !self.nodes.contains_key(&start_id) {
+            return Ok(Vec::new());
+        }
+        let edges_from = |node_id| {
+            self.edges_incident_on(node_id)
+                .filter(move |edge| edge.start_id() == node_id)
+                .map(|edge| (edge.end_id(), edge.label()))
+        };
+        label_path::match_label_path_with_progress(start_id, pattern, edges_from, self.nodes.len(), progress)
+    }
+}
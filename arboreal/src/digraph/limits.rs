@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+
+use crate::graph_base::graph_components::{GraphError, Id};
+use super::{Nodal, DirEdge, DiGraph};
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+
+    /// Caps the number of edges leaving `node_id` at `max` (e.g. choices shown by a dialogue node);
+    /// edge insertions past it return `GraphError::OutDegreeLimit`.
+    ///
+    /// Limits are saved with the graph. They are lifted when the node is removed, and come back
+    /// if the removal is undone; `clear_degree_limits()` lifts them all.
+    ///
+    /// If `node_id` is not found, or already has more than `max` edges leaving it, an error is returned.
+    pub fn set_max_out_degree(&mut self, node_id: Id, max: usize) -> Result<(), GraphError> {
+        let out_degree = self.out_degree(node_id)
+            .ok_or(GraphError::Invalid("Node with this id not found."))?;
+        if out_degree > max {
+            return Err(GraphError::OutDegreeLimit { id: node_id, max });
+        }
+        self.max_out_degrees.insert(node_id, max);
+        Ok(())
+    }
+
+    /// Same as `set_max_out_degree()`, for edges entering `node_id`
+    pub fn set_max_in_degree(&mut self, node_id: Id, max: usize) -> Result<(), GraphError> {
+        let in_degree = self.in_degree(node_id)
+            .ok_or(GraphError::Invalid("Node with this id not found."))?;
+        if in_degree > max {
+            return Err(GraphError::InDegreeLimit { id: node_id, max });
+        }
+        self.max_in_degrees.insert(node_id, max);
+        Ok(())
+    }
+
+    pub fn max_out_degree(&self, node_id: Id) -> Option<usize> {
+        self.max_out_degrees.get(&node_id).copied()
+    }
+    pub fn max_in_degree(&self, node_id: Id) -> Option<usize> {
+        self.max_in_degrees.get(&node_id).copied()
+    }
+
+    /// Lifts both limits on `node_id`
+    pub fn clear_degree_limits(&mut self, node_id: Id) {
+        self.max_out_degrees.remove(&node_id);
+        self.max_in_degrees.remove(&node_id);
+    }
+
+    /// Fails if `added_out` more edges leaving, or `added_in` more edges entering,
+    /// `node_id` would break its limits
+    pub(super) fn check_degree_limits(&self, node_id: Id, added_out: usize, added_in: usize) -> Result<(), GraphError> {
+        if let Some(&max) = self.max_out_degrees.get(&node_id)
+            && added_out > 0
+            && self.out_degree(node_id).unwrap_or(0) + added_out > max
+        {
+            return Err(GraphError::OutDegreeLimit { id: node_id, max });
+        }
+        if let Some(&max) = self.max_in_degrees.get(&node_id)
+            && added_in > 0
+            && self.in_degree(node_id).unwrap_or(0) + added_in > max
+        {
+            return Err(GraphError::InDegreeLimit { id: node_id, max });
+        }
+        Ok(())
+    }
+
+    /// Same as `check_degree_limits()`, for one new edge
+    pub(super) fn check_edge_limits(&self, start_id: Id, end_id: Id) -> Result<(), GraphError> {
+        self.check_degree_limits(start_id, 1, 0)?;
+        self.check_degree_limits(end_id, 0, 1)
+    }
+
+    /// Same as `check_degree_limits()`, for edges with terminals `removed` taken out
+    /// and edges with terminals `added` put in
+    pub(super) fn check_moved_edge_limits(&self, removed: &[(Id, Id)], added: &[(Id, Id)]) -> Result<(), GraphError> {
+        let mut net_changes: HashMap<Id, (isize, isize)> = HashMap::new();
+        for (terminals, step) in removed.iter().map(|t| (t, -1)).chain(added.iter().map(|t| (t, 1))) {
+            net_changes.entry(terminals.0).or_default().0 += step;
+            net_changes.entry(terminals.1).or_default().1 += step;
+        }
+        let mut ids: Vec<Id> = net_changes.keys().copied().collect();
+        ids.sort_unstable();
+        for id in ids {
+            let (added_out, added_in) = net_changes[&id];
+            self.check_degree_limits(id, added_out.max(0) as usize, added_in.max(0) as usize)?;
+        }
+        Ok(())
+    }
+}
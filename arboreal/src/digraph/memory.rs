@@ -0,0 +1,129 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::mem::size_of;
+
+use smallvec::{Array, SmallVec};
+
+use crate::graph_base::graph_components::{GraphChange, Id, NodeGroup};
+use super::{Nodal, DirEdge, DiGraph};
+
+/// Estimated bytes held by a graph, from `DiGraph::memory_footprint()`
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct MemoryReport {
+    /// Node map, with the heap size each payload reports
+    pub nodes: usize,
+    /// Edge list, with the heap size each payload reports
+    pub edges: usize,
+    /// Neighbor and incident edge lookups, rebuilt from the edges
+    pub adjacency: usize,
+    /// Undo history, with the payloads it keeps
+    pub history: usize,
+    /// Everything else: name, metadata, tags, groups, successor orders and the like
+    pub other: usize,
+}
+
+impl MemoryReport {
+    pub fn total(&self) -> usize {
+        self.nodes + self.edges + self.adjacency + self.history + self.other
+    }
+}
+
+/// Allocated slots, plus a control byte each as in the std hash table
+fn hash_map_bytes<K, V>(map: &HashMap<K, V>) -> usize {
+    map.capacity() * (size_of::<(K, V)>() + 1)
+}
+
+// B-tree nodes are not exposed, so count entries only
+fn btree_map_bytes<K, V>(map: &BTreeMap<K, V>) -> usize {
+    map.len() * size_of::<(K, V)>()
+}
+
+fn btree_set_bytes<T>(set: &BTreeSet<T>) -> usize {
+    set.len() * size_of::<T>()
+}
+
+fn vec_bytes<T>(vec: &Vec<T>) -> usize {
+    vec.capacity() * size_of::<T>()
+}
+
+/// Heap only: small vectors that haven't spilled live inside their map entry
+fn small_vec_bytes<A: Array>(vec: &SmallVec<A>) -> usize {
+    match vec.spilled() {
+        true => vec.capacity() * size_of::<A::Item>(),
+        false => 0,
+    }
+}
+
+fn strings_bytes<'a, I: IntoIterator<Item = &'a String>>(strings: I) -> usize {
+    strings.into_iter().map(String::capacity).sum()
+}
+
+/// Heap held by `group`, not counting the group itself
+fn group_bytes<N: Nodal, E: DirEdge>(group: &NodeGroup<N, E>) -> usize {
+    vec_bytes(&group.nodes) + group.nodes.iter().map(Nodal::heap_size).sum::<usize>()
+        + vec_bytes(&group.edges) + group.edges.iter().map(DirEdge::heap_size).sum::<usize>()
+        + vec_bytes(&group.edge_counts)
+}
+
+/// Heap held by `change`, not counting the change itself
+fn change_bytes<N: Nodal, E: DirEdge>(change: &GraphChange<N, E>) -> usize {
+    match change {
+        GraphChange::AddNode(node) | GraphChange::ReplaceNode(node, _) => node.heap_size(),
+        GraphChange::RemoveNode(node, edges) => {
+            node.heap_size() + vec_bytes(edges) + edges.iter().map(DirEdge::heap_size).sum::<usize>()
+        },
+        GraphChange::AddEdge(edge) | GraphChange::AddEdgeWith(edge, _, _) | GraphChange::RemoveEdge(edge) => edge.heap_size(),
+        GraphChange::InsertNodeAlongEdge(node, edge) => node.heap_size() + edge.heap_size(),
+        GraphChange::ReorderSuccessors(_, order) => vec_bytes(order),
+        GraphChange::SetGroup(_, group) => group.as_ref().map_or(0, group_bytes),
+        GraphChange::SetNodeExtras(_, extras) => {
+            btree_set_bytes(&extras.tags) + strings_bytes(&extras.tags) + vec_bytes(&extras.successor_order)
+        },
+        GraphChange::SetEdgeTags(_, _, tags) => btree_set_bytes(tags) + strings_bytes(tags),
+        GraphChange::Batch(changes) => vec_bytes(changes) + changes.iter().map(change_bytes).sum::<usize>(),
+        GraphChange::ReverseEdge(..) | GraphChange::ReverseAllEdges | GraphChange::Failure(_) => 0,
+    }
+}
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+
+    /// Estimates the bytes this graph holds, by part, e.g. to budget many graphs in one process.
+    ///
+    /// Collections are counted by their allocated capacity, and payloads by `size_of` plus what
+    /// `Nodal::heap_size()` and `DirEdge::heap_size()` report (0 unless implemented), so strings or
+    /// vectors inside payloads are missed unless those are implemented. Allocator overhead and
+    /// the internals of ordered maps are not counted.
+    pub fn memory_footprint(&self) -> MemoryReport {
+        trace_span!("memory_footprint");
+        let nodes = hash_map_bytes(&self.nodes) + self.nodes.values().map(Nodal::heap_size).sum::<usize>();
+        let edges = vec_bytes(&self.edges) + self.edges.iter().map(DirEdge::heap_size).sum::<usize>();
+        let adjacency = hash_map_bytes(&self.neighbors_before)
+            + self.neighbors_before.values().map(small_vec_bytes).sum::<usize>()
+            + hash_map_bytes(&self.neighbors_after)
+            + self.neighbors_after.values().map(small_vec_bytes).sum::<usize>()
+            + hash_map_bytes(&self.incident_edges)
+            + self.incident_edges.values().map(small_vec_bytes).sum::<usize>();
+        let history = self.undo_history.iter_changes()
+            .map(|change| size_of::<GraphChange<N, E>>() + change_bytes(change))
+            .sum::<usize>()
+            + self.history_iter()
+                .map(|record| size_of_val(record) + vec_bytes(&record.node_ids) + vec_bytes(&record.edges))
+                .sum::<usize>();
+        let other = size_of::<Self>()
+            + self.name.as_ref().map_or(0, String::capacity)
+            + btree_map_bytes(&self.metadata) + strings_bytes(self.metadata.iter().flat_map(|(key, value)| [key, value]))
+            + self.reserved_ids.capacity() * (size_of::<Id>() + 1)
+            + hash_map_bytes(&self.successor_order) + self.successor_order.values().map(vec_bytes).sum::<usize>()
+            + hash_map_bytes(&self.generations)
+            + self.freed_ids.capacity() * size_of::<Id>()
+            + btree_set_bytes(&self.pinned)
+            + btree_map_bytes(&self.groups) + self.groups.values().map(group_bytes).sum::<usize>()
+            + btree_map_bytes(&self.node_tags)
+            + self.node_tags.values().map(|tags| btree_set_bytes(tags) + strings_bytes(tags)).sum::<usize>()
+            + btree_map_bytes(&self.edge_tags)
+            + self.edge_tags.values().flat_map(BTreeMap::values)
+                .map(|tags| size_of::<(Id, BTreeSet<String>)>() + btree_set_bytes(tags) + strings_bytes(tags))
+                .sum::<usize>()
+            + btree_set_bytes(&self.dirty);
+        MemoryReport { nodes, edges, adjacency, history, other }
+    }
+}
@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+
+use crate::graph_base::graph_components::{ChangeKind, GraphChange};
+use super::{Nodal, DirEdge, DiGraph};
+
+/// Edits made to a graph since it was created or `reset_metrics()` was last called,
+/// e.g. for analytics on how an editor is used; see `DiGraph::metrics()`
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct MutationMetrics {
+    /// Changes registered for undo (a batch, such as `rewire()`, counts once)
+    pub mutations: u64,
+    pub undos: u64,
+    /// `mutations` by the kind of change made
+    pub by_kind: HashMap<ChangeKind, u64>,
+}
+
+impl MutationMetrics {
+    fn add(&mut self, other: &Self) {
+        self.mutations += other.mutations;
+        self.undos += other.undos;
+        for (&kind, &count) in other.by_kind.iter() {
+            *self.by_kind.entry(kind).or_default() += count;
+        }
+    }
+}
+
+/// Kept out of `DiGraph` equality, like constraints
+#[derive(Default)]
+pub(super) struct Metrics(pub(super) MutationMetrics);
+
+impl PartialEq for Metrics {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+
+    /// Counts of edits made since the graph was created or `reset_metrics()` was called.
+    ///
+    /// Metrics are not saved with the graph, nor copied by `clone()`. Edits made through a
+    /// `SpeculativeGraph` count once it is committed. With feature `trace`, each edit and undo
+    /// is also logged as a trace event.
+    pub fn metrics(&self) -> &MutationMetrics {
+        &self.metrics.0
+    }
+
+    pub fn mutation_count(&self) -> u64 {
+        self.metrics.0.mutations
+    }
+
+    pub fn undo_count(&self) -> u64 {
+        self.metrics.0.undos
+    }
+
+    /// Number of edits of this kind
+    pub fn mutation_tally(&self, kind: ChangeKind) -> u64 {
+        self.metrics.0.by_kind.get(&kind).copied().unwrap_or(0)
+    }
+
+    pub fn reset_metrics(&mut self) {
+        self.metrics.0 = MutationMetrics::default();
+    }
+
+    pub(super) fn count_mutation(&mut self, change: &GraphChange<N, E>) {
+        let kind = change.kind();
+        self.metrics.0.mutations += 1;
+        *self.metrics.0.by_kind.entry(kind).or_default() += 1;
+        #[cfg(feature = "trace")]
+        tracing::trace!(?kind, mutations = self.metrics.0.mutations, "graph edited");
+    }
+
+    pub(super) fn count_undo(&mut self) {
+        self.metrics.0.undos += 1;
+        #[cfg(feature = "trace")]
+        tracing::trace!(undos = self.metrics.0.undos, "graph edit undone");
+    }
+
+    /// Adds `other` (e.g. from a committed speculative copy) to this graph's counts
+    pub(super) fn absorb_metrics(&mut self, other: &MutationMetrics) {
+        self.metrics.0.add(other);
+    }
+}
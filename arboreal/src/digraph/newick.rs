@@ -0,0 +1,201 @@
+use crate::graph_base::graph_components::{GraphError, Id, Titled, Weighted};
+use super::{Nodal, DirEdge, DiGraph, Tree};
+
+/// Characters that end an unquoted Newick label or branch length
+const NEWICK_DELIMITERS: &str = "()[]':;,";
+
+/// Parser position within a Newick string
+enum Expect {
+    /// A leaf or a `(`
+    Subtree,
+    /// A `,`, `)` or `;`
+    AfterSubtree,
+}
+
+/// Writer steps, kept on a stack rather than recursing, so deep trees can't overflow
+enum Step {
+    Open(Id),
+    Comma,
+    Close(Id),
+}
+
+impl<N: Nodal + Titled, E: DirEdge + Weighted> Tree<N, E> {
+
+    /// Builds a tree from Newick text, e.g. `((A:0.1,B:0.2)AB:0.05,C:0.3)root;`.
+    ///
+    /// Labels become node titles (empty where missing) and branch lengths become the weights
+    /// of the edges into their nodes. Nodes are numbered from 0 in the order written, which
+    /// puts every parent before its children and keeps children in order. Labels may be
+    /// quoted (`'Homo sapiens'`, with `''` for a quote); `[...]` comments and whitespace
+    /// outside quotes are skipped. A branch length on the root is dropped, as there is no
+    /// edge to hold it, and the closing `;` may be left out.
+    ///
+    /// If the text is not a single well-formed Newick tree, an error is returned.
+    pub fn from_newick(newick: &str) -> Result<Self, GraphError> {
+        let mut graph = DiGraph::new();
+        let mut next_id: Id = 0;
+        // Internal nodes whose `)` has not been read yet
+        let mut open: Vec<Id> = Vec::new();
+        let mut expect = Expect::Subtree;
+        let mut rest = skip_blanks(newick)?;
+        if rest.is_empty() || rest.starts_with(';') {
+            return Err("Newick text has no tree.".into());
+        }
+        loop {
+            match expect {
+                Expect::Subtree => {
+                    let node_id = next_id;
+                    next_id = next_id.checked_add(1).ok_or(GraphError::IdSpaceExhausted)?;
+                    graph.insert_node(N::bare(node_id))?;
+                    if let Some(&parent) = open.last() {
+                        graph.insert_edge(E::bare(parent, node_id))?;
+                    }
+                    if let Some(after) = rest.strip_prefix('(') {
+                        open.push(node_id);
+                        rest = skip_blanks(after)?;
+                    } else {
+                        rest = read_label_and_length(&mut graph, open.last().copied(), node_id, rest)?;
+                        expect = Expect::AfterSubtree;
+                    }
+                },
+                Expect::AfterSubtree => {
+                    if let Some(after) = rest.strip_prefix(',') {
+                        if open.is_empty() {
+                            return Err("Newick text has more than one root.".into());
+                        }
+                        rest = skip_blanks(after)?;
+                        expect = Expect::Subtree;
+                    } else if let Some(after) = rest.strip_prefix(')') {
+                        let node_id = open.pop().ok_or(GraphError::Invalid("Newick text has an unmatched ')'."))?;
+                        rest = read_label_and_length(&mut graph, open.last().copied(), node_id, skip_blanks(after)?)?;
+                    } else if rest.is_empty() || rest.starts_with(';') {
+                        if !open.is_empty() {
+                            return Err("Newick text has an unclosed '('.".into());
+                        }
+                        if !skip_blanks(rest.get(1..).unwrap_or_default())?.is_empty() {
+                            return Err("Newick text goes on after the closing ';'.".into());
+                        }
+                        break;
+                    } else {
+                        return Err("Expected ',', ')' or ';' in Newick text.".into());
+                    }
+                },
+            }
+        }
+        Self::from_graph(graph, 0)
+    }
+
+    /// The tree in Newick format, read back by `from_newick()`: children in `children()`
+    /// order, titles as labels (quoted where needed) and edge weights as branch lengths.
+    pub fn to_newick(&self) -> String {
+        let mut newick = String::new();
+        let mut steps = vec![Step::Open(self.root())];
+        while let Some(step) = steps.pop() {
+            match step {
+                Step::Open(node_id) => {
+                    let children = self.children(node_id).unwrap_or_default();
+                    if children.is_empty() {
+                        self.push_label_and_length(&mut newick, node_id);
+                        continue;
+                    }
+                    newick.push('(');
+                    steps.push(Step::Close(node_id));
+                    for (i, &child) in children.iter().enumerate().rev() {
+                        steps.push(Step::Open(child));
+                        if i > 0 {
+                            steps.push(Step::Comma);
+                        }
+                    }
+                },
+                Step::Comma => newick.push(','),
+                Step::Close(node_id) => {
+                    newick.push(')');
+                    self.push_label_and_length(&mut newick, node_id);
+                },
+            }
+        }
+        newick.push(';');
+        newick
+    }
+
+    fn push_label_and_length(&self, newick: &mut String, node_id: Id) {
+        newick.push_str(&quote_label(self.get_node(node_id).unwrap().title()));
+        let length = self.parent(node_id)
+            .and_then(|parent| self.get_edge(parent, node_id))
+            .and_then(Weighted::weight);
+        if let Some(length) = length {
+            newick.push_str(&format!(":{length}"));
+        }
+    }
+}
+
+/// Reads the label and optional `:length` of `node_id`, just added under `parent`
+fn read_label_and_length<'a, N: Nodal + Titled, E: DirEdge + Weighted>(
+    graph: &mut DiGraph<N, E>,
+    parent: Option<Id>,
+    node_id: Id,
+    text: &'a str,
+) -> Result<&'a str, GraphError> {
+    let (label, mut rest) = read_label(text)?;
+    graph.get_node_mut(node_id).unwrap().set_title(label);
+    rest = skip_blanks(rest)?;
+    if let Some(after) = rest.strip_prefix(':') {
+        let after = skip_blanks(after)?;
+        let end = token_end(after);
+        let length: f64 = after[..end].parse()
+            .map_err(|_| GraphError::Invalid("Newick branch length is not a number."))?;
+        if let Some(parent) = parent {
+            graph.get_edge_mut(parent, node_id).unwrap().set_weight(Some(length));
+        }
+        rest = skip_blanks(&after[end..])?;
+    }
+    Ok(rest)
+}
+
+/// Splits a quoted or unquoted (possibly empty) label off the front of `text`
+fn read_label(text: &str) -> Result<(String, &str), GraphError> {
+    let Some(quoted) = text.strip_prefix('\'') else {
+        let end = token_end(text);
+        return Ok((text[..end].to_string(), &text[end..]));
+    };
+    let mut label = String::new();
+    let mut rest = quoted;
+    loop {
+        let close = rest.find('\'').ok_or(GraphError::Invalid("Newick label has no closing quote."))?;
+        label.push_str(&rest[..close]);
+        rest = &rest[close + 1..];
+        match rest.strip_prefix('\'') {
+            Some(after) => {
+                label.push('\'');
+                rest = after;
+            },
+            None => return Ok((label, rest)),
+        }
+    }
+}
+
+/// Length of the unquoted label or branch length at the front of `text`
+fn token_end(text: &str) -> usize {
+    text.find(|c: char| c.is_whitespace() || NEWICK_DELIMITERS.contains(c))
+        .unwrap_or(text.len())
+}
+
+/// Skips whitespace and `[...]` comments
+fn skip_blanks(mut text: &str) -> Result<&str, GraphError> {
+    loop {
+        text = text.trim_start();
+        let Some(comment) = text.strip_prefix('[') else {
+            return Ok(text);
+        };
+        let close = comment.find(']').ok_or(GraphError::Invalid("Newick comment has no closing ']'."))?;
+        text = &comment[close + 1..];
+    }
+}
+
+fn quote_label(label: &str) -> String {
+    if label.contains(|c: char| c.is_whitespace() || NEWICK_DELIMITERS.contains(c)) {
+        format!("'{}'", label.replace('\'', "''"))
+    } else {
+        label.to_string()
+    }
+}
@@ -0,0 +1,84 @@
+use crate::graph_base::graph_components::{GraphChange, GraphError, Id};
+use super::{Nodal, DirEdge, DiGraph};
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+
+    /// Distinct successors of `node_id` in the order set by `set_successor_order()`,
+    /// with any successors missing from that order (e.g. from edges added since) after it, ascending.
+    ///
+    /// Returns `None` if `node_id` is not found among the nodes.
+    pub fn ordered_successors(&self, node_id: Id) -> Option<Vec<Id>> {
+        let mut remaining = self.successors_sorted(node_id)?;
+        let Some(order) = self.successor_order.get(&node_id) else {
+            return Some(remaining);
+        };
+        // Entries for since-removed edges are skipped rather than cleaned up,
+        // so undoing the removal puts the successor back in its old place
+        let mut ordered = Vec::with_capacity(remaining.len());
+        for id in order {
+            if let Ok(position) = remaining.binary_search(id) {
+                ordered.push(remaining.remove(position));
+            }
+        }
+        ordered.append(&mut remaining);
+        Some(ordered)
+    }
+
+    /// Same as `ordered_successors()`, but with one entry per edge, as in the neighbor lists
+    pub(super) fn ordered_successor_entries(&self, node_id: Id) -> Vec<Id> {
+        let ids_after = &self.neighbors_after[&node_id];
+        let mut entries = Vec::with_capacity(ids_after.len());
+        for id in self.ordered_successors(node_id).unwrap() {
+            let repeats = ids_after.iter().filter(|&&x| x == id).count();
+            entries.extend(std::iter::repeat_n(id, repeats));
+        }
+        entries
+    }
+
+    pub(super) fn has_successor_order(&self, node_id: Id) -> bool {
+        self.nodes.contains_key(&node_id) && self.successor_order.contains_key(&node_id)
+    }
+
+    pub(super) fn set_successor_order_unregistered(&mut self, node_id: Id, order: Vec<Id>) -> Vec<Id> {
+        let previous_order = match order.is_empty() {
+            true => self.successor_order.remove(&node_id),
+            false => self.successor_order.insert(node_id, order),
+        };
+        previous_order.unwrap_or_default()
+    }
+
+    /// Sets the order in which successors of `node_id` are visited (by `ordered_successors()`,
+    /// `GraphView::successors()`, and everything built on them). The order is saved with the graph,
+    /// and dropped when the node is removed (undoing the removal brings it back).
+    ///
+    /// `order` must list each successor exactly once; an empty `order` restores ascending order.
+    /// If `node_id` is not found, or `order` is not such a list, an error is returned.
+    pub fn set_successor_order(&mut self, node_id: Id, order: Vec<Id>) -> Result<(), GraphError> {
+        let successor_ids = self.successors_sorted(node_id)
+            .ok_or(GraphError::Invalid("Node with this id not found."))?;
+        let mut sorted_order = order.clone();
+        sorted_order.sort_unstable();
+        if !order.is_empty() && sorted_order != successor_ids {
+            return Err("Order must list each successor exactly once.".into());
+        }
+        self.begin_change();
+        let previous_order = self.set_successor_order_unregistered(node_id, order);
+        self.commit_change(GraphChange::ReorderSuccessors(node_id, previous_order))?;
+        Ok(())
+    }
+
+    /// Moves the successor at position `from_index` in `ordered_successors(node_id)` to `to_index`,
+    /// shifting those in between.
+    ///
+    /// If `node_id` is not found, or either index is out of range, an error is returned.
+    pub fn move_successor(&mut self, node_id: Id, from_index: usize, to_index: usize) -> Result<(), GraphError> {
+        let mut order = self.ordered_successors(node_id)
+            .ok_or(GraphError::Invalid("Node with this id not found."))?;
+        if from_index >= order.len() || to_index >= order.len() {
+            return Err("Successor index out of range.".into());
+        }
+        let moved_id = order.remove(from_index);
+        order.insert(to_index, moved_id);
+        self.set_successor_order(node_id, order)
+    }
+}
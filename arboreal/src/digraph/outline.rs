@@ -0,0 +1,135 @@
+use crate::graph_base::graph_components::{GraphError, Id, Titled};
+use super::{Nodal, DirEdge, DiGraph, Tree};
+
+/// Columns a tab counts for when comparing indentation
+const TAB_WIDTH: usize = 4;
+
+impl<N: Nodal + Titled, E: DirEdge> Tree<N, E> {
+
+    /// Builds a tree from a Markdown outline: each list item (`-`, `*`, `+`, `1.` or `1)`)
+    /// is a child of the nearest item above it that is indented less.
+    ///
+    /// ```text
+    /// # Launch
+    /// - Design
+    ///   - Sketches
+    /// - Build
+    /// ```
+    ///
+    /// A `#` heading before the first item is the root; without one, the outline must have
+    /// a single top-level item, which becomes the root. Nodes are numbered from 0 in the
+    /// order found, and children keep it. Other text continues the item above it, as in
+    /// Markdown, and is ignored before the first item.
+    ///
+    /// If the outline is empty, has a heading after the first item or a second heading,
+    /// or has several top-level items and no heading, an error is returned.
+    pub fn from_markdown_outline(outline: &str) -> Result<Self, GraphError> {
+        let mut titles: Vec<String> = Vec::new();
+        let mut parents: Vec<Option<Id>> = Vec::new();
+        let mut heading_root = false;
+        // Items that could still take children, with their indentation
+        let mut open: Vec<(usize, Id)> = Vec::new();
+        for line in outline.lines() {
+            let trimmed = line.trim();
+            if let Some(heading) = heading_text(trimmed) {
+                if !titles.is_empty() {
+                    return Err("Outline heading must come first, and only once.".into());
+                }
+                titles.push(heading.to_string());
+                parents.push(None);
+                heading_root = true;
+                continue;
+            }
+            let Some(text) = list_item_text(trimmed) else {
+                // Never the heading's, even with no item since
+                if let Some(&(_, id)) = open.last() && !trimmed.is_empty() {
+                    let title = &mut titles[usize::from(id)];
+                    title.push(' ');
+                    title.push_str(trimmed);
+                }
+                continue;
+            };
+            let indent = indentation(line);
+            while open.last().is_some_and(|&(open_indent, _)| open_indent >= indent) {
+                open.pop();
+            }
+            let parent = match open.last() {
+                Some(&(_, id)) => Some(id),
+                None if heading_root => Some(0),
+                None if titles.is_empty() => None,
+                None => return Err("Outline has several top-level items; add a heading to be the root.".into()),
+            };
+            let id = Id::try_from(titles.len()).map_err(|_| GraphError::IdSpaceExhausted)?;
+            titles.push(text.to_string());
+            parents.push(parent);
+            open.push((indent, id));
+        }
+        if titles.is_empty() {
+            return Err("Outline has no heading or items.".into());
+        }
+        let mut graph = DiGraph::new();
+        for (id, (title, parent)) in (0..).zip(titles.into_iter().zip(parents)) {
+            let mut node = N::bare(id);
+            node.set_title(title);
+            graph.insert_node(node)?;
+            if let Some(parent) = parent {
+                graph.insert_edge(E::bare(parent, id))?;
+            }
+        }
+        Self::from_graph(graph, 0)
+    }
+
+    /// The tree as a Markdown outline read back by `from_markdown_outline()`: the root's
+    /// title as a heading, then every other node as a `-` item indented two spaces per
+    /// level, children in `children()` order.
+    ///
+    /// Line breaks in titles become spaces.
+    pub fn to_markdown_outline(&self) -> String {
+        let title = |node_id: Id| self.get_node(node_id).unwrap().title().replace(['\r', '\n'], " ");
+        let mut outline = format!("# {}\n", title(self.root()));
+        let children_below = |node_id: Id, depth: usize| {
+            self.children(node_id).unwrap_or_default().into_iter().rev().map(move |child| (child, depth))
+        };
+        let mut stack: Vec<(Id, usize)> = children_below(self.root(), 0).collect();
+        while let Some((node_id, depth)) = stack.pop() {
+            outline.push_str(&format!("{}- {}\n", "  ".repeat(depth), title(node_id)));
+            stack.extend(children_below(node_id, depth + 1));
+        }
+        outline
+    }
+}
+
+/// Text of a `#` to `######` heading, or `None` for other lines (`#tag` included)
+fn heading_text(line: &str) -> Option<&str> {
+    let rest = line.trim_start_matches('#');
+    let level = line.len() - rest.len();
+    if level == 0 || level > 6 || !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some(rest.trim())
+}
+
+/// Text of a bullet (`- text`) or numbered (`1. text`) list item, or `None` for other lines
+fn list_item_text(line: &str) -> Option<&str> {
+    let rest = match line.strip_prefix(['-', '*', '+']) {
+        Some(rest) => rest,
+        None => {
+            let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            if digits == 0 {
+                return None;
+            }
+            line[digits..].strip_prefix(['.', ')'])?
+        },
+    };
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some(rest.trim())
+}
+
+fn indentation(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { TAB_WIDTH } else { 1 })
+        .sum()
+}
@@ -0,0 +1,66 @@
+use std::collections::HashSet;
+
+use crate::graph_base::graph_components::{GraphError, Id};
+use super::{Nodal, DirEdge, DiGraph};
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+
+    /// Builds a graph from its nodes and edges (e.g. read back by custom deserialization),
+    /// with an empty undo history.
+    ///
+    /// If two nodes share an id, two edges share terminals, or an edge terminal is not
+    /// found among the nodes, an error is returned.
+    pub fn from_parts(nodes: Vec<N>, edges: Vec<E>) -> Result<Self, GraphError> {
+        let mut graph = Self::new();
+        graph.nodes.reserve(nodes.len());
+        for node in nodes {
+            if graph.nodes.insert(node.node_id(), node).is_some() {
+                return Err("Node with this id already exists.".into());
+            }
+        }
+        let mut terminals: HashSet<(Id, Id)> = HashSet::with_capacity(edges.len());
+        for edge in edges.iter() {
+            let (start_id, end_id) = edge.terminal_ids();
+            if !graph.nodes.contains_key(&start_id) || !graph.nodes.contains_key(&end_id) {
+                return Err("Edge terminal not found in graph.".into());
+            }
+            if !terminals.insert((start_id, end_id)) {
+                return Err("Edge with these terminals already exists.".into());
+            }
+        }
+        graph.edges = edges;
+        graph.rebuild_neighbors();
+        Ok(graph)
+    }
+
+    /// Consumes the graph, returning its nodes (ascending by id) and edges (in storage order).
+    ///
+    /// Everything else is dropped: name, metadata, tags, history, and nodes hidden by
+    /// `group_nodes()`; see `TryFrom` for a conversion that refuses to drop grouped nodes.
+    pub fn into_parts(self) -> (Vec<N>, Vec<E>) {
+        let mut nodes: Vec<N> = self.nodes.into_values().collect();
+        nodes.sort_unstable_by_key(Nodal::node_id);
+        (nodes, self.edges)
+    }
+}
+
+/// Same as `from_terminal_pairs()`, except that repeated pairs are skipped instead of panicking
+impl<N: Nodal, E: DirEdge> From<Vec<(Id, Id)>> for DiGraph<N, E> {
+    fn from(terminal_pairs: Vec<(Id, Id)>) -> Self {
+        let mut graph = Self::new();
+        graph.extend_from_edges(terminal_pairs, true).unwrap();
+        graph
+    }
+}
+
+/// Same as `into_parts()`, but fails instead of dropping nodes hidden by `group_nodes()`
+impl<N: Nodal, E: DirEdge> TryFrom<DiGraph<N, E>> for (Vec<N>, Vec<E>) {
+    type Error = GraphError;
+
+    fn try_from(graph: DiGraph<N, E>) -> Result<Self, GraphError> {
+        if !graph.groups.is_empty() {
+            return Err("Graph has grouped nodes; ungroup them first.".into());
+        }
+        Ok(graph.into_parts())
+    }
+}
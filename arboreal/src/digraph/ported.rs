@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+
+use crate::graph_base::{graph_components::{GraphError, Id, Ported}, graph_ref};
+use super::{Nodal, DiGraph};
+
+impl<N: Nodal, E: Ported> DiGraph<N, E> {
+
+    /// Returns the id at the end of the edge leaving `node_id` through output `port`, if connected
+    pub fn successor_via_port(&self, node_id: Id, port: &str) -> Option<Id> {
+        self.edges_incident_on(node_id)
+            .find(|edge| edge.start_id() == node_id && edge.start_port() == Some(port))
+            .map(|edge| edge.end_id())
+    }
+
+    /// Returns the id at the start of the edge entering `node_id` through input `port`, if connected
+    pub fn predecessor_via_port(&self, node_id: Id, port: &str) -> Option<Id> {
+        self.edges_incident_on(node_id)
+            .find(|edge| edge.end_id() == node_id && edge.end_port() == Some(port))
+            .map(|edge| edge.start_id())
+    }
+
+    /// Same as `insert_edge()`, but also returns an error if a port named by `edge`
+    /// already has an edge attached.
+    pub fn insert_ported_edge(&mut self, edge: E) -> Result<(), GraphError> {
+        let change =
+            graph_ref::check_add_ported_edge::<N, E, _>(&self.nodes, self.edges_incident_on(edge.start_id()).chain(self.edges_incident_on(edge.end_id())), edge);
+        let new_edge = change.try_get_edge()?;
+        self.check_edge_limits(new_edge.start_id(), new_edge.end_id())?;
+        self.begin_change();
+        self.insert_edge_unregistered(new_edge);
+        self.commit_change(change)?;
+        Ok(())
+    }
+
+    /// Returns `(node id, port, is_output)` for every port with more than one edge attached,
+    /// which `insert_ported_edge()` prevents but `insert_edge()` or a hand-edited file may not.
+    ///
+    /// Ordered by node id, then outputs before inputs, then port name.
+    pub fn double_connected_ports(&self) -> Vec<(Id, String, bool)> {
+        let mut port_uses: HashMap<(Id, &str, bool), usize> = HashMap::new();
+        for edge in self.edges.iter() {
+            if let Some(port) = edge.start_port() {
+                *port_uses.entry((edge.start_id(), port, true)).or_default() += 1;
+            }
+            if let Some(port) = edge.end_port() {
+                *port_uses.entry((edge.end_id(), port, false)).or_default() += 1;
+            }
+        }
+        let mut doubled: Vec<(Id, String, bool)> = port_uses
+            .into_iter()
+            .filter(|&(_, uses)| uses > 1)
+            .map(|((node_id, port, is_output), _)| (node_id, port.to_string(), is_output))
+            .collect();
+        doubled.sort_by(|a, b| (a.0, !a.2, &a.1).cmp(&(b.0, !b.2, &b.1)));
+        doubled
+    }
+}
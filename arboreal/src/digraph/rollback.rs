@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+
+use crate::graph_base::graph_components::{GraphChange, GraphError, Id};
+use super::{Nodal, DirEdge, DiGraph};
+
+/// What the `*_unregistered` helpers do that reversing a `GraphChange` would not put back
+/// as it was: edge positions, removal counts, freed ids and reservations. Kept from
+/// `begin_change()` until `commit_change()`, and only while an edit could be refused.
+#[derive(Default)]
+pub(super) struct RollbackLog(Option<Effects>);
+
+#[derive(Default)]
+struct Effects {
+    /// In the order done
+    edge_ops: Vec<EdgeOp>,
+    /// Removal count of each id before its first bump (`None` if it had none)
+    generations: Vec<(Id, Option<u32>)>,
+    /// `freed_ids` before its first change
+    freed_ids: Option<Vec<Id>>,
+    /// Reserved ids taken by inserted nodes
+    unreserved: Vec<Id>,
+}
+
+enum EdgeOp {
+    Pushed,
+    /// `swap_remove()` at this index of the edge with these terminals
+    SwapRemoved(usize, (Id, Id)),
+}
+
+impl RollbackLog {
+    pub(super) fn edge_pushed(&mut self) {
+        if let Some(effects) = &mut self.0 {
+            effects.edge_ops.push(EdgeOp::Pushed);
+        }
+    }
+    pub(super) fn edge_swap_removed(&mut self, edge_index: usize, terminals: (Id, Id)) {
+        if let Some(effects) = &mut self.0 {
+            effects.edge_ops.push(EdgeOp::SwapRemoved(edge_index, terminals));
+        }
+    }
+    /// Before `node_id`'s removal count goes up from `previous`
+    pub(super) fn generation_bumped(&mut self, node_id: Id, previous: Option<u32>) {
+        if let Some(effects) = &mut self.0
+            && !effects.generations.iter().any(|&(id, _)| id == node_id)
+        {
+            effects.generations.push((node_id, previous));
+        }
+    }
+    /// Before `freed_ids` changes
+    pub(super) fn freed_ids_changing(&mut self, freed_ids: &[Id]) {
+        if let Some(effects) = &mut self.0 {
+            effects.freed_ids.get_or_insert_with(|| freed_ids.to_vec());
+        }
+    }
+    pub(super) fn unreserved(&mut self, node_id: Id) {
+        if let Some(effects) = &mut self.0 {
+            effects.unreserved.push(node_id);
+        }
+    }
+}
+
+// Bookkeeping, not data
+impl PartialEq for RollbackLog {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+
+    /// Called before an edit starts applying its change, so that if `commit_change()`
+    /// refuses it, the graph is left exactly as it was (edge order, handles, freed and
+    /// reserved ids included). Does nothing while no edit can be refused, i.e. with no
+    /// constraints and DAG mode off.
+    pub(super) fn begin_change(&mut self) {
+        let refusable = !self.constraints.is_empty() || self.is_acyclic_enforced() || self.strict.0;
+        self.rollback.0 = refusable.then(Effects::default);
+    }
+
+    /// As `begin_change()`, but logging whatever the settings, for an edit that may back
+    /// out with `roll_back()` before it reaches `commit_change()`
+    pub(super) fn begin_tentative_change(&mut self) {
+        self.rollback.0 = Some(Effects::default());
+    }
+
+    /// Stops logging for `begin_change()`, as `change` is being kept
+    pub(super) fn end_change(&mut self) {
+        self.rollback.0 = None;
+    }
+
+    /// Reverses `change`, just applied, and then puts back what `begin_change()` logged
+    pub(super) fn roll_back(&mut self, change: GraphChange<N, E>) -> Result<(), GraphError> {
+        let edge_order = self.rollback.0.as_ref().map(|effects| self.edge_order_before(&effects.edge_ops));
+        // Still logging, for the nodes that reversing the change removes
+        let reversed = self.reverse_change(change);
+        let effects = self.rollback.0.take();
+        reversed?;
+        let Some(effects) = effects else {
+            return Ok(());
+        };
+        for (node_id, previous) in effects.generations {
+            match previous {
+                Some(generation) => self.generations.insert(node_id, generation),
+                None => self.generations.remove(&node_id),
+            };
+        }
+        if let Some(freed_ids) = effects.freed_ids {
+            self.freed_ids = freed_ids;
+        }
+        for node_id in effects.unreserved {
+            if !self.nodes.contains_key(&node_id) {
+                self.reserved_ids.insert(node_id);
+            }
+        }
+        if let Some(edge_order) = edge_order {
+            self.restore_edge_order(edge_order);
+        }
+        Ok(())
+    }
+
+    /// Terminals of every edge, in the order they were before `edge_ops`
+    fn edge_order_before(&self, edge_ops: &[EdgeOp]) -> Vec<(Id, Id)> {
+        let mut order: Vec<(Id, Id)> = self.edges.iter().map(|edge| edge.terminal_ids()).collect();
+        for op in edge_ops.iter().rev() {
+            match *op {
+                EdgeOp::Pushed => {
+                    order.pop();
+                },
+                EdgeOp::SwapRemoved(edge_index, terminals) => {
+                    order.push(terminals);
+                    let last_index = order.len() - 1;
+                    order.swap(edge_index, last_index);
+                },
+            }
+        }
+        order
+    }
+
+    /// Puts the edges (the same ones, now in another order) back in `order`
+    fn restore_edge_order(&mut self, order: Vec<(Id, Id)>) {
+        if order.len() != self.edges.len()
+            || order.iter().zip(&self.edges).all(|(&terminals, edge)| terminals == edge.terminal_ids())
+        {
+            return;
+        }
+        // Reversed, so that pop() hands out parallel edges in their current order
+        let mut by_terminals: HashMap<(Id, Id), Vec<E>> = HashMap::with_capacity(order.len());
+        for edge in self.edges.drain(..).rev() {
+            by_terminals.entry(edge.terminal_ids()).or_default().push(edge);
+        }
+        self.edges = order.into_iter()
+            .filter_map(|terminals| by_terminals.get_mut(&terminals)?.pop())
+            .collect();
+        self.rebuild_neighbors();
+    }
+}
@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+
+use crate::graph_base::graph_components::{GraphError, Id};
+use super::{Nodal, DirEdge, DiGraph};
+
+/// A path saved as its first node and, at each step after it, the position of the next node
+/// in `DiGraph::ordered_successors()`, e.g. to keep where a player is in a save game.
+///
+/// Obtained from `DiGraph::encode_path()`. The token stays valid as payloads change, and as
+/// nodes and edges off the path come and go, unless they shift a choice's position.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PathToken {
+    pub root: Id,
+    pub choices: Vec<u16>,
+}
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+
+    /// Encodes `path`, a sequence of ids each linked by an edge to the next, as a `PathToken`.
+    ///
+    /// If `path` is empty, an id is not found among the nodes, or two consecutive ids have no
+    /// edge between them, an error is returned.
+    pub fn encode_path(&self, path: &[Id]) -> Result<PathToken, GraphError> {
+        let (&root, _) = path.split_first().ok_or(GraphError::Invalid("Path is empty."))?;
+        if !self.nodes.contains_key(&root) {
+            return Err("Node with this id not found.".into());
+        }
+        let mut choices = Vec::with_capacity(path.len() - 1);
+        for step in path.windows(2) {
+            let successor_ids = self.ordered_successors(step[0]).unwrap();
+            let choice = successor_ids.iter().position(|&id| id == step[1])
+                .ok_or(GraphError::Invalid("Edge not found in graph."))?;
+            choices.push(choice as u16);
+        }
+        Ok(PathToken { root, choices })
+    }
+
+    /// Returns the ids along the path `token` describes, from its root.
+    ///
+    /// If the root is not found among the nodes, or a choice is out of range for the node it
+    /// is made at (the graph changed too much since encoding), an error is returned.
+    pub fn decode_path(&self, token: &PathToken) -> Result<Vec<Id>, GraphError> {
+        if !self.nodes.contains_key(&token.root) {
+            return Err("Node with this id not found.".into());
+        }
+        let mut path = Vec::with_capacity(token.choices.len() + 1);
+        path.push(token.root);
+        for &choice in token.choices.iter() {
+            let successor_ids = self.ordered_successors(*path.last().unwrap()).unwrap();
+            let &next_id = successor_ids.get(choice as usize)
+                .ok_or(GraphError::Invalid("Successor index out of range."))?;
+            path.push(next_id);
+        }
+        Ok(path)
+    }
+}
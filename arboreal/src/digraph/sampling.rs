@@ -0,0 +1,58 @@
+use crate::graph_base::{graph_algo, graph_components::Id};
+use super::{Nodal, DirEdge, DiGraph};
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+
+    /// Returns a new graph with copies of the nodes in `node_ids` (unknown ids are skipped)
+    /// and of every edge between them. Name and metadata are copied; history starts empty.
+    pub fn induced_subgraph(&self, node_ids: &[Id]) -> Self {
+        let mut subgraph = Self::new();
+        subgraph.name = self.name.clone();
+        subgraph.metadata = self.metadata.clone();
+        for node_id in node_ids {
+            if let Some(node) = self.nodes.get(node_id) {
+                subgraph.insert_node_unregistered(node.clone());
+            }
+        }
+        for edge in self.edges.iter() {
+            let (start_id, end_id) = edge.terminal_ids();
+            if subgraph.nodes.contains_key(&start_id) && subgraph.nodes.contains_key(&end_id) {
+                subgraph.insert_edge_unregistered(edge.clone());
+            }
+        }
+        subgraph
+    }
+
+    /// Returns the subgraph induced by `k` nodes picked uniformly at random
+    /// (all nodes, if there are no more than `k`).
+    ///
+    /// `rng` must return uniformly distributed `u64`s, e.g. `|| rng.next_u64()` with the `rand` crate.
+    pub fn sample_nodes<R: FnMut() -> u64>(&self, k: usize, mut rng: R) -> Self {
+        let mut node_ids = self.all_node_ids();
+        let k = k.min(node_ids.len());
+        // Partial Fisher-Yates shuffle: the first k slots end up a uniform sample
+        for index in 0..k {
+            let remaining = (node_ids.len() - index) as u64;
+            let pick = index + (rng() % remaining) as usize;
+            node_ids.swap(index, pick);
+        }
+        node_ids.truncate(k);
+        self.induced_subgraph(&node_ids)
+    }
+
+    /// Returns the subgraph induced by the first `max_nodes` nodes found by a breadth-first
+    /// search along edges from `start_id` (successors in ascending id order).
+    ///
+    /// The result is empty if `start_id` is not found among the nodes.
+    pub fn sample_subgraph_bfs(&self, start_id: Id, max_nodes: usize) -> Self {
+        self.induced_subgraph(&graph_algo::bfs_order(self, start_id, max_nodes, usize::MAX))
+    }
+
+    /// Returns the subgraph induced by `node_id` and every node reachable from it in at most
+    /// `radius` edges.
+    ///
+    /// The result is empty if `node_id` is not found among the nodes.
+    pub fn ego_graph(&self, node_id: Id, radius: usize) -> Self {
+        self.induced_subgraph(&graph_algo::bfs_order(self, node_id, usize::MAX, radius))
+    }
+}
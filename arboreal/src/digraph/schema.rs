@@ -0,0 +1,14 @@
+use schemars::{schema_for, JsonSchema, Schema};
+
+use super::{Nodal, DirEdge, DiGraph};
+
+impl<N: Nodal + JsonSchema, E: DirEdge + JsonSchema> DiGraph<N, E> {
+
+    /// Returns a JSON Schema for this graph type as written by `to_json_string()`
+    /// (feature "json"), so that external tools can check the files they produce.
+    ///
+    /// `schema().to_value()` gives the schema as a `serde_json::Value`.
+    pub fn schema() -> Schema {
+        schema_for!(Self)
+    }
+}
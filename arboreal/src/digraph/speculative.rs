@@ -0,0 +1,145 @@
+use std::ops::{Deref, DerefMut};
+
+use crate::graph_base::graph_components::{GraphChange, GraphError};
+use super::{Nodal, DirEdge, DiGraph, HistoryDeque};
+
+/// Tentative edits on top of a `DiGraph`, from `DiGraph::speculate()`.
+///
+/// Derefs (mutably too) to a graph that reads as the base graph until the first edit, which
+/// copies the base; the base itself is never touched until `commit()`. Dropping the
+/// `SpeculativeGraph` discards every edit. The base graph's constraints are only checked
+/// by `commit()`.
+pub struct SpeculativeGraph<'a, N: Nodal, E: DirEdge> {
+    base: &'a mut DiGraph<N, E>,
+    overlay: Option<DiGraph<N, E>>,
+}
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+
+    /// Starts a set of "what if" edits that leave this graph and its undo history alone
+    /// unless committed; see `SpeculativeGraph`.
+    pub fn speculate(&mut self) -> SpeculativeGraph<'_, N, E> {
+        SpeculativeGraph { base: self, overlay: None }
+    }
+}
+
+impl<N: Nodal, E: DirEdge> SpeculativeGraph<'_, N, E> {
+
+    /// Whether anything has been edited (or borrowed mutably) since `speculate()`
+    pub fn is_modified(&self) -> bool {
+        self.overlay.is_some()
+    }
+
+    /// Applies the speculative edits to the base graph, registered as a single change,
+    /// so one `undo()` on the base reverts them all. Speculative edits since undone
+    /// are left out.
+    ///
+    /// If the result breaks one of the base graph's constraints (see `DiGraph::add_constraint()`),
+    /// or has a cycle while the base is in DAG mode (see `DiGraph::enforce_acyclic()`),
+    /// nothing is applied and `GraphError::ConstraintViolated` is returned.
+    pub fn commit(self) -> Result<(), GraphError> {
+        trace_span!("speculative_commit");
+        let Some(mut overlay) = self.overlay else {
+            return Ok(());
+        };
+        if let Some(name) = self.base.constraints.first_violated(&overlay) {
+            return Err(GraphError::ConstraintViolated(name));
+        }
+        if self.base.is_acyclic_enforced() && overlay.topological_order().is_none() {
+            return Err(GraphError::ConstraintViolated("acyclic"));
+        }
+        let changes = overlay.undo_history.take_changes();
+        // No `..`, so that a new field can't be left out unnoticed
+        let DiGraph {
+            format_version: _,
+            name,
+            metadata,
+            nodes,
+            edges,
+            neighbors_before,
+            neighbors_after,
+            incident_edges,
+            // Its changes are registered below, as one
+            undo_history: _,
+            reserved_ids,
+            successor_order,
+            generations,
+            freed_ids,
+            id_reuse,
+            entry,
+            exit,
+            pinned,
+            groups,
+            node_tags,
+            edge_tags,
+            max_out_degrees,
+            max_in_degrees,
+            // Never copied to the overlay, so the base's stand
+            constraints: _,
+            bare_node_policy,
+            strict,
+            metrics,
+            acyclic_order: _,
+            dirty,
+            rollback: _,
+            #[cfg(feature = "crdt")]
+            crdt: _,
+        } = overlay;
+        let base = self.base;
+        base.name = name;
+        base.metadata = metadata;
+        base.nodes = nodes;
+        base.edges = edges;
+        base.neighbors_before = neighbors_before;
+        base.neighbors_after = neighbors_after;
+        base.incident_edges = incident_edges;
+        base.reserved_ids = reserved_ids;
+        base.successor_order = successor_order;
+        base.generations = generations;
+        base.freed_ids = freed_ids;
+        base.id_reuse = id_reuse;
+        base.entry = entry;
+        base.exit = exit;
+        base.pinned = pinned;
+        base.groups = groups;
+        base.node_tags = node_tags;
+        base.edge_tags = edge_tags;
+        base.max_out_degrees = max_out_degrees;
+        base.max_in_degrees = max_in_degrees;
+        base.bare_node_policy = bare_node_policy;
+        base.strict = strict;
+        base.dirty = dirty;
+        base.refresh_acyclic_order();
+        base.absorb_metrics(&metrics.0);
+        if !changes.is_empty() {
+            let change = GraphChange::Batch(changes);
+            #[cfg(feature = "crdt")]
+            base.stamp_change(&change);
+            base.keep_in_history(change);
+        }
+        Ok(())
+    }
+}
+
+impl<N: Nodal, E: DirEdge> Deref for SpeculativeGraph<'_, N, E> {
+    type Target = DiGraph<N, E>;
+
+    fn deref(&self) -> &DiGraph<N, E> {
+        self.overlay.as_ref().unwrap_or(self.base)
+    }
+}
+
+impl<N: Nodal, E: DirEdge> DerefMut for SpeculativeGraph<'_, N, E> {
+    fn deref_mut(&mut self) -> &mut DiGraph<N, E> {
+        self.overlay.get_or_insert_with(|| {
+            let mut copy = self.base.snapshot();
+            copy.reserved_ids = self.base.reserved_ids.clone();
+            copy.set_bare_node_policy(self.base.bare_node_policy());
+            copy.strict = self.base.strict;
+            copy.dirty = self.base.dirty.clone();
+            // Every edit must stay in the history for commit() to register it
+            copy.undo_history = HistoryDeque::unbounded();
+            copy
+        })
+    }
+}
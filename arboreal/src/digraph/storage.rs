@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Byte-level persistence backend used by `FileIO`.
+///
+/// Keys are plain strings so that backends without a filesystem
+/// (in-memory, browser storage) can be swapped in for `FileStorage`.
+pub trait Storage {
+    fn read(&self, key: &str) -> io::Result<Vec<u8>>;
+    fn write(&mut self, key: &str, bytes: &[u8]) -> io::Result<()>;
+    fn remove(&mut self, key: &str) -> io::Result<()>;
+
+    fn contains(&self, key: &str) -> bool {
+        self.read(key).is_ok()
+    }
+}
+
+/// Stores each key as a file, relative to `root` (the working directory by default)
+#[derive(Debug, Default, Clone)]
+pub struct FileStorage {
+    pub root: PathBuf,
+}
+
+impl FileStorage {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Storage rooted at the directory of `path`, and the key of the file there, for the
+    /// path-based methods of `FileIO`. The file name must be valid UTF-8.
+    pub(super) fn for_file(path: &Path) -> io::Result<(Self, String)> {
+        let key = path.file_name()
+            .and_then(|name| name.to_str())
+            .ok_or(io::Error::new(io::ErrorKind::InvalidInput, "Path has no UTF-8 file name."))?;
+        Ok((Self::new(path.parent().unwrap_or(Path::new(""))), key.to_string()))
+    }
+}
+
+impl Storage for FileStorage {
+    fn read(&self, key: &str) -> io::Result<Vec<u8>> {
+        fs::read(self.root.join(key))
+    }
+    fn write(&mut self, key: &str, bytes: &[u8]) -> io::Result<()> {
+        fs::write(self.root.join(key), bytes)
+    }
+    fn remove(&mut self, key: &str) -> io::Result<()> {
+        fs::remove_file(self.root.join(key))
+    }
+    fn contains(&self, key: &str) -> bool {
+        self.root.join(key).is_file()
+    }
+}
+
+/// Keeps serialized items in a `HashMap`; works on every target, including wasm
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MemoryStorage(HashMap<String, Vec<u8>>);
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn keys(&self) -> Vec<&str> {
+        let mut keys: Vec<&str> = self.0
+            .keys()
+            .map(|key| key.as_str())
+            .collect();
+        keys.sort();
+        keys
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn read(&self, key: &str) -> io::Result<Vec<u8>> {
+        self.0
+            .get(key)
+            .cloned()
+            .ok_or(io::Error::new(io::ErrorKind::NotFound, "Key not found in MemoryStorage."))
+    }
+    fn write(&mut self, key: &str, bytes: &[u8]) -> io::Result<()> {
+        self.0.insert(key.to_string(), bytes.to_vec());
+        Ok(())
+    }
+    fn remove(&mut self, key: &str) -> io::Result<()> {
+        self.0.remove(key);
+        Ok(())
+    }
+    fn contains(&self, key: &str) -> bool {
+        self.0.contains_key(key)
+    }
+}
+
+/// Browser `window.localStorage` backend (requires feature `web` on wasm32).
+///
+/// localStorage only holds strings, so bytes must be valid UTF-8 (true for RON output).
+#[cfg(all(feature = "web", target_arch = "wasm32"))]
+#[derive(Debug, Default, Clone)]
+pub struct LocalStorage {
+    /// Prepended to every key, to keep graphs apart from other site data
+    pub prefix: String,
+}
+
+#[cfg(all(feature = "web", target_arch = "wasm32"))]
+impl LocalStorage {
+    pub fn new<S: Into<String>>(prefix: S) -> Self {
+        Self { prefix: prefix.into() }
+    }
+
+    fn backend(&self) -> io::Result<web_sys::Storage> {
+        web_sys::window()
+            .and_then(|window| window.local_storage().ok().flatten())
+            .ok_or(io::Error::new(io::ErrorKind::Unsupported, "localStorage is not available."))
+    }
+}
+
+#[cfg(all(feature = "web", target_arch = "wasm32"))]
+impl Storage for LocalStorage {
+    fn read(&self, key: &str) -> io::Result<Vec<u8>> {
+        let full_key = format!("{}{}", self.prefix, key);
+        match self.backend()?.get_item(&full_key) {
+            Ok(Some(text)) => Ok(text.into_bytes()),
+            Ok(None) => Err(io::Error::new(io::ErrorKind::NotFound, "Key not found in localStorage.")),
+            Err(_) => Err(io::Error::other("localStorage read failed.")),
+        }
+    }
+    fn write(&mut self, key: &str, bytes: &[u8]) -> io::Result<()> {
+        let full_key = format!("{}{}", self.prefix, key);
+        let text = std::str::from_utf8(bytes)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "localStorage only holds UTF-8 text."))?;
+        self.backend()?
+            .set_item(&full_key, text)
+            .map_err(|_| io::Error::other("localStorage write failed (quota exceeded?)."))
+    }
+    fn remove(&mut self, key: &str) -> io::Result<()> {
+        let full_key = format!("{}{}", self.prefix, key);
+        self.backend()?
+            .remove_item(&full_key)
+            .map_err(|_| io::Error::other("localStorage remove failed."))
+    }
+}
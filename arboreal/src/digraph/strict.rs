@@ -0,0 +1,43 @@
+use crate::graph_base::graph_components::GraphChange;
+use super::{Nodal, DirEdge, DiGraph, ChangeCache};
+
+/// Whether strict mode is on. A setting, not data, so it is never compared.
+#[derive(Default, Clone, Copy)]
+pub(super) struct StrictMode(pub(super) bool);
+
+impl PartialEq for StrictMode {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+
+    /// Turns strict mode on or off (off by default), e.g. on during development so that
+    /// data-loss bugs surface. In strict mode, operations that would quietly do less than asked
+    /// return an error instead: `undo()` and `redo()` with nothing to undo or redo, and edits
+    /// that would push the oldest change out of a full undo history (which are rolled back;
+    /// `clear_undo_history()` makes room). Out of strict mode, changes dropped from the history
+    /// are logged as warnings with feature `trace`.
+    ///
+    /// Loading and importing are done before there is a graph to configure, so they have
+    /// variants of their own: `FileIO::load_or_default_strict()`, and the importers'
+    /// `*_reporting()` variants (e.g. `import::from_twee_reporting()`), which return the links
+    /// they drop. The setting is not saved with the graph.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict.0 = strict;
+    }
+
+    pub fn is_strict(&self) -> bool {
+        self.strict.0
+    }
+
+    /// Registers `change`, logging it if the undo history drops its oldest change to make room
+    pub(super) fn keep_in_history(&mut self, change: GraphChange<N, E>) {
+        let _dropped = self.register_change(change);
+        #[cfg(feature = "trace")]
+        if _dropped.is_some() {
+            tracing::warn!("undo history full; dropped the oldest change");
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use crate::graph_base::graph_components::{GraphError, Id};
+use super::{Nodal, DirEdge, DiGraph};
+
+/// Tags per node id, or per end id of a start id's edges
+type TagMap = BTreeMap<Id, BTreeSet<String>>;
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+
+    /// Adds `tag` (e.g. "deprecated") to the node with id `node_id`, returning whether it was new.
+    ///
+    /// Tags are saved with the graph but kept apart from node and edge payloads, and are not
+    /// part of the undo history. They follow a node given a new id by `replace_node()`, an
+    /// edge flipped by `reverse_edge()`, and an edge moved by `rewire()`. Tags of a removed
+    /// node or edge are dropped with it, and come back if the removal is undone (see
+    /// `NodeExtras`); those of nodes hidden by `group_nodes()`, and of their edges, are kept
+    /// for `ungroup()`.
+    ///
+    /// If `node_id` is not found, an error is returned.
+    pub fn tag_node<T: Into<String>>(&mut self, node_id: Id, tag: T) -> Result<bool, GraphError> {
+        if !self.nodes.contains_key(&node_id) {
+            return Err("Node with this id not found.".into());
+        }
+        Ok(self.node_tags.entry(node_id).or_default().insert(tag.into()))
+    }
+
+    /// Returns whether the node had `tag`
+    pub fn untag_node(&mut self, node_id: Id, tag: &str) -> bool {
+        remove_tag(&mut self.node_tags, node_id, tag)
+    }
+
+    /// Tags of the node with id `node_id`, in alphabetical order (none if it is not found)
+    pub fn node_tags(&self, node_id: Id) -> Vec<&str> {
+        match self.nodes.contains_key(&node_id) {
+            true => tags_of(&self.node_tags, &node_id),
+            false => Vec::new(),
+        }
+    }
+
+    /// Ids of nodes tagged with `tag`, ascending
+    pub fn nodes_with_tag(&self, tag: &str) -> Vec<Id> {
+        self.node_tags.iter()
+            .filter(|(node_id, tags)| tags.contains(tag) && self.nodes.contains_key(node_id))
+            .map(|(&node_id, _)| node_id)
+            .collect()
+    }
+
+    /// Same as `tag_node()`, for the edge from `start_id` to `end_id`
+    pub fn tag_edge<T: Into<String>>(&mut self, start_id: Id, end_id: Id, tag: T) -> Result<bool, GraphError> {
+        if self.edge_index(start_id, end_id).is_none() {
+            return Err("Edge not found in graph.".into());
+        }
+        Ok(self.edge_tags.entry(start_id).or_default().entry(end_id).or_default().insert(tag.into()))
+    }
+
+    /// Returns whether the edge had `tag`
+    pub fn untag_edge(&mut self, start_id: Id, end_id: Id, tag: &str) -> bool {
+        let Some(tags_by_end) = self.edge_tags.get_mut(&start_id) else {
+            return false;
+        };
+        let removed = remove_tag(tags_by_end, end_id, tag);
+        if tags_by_end.is_empty() {
+            self.edge_tags.remove(&start_id);
+        }
+        removed
+    }
+
+    /// Tags of the edge from `start_id` to `end_id`, in alphabetical order (none if it is not found)
+    pub fn edge_tags(&self, start_id: Id, end_id: Id) -> Vec<&str> {
+        match (self.edge_tags.get(&start_id), self.edge_index(start_id, end_id)) {
+            (Some(tags_by_end), Some(_)) => tags_of(tags_by_end, &end_id),
+            _ => Vec::new(),
+        }
+    }
+
+    /// (start, end) of edges tagged with `tag`, ascending
+    pub fn edges_with_tag(&self, tag: &str) -> Vec<(Id, Id)> {
+        self.edge_tags.iter()
+            .flat_map(|(&start_id, tags_by_end)| {
+                tags_by_end.iter()
+                    .filter(|(_, tags)| tags.contains(tag))
+                    .map(move |(&end_id, _)| (start_id, end_id))
+            })
+            .filter(|&(start_id, end_id)| self.edge_index(start_id, end_id).is_some())
+            .collect()
+    }
+
+    /// Replaces the tags of the node with id `node_id` (dropping them if `tags` is empty), and
+    /// returns those it had
+    pub(super) fn set_node_tags_unregistered(&mut self, node_id: Id, tags: BTreeSet<String>) -> BTreeSet<String> {
+        let previous = self.node_tags.remove(&node_id).unwrap_or_default();
+        if !tags.is_empty() {
+            self.node_tags.insert(node_id, tags);
+        }
+        previous
+    }
+
+    /// Same as `set_node_tags_unregistered()`, for the edge from `start_id` to `end_id`
+    pub(super) fn set_edge_tags_unregistered(&mut self, start_id: Id, end_id: Id, tags: BTreeSet<String>) -> BTreeSet<String> {
+        let tags_by_end = self.edge_tags.entry(start_id).or_default();
+        let previous = tags_by_end.remove(&end_id).unwrap_or_default();
+        if !tags.is_empty() {
+            tags_by_end.insert(end_id, tags);
+        }
+        if tags_by_end.is_empty() {
+            self.edge_tags.remove(&start_id);
+        }
+        previous
+    }
+
+    /// Moves every tag kept under `old_id`, including those of its edges, over to `new_id`
+    pub(super) fn rename_tagged(&mut self, old_id: Id, new_id: Id) {
+        self.renumber_tags(&HashMap::from([(old_id, new_id)]), true);
+    }
+
+    /// Gives tags kept under an id in `id_map` to the id it maps to. The rest are kept as they
+    /// are if `keep_unmapped` (unless their id is mapped to, replacing them), and dropped otherwise.
+    pub(super) fn renumber_tags(&mut self, id_map: &HashMap<Id, Id>, keep_unmapped: bool) {
+        let new_ids: HashSet<Id> = id_map.values().copied().collect();
+        let renumbered = |id: Id| match id_map.get(&id) {
+            Some(&new_id) => Some(new_id),
+            None => (keep_unmapped && !new_ids.contains(&id)).then_some(id),
+        };
+        self.node_tags = std::mem::take(&mut self.node_tags).into_iter()
+            .filter_map(|(node_id, tags)| Some((renumbered(node_id)?, tags)))
+            .collect();
+        self.edge_tags = std::mem::take(&mut self.edge_tags).into_iter()
+            .filter_map(|(start_id, tags_by_end)| {
+                let tags_by_end: TagMap = tags_by_end.into_iter()
+                    .filter_map(|(end_id, tags)| Some((renumbered(end_id)?, tags)))
+                    .collect();
+                Some((renumbered(start_id)?, tags_by_end)).filter(|(_, tags_by_end)| !tags_by_end.is_empty())
+            })
+            .collect();
+    }
+
+    /// Moves the tags of the edge from `start_id` to `end_id` to the edge from `end_id` to `start_id`
+    pub(super) fn flip_edge_tags(&mut self, start_id: Id, end_id: Id) {
+        let Some(tags_by_end) = self.edge_tags.get_mut(&start_id) else {
+            return;
+        };
+        let Some(tags) = tags_by_end.remove(&end_id) else {
+            return;
+        };
+        if tags_by_end.is_empty() {
+            self.edge_tags.remove(&start_id);
+        }
+        self.edge_tags.entry(end_id).or_default().insert(start_id, tags);
+    }
+
+    pub(super) fn flip_all_edge_tags(&mut self) {
+        let mut flipped: BTreeMap<Id, TagMap> = BTreeMap::new();
+        for (start_id, tags_by_end) in std::mem::take(&mut self.edge_tags) {
+            for (end_id, tags) in tags_by_end {
+                flipped.entry(end_id).or_default().insert(start_id, tags);
+            }
+        }
+        self.edge_tags = flipped;
+    }
+}
+
+fn tags_of<'a>(tag_map: &'a TagMap, id: &Id) -> Vec<&'a str> {
+    tag_map.get(id)
+        .map(|tags| tags.iter().map(String::as_str).collect())
+        .unwrap_or_default()
+}
+
+fn remove_tag(tag_map: &mut TagMap, id: Id, tag: &str) -> bool {
+    let Some(tags) = tag_map.get_mut(&id) else {
+        return false;
+    };
+    let removed = tags.remove(tag);
+    if tags.is_empty() {
+        tag_map.remove(&id);
+    }
+    removed
+}
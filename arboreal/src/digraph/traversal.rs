@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+
+use crate::graph_base::{
+    graph_algo::{self, ProgressFn, WalkControl},
+    graph_components::{GraphError, Id, IdSet},
+    graph_query::{self, Bindings, Pattern},
+    graph_view::Avoiding,
+};
+use super::{Nodal, DirEdge, DiGraph};
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+
+    /// Returns `Some(ids)` along a path with the fewest edges from `start_id` to `end_id` (both included),
+    ///
+    /// Or `None` if either id is not found among the nodes, or `end_id` is unreachable.
+    pub fn shortest_path(&self, start_id: Id, end_id: Id) -> Option<Vec<Id>> {
+        graph_algo::shortest_path(self, start_id, end_id)
+    }
+
+    /// Same as `shortest_path()`, but never passing through a node in `banned_nodes` or along
+    /// an edge listed as (start, end) in `banned_edges`, e.g. to route around blocked states
+    /// without copying the graph. `None` if `start_id` or `end_id` is itself banned.
+    pub fn shortest_path_avoiding(&self, start_id: Id, end_id: Id, banned_nodes: &IdSet, banned_edges: &[(Id, Id)]) -> Option<Vec<Id>> {
+        let view = Avoiding { graph: self, nodes: banned_nodes, edges: banned_edges };
+        graph_algo::shortest_path(&view, start_id, end_id)
+    }
+
+    /// Same as `shortest_path()`, but searches from both ends at once
+    /// (using `neighbors_before` for the backward half), which visits far
+    /// fewer nodes on large graphs.
+    ///
+    /// When several shortest paths exist, the two methods may return different ones.
+    pub fn shortest_path_bidirectional(&self, start_id: Id, end_id: Id) -> Option<Vec<Id>> {
+        graph_algo::shortest_path_bidirectional(self, start_id, end_id)
+    }
+
+    /// Number of edges on a shortest path from `start_id` to each node it can reach (itself
+    /// included, at 0), e.g. how many choices deep each passage is from a story's start.
+    /// Empty if `start_id` is not found among the nodes.
+    pub fn distances_from(&self, start_id: Id) -> HashMap<Id, usize> {
+        graph_algo::bfs_distances(self, start_id)
+    }
+
+    /// Same as `distances_from()`, adding up `weight(edge)` instead of counting edges.
+    /// Edges whose weight is negative or NaN are skipped.
+    pub fn weighted_distances_from<F: Fn(&E) -> f64>(&self, start_id: Id, weight: F) -> HashMap<Id, f64> {
+        graph_algo::weighted_distances(self, start_id, |current, next| self.get_edge(current, next).map_or(f64::NAN, &weight))
+    }
+
+    /// Greatest of the `distances_from(node_id)`, i.e. how deep the branch from `node_id`
+    /// goes along shortest paths, or `None` if `node_id` is not found among the nodes
+    pub fn eccentricity(&self, node_id: Id) -> Option<usize> {
+        self.distances_from(node_id).into_values().max()
+    }
+
+    /// Greatest eccentricity of any node: the longest of all shortest paths (0 for an empty graph)
+    pub fn diameter(&self) -> usize {
+        trace_span!("diameter", nodes = self.nodes.len());
+        self.nodes.keys().filter_map(|&node_id| self.eccentricity(node_id)).max().unwrap_or(0)
+    }
+
+    /// Smallest eccentricity of a node from which every node can be reached, or `None` if there
+    /// is no such node
+    pub fn radius(&self) -> Option<usize> {
+        trace_span!("radius", nodes = self.nodes.len());
+        self.nodes.keys()
+            .map(|&node_id| self.distances_from(node_id))
+            .filter(|distances| distances.len() == self.nodes.len())
+            .filter_map(|distances| distances.into_values().max())
+            .min()
+    }
+
+    /// Returns the ids reachable from `start_id`, best first: after `start_id` itself, each is
+    /// the highest-scoring node among the successors of those already returned (ties go to the
+    /// smallest id). Each node is scored once, when first reached, so `score` can be costly.
+    ///
+    /// The search runs as the iterator is consumed, e.g. to stop at the first goal state found.
+    /// Nothing is returned if `start_id` is not found among the nodes.
+    pub fn best_first<'a, F: Fn(Id, &N) -> f64 + 'a>(&'a self, start_id: Id, score: F) -> impl Iterator<Item = Id> + 'a {
+        graph_algo::best_first(self, start_id, score)
+    }
+
+    /// Calls `on_walk` with every path from `start_id` along at most `max_depth` edges, shortest
+    /// first along each branch, e.g. to drive a state machine under test through every short
+    /// sequence of transitions. `on_walk` can prune a path's extensions or stop the search;
+    /// see `graph_algo::enumerate_walks()`.
+    ///
+    /// Returns how many paths were reported (0 if `start_id` is not found among the nodes).
+    pub fn enumerate_walks<F: FnMut(&[Id]) -> WalkControl>(&self, start_id: Id, max_depth: usize, on_walk: F) -> usize {
+        graph_algo::enumerate_walks(self, start_id, max_depth, 0, on_walk)
+    }
+
+    /// Same as `enumerate_walks()`, but each node may come back up to `max_revisits` times
+    /// in a walk, so that cycles are gone around too
+    pub fn enumerate_walks_with_revisits<F>(&self, start_id: Id, max_depth: usize, max_revisits: usize, on_walk: F) -> usize
+    where
+        F: FnMut(&[Id]) -> WalkControl,
+    {
+        graph_algo::enumerate_walks(self, start_id, max_depth, max_revisits, on_walk)
+    }
+
+    /// Returns `Some(ids)` of all nodes, each edge's start before its end, or `None` if the graph
+    /// has a cycle. Among nodes that could come next, the smallest id goes first, so the order
+    /// is the same on every run.
+    pub fn topological_order(&self) -> Option<Vec<Id>> {
+        graph_algo::topological_order_by_key(self, |_| ())
+    }
+
+    /// Same as `topological_order()`, but ties go to the node with the smallest `key(node)`,
+    /// and only then to the smallest id.
+    pub fn topological_order_by<K: Ord, F: Fn(&N) -> K>(&self, key: F) -> Option<Vec<Id>> {
+        graph_algo::topological_order_by_key(self, |id| key(&self.nodes[&id]))
+    }
+
+    /// Returns the bindings of every match of a pattern such as
+    /// `(a)-[]->(b) WHERE out_degree(b) == 0`; see `graph_query` for the syntax.
+    ///
+    /// If the query can't be parsed, an error is returned.
+    pub fn query(&self, text: &str) -> Result<Vec<Bindings>, GraphError> {
+        graph_query::query(self, text)
+    }
+
+    /// Same as `query()`, for a pattern built in code
+    pub fn match_pattern(&self, pattern: &Pattern) -> Vec<Bindings> {
+        graph_query::match_pattern(self, pattern)
+    }
+
+    /// Same as `match_pattern()`, reporting each node tried for the pattern's first node;
+    /// see `graph_algo::Progress`.
+    ///
+    /// If `progress` cancels, `GraphError::Cancelled` is returned.
+    pub fn match_pattern_with_progress(&self, pattern: &Pattern, progress: ProgressFn) -> Result<Vec<Bindings>, GraphError> {
+        graph_query::match_pattern_with_progress(self, pattern, progress)
+    }
+}
@@ -0,0 +1,285 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::Deref;
+
+use crate::graph_base::{graph_algo, graph_components::{GraphChange, GraphError, Id, UndoneChange}, graph_ref};
+use super::{Nodal, DirEdge, DiGraph, ChangeCache};
+
+/// A `DiGraph` known to be an arborescence: every edge points away from `root()`, and every
+/// other node has exactly one parent.
+///
+/// Derefs to `DiGraph` for all read-only methods; `into_graph()` gives back the plain graph.
+/// Edits go through `Tree` methods that keep the shape intact, each undone as one change.
+#[derive(PartialEq)]
+pub struct Tree<N, E> {
+    graph: DiGraph<N, E>,
+    root: Id,
+}
+
+impl<N: Nodal, E: DirEdge> Tree<N, E> {
+
+    /// The undo history of `graph` is cleared, as undoing earlier edits could break the tree shape.
+    ///
+    /// If `graph` is not an arborescence rooted at `root` (see `DiGraph::is_arborescence()`),
+    /// an error is returned.
+    pub fn from_graph(mut graph: DiGraph<N, E>, root: Id) -> Result<Self, GraphError> {
+        if !graph.is_arborescence(root) {
+            return Err("Graph is not a tree with this root; see tree_violations().".into());
+        }
+        graph.clear_history();
+        Ok(Self { graph, root })
+    }
+
+    pub fn root(&self) -> Id {
+        self.root
+    }
+    pub fn into_graph(self) -> DiGraph<N, E> {
+        self.graph
+    }
+
+    /// Returns `None` for the root, or if `node_id` is not found among the nodes
+    pub fn parent(&self, node_id: Id) -> Option<Id> {
+        self.graph.neighbors_before.get(&node_id)?.first().copied()
+    }
+
+    /// Children in `ordered_successors()` order, or `None` if `node_id` is not found
+    pub fn children(&self, node_id: Id) -> Option<Vec<Id>> {
+        self.graph.ordered_successors(node_id)
+    }
+
+    /// Number of edges between the root and `node_id`
+    pub fn depth(&self, node_id: Id) -> Option<usize> {
+        Some(self.ancestors(node_id)?.len())
+    }
+
+    /// Ids from the parent of `node_id` up to the root (empty for the root itself),
+    /// or `None` if `node_id` is not found
+    pub fn ancestors(&self, node_id: Id) -> Option<Vec<Id>> {
+        if !self.graph.nodes.contains_key(&node_id) {
+            return None;
+        }
+        let mut ancestors = Vec::new();
+        let mut current = node_id;
+        while let Some(parent) = self.parent(current) {
+            ancestors.push(parent);
+            current = parent;
+        }
+        Some(ancestors)
+    }
+
+    /// Lowest common ancestor: the deepest node with both `a` and `b` in its subtree
+    /// (a node counts as being in its own subtree), found by walking up from both.
+    ///
+    /// Returns `None` if either id is not found. For many queries, see `build_lca_index()`.
+    pub fn lca(&self, a: Id, b: Id) -> Option<Id> {
+        let mut above_a: HashSet<Id> = self.ancestors(a)?.into_iter().collect();
+        above_a.insert(a);
+        let mut current = b;
+        loop {
+            if above_a.contains(&current) {
+                return Some(current);
+            }
+            current = self.parent(current)?;
+        }
+    }
+
+    /// Moves `node_id`, with everything below it, to be the last child of `new_parent`.
+    ///
+    /// Does nothing if `new_parent` is already the parent. If either id is not found,
+    /// `node_id` is the root, `new_parent` is in the subtree of `node_id`, or a degree limit
+    /// would be broken, an error is returned.
+    pub fn move_subtree(&mut self, node_id: Id, new_parent: Id) -> Result<(), GraphError> {
+        let old_parent = self.movable_parent(node_id, new_parent)?;
+        if self.ancestors(new_parent).unwrap().contains(&node_id) {
+            return Err("Cannot move a subtree below itself.".into());
+        }
+        if old_parent == new_parent {
+            return Ok(());
+        }
+        self.graph.check_moved_edge_limits(&[(old_parent, node_id)], &[(new_parent, node_id)])?;
+        self.graph.begin_change();
+        let mut changes = Vec::new();
+        self.relink(&mut changes, node_id, old_parent, new_parent);
+        self.place_child(&mut changes, new_parent, node_id, None);
+        self.graph.commit_change(GraphChange::Batch(changes))?;
+        Ok(())
+    }
+
+    /// Moves `node_id` alone to be the last child of `new_parent`; its children take its
+    /// place among the children of its old parent. `new_parent` may be a descendant of `node_id`.
+    ///
+    /// If either id is not found, `node_id` is the root, `new_parent` is `node_id`,
+    /// or a degree limit would be broken, an error is returned.
+    pub fn reparent(&mut self, node_id: Id, new_parent: Id) -> Result<(), GraphError> {
+        let old_parent = self.movable_parent(node_id, new_parent)?;
+        if node_id == new_parent {
+            return Err("Cannot make a node its own parent.".into());
+        }
+        let children = self.children(node_id).unwrap();
+        let mut removed: Vec<(Id, Id)> = children.iter().map(|&child| (node_id, child)).collect();
+        let mut added: Vec<(Id, Id)> = children.iter().map(|&child| (old_parent, child)).collect();
+        removed.push((old_parent, node_id));
+        added.push((new_parent, node_id));
+        self.graph.check_moved_edge_limits(&removed, &added)?;
+        self.graph.begin_change();
+        let mut changes = Vec::new();
+        let mut siblings = self.children(old_parent).unwrap();
+        let position = siblings.iter().position(|&id| id == node_id).unwrap();
+        siblings.splice(position..=position, children.iter().copied());
+        for &child in children.iter() {
+            self.relink(&mut changes, child, node_id, old_parent);
+        }
+        if !children.is_empty() {
+            self.set_order(&mut changes, old_parent, siblings);
+        }
+        if old_parent != new_parent {
+            self.relink(&mut changes, node_id, old_parent, new_parent);
+        }
+        self.place_child(&mut changes, new_parent, node_id, None);
+        self.graph.commit_change(GraphChange::Batch(changes))?;
+        Ok(())
+    }
+
+    /// Adds `node` as a child of `parent`, at position `index` among its children
+    /// (`index` equal to the number of children appends it).
+    ///
+    /// If `parent` is not found, the id of `node` is taken, `index` is out of range,
+    /// or a degree limit would be broken, an error is returned.
+    pub fn insert_child_at(&mut self, parent: Id, index: usize, node: N) -> Result<(), GraphError> {
+        let child_count = self.children(parent)
+            .ok_or(GraphError::Invalid("Node with this id not found."))?
+            .len();
+        if index > child_count {
+            return Err("Child index out of range.".into());
+        }
+        let change = graph_ref::check_add_node::<N, E>(&self.graph.nodes, node);
+        let node = change.try_get_node()?;
+        let node_id = node.node_id();
+        self.graph.check_edge_limits(parent, node_id)?;
+        self.graph.begin_change();
+        self.graph.insert_node_unregistered(node);
+        let edge = E::bare(parent, node_id);
+        self.graph.insert_edge_unregistered(edge.clone());
+        let mut changes = vec![change, GraphChange::AddEdge(edge)];
+        self.place_child(&mut changes, parent, node_id, Some(index));
+        self.graph.commit_change(GraphChange::Batch(changes))?;
+        Ok(())
+    }
+
+    /// Reverses the most recent edit, and returns it; see `DiGraph::undo()`
+    pub fn undo(&mut self) -> Result<Option<UndoneChange<N, E>>, GraphError> {
+        self.graph.undo()
+    }
+
+    /// Checks shared by moves, returning the current parent of `node_id`
+    fn movable_parent(&self, node_id: Id, new_parent: Id) -> Result<Id, GraphError> {
+        if !self.graph.nodes.contains_key(&new_parent) {
+            return Err("Node with this id not found.".into());
+        }
+        match self.graph.nodes.contains_key(&node_id) {
+            true => self.parent(node_id).ok_or(GraphError::Invalid("The root cannot be moved.")),
+            false => Err("Node with this id not found.".into()),
+        }
+    }
+
+    /// Moves the edge from `old_parent` to `child` so that it starts at `new_parent`
+    fn relink(&mut self, changes: &mut Vec<GraphChange<N, E>>, child: Id, old_parent: Id, new_parent: Id) {
+        let edge_index = self.graph.edge_index(old_parent, child).unwrap();
+        let mut edge = self.graph.edges[edge_index].clone();
+        self.graph.remove_edge_unregistered(edge_index);
+        changes.push(GraphChange::RemoveEdge(edge.clone()));
+        edge.change_start(new_parent);
+        self.graph.insert_edge_unregistered(edge.clone());
+        changes.push(GraphChange::AddEdge(edge));
+    }
+
+    /// Puts `child` at `index` (or last) among the children of `parent`, if not already there
+    fn place_child(&mut self, changes: &mut Vec<GraphChange<N, E>>, parent: Id, child: Id, index: Option<usize>) {
+        let mut order = self.children(parent).unwrap();
+        order.retain(|&id| id != child);
+        order.insert(index.unwrap_or(order.len()), child);
+        if self.children(parent).unwrap() != order {
+            self.set_order(changes, parent, order);
+        }
+    }
+
+    fn set_order(&mut self, changes: &mut Vec<GraphChange<N, E>>, parent: Id, order: Vec<Id>) {
+        let previous_order = self.graph.set_successor_order_unregistered(parent, order);
+        changes.push(GraphChange::ReorderSuccessors(parent, previous_order));
+    }
+
+    /// Precomputes ancestor tables so that each `LcaIndex::lca()` takes O(log n) steps.
+    ///
+    /// The index describes the tree as it is now; rebuild it after editing the tree.
+    pub fn build_lca_index(&self) -> LcaIndex {
+        let order = graph_algo::bfs_order(&self.graph, self.root, usize::MAX, usize::MAX);
+        let mut depths = HashMap::with_capacity(order.len());
+        let mut jumps: HashMap<Id, Vec<Id>> = HashMap::with_capacity(order.len());
+        // Parents come before children in BFS order, so their jump tables are ready in time
+        for id in order {
+            let Some(parent) = self.parent(id) else {
+                depths.insert(id, 0);
+                jumps.insert(id, vec![]);
+                continue;
+            };
+            depths.insert(id, depths[&parent] + 1);
+            // jumps[id][k] is the ancestor 2^k levels up
+            let mut table = vec![parent];
+            while let Some(&next) = jumps[&table[table.len() - 1]].get(table.len() - 1) {
+                table.push(next);
+            }
+            jumps.insert(id, table);
+        }
+        LcaIndex { depths, jumps }
+    }
+}
+
+impl<N, E> Deref for Tree<N, E> {
+    type Target = DiGraph<N, E>;
+
+    fn deref(&self) -> &DiGraph<N, E> {
+        &self.graph
+    }
+}
+
+/// Binary-lifting tables for repeated `lca()` queries; see `Tree::build_lca_index()`
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct LcaIndex {
+    depths: HashMap<Id, usize>,
+    jumps: HashMap<Id, Vec<Id>>,
+}
+
+impl LcaIndex {
+    fn jump(&self, mut id: Id, mut levels: usize) -> Id {
+        let mut k = 0;
+        while levels > 0 {
+            if levels & 1 == 1 {
+                id = self.jumps[&id][k];
+            }
+            levels >>= 1;
+            k += 1;
+        }
+        id
+    }
+
+    /// Same as `Tree::lca()`, for the tree as it was when the index was built
+    pub fn lca(&self, a: Id, b: Id) -> Option<Id> {
+        let (&depth_a, &depth_b) = (self.depths.get(&a)?, self.depths.get(&b)?);
+        let (mut a, mut b) = match depth_a >= depth_b {
+            true => (self.jump(a, depth_a - depth_b), b),
+            false => (a, self.jump(b, depth_b - depth_a)),
+        };
+        if a == b {
+            return Some(a);
+        }
+        // Climb by the largest jumps that keep the two below their common ancestor
+        for k in (0..self.jumps[&a].len()).rev() {
+            if let (Some(&up_a), Some(&up_b)) = (self.jumps[&a].get(k), self.jumps[&b].get(k))
+                && up_a != up_b
+            {
+                a = up_a;
+                b = up_b;
+            }
+        }
+        self.jumps[&a].first().copied()
+    }
+}
@@ -0,0 +1,188 @@
+use std::borrow::Cow;
+use std::ops::Deref;
+
+use crate::graph_base::{graph_components::{Id, Temporal}, graph_view::{AnyGraph, GraphView}};
+use super::{Nodal, DirEdge, DiGraph, ChangeCache};
+
+impl<N: Nodal, E: DirEdge> AnyGraph for DiGraph<N, E> {
+    fn all_node_ids(&self) -> Vec<Id> {
+        DiGraph::all_node_ids(self)
+    }
+    fn all_edge_pairs(&self) -> Vec<(Id, Id)> {
+        DiGraph::all_edge_pairs(self)
+    }
+    fn successors_sorted(&self, node_id: Id) -> Option<Vec<Id>> {
+        DiGraph::successors_sorted(self, node_id)
+    }
+    fn has_node(&self, node_id: Id) -> bool {
+        self.nodes.contains_key(&node_id)
+    }
+}
+
+impl<N: Nodal, E: DirEdge> GraphView for DiGraph<N, E> {
+    type Node = N;
+
+    fn node_ids(&self) -> Vec<Id> {
+        self.all_node_ids()
+    }
+    fn contains_node(&self, id: Id) -> bool {
+        self.nodes.contains_key(&id)
+    }
+    fn node(&self, id: Id) -> Option<&N> {
+        self.nodes.get(&id)
+    }
+    fn successors(&self, id: Id) -> Cow<'_, [Id]> {
+        if self.has_successor_order(id) {
+            return Cow::Owned(self.ordered_successor_entries(id));
+        }
+        match self.neighbors_after.get(&id) {
+            Some(ids_after) => Cow::Borrowed(ids_after.as_slice()),
+            None => Cow::Borrowed(&[]),
+        }
+    }
+    fn predecessors(&self, id: Id) -> Cow<'_, [Id]> {
+        match self.neighbors_before.get(&id) {
+            Some(ids_before) => Cow::Borrowed(ids_before.as_slice()),
+            None => Cow::Borrowed(&[]),
+        }
+    }
+    fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+    fn edge_pairs(&self) -> Vec<(Id, Id)> {
+        self.all_edge_pairs()
+    }
+}
+
+/// Borrowed view of a `DiGraph` that hides nodes failing `node_pred` and edges failing
+/// `edge_pred` (or touching a hidden node), built by `DiGraph::filtered()`.
+pub struct FilteredView<'a, N, E> {
+    graph: &'a DiGraph<N, E>,
+    node_pred: Box<dyn Fn(&N) -> bool + 'a>,
+    edge_pred: Box<dyn Fn(&E) -> bool + 'a>,
+}
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+    /// Returns a view of the subgraph of nodes passing `node_pred` and edges passing `edge_pred`,
+    /// without copying anything. Crate algorithms (see `graph_algo`) run on it like on a `DiGraph`.
+    pub fn filtered<'a>(
+        &'a self,
+        node_pred: impl Fn(&N) -> bool + 'a,
+        edge_pred: impl Fn(&E) -> bool + 'a,
+    ) -> FilteredView<'a, N, E> {
+        FilteredView { graph: self, node_pred: Box::new(node_pred), edge_pred: Box::new(edge_pred) }
+    }
+
+    /// Returns a view of the graph at time `t`, keeping every node but only the edges valid then.
+    /// Like `filtered()`, nothing is copied and lookups still go through the graph's adjacency.
+    pub fn as_of(&self, t: u64) -> FilteredView<'_, N, E> where E: Temporal {
+        self.filtered(|_| true, move |edge| edge.is_valid_at(t))
+    }
+}
+
+impl<N: Nodal, E: DirEdge> FilteredView<'_, N, E> {
+    fn keeps_edge(&self, edge: &E) -> bool {
+        let (start_id, end_id) = edge.terminal_ids();
+        (self.edge_pred)(edge) && self.contains_node(start_id) && self.contains_node(end_id)
+    }
+
+    /// Ids at the other end of kept edges touching `id`, ascending
+    fn kept_neighbors(&self, id: Id, outgoing: bool) -> Vec<Id> {
+        if !self.contains_node(id) {
+            return Vec::new();
+        }
+        let mut neighbor_ids: Vec<Id> = self.graph.edges_incident_on(id)
+            .filter(|edge| self.keeps_edge(edge))
+            .filter_map(|edge| {
+                let (start_id, end_id) = edge.terminal_ids();
+                match outgoing {
+                    true if start_id == id => Some(end_id),
+                    false if end_id == id => Some(start_id),
+                    _ => None,
+                }
+            })
+            .collect();
+        neighbor_ids.sort_unstable();
+        neighbor_ids
+    }
+}
+
+impl<N: Nodal, E: DirEdge> GraphView for FilteredView<'_, N, E> {
+    type Node = N;
+
+    fn node_ids(&self) -> Vec<Id> {
+        let mut ids: Vec<Id> = self.graph.nodes.iter()
+            .filter(|(_, node)| (self.node_pred)(node))
+            .map(|(&id, _)| id)
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+    fn contains_node(&self, id: Id) -> bool {
+        self.node(id).is_some()
+    }
+    fn node(&self, id: Id) -> Option<&N> {
+        self.graph.nodes.get(&id).filter(|node| (self.node_pred)(node))
+    }
+    fn successors(&self, id: Id) -> Cow<'_, [Id]> {
+        Cow::Owned(self.kept_neighbors(id, true))
+    }
+    fn predecessors(&self, id: Id) -> Cow<'_, [Id]> {
+        Cow::Owned(self.kept_neighbors(id, false))
+    }
+}
+
+/// A `DiGraph` that can no longer be edited, and carries no undo history.
+///
+/// Derefs to `DiGraph` for all read-only methods; `thaw()` gives back an editable graph.
+#[derive(PartialEq)]
+pub struct FrozenDiGraph<N, E>(DiGraph<N, E>);
+
+impl<N: Nodal, E: DirEdge> DiGraph<N, E> {
+    /// Consumes the graph, dropping its undo history
+    pub fn freeze(mut self) -> FrozenDiGraph<N, E> {
+        self.clear_history();
+        FrozenDiGraph(self)
+    }
+}
+
+impl<N: Nodal, E: DirEdge> FrozenDiGraph<N, E> {
+    /// Returns the editable graph, with an empty undo history
+    pub fn thaw(self) -> DiGraph<N, E> {
+        self.0
+    }
+}
+
+impl<N, E> Deref for FrozenDiGraph<N, E> {
+    type Target = DiGraph<N, E>;
+
+    fn deref(&self) -> &DiGraph<N, E> {
+        &self.0
+    }
+}
+
+impl<N: Nodal, E: DirEdge> GraphView for FrozenDiGraph<N, E> {
+    type Node = N;
+
+    fn node_ids(&self) -> Vec<Id> {
+        self.0.node_ids()
+    }
+    fn contains_node(&self, id: Id) -> bool {
+        self.0.contains_node(id)
+    }
+    fn node(&self, id: Id) -> Option<&N> {
+        self.0.node(id)
+    }
+    fn successors(&self, id: Id) -> Cow<'_, [Id]> {
+        self.0.successors(id)
+    }
+    fn predecessors(&self, id: Id) -> Cow<'_, [Id]> {
+        self.0.predecessors(id)
+    }
+    fn node_count(&self) -> usize {
+        self.0.node_count()
+    }
+    fn edge_pairs(&self) -> Vec<(Id, Id)> {
+        self.0.edge_pairs()
+    }
+}
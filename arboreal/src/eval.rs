@@ -0,0 +1,169 @@
+//! Dataflow evaluation: each node computes an output from the outputs of its predecessors.
+//!
+//! `DiGraph::evaluate()` computes everything upstream of a node once. An `Evaluator` keeps
+//! outputs between calls and recomputes only nodes flagged with `DiGraph::mark_dirty()`
+//! (and those downstream of them), optionally spreading each step across threads.
+
+use std::collections::HashMap;
+
+use crate::digraph::DiGraph;
+use crate::graph_base::{
+    graph_algo,
+    graph_components::{DirEdge, GraphError, Id, IdSet, Nodal},
+    graph_view::{GraphView, Reversed},
+};
+
+pub trait Compute {
+    type Output;
+
+    /// `inputs` holds the outputs of the node's predecessors, ascending by id
+    /// (once per edge, so parallel edges repeat an input)
+    fn compute(&mut self, inputs: &[&Self::Output]) -> Self::Output;
+}
+
+/// Nodes that can be computed together, each with its predecessor ids (its inputs)
+type Step = Vec<(Id, Vec<Id>)>;
+
+/// Outputs kept between evaluations; see the module docs.
+///
+/// Flags set by `mark_dirty()` are consumed by the next `evaluate()`. Structural edits
+/// (new or removed edges) don't flag anything, so mark the nodes whose inputs changed.
+pub struct Evaluator<O> {
+    outputs: HashMap<Id, O>,
+}
+
+impl<O> Evaluator<O> {
+    pub fn new() -> Self {
+        Self { outputs: HashMap::new() }
+    }
+
+    /// Output of `node_id` from the last evaluation that needed it
+    pub fn output(&self, node_id: Id) -> Option<&O> {
+        self.outputs.get(&node_id)
+    }
+
+    /// Drops every kept output
+    pub fn clear(&mut self) {
+        self.outputs.clear();
+    }
+
+    /// Computes `sink_id` and everything upstream of it, in topological order, skipping
+    /// nodes whose kept output is still valid, and returns the output of `sink_id`.
+    ///
+    /// If `sink_id` is not found, or a cycle leads into it, an error is returned.
+    pub fn evaluate<N, E>(&mut self, graph: &mut DiGraph<N, E>, sink_id: Id) -> Result<&O, GraphError>
+    where
+        N: Nodal + Compute<Output = O>,
+        E: DirEdge,
+    {
+        trace_span!("evaluate", sink_id, memoized = true);
+        for node_id in graph.propagate_dirty() {
+            self.outputs.remove(&node_id);
+        }
+        for level in self.plan(graph, sink_id)? {
+            for (node_id, input_ids) in level {
+                let inputs: Vec<&O> = input_ids.iter().map(|id| &self.outputs[id]).collect();
+                let output = graph.get_node_mut(node_id).unwrap().compute(&inputs);
+                self.outputs.insert(node_id, output);
+            }
+        }
+        Ok(&self.outputs[&sink_id])
+    }
+
+    /// Same as `evaluate()`, but nodes that don't depend on each other are computed
+    /// on separate threads
+    pub fn evaluate_parallel<N, E>(&mut self, graph: &mut DiGraph<N, E>, sink_id: Id) -> Result<&O, GraphError>
+    where
+        N: Nodal + Compute<Output = O> + Send,
+        E: DirEdge,
+        O: Send + Sync,
+    {
+        trace_span!("evaluate", sink_id, memoized = true, parallel = true);
+        for node_id in graph.propagate_dirty() {
+            self.outputs.remove(&node_id);
+        }
+        let threads = std::thread::available_parallelism().map_or(1, |count| count.get());
+        for level in self.plan(graph, sink_id)? {
+            let mut jobs: Vec<(&mut N, Vec<Id>)> = Vec::with_capacity(level.len());
+            let mut level: HashMap<Id, Vec<Id>> = level.into_iter().collect();
+            for node in graph.nodes_mut() {
+                if let Some(input_ids) = level.remove(&node.node_id()) {
+                    jobs.push((node, input_ids));
+                }
+            }
+            let outputs = &self.outputs;
+            let chunk_size = jobs.len().div_ceil(threads);
+            let results: Vec<(Id, O)> = std::thread::scope(|scope| {
+                let handles: Vec<_> = jobs.chunks_mut(chunk_size)
+                    .map(|chunk| scope.spawn(move || {
+                        chunk.iter_mut()
+                            .map(|(node, input_ids)| {
+                                let inputs: Vec<&O> = input_ids.iter().map(|id| &outputs[id]).collect();
+                                (node.node_id(), node.compute(&inputs))
+                            })
+                            .collect::<Vec<_>>()
+                    }))
+                    .collect();
+                handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect()
+            });
+            self.outputs.extend(results);
+        }
+        Ok(&self.outputs[&sink_id])
+    }
+
+    /// Groups the nodes to compute into steps, each node after every predecessor it waits on
+    fn plan<G: GraphView + ?Sized>(&self, graph: &G, sink_id: Id) -> Result<Vec<Step>, GraphError> {
+        if !graph.contains_node(sink_id) {
+            return Err("Node with this id not found.".into());
+        }
+        let mut order = graph_algo::downstream_order(&Reversed(graph), &[sink_id]);
+        order.reverse();
+        let mut placed = IdSet::new();
+        let mut steps: HashMap<Id, usize> = HashMap::new();
+        let mut levels: Vec<Step> = Vec::new();
+        for node_id in order {
+            let input_ids = graph.predecessors(node_id).into_owned();
+            if input_ids.iter().any(|&id| !placed.contains(id)) {
+                return Err("Cannot evaluate a node that depends on itself (cycle).".into());
+            }
+            placed.insert(node_id);
+            let waits_on = input_ids.iter().filter_map(|id| steps.get(id)).max();
+            if waits_on.is_none() && self.outputs.contains_key(&node_id) {
+                continue;
+            }
+            let step = waits_on.map_or(0, |step| step + 1);
+            steps.insert(node_id, step);
+            if levels.len() <= step {
+                levels.push(Vec::new());
+            }
+            levels[step].push((node_id, input_ids));
+        }
+        Ok(levels)
+    }
+}
+
+impl<O> Default for Evaluator<O> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<N: Nodal + Compute, E: DirEdge> DiGraph<N, E> {
+
+    /// Computes `sink_id` and everything upstream of it, in topological order, and returns
+    /// the output of `sink_id`. Nothing is kept; see `Evaluator` for repeated evaluation.
+    ///
+    /// If `sink_id` is not found, or a cycle leads into it, an error is returned.
+    pub fn evaluate(&mut self, sink_id: Id) -> Result<N::Output, GraphError> {
+        trace_span!("evaluate", sink_id, memoized = false);
+        let mut evaluator = Evaluator::new();
+        for level in evaluator.plan(self, sink_id)? {
+            for (node_id, input_ids) in level {
+                let inputs: Vec<&N::Output> = input_ids.iter().map(|id| &evaluator.outputs[id]).collect();
+                let output = self.get_node_mut(node_id).unwrap().compute(&inputs);
+                evaluator.outputs.insert(node_id, output);
+            }
+        }
+        Ok(evaluator.outputs.remove(&sink_id).unwrap())
+    }
+}
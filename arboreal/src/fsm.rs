@@ -0,0 +1,100 @@
+//! Finite-state-machine runtime over a `DiGraph`.
+//!
+//! Nodes are states and edges are transitions. An edge says which events trigger it, and may
+//! add a guard (checked before the edge is taken) and an action (run when it is taken), both
+//! with access to a context owned by the machine.
+
+use crate::digraph::DiGraph;
+use crate::graph_base::graph_components::{DirEdge, GraphError, Id, Nodal};
+
+pub trait Transition: DirEdge {
+    type Event;
+    /// State shared by all guards and actions (counters, flags, handles, ...)
+    type Context;
+
+    fn triggered_by(&self, event: &Self::Event) -> bool;
+
+    fn guard(&self, _event: &Self::Event, _ctx: &Self::Context) -> bool {
+        true
+    }
+    fn action(&self, _event: &Self::Event, _ctx: &mut Self::Context) {}
+}
+
+pub struct StateMachine<N, E: Transition> {
+    graph: DiGraph<N, E>,
+    current: Id,
+    context: E::Context,
+}
+
+impl<N: Nodal, E: Transition> StateMachine<N, E>
+where
+    E::Context: Default,
+{
+    /// Starts a machine in state `initial`, with a default context.
+    ///
+    /// If `initial` is not found among the nodes, an error is returned.
+    pub fn from_graph(graph: DiGraph<N, E>, initial: Id) -> Result<Self, GraphError> {
+        Self::with_context(graph, initial, E::Context::default())
+    }
+}
+
+impl<N: Nodal, E: Transition> StateMachine<N, E> {
+
+    /// Same as `from_graph()`, starting from the given `context`
+    pub fn with_context(graph: DiGraph<N, E>, initial: Id, context: E::Context) -> Result<Self, GraphError> {
+        if graph.get_node(initial).is_none() {
+            return Err("Initial state not found in graph.".into());
+        }
+        Ok(Self { graph, current: initial, context })
+    }
+
+    /// Id of the current state
+    pub fn current(&self) -> Id {
+        self.current
+    }
+    pub fn current_state(&self) -> &N {
+        self.graph.get_node(self.current).unwrap()
+    }
+
+    pub fn context(&self) -> &E::Context {
+        &self.context
+    }
+    pub fn context_mut(&mut self) -> &mut E::Context {
+        &mut self.context
+    }
+
+    pub fn graph(&self) -> &DiGraph<N, E> {
+        &self.graph
+    }
+    pub fn into_graph(self) -> DiGraph<N, E> {
+        self.graph
+    }
+
+    /// The edge `event` would take from the current state: the first, in `ordered_successors()`
+    /// order, among those triggered by `event` whose guard passes.
+    fn enabled_transition(&self, event: &E::Event) -> Option<&E> {
+        self.graph.ordered_successors(self.current)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|end_id| self.graph.get_edge(self.current, end_id))
+            .find(|edge| edge.triggered_by(event) && edge.guard(event, &self.context))
+    }
+
+    /// Whether `fire(event)` would succeed from the current state
+    pub fn can_fire(&self, event: &E::Event) -> bool {
+        self.enabled_transition(event).is_some()
+    }
+
+    /// Takes the transition `event` enables from the current state, running its action,
+    /// and returns the new state's id.
+    ///
+    /// If no transition is enabled, `GraphError::IllegalTransition` is returned and the state is unchanged.
+    pub fn fire(&mut self, event: &E::Event) -> Result<Id, GraphError> {
+        let edge = self.enabled_transition(event)
+            .ok_or(GraphError::IllegalTransition { from: self.current })?
+            .clone();
+        edge.action(event, &mut self.context);
+        self.current = edge.end_id();
+        Ok(self.current)
+    }
+}
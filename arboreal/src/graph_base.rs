@@ -1,3 +1,7 @@
 
 pub mod graph_components;
 pub(super) mod graph_ref;
+pub mod graph_algo;
+pub mod graph_query;
+pub mod label_path;
+pub mod graph_view;
@@ -0,0 +1,1085 @@
+//! Graph algorithms, written once against `GraphView`.
+//!
+//! `DiGraph` exposes each of these as a method; call them directly to run
+//! them on other views (e.g. `Reversed`) or on your own graph types.
+//!
+//! Long-running algorithms also come as `*_with_progress()`, taking a `ProgressFn` that is
+//! called as work completes; returning `ControlFlow::Break(())` from it stops the algorithm,
+//! which then returns `GraphError::Cancelled`.
+
+use std::cmp::Reverse;
+use std::collections::{hash_map::Entry, BinaryHeap, HashMap, HashSet, VecDeque};
+use std::ops::ControlFlow;
+
+use crate::graph_base::{graph_components::{GraphError, Id, IdSet, TreeViolation}, graph_view::{Avoiding, GraphView}};
+
+/// How far a long-running algorithm has got: `done` of `total` steps (what a step is
+/// depends on the algorithm). `done` only grows, but may stop short of `total` when
+/// the algorithm finishes early.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Progress {
+    pub done: usize,
+    pub total: usize,
+}
+
+impl Progress {
+    /// `done / total`, from 0.0 to 1.0 (1.0 when there is nothing to do)
+    pub fn fraction(&self) -> f64 {
+        match self.total {
+            0 => 1.0,
+            total => self.done as f64 / total as f64,
+        }
+    }
+}
+
+/// Progress callback; return `ControlFlow::Break(())` to cancel
+pub type ProgressFn<'a> = &'a dyn Fn(Progress) -> ControlFlow<()>;
+
+/// For the plain versions of algorithms with a `*_with_progress()` form
+pub(crate) fn never_cancel(_: Progress) -> ControlFlow<()> {
+    ControlFlow::Continue(())
+}
+
+/// Reports `done` of `total` steps, turning a cancellation into `GraphError::Cancelled`
+pub(crate) fn report(progress: ProgressFn, done: usize, total: usize) -> Result<(), GraphError> {
+    match progress(Progress { done, total }) {
+        ControlFlow::Continue(()) => Ok(()),
+        ControlFlow::Break(()) => Err(GraphError::Cancelled),
+    }
+}
+
+/// Follows `parents` from `end` back to `start`, returning the path in forward order
+fn trace_back(parents: &HashMap<Id, Id>, start: Id, end: Id) -> Vec<Id> {
+    let mut path = vec![end];
+    let mut current = end;
+    while current != start {
+        current = parents[&current];
+        path.push(current);
+    }
+    path.reverse();
+    path
+}
+
+/// Breadth-first search along edges; returns the node ids of a shortest path,
+/// including both ends, or `None` if `end` cannot be reached.
+pub fn shortest_path<G: GraphView + ?Sized>(graph: &G, start: Id, end: Id) -> Option<Vec<Id>> {
+    trace_span!("shortest_path", start, end);
+    if !graph.contains_node(start) || !graph.contains_node(end) {
+        return None;
+    }
+    if start == end {
+        return Some(vec![start]);
+    }
+    let mut parents = HashMap::new();
+    let mut visited = IdSet::from_iter([start]);
+    let mut queue = VecDeque::from([start]);
+    while let Some(current) = queue.pop_front() {
+        for &next in graph.successors(current).iter() {
+            if visited.insert(next) {
+                parents.insert(next, current);
+                if next == end {
+                    return Some(trace_back(&parents, start, end));
+                }
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+/// Ids in breadth-first order from `start` (successors in the order the view lists them),
+/// stopping at `max_nodes` ids or `max_depth` edges out. Empty if `start` is not a node.
+pub fn bfs_order<G: GraphView + ?Sized>(graph: &G, start: Id, max_nodes: usize, max_depth: usize) -> Vec<Id> {
+    trace_span!("bfs_order", start, max_nodes, max_depth);
+    let mut found = Vec::new();
+    if !graph.contains_node(start) || max_nodes == 0 {
+        return found;
+    }
+    let mut visited = IdSet::from_iter([start]);
+    let mut queue = VecDeque::from([(start, 0)]);
+    found.push(start);
+    while let Some((current, depth)) = queue.pop_front() {
+        if depth == max_depth {
+            continue;
+        }
+        for &next in graph.successors(current).iter() {
+            if found.len() == max_nodes {
+                return found;
+            }
+            if visited.insert(next) {
+                found.push(next);
+                queue.push_back((next, depth + 1));
+            }
+        }
+    }
+    found
+}
+
+/// Number of edges on a shortest path from `start` to each node it can reach (`start`
+/// included, at 0). Empty if `start` is not a node.
+pub fn bfs_distances<G: GraphView + ?Sized>(graph: &G, start: Id) -> HashMap<Id, usize> {
+    let mut distances = HashMap::new();
+    if !graph.contains_node(start) {
+        return distances;
+    }
+    distances.insert(start, 0);
+    let mut queue = VecDeque::from([start]);
+    while let Some(current) = queue.pop_front() {
+        let next_distance = distances[&current] + 1;
+        for &next in graph.successors(current).iter() {
+            if let Entry::Vacant(entry) = distances.entry(next) {
+                entry.insert(next_distance);
+                queue.push_back(next);
+            }
+        }
+    }
+    distances
+}
+
+/// Dijkstra's algorithm: total `weight(start, end)` along a lightest path from `start` to each
+/// node it can reach (`start` included, at 0.0). Edges whose weight is negative or NaN are
+/// skipped. Empty if `start` is not a node.
+pub fn weighted_distances<G, F>(graph: &G, start: Id, weight: F) -> HashMap<Id, f64>
+where
+    G: GraphView + ?Sized,
+    F: Fn(Id, Id) -> f64,
+{
+    trace_span!("weighted_distances", start);
+    let mut distances = HashMap::new();
+    if !graph.contains_node(start) {
+        return distances;
+    }
+    let mut done = IdSet::new();
+    let mut frontier = BinaryHeap::from([Reverse(Scored(0.0, start))]);
+    distances.insert(start, 0.0);
+    while let Some(Reverse(Scored(distance, current))) = frontier.pop() {
+        if !done.insert(current) {
+            continue;
+        }
+        for &next in graph.successors(current).iter() {
+            let edge_weight = weight(current, next);
+            let usable = edge_weight >= 0.0;
+            if !usable || done.contains(next) {
+                continue;
+            }
+            let next_distance = distance + edge_weight;
+            if distances.get(&next).is_none_or(|&known| next_distance < known) {
+                distances.insert(next, next_distance);
+                frontier.push(Reverse(Scored(next_distance, next)));
+            }
+        }
+    }
+    distances
+}
+
+/// An id with a score, greater for a higher score (by `f64::total_cmp`), then for a smaller id,
+/// for use in a `BinaryHeap`
+struct Scored(f64, Id);
+
+impl PartialEq for Scored {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Scored {}
+
+impl PartialOrd for Scored {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Scored {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.total_cmp(&other.0).then(other.1.cmp(&self.1))
+    }
+}
+
+/// Ids reachable from `start` (itself first), each next one the highest-scoring successor of
+/// any id yielded so far (ties go to the smallest id). Each node is scored once, when first
+/// reached; scores are compared with `f64::total_cmp`. Empty if `start` is not a node.
+///
+/// Nodes are found lazily, so stopping early skips the rest of the search.
+pub fn best_first<'a, G, F>(graph: &'a G, start: Id, score: F) -> impl Iterator<Item = Id> + 'a
+where
+    G: GraphView + ?Sized,
+    F: Fn(Id, &G::Node) -> f64 + 'a,
+{
+    let mut frontier = BinaryHeap::new();
+    let mut reached = IdSet::new();
+    if let Some(node) = graph.node(start) {
+        reached.insert(start);
+        frontier.push(Scored(score(start, node), start));
+    }
+    // Successors are only scored once the caller asks for the next id
+    let mut last = None;
+    std::iter::from_fn(move || {
+        if let Some(previous) = last.take() {
+            for &next in graph.successors(previous).iter() {
+                if reached.insert(next) {
+                    frontier.push(Scored(score(next, graph.node(next).unwrap()), next));
+                }
+            }
+        }
+        let Scored(_, current) = frontier.pop()?;
+        last = Some(current);
+        Some(current)
+    })
+}
+
+/// One direction of a bidirectional search
+struct Frontier {
+    level: Vec<Id>,
+    /// Distance from this side's starting point, for every id seen so far
+    distances: HashMap<Id, usize>,
+    /// Id through which each seen id was first reached
+    parents: HashMap<Id, Id>,
+}
+
+impl Frontier {
+    fn new(origin: Id) -> Self {
+        Self { level: vec![origin], distances: HashMap::from([(origin, 0)]), parents: HashMap::new() }
+    }
+
+    /// Expands one full level (backward if `reverse`); returns the best id also seen by `other`, if any
+    fn expand<G: GraphView + ?Sized>(&mut self, graph: &G, reverse: bool, other: &Frontier) -> Option<Id> {
+        let mut next_level = Vec::new();
+        let mut meeting: Option<(usize, Id)> = None;
+        for &current in self.level.iter() {
+            let next_distance = self.distances[&current] + 1;
+            let neighbors = match reverse {
+                false => graph.successors(current),
+                true => graph.predecessors(current),
+            };
+            for &next in neighbors.iter() {
+                if self.distances.contains_key(&next) {
+                    continue;
+                }
+                self.distances.insert(next, next_distance);
+                self.parents.insert(next, current);
+                next_level.push(next);
+                if let Some(&other_distance) = other.distances.get(&next) {
+                    let total = next_distance + other_distance;
+                    if meeting.is_none_or(|(best, _)| total < best) {
+                        meeting = Some((total, next));
+                    }
+                }
+            }
+        }
+        self.level = next_level;
+        meeting.map(|(_, id)| id)
+    }
+}
+
+/// Same result length as `shortest_path()`, but searches forward from `start` (along
+/// successors) and backward from `end` (along predecessors) at once,
+/// always growing the smaller frontier. Far fewer nodes are visited on large graphs.
+pub fn shortest_path_bidirectional<G: GraphView + ?Sized>(graph: &G, start: Id, end: Id) -> Option<Vec<Id>> {
+    trace_span!("shortest_path_bidirectional", start, end);
+    if !graph.contains_node(start) || !graph.contains_node(end) {
+        return None;
+    }
+    if start == end {
+        return Some(vec![start]);
+    }
+    let mut forward = Frontier::new(start);
+    let mut backward = Frontier::new(end);
+    while !forward.level.is_empty() && !backward.level.is_empty() {
+        let meeting = match forward.level.len() <= backward.level.len() {
+            true => forward.expand(graph, false, &backward),
+            false => backward.expand(graph, true, &forward),
+        };
+        if let Some(meeting_id) = meeting {
+            let mut path = trace_back(&forward.parents, start, meeting_id);
+            let mut current = meeting_id;
+            while current != end {
+                current = backward.parents[&current];
+                path.push(current);
+            }
+            return Some(path);
+        }
+    }
+    None
+}
+
+/// Undirected view of `edge_pairs`: for each id, its neighbors paired with the index of the
+/// connecting edge. Parallel and antiparallel edges stay separate links; self-loops are dropped.
+fn undirected_links(node_ids: &[Id], edge_pairs: &[(Id, Id)]) -> HashMap<Id, Vec<(Id, usize)>> {
+    let mut links: HashMap<Id, Vec<(Id, usize)>> = node_ids
+        .iter()
+        .map(|&id| (id, Vec::new()))
+        .collect();
+    for (edge_index, &(start, end)) in edge_pairs.iter().enumerate() {
+        if start == end {
+            continue;
+        }
+        links.entry(start).or_default().push((end, edge_index));
+        links.entry(end).or_default().push((start, edge_index));
+    }
+    links
+}
+
+/// Tarjan's low-link search over the undirected view, done iteratively so deep graphs
+/// cannot overflow the stack.
+///
+/// Returns (articulation point ids, (start, end) of bridges), both ascending.
+pub fn cut_vertices_and_bridges<G: GraphView + ?Sized>(graph: &G) -> (Vec<Id>, Vec<(Id, Id)>) {
+    trace_span!("cut_vertices_and_bridges", nodes = graph.node_count());
+    let node_ids = graph.node_ids();
+    let edge_pairs = graph.edge_pairs();
+    let links = undirected_links(&node_ids, &edge_pairs);
+    let mut discovery: HashMap<Id, usize> = HashMap::with_capacity(node_ids.len());
+    let mut low: HashMap<Id, usize> = HashMap::with_capacity(node_ids.len());
+    let mut cut_vertices = IdSet::new();
+    let mut bridges = Vec::new();
+    let mut clock = 0;
+    for &root in node_ids.iter() {
+        if discovery.contains_key(&root) {
+            continue;
+        }
+        discovery.insert(root, clock);
+        low.insert(root, clock);
+        clock += 1;
+        let mut root_children = 0;
+        // (node, edge used to reach it, position in its link list)
+        let mut stack: Vec<(Id, Option<usize>, usize)> = vec![(root, None, 0)];
+        while let Some((node, via_edge, position)) = stack.last_mut() {
+            let node = *node;
+            if let Some(&(next, edge_index)) = links[&node].get(*position) {
+                *position += 1;
+                if Some(edge_index) == *via_edge {
+                    continue;
+                }
+                if let Some(&next_discovery) = discovery.get(&next) {
+                    let node_low = low[&node].min(next_discovery);
+                    low.insert(node, node_low);
+                } else {
+                    discovery.insert(next, clock);
+                    low.insert(next, clock);
+                    clock += 1;
+                    if node == root {
+                        root_children += 1;
+                    }
+                    stack.push((next, Some(edge_index), 0));
+                }
+            } else {
+                let (_, via_edge, _) = stack.pop().unwrap();
+                if let Some(&(parent, _, _)) = stack.last() {
+                    let node_low = low[&node];
+                    low.insert(parent, low[&parent].min(node_low));
+                    if node_low > discovery[&parent] {
+                        bridges.push(edge_pairs[via_edge.unwrap()]);
+                    }
+                    if parent != root && node_low >= discovery[&parent] {
+                        cut_vertices.insert(parent);
+                    }
+                }
+            }
+        }
+        if root_children > 1 {
+            cut_vertices.insert(root);
+        }
+    }
+    let cut_vertices: Vec<Id> = cut_vertices.iter().collect();
+    bridges.sort();
+    (cut_vertices, bridges)
+}
+
+/// (start, end) of every edge whose removal alone leaves no path from `start` to `end`,
+/// in the order a path meets them. Empty if there is no path to begin with, or `start == end`.
+///
+/// Each such edge lies on every path, so only the edges of one shortest path are tried,
+/// each with a search that avoids it.
+pub fn critical_edges<G: GraphView + ?Sized>(graph: &G, start: Id, end: Id) -> Vec<(Id, Id)> {
+    critical_edges_with_progress(graph, start, end, &never_cancel).unwrap()
+}
+
+/// Same as `critical_edges()`, reporting each edge of the shortest path tried
+pub fn critical_edges_with_progress<G: GraphView + ?Sized>(
+    graph: &G,
+    start: Id,
+    end: Id,
+    progress: ProgressFn,
+) -> Result<Vec<(Id, Id)>, GraphError> {
+    trace_span!("critical_edges", start, end);
+    let Some(path) = shortest_path(graph, start, end) else {
+        return Ok(Vec::new());
+    };
+    let no_nodes = IdSet::new();
+    let mut critical = Vec::new();
+    for (done, step) in path.windows(2).enumerate() {
+        let edge = (step[0], step[1]);
+        // A parallel edge would still connect them
+        if count_of(&graph.successors(edge.0), edge.1) == 1 {
+            let view = Avoiding { graph, nodes: &no_nodes, edges: std::slice::from_ref(&edge) };
+            if shortest_path(&view, start, end).is_none() {
+                critical.push(edge);
+            }
+        }
+        report(progress, done + 1, path.len() - 1)?;
+    }
+    Ok(critical)
+}
+
+/// Ids of every node (`start` and `end` aside) whose removal alone leaves no path from `start`
+/// to `end`, in the order a path meets them. Empty if there is no path to begin with.
+pub fn critical_nodes<G: GraphView + ?Sized>(graph: &G, start: Id, end: Id) -> Vec<Id> {
+    trace_span!("critical_nodes", start, end);
+    let Some(path) = shortest_path(graph, start, end) else {
+        return Vec::new();
+    };
+    let interior = match path.len() {
+        0..=2 => &[][..],
+        len => &path[1..len - 1],
+    };
+    interior.iter()
+        .copied()
+        .filter(|&id| {
+            let banned = IdSet::from_iter([id]);
+            let view = Avoiding { graph, nodes: &banned, edges: &[] };
+            shortest_path(&view, start, end).is_none()
+        })
+        .collect()
+}
+
+/// Splits the undirected view into two sides with no link inside either side,
+/// or returns `None` if that is impossible (an odd cycle or a self-loop exists).
+///
+/// In each component, the smallest id lands on the first side.
+pub fn bipartition<G: GraphView + ?Sized>(graph: &G) -> Option<(Vec<Id>, Vec<Id>)> {
+    trace_span!("bipartition", nodes = graph.node_count());
+    let sorted_ids = graph.node_ids();
+    let edge_pairs = graph.edge_pairs();
+    if edge_pairs.iter().any(|&(start, end)| start == end) {
+        return None;
+    }
+    let links = undirected_links(&sorted_ids, &edge_pairs);
+    let mut sides: HashMap<Id, bool> = HashMap::with_capacity(sorted_ids.len());
+    for &origin in sorted_ids.iter() {
+        if sides.contains_key(&origin) {
+            continue;
+        }
+        sides.insert(origin, false);
+        let mut queue = VecDeque::from([origin]);
+        while let Some(current) = queue.pop_front() {
+            let side = sides[&current];
+            for &(next, _) in links[&current].iter() {
+                match sides.get(&next) {
+                    Some(&next_side) if next_side == side => return None,
+                    Some(_) => {},
+                    None => {
+                        sides.insert(next, !side);
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+    }
+    let (first, second): (Vec<Id>, Vec<Id>) = sorted_ids
+        .into_iter()
+        .partition(|id| !sides[id]);
+    Some((first, second))
+}
+
+/// Welsh-Powell greedy coloring of the undirected view: nodes are visited by descending
+/// degree (ties by id) and given the smallest color (`0..k`) unused by their neighbors.
+///
+/// Returns `None` if some node would need more than `k` colors, or has a self-loop.
+/// Greedy coloring is not optimal, so `None` does not prove that `k` colors are too few.
+pub fn greedy_coloring<G: GraphView + ?Sized>(graph: &G, k: usize) -> Option<HashMap<Id, usize>> {
+    trace_span!("greedy_coloring", nodes = graph.node_count(), k);
+    let node_ids = graph.node_ids();
+    let edge_pairs = graph.edge_pairs();
+    if edge_pairs.iter().any(|&(start, end)| start == end) {
+        return None;
+    }
+    let links = undirected_links(&node_ids, &edge_pairs);
+    let mut order = node_ids.clone();
+    order.sort_by_key(|id| (std::cmp::Reverse(links[id].len()), *id));
+    let mut colors: HashMap<Id, usize> = HashMap::with_capacity(node_ids.len());
+    for id in order {
+        let taken: HashSet<usize> = links[&id]
+            .iter()
+            .filter_map(|(neighbor, _)| colors.get(neighbor).copied())
+            .collect();
+        let color = (0..k).find(|color| !taken.contains(color))?;
+        colors.insert(id, color);
+    }
+    Some(colors)
+}
+
+/// Hierholzer's algorithm: returns the node sequence of a walk using every edge exactly once
+/// (an empty Vec if there are no edges), or `None` if no such walk exists.
+///
+/// The walk is closed whenever every in-degree matches its out-degree. Among equal choices,
+/// the smallest ids are taken first, so the result is deterministic.
+pub fn eulerian_walk<G: GraphView + ?Sized>(graph: &G) -> Option<Vec<Id>> {
+    trace_span!("eulerian_walk", nodes = graph.node_count());
+    let sorted_ids = graph.node_ids();
+    // Reversed so that pop() hands out the smallest remaining successor
+    let mut remaining: HashMap<Id, Vec<Id>> = sorted_ids
+        .iter()
+        .map(|&id| (id, graph.successors(id).iter().rev().copied().collect()))
+        .collect();
+    let edge_count: usize = remaining.values().map(|ids| ids.len()).sum();
+    if edge_count == 0 {
+        return Some(vec![]);
+    }
+    let mut start = None;
+    let mut end_count = 0;
+    for &id in sorted_ids.iter() {
+        let out_degree = remaining[&id].len() as isize;
+        let in_degree = graph.predecessors(id).len() as isize;
+        match out_degree - in_degree {
+            0 => {},
+            1 if start.is_none() => start = Some(id),
+            -1 if end_count == 0 => end_count += 1,
+            _ => return None,
+        }
+    }
+    let start = match start {
+        Some(id) => id,
+        None if end_count == 0 => *sorted_ids
+            .iter()
+            .find(|id| !remaining[id].is_empty())
+            .unwrap(),
+        None => return None,
+    };
+    let mut stack = vec![start];
+    let mut walk = Vec::with_capacity(edge_count + 1);
+    while let Some(&current) = stack.last() {
+        match remaining.get_mut(&current).and_then(|ids| ids.pop()) {
+            Some(next) => stack.push(next),
+            None => walk.push(stack.pop().unwrap()),
+        }
+    }
+    // Edges left over means they were not connected to the rest
+    if walk.len() != edge_count + 1 {
+        return None;
+    }
+    walk.reverse();
+    Some(walk)
+}
+
+/// Asynchronous label propagation over the undirected view. Every node starts in its own
+/// community, then repeatedly adopts the label with the most votes among its neighbors,
+/// until nothing changes or `max_iters` passes are done.
+///
+/// Each neighbor's vote is weighted by 1 + the number of neighbors both nodes share, so that
+/// tightly knit groups hold together without the random tie-breaking of classic label propagation.
+/// Nodes are visited in id order and ties keep the current label (else take the smallest),
+/// so results are deterministic. Communities are numbered from 0, in order of their smallest id.
+pub fn label_propagation<G: GraphView + ?Sized>(graph: &G, max_iters: usize) -> HashMap<Id, usize> {
+    label_propagation_with_progress(graph, max_iters, &never_cancel).unwrap()
+}
+
+/// Same as `label_propagation()`, reporting each pass (of `max_iters`)
+pub fn label_propagation_with_progress<G: GraphView + ?Sized>(
+    graph: &G,
+    max_iters: usize,
+    progress: ProgressFn,
+) -> Result<HashMap<Id, usize>, GraphError> {
+    trace_span!("label_propagation", nodes = graph.node_count(), max_iters);
+    let sorted_ids = graph.node_ids();
+    let links = undirected_links(&sorted_ids, &graph.edge_pairs());
+    let neighbor_sets: HashMap<Id, HashSet<Id>> = links
+        .iter()
+        .map(|(&id, id_links)| (id, id_links.iter().map(|(neighbor, _)| *neighbor).collect()))
+        .collect();
+    let mut labels: HashMap<Id, Id> = sorted_ids.iter().map(|&id| (id, id)).collect();
+    for pass in 0..max_iters {
+        let mut changed = false;
+        for &id in sorted_ids.iter() {
+            let mut counts: HashMap<Id, usize> = HashMap::new();
+            for (neighbor, _) in links[&id].iter() {
+                let shared = neighbor_sets[&id].intersection(&neighbor_sets[neighbor]).count();
+                *counts.entry(labels[neighbor]).or_default() += 1 + shared;
+            }
+            let Some(&top_count) = counts.values().max() else {
+                continue;
+            };
+            let current = labels[&id];
+            if counts.get(&current) == Some(&top_count) {
+                continue;
+            }
+            let best = counts
+                .iter()
+                .filter(|&(_, &count)| count == top_count)
+                .map(|(&label, _)| label)
+                .min()
+                .unwrap();
+            labels.insert(id, best);
+            changed = true;
+        }
+        report(progress, pass + 1, max_iters)?;
+        if !changed {
+            break;
+        }
+    }
+    let mut community_numbers: HashMap<Id, usize> = HashMap::new();
+    Ok(sorted_ids
+        .iter()
+        .map(|id| {
+            let next_number = community_numbers.len();
+            let number = *community_numbers.entry(labels[id]).or_insert(next_number);
+            (*id, number)
+        })
+        .collect())
+}
+
+/// Pairs `(a, b)` with `a < b` joined by edges in both directions, ascending
+pub fn mutual_pairs<G: GraphView + ?Sized>(graph: &G) -> Vec<(Id, Id)> {
+    trace_span!("mutual_pairs", nodes = graph.node_count());
+    let mut pairs = Vec::new();
+    for id in graph.node_ids() {
+        let successors = graph.successors(id);
+        let predecessors = graph.predecessors(id);
+        let mut ids_after: Vec<Id> = successors.iter().copied().filter(|&next| next > id).collect();
+        ids_after.sort_unstable();
+        ids_after.dedup();
+        for next in ids_after {
+            if predecessors.contains(&next) {
+                pairs.push((id, next));
+            }
+        }
+    }
+    pairs
+}
+
+/// Fraction of edges between distinct nodes whose reverse edge also exists
+/// (0.0 if there are no such edges). Self-loops are left out entirely.
+pub fn reciprocity<G: GraphView + ?Sized>(graph: &G) -> f64 {
+    let mut total = 0usize;
+    let mut reciprocated = 0usize;
+    for id in graph.node_ids() {
+        let predecessors = graph.predecessors(id);
+        for &next in graph.successors(id).iter().filter(|&&next| next != id) {
+            total += 1;
+            if predecessors.contains(&next) {
+                reciprocated += 1;
+            }
+        }
+    }
+    match total {
+        0 => 0.0,
+        _ => reciprocated as f64 / total as f64,
+    }
+}
+
+/// Kahn's algorithm: returns every node id with each edge's start before its end, or `None`
+/// if the graph has a cycle. Whenever several nodes are ready, the one with the smallest
+/// `(key(id), id)` comes first, so the result depends only on the graph and `key`.
+pub fn topological_order_by_key<G, K, F>(graph: &G, key: F) -> Option<Vec<Id>>
+where
+    G: GraphView + ?Sized,
+    K: Ord,
+    F: Fn(Id) -> K,
+{
+    trace_span!("topological_order", nodes = graph.node_count());
+    let node_ids = graph.node_ids();
+    let mut in_degrees: HashMap<Id, usize> = node_ids
+        .iter()
+        .map(|&id| (id, graph.predecessors(id).len()))
+        .collect();
+    let mut ready: BinaryHeap<Reverse<(K, Id)>> = node_ids
+        .iter()
+        .filter(|id| in_degrees[id] == 0)
+        .map(|&id| Reverse((key(id), id)))
+        .collect();
+    let mut order = Vec::with_capacity(node_ids.len());
+    while let Some(Reverse((_, id))) = ready.pop() {
+        order.push(id);
+        for &next in graph.successors(id).iter() {
+            let in_degree = in_degrees.get_mut(&next).unwrap();
+            *in_degree -= 1;
+            if *in_degree == 0 {
+                ready.push(Reverse((key(next), next)));
+            }
+        }
+    }
+    (order.len() == node_ids.len()).then_some(order)
+}
+
+/// Up to `limit` paths from a node with no incoming edges to one with no outgoing edges,
+/// never visiting a node twice (so a cycle is never gone around). Sources are taken in
+/// ascending order, and each path's branches in successor order; a node that is both a source
+/// and a sink is a path on its own.
+///
+/// The number of paths can grow exponentially with the size of the graph, hence `limit`.
+pub fn source_to_sink_paths<G: GraphView + ?Sized>(graph: &G, limit: usize) -> Vec<Vec<Id>> {
+    source_to_sink_paths_with_progress(graph, limit, &never_cancel).unwrap()
+}
+
+/// Same as `source_to_sink_paths()`, reporting each source whose paths are done (of all
+/// sources), and again, with the same count, at each path found, so that a search stuck on
+/// one source can still be cancelled
+pub fn source_to_sink_paths_with_progress<G: GraphView + ?Sized>(
+    graph: &G,
+    limit: usize,
+    progress: ProgressFn,
+) -> Result<Vec<Vec<Id>>, GraphError> {
+    trace_span!("source_to_sink_paths", limit);
+    let mut paths = Vec::new();
+    let mut on_path = IdSet::new();
+    let sources: Vec<Id> = graph.node_ids().into_iter().filter(|&id| graph.predecessors(id).is_empty()).collect();
+    for (done, &source) in sources.iter().enumerate() {
+        // Each frame holds a node on the current path and the successors left to try from it
+        let mut path = vec![source];
+        on_path.insert(source);
+        let mut frames: Vec<Vec<Id>> = vec![distinct_successors(graph, source)];
+        while let Some(remaining) = frames.last_mut() {
+            if paths.len() == limit {
+                return Ok(paths);
+            }
+            let current = *path.last().unwrap();
+            if remaining.is_empty() && graph.successors(current).is_empty() {
+                paths.push(path.clone());
+                report(progress, done, sources.len())?;
+            }
+            match remaining.pop() {
+                Some(next) if !on_path.contains(next) => {
+                    path.push(next);
+                    on_path.insert(next);
+                    frames.push(distinct_successors(graph, next));
+                },
+                Some(_) => {},
+                None => {
+                    on_path.remove(current);
+                    path.pop();
+                    frames.pop();
+                },
+            }
+        }
+        report(progress, done + 1, sources.len())?;
+    }
+    Ok(paths)
+}
+
+/// Successors of `id` without repeats, last to first so that popping visits them in order
+fn distinct_successors<G: GraphView + ?Sized>(graph: &G, id: Id) -> Vec<Id> {
+    let mut successors: Vec<Id> = Vec::new();
+    for &next in graph.successors(id).iter() {
+        if !successors.contains(&next) {
+            successors.push(next);
+        }
+    }
+    successors.reverse();
+    successors
+}
+
+/// What `enumerate_walks()` does after reporting a walk
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum WalkControl {
+    /// Go on, including with walks that extend this one
+    Continue,
+    /// Go on, but skip every walk that extends this one (e.g. a state the system under test rejects)
+    Prune,
+    /// Report no more walks
+    Stop,
+}
+
+/// Calls `on_walk` with every walk from `start` along at most `max_depth` edges, `[start]`
+/// itself included, depth first (so each walk comes right after the one it extends) and in
+/// successor order. A node appears at most `1 + max_revisits` times in a walk, so with 0 only
+/// paths are walked, and higher values go around cycles that many more times.
+///
+/// Returns how many walks were reported (0 if `start` is not found among the nodes).
+/// The number of walks can grow exponentially with `max_depth`.
+pub fn enumerate_walks<G, F>(graph: &G, start: Id, max_depth: usize, max_revisits: usize, mut on_walk: F) -> usize
+where
+    G: GraphView + ?Sized,
+    F: FnMut(&[Id]) -> WalkControl,
+{
+    trace_span!("enumerate_walks", start, max_depth, max_revisits);
+    if !graph.contains_node(start) {
+        return 0;
+    }
+    let mut walk = vec![start];
+    let mut visits: HashMap<Id, usize> = HashMap::from([(start, 1)]);
+    // One per node of the walk: the successors left to extend it with from there
+    let mut frames: Vec<Vec<Id>> = Vec::new();
+    let mut count = 0;
+    loop {
+        count += 1;
+        let extensions = match on_walk(&walk) {
+            WalkControl::Stop => return count,
+            WalkControl::Continue if walk.len() <= max_depth => distinct_successors(graph, *walk.last().unwrap()),
+            WalkControl::Continue | WalkControl::Prune => Vec::new(),
+        };
+        frames.push(extensions);
+        loop {
+            let Some(remaining) = frames.last_mut() else {
+                return count;
+            };
+            match remaining.pop() {
+                Some(next) if visits.get(&next).copied().unwrap_or(0) <= max_revisits => {
+                    *visits.entry(next).or_default() += 1;
+                    walk.push(next);
+                    break;
+                },
+                Some(_) => {},
+                None => {
+                    frames.pop();
+                    let last = walk.pop().unwrap();
+                    *visits.get_mut(&last).unwrap() -= 1;
+                },
+            }
+        }
+    }
+}
+
+/// Returns `start_ids` and every node reachable from them, each edge's start before its end
+/// (ids not found are skipped). Whenever several nodes are ready, the smallest id comes first;
+/// if only nodes on a cycle remain, the smallest of them is taken as if it were ready.
+pub fn downstream_order<G: GraphView + ?Sized>(graph: &G, start_ids: &[Id]) -> Vec<Id> {
+    trace_span!("downstream_order", starts = start_ids.len());
+    let mut reached: IdSet = start_ids.iter().copied().filter(|&id| graph.contains_node(id)).collect();
+    let mut queue: VecDeque<Id> = reached.iter().collect();
+    while let Some(id) = queue.pop_front() {
+        for &next in graph.successors(id).iter() {
+            if reached.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+    let mut in_degrees: HashMap<Id, usize> = reached.iter()
+        .map(|id| (id, graph.predecessors(id).iter().filter(|&&before| reached.contains(before)).count()))
+        .collect();
+    let mut ready: BinaryHeap<Reverse<Id>> = in_degrees.iter()
+        .filter(|&(_, &in_degree)| in_degree == 0)
+        .map(|(&id, _)| Reverse(id))
+        .collect();
+    let mut order = Vec::with_capacity(reached.len());
+    while order.len() < reached.len() {
+        let id = match ready.pop() {
+            Some(Reverse(id)) => id,
+            None => *in_degrees.iter().filter(|&(_, &in_degree)| in_degree > 0).map(|(id, _)| id).min().unwrap(),
+        };
+        in_degrees.insert(id, 0);
+        order.push(id);
+        for &next in graph.successors(id).iter() {
+            let in_degree = in_degrees.get_mut(&next).unwrap();
+            if *in_degree > 0 {
+                *in_degree -= 1;
+                if *in_degree == 0 {
+                    ready.push(Reverse(next));
+                }
+            }
+        }
+    }
+    order
+}
+
+/// Orders pattern nodes so each one (where possible) touches an earlier one, starting from
+/// the best-connected node; this lets the search draw candidates from neighbors of nodes
+/// already matched, as in VF2.
+fn pattern_match_order<P: GraphView + ?Sized>(pattern: &P) -> Vec<Id> {
+    let degree = |id: Id| pattern.successors(id).len() + pattern.predecessors(id).len();
+    let mut remaining = pattern.node_ids();
+    let mut order: Vec<Id> = Vec::with_capacity(remaining.len());
+    while !remaining.is_empty() {
+        let links_to_order = |id: Id| {
+            pattern.successors(id).iter().chain(pattern.predecessors(id).iter())
+                .filter(|&other| order.contains(other))
+                .count()
+        };
+        // Most links to the order so far, then highest degree, then smallest id
+        let (index, _) = remaining.iter()
+            .enumerate()
+            .max_by_key(|&(_, &id)| (links_to_order(id), degree(id), Reverse(id)))
+            .unwrap();
+        order.push(remaining.remove(index));
+    }
+    order
+}
+
+fn count_of(ids: &[Id], id: Id) -> usize {
+    ids.iter().filter(|&&other| other == id).count()
+}
+
+/// Whether mapping `pattern_id` to `graph_id` keeps every pattern edge between it and the
+/// already-mapped nodes (itself included), with at least as many parallel edges
+fn is_feasible<G, P>(graph: &G, pattern: &P, mapping: &HashMap<Id, Id>, pattern_id: Id, graph_id: Id) -> bool
+where
+    G: GraphView + ?Sized,
+    P: GraphView + ?Sized,
+{
+    let pattern_after = pattern.successors(pattern_id);
+    let pattern_before = pattern.predecessors(pattern_id);
+    let graph_after = graph.successors(graph_id);
+    let graph_before = graph.predecessors(graph_id);
+    if graph_after.len() < pattern_after.len() || graph_before.len() < pattern_before.len() {
+        return false;
+    }
+    let mapped_to = |id: Id| if id == pattern_id { Some(graph_id) } else { mapping.get(&id).copied() };
+    pattern_after.iter().all(|&next| match mapped_to(next) {
+        Some(graph_next) => count_of(&graph_after, graph_next) >= count_of(&pattern_after, next),
+        None => true,
+    }) && pattern_before.iter().all(|&previous| match mapped_to(previous) {
+        Some(graph_previous) => count_of(&graph_before, graph_previous) >= count_of(&pattern_before, previous),
+        None => true,
+    })
+}
+
+fn extend_subgraph_match<G, P>(
+    graph: &G,
+    pattern: &P,
+    order: &[Id],
+    mapping: &mut HashMap<Id, Id>,
+    used: &mut IdSet,
+    limit: usize,
+    found: &mut Vec<HashMap<Id, Id>>,
+) where
+    G: GraphView + ?Sized,
+    P: GraphView + ?Sized,
+{
+    let Some(&pattern_id) = order.get(mapping.len()) else {
+        found.push(mapping.clone());
+        return;
+    };
+    // Candidates come from the first matched neighbor, if any; otherwise from every node
+    let anchor = pattern.predecessors(pattern_id).iter()
+        .find_map(|previous| mapping.get(previous).map(|&graph_id| graph.successors(graph_id).into_owned()))
+        .or_else(|| pattern.successors(pattern_id).iter()
+            .find_map(|next| mapping.get(next).map(|&graph_id| graph.predecessors(graph_id).into_owned())));
+    let mut candidates = anchor.unwrap_or_else(|| graph.node_ids());
+    candidates.sort_unstable();
+    candidates.dedup();
+    for graph_id in candidates {
+        if found.len() >= limit {
+            return;
+        }
+        if used.contains(graph_id) || !is_feasible(graph, pattern, mapping, pattern_id, graph_id) {
+            continue;
+        }
+        mapping.insert(pattern_id, graph_id);
+        used.insert(graph_id);
+        extend_subgraph_match(graph, pattern, order, mapping, used, limit, found);
+        mapping.remove(&pattern_id);
+        used.remove(graph_id);
+    }
+}
+
+/// Backtracking search (VF2-style) for every one-to-one mapping of `pattern`'s nodes onto
+/// `graph`'s nodes that carries each pattern edge onto a graph edge. Extra graph edges between
+/// matched nodes are allowed, so this finds non-induced copies of `pattern`.
+///
+/// Each symmetry of the pattern gives a separate mapping (a hub with three leaves matches
+/// every hub six times). The search stops after `limit` mappings.
+pub fn subgraph_matches<G, P>(graph: &G, pattern: &P, limit: usize) -> Vec<HashMap<Id, Id>>
+where
+    G: GraphView + ?Sized,
+    P: GraphView + ?Sized,
+{
+    subgraph_matches_with_progress(graph, pattern, limit, &never_cancel).unwrap()
+}
+
+/// Same as `subgraph_matches()`, reporting each graph node tried for the first pattern node
+pub fn subgraph_matches_with_progress<G, P>(
+    graph: &G,
+    pattern: &P,
+    limit: usize,
+    progress: ProgressFn,
+) -> Result<Vec<HashMap<Id, Id>>, GraphError>
+where
+    G: GraphView + ?Sized,
+    P: GraphView + ?Sized,
+{
+    trace_span!("subgraph_matches", nodes = graph.node_count(), pattern_nodes = pattern.node_count(), limit);
+    let mut found = Vec::new();
+    if pattern.node_count() > graph.node_count() {
+        return Ok(found);
+    }
+    let order = pattern_match_order(pattern);
+    let (mut mapping, mut used) = (HashMap::new(), IdSet::new());
+    let Some(&first_id) = order.first() else {
+        extend_subgraph_match(graph, pattern, &order, &mut mapping, &mut used, limit, &mut found);
+        return Ok(found);
+    };
+    // Same as the first level of the search, which has every node as a candidate
+    let graph_ids = graph.node_ids();
+    for (index, &graph_id) in graph_ids.iter().enumerate() {
+        if found.len() >= limit {
+            break;
+        }
+        if is_feasible(graph, pattern, &mapping, first_id, graph_id) {
+            mapping.insert(first_id, graph_id);
+            used.insert(graph_id);
+            extend_subgraph_match(graph, pattern, &order, &mut mapping, &mut used, limit, &mut found);
+            mapping.remove(&first_id);
+            used.remove(graph_id);
+        }
+        report(progress, index + 1, graph_ids.len())?;
+    }
+    Ok(found)
+}
+
+/// Lists every node that keeps `graph` from being an arborescence rooted at `root`, or a forest
+/// of arborescences (rooted at each node with no incoming edge) if `root` is `None`.
+/// Each node is listed once, with its first problem in `TreeViolation` order, ascending by id.
+///
+/// If `root` is not found, no node is reachable, so every node with one parent is `Unreachable`.
+pub fn tree_violations<G: GraphView + ?Sized>(graph: &G, root: Option<Id>) -> Vec<(Id, TreeViolation)> {
+    trace_span!("tree_violations", nodes = graph.node_count());
+    let node_ids = graph.node_ids();
+    let mut queue: VecDeque<Id> = match root {
+        Some(root) => graph.contains_node(root).then_some(root).into_iter().collect(),
+        None => node_ids.iter().copied().filter(|&id| graph.predecessors(id).is_empty()).collect(),
+    };
+    let mut reachable: IdSet = queue.iter().copied().collect();
+    while let Some(current) = queue.pop_front() {
+        for &next in graph.successors(current).iter() {
+            if reachable.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+    let mut violations = Vec::new();
+    for id in node_ids {
+        let parent_count = graph.predecessors(id).len();
+        let violation = if root == Some(id) {
+            (parent_count > 0).then_some(TreeViolation::RootHasParents)
+        } else if parent_count == 0 {
+            root.is_some().then_some(TreeViolation::NoParent)
+        } else if parent_count > 1 {
+            Some(TreeViolation::SeveralParents(parent_count))
+        } else {
+            (!reachable.contains(id)).then_some(TreeViolation::Unreachable)
+        };
+        if let Some(violation) = violation {
+            violations.push((id, violation));
+        }
+    }
+    violations
+}
+
+/// Every id that reaches `id` along edges, `id` included
+fn ancestors_inclusive<G: GraphView + ?Sized>(graph: &G, id: Id) -> IdSet {
+    let mut found = IdSet::from_iter([id]);
+    let mut queue = VecDeque::from([id]);
+    while let Some(current) = queue.pop_front() {
+        for &previous in graph.predecessors(current).iter() {
+            if found.insert(previous) {
+                queue.push_back(previous);
+            }
+        }
+    }
+    found
+}
+
+/// Returns the lowest common ancestors of `a` and `b` in a DAG, ascending: nodes reaching
+/// both (a node reaches itself) with no edge to another such node. Empty if either id
+/// is not found, or they share no ancestor.
+pub fn lowest_common_ancestors<G: GraphView + ?Sized>(graph: &G, a: Id, b: Id) -> Vec<Id> {
+    trace_span!("lowest_common_ancestors", a, b);
+    if !graph.contains_node(a) || !graph.contains_node(b) {
+        return vec![];
+    }
+    let mut common = ancestors_inclusive(graph, a);
+    common.intersect_with(&ancestors_inclusive(graph, b));
+    // If a descendant of `id` were common, so would be the next node on the path to it
+    common.iter()
+        .filter(|&id| !graph.successors(id).iter().any(|&next| next != id && common.contains(next)))
+        .collect()
+}
@@ -1,13 +1,203 @@
 
-use serde::{de::DeserializeOwned, Serialize};
+use std::collections::BTreeSet;
+use std::fmt::{self, Display};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use smallvec::SmallVec;
+
+/// `#[derive(Nodal)]` and `#[derive(DirEdge)]`, for payloads whose ids are plain fields
+#[cfg(feature = "derive")]
+pub use arboreal_derive::{DirEdge, Nodal};
 
 pub type Id = u16;
 
+/// Neighbor list of one node; most nodes have only a few neighbors, so short lists stay inline
+pub type NeighborIds = SmallVec<[Id; 4]>;
+
+/// Largest id that can be assigned to a node
+pub const ID_MAX: Id = Id::MAX;
+
+const ID_SET_WORDS: usize = (ID_MAX as usize + 1) / 64;
+
+/// Set of ids kept as one bit per possible id (8 KiB however many ids it holds), so inserts
+/// and lookups never hash. Iterates in ascending order.
+///
+/// Used as the visited set by traversal algorithms; handy for your own passes over a graph.
+#[derive(PartialEq, Eq, Clone)]
+pub struct IdSet {
+    words: Box<[u64]>,
+    len: usize,
+}
+
+impl IdSet {
+    pub fn new() -> Self {
+        Self { words: vec![0; ID_SET_WORDS].into_boxed_slice(), len: 0 }
+    }
+    pub fn contains(&self, id: Id) -> bool {
+        self.words[id as usize / 64] & (1 << (id % 64)) != 0
+    }
+    /// Returns whether `id` was newly added
+    pub fn insert(&mut self, id: Id) -> bool {
+        let added = !self.contains(id);
+        self.words[id as usize / 64] |= 1 << (id % 64);
+        self.len += added as usize;
+        added
+    }
+    /// Returns whether `id` was present
+    pub fn remove(&mut self, id: Id) -> bool {
+        let removed = self.contains(id);
+        self.words[id as usize / 64] &= !(1 << (id % 64));
+        self.len -= removed as usize;
+        removed
+    }
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    pub fn clear(&mut self) {
+        self.words.fill(0);
+        self.len = 0;
+    }
+    /// Ids in the set, ascending
+    pub fn iter(&self) -> impl Iterator<Item = Id> + '_ {
+        self.words.iter().enumerate().flat_map(|(index, &word)| {
+            let mut bits = word;
+            std::iter::from_fn(move || {
+                if bits == 0 {
+                    return None;
+                }
+                let bit = bits.trailing_zeros() as usize;
+                bits &= bits - 1;
+                Some((index * 64 + bit) as Id)
+            })
+        })
+    }
+    /// Adds every id in `other`
+    pub fn union_with(&mut self, other: &IdSet) {
+        self.combine_with(other, |mine, theirs| mine | theirs);
+    }
+    /// Keeps only ids also in `other`
+    pub fn intersect_with(&mut self, other: &IdSet) {
+        self.combine_with(other, |mine, theirs| mine & theirs);
+    }
+    /// Drops every id in `other`
+    pub fn difference_with(&mut self, other: &IdSet) {
+        self.combine_with(other, |mine, theirs| mine & !theirs);
+    }
+
+    fn combine_with(&mut self, other: &IdSet, combine: impl Fn(u64, u64) -> u64) {
+        self.len = 0;
+        for (mine, &theirs) in self.words.iter_mut().zip(other.words.iter()) {
+            *mine = combine(*mine, theirs);
+            self.len += mine.count_ones() as usize;
+        }
+    }
+}
+
+impl Default for IdSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for IdSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl FromIterator<Id> for IdSet {
+    fn from_iter<I: IntoIterator<Item = Id>>(ids: I) -> Self {
+        let mut set = Self::new();
+        set.extend(ids);
+        set
+    }
+}
+
+impl Extend<Id> for IdSet {
+    fn extend<I: IntoIterator<Item = Id>>(&mut self, ids: I) {
+        for id in ids {
+            self.insert(id);
+        }
+    }
+}
+
+/// Reference to a node that goes stale once the node is removed, even if its id is later reused.
+///
+/// Obtained from `DiGraph::node_handle()`.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct NodeHandle {
+    pub id: Id,
+    /// Number of times a node with this id had been removed when the handle was made
+    pub generation: u32,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum GraphError {
+    /// Every id up to `ID_MAX` is already used or reserved
+    IdSpaceExhausted,
+    /// No transition leaving state `from` accepts the fired event
+    IllegalTransition { from: Id },
+    /// The handle's node was removed (its id may since have been reused)
+    StaleHandle(NodeHandle),
+    /// The node is pinned (see `DiGraph::pin_node()`), so it may not be removed
+    NodePinned(Id),
+    /// Another edge leaving node `id` would go past its `set_max_out_degree()` limit
+    OutDegreeLimit { id: Id, max: usize },
+    /// Another edge entering node `id` would go past its `set_max_in_degree()` limit
+    InDegreeLimit { id: Id, max: usize },
+    /// The edit would break the named constraint (see `DiGraph::add_constraint()`), so it was rolled back
+    ConstraintViolated(&'static str),
+    /// A progress callback asked the algorithm to stop (see `graph_algo::Progress`)
+    Cancelled,
+    /// Operation refused; the message explains why
+    Invalid(&'static str),
+}
+
+impl Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IdSpaceExhausted => write!(f, "No unused ids remain (ID_MAX = {ID_MAX})."),
+            Self::IllegalTransition { from } => write!(f, "No transition from state {from} accepts this event."),
+            Self::StaleHandle(handle) => write!(f, "Handle to node {} (generation {}) is stale.", handle.id, handle.generation),
+            Self::NodePinned(id) => write!(f, "Node {id} is pinned and cannot be removed."),
+            Self::OutDegreeLimit { id, max } => write!(f, "Node {id} may have at most {max} outgoing edges."),
+            Self::InDegreeLimit { id, max } => write!(f, "Node {id} may have at most {max} incoming edges."),
+            Self::ConstraintViolated(name) => write!(f, "Edit refused: it breaks the \"{name}\" constraint."),
+            Self::Cancelled => write!(f, "Cancelled before finishing."),
+            Self::Invalid(reason) => write!(f, "{reason}"),
+        }
+    }
+}
+
+impl std::error::Error for GraphError {}
+
+impl From<&'static str> for GraphError {
+    fn from(reason: &'static str) -> Self {
+        Self::Invalid(reason)
+    }
+}
+
+/// Node payload; with the `derive` feature, `#[derive(Nodal)]` implements it from a field
+/// marked `#[arboreal(id)]`
 pub trait Nodal: Clone + PartialEq + Serialize + DeserializeOwned + Default {
     fn bare(id: Id) -> Self;
     fn node_id(&self) -> Id;
+
+    // Only called on nodes outside any graph, e.g. copies about to be pasted
+    fn change_id(&mut self, new_id: Id);
+
+    /// Bytes the payload owns on the heap (strings, vectors, ...), for `DiGraph::memory_footprint()`;
+    /// 0 unless overridden
+    fn heap_size(&self) -> usize {
+        0
+    }
 }
 
+/// Edge payload; with the `derive` feature, `#[derive(DirEdge)]` implements it from fields
+/// marked `#[arboreal(start)]` and `#[arboreal(end)]`
 pub trait DirEdge: Clone + PartialEq + Serialize + DeserializeOwned + Default {
 
     fn bare(start: Id, end: Id) -> Self;
@@ -23,9 +213,119 @@ pub trait DirEdge: Clone + PartialEq + Serialize + DeserializeOwned + Default {
     fn end_id(&self) -> Id {
         self.terminal_ids().1
     }
+
+    /// Same as `Nodal::heap_size()`
+    fn heap_size(&self) -> usize {
+        0
+    }
 }
 
-#[derive(PartialEq, Debug, Clone)]
+/// Edge that only holds during a span of time, e.g. a relation in a knowledge graph;
+/// see `DiGraph::as_of()`
+pub trait Temporal {
+    /// First instant the edge holds, and the first after it that it no longer does
+    /// (in whatever unit the graph uses, e.g. seconds since the epoch)
+    fn valid_range(&self) -> (u64, u64);
+
+    fn is_valid_at(&self, t: u64) -> bool {
+        let (from, until) = self.valid_range();
+        from <= t && t < until
+    }
+}
+
+/// Node with a one-line title (e.g. an outline item's text); see `Tree::from_markdown_outline()`
+pub trait Titled {
+    fn title(&self) -> &str;
+    fn set_title(&mut self, title: String);
+}
+
+/// Edge with an optional numeric weight (e.g. a branch length); see `Tree::from_newick()`
+pub trait Weighted {
+    /// `None` if the edge has no weight
+    fn weight(&self) -> Option<f64>;
+    fn set_weight(&mut self, weight: Option<f64>);
+}
+
+/// Edge with a label (e.g. the symbol a state machine or parser transition consumes);
+/// see `DiGraph::successor_by_label()` and the `UniqueEdgeLabels` constraint
+pub trait LabeledEdge {
+    fn label(&self) -> &str;
+}
+
+/// Node with nothing but an id, e.g. for `DiGraph::load_structure_only()`
+#[derive(PartialEq, Eq, Clone, Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BareNode {
+    pub id: Id,
+}
+impl Nodal for BareNode {
+    fn bare(id: Id) -> Self {
+        Self { id }
+    }
+    fn node_id(&self) -> Id {
+        self.id
+    }
+    fn change_id(&mut self, new_id: Id) {
+        self.id = new_id;
+    }
+}
+
+/// Edge with nothing but its terminals, e.g. for `DiGraph::load_structure_only()`
+#[derive(PartialEq, Eq, Clone, Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BareEdge {
+    pub start: Id,
+    pub end: Id,
+}
+impl DirEdge for BareEdge {
+    fn bare(start: Id, end: Id) -> Self {
+        Self { start, end }
+    }
+    fn terminal_ids(&self) -> (Id, Id) {
+        (self.start, self.end)
+    }
+    fn change_start(&mut self, new_start: Id) {
+        self.start = new_start;
+    }
+    fn change_end(&mut self, new_end: Id) {
+        self.end = new_end;
+    }
+}
+
+/// Why a node keeps a graph from being a tree; see `DiGraph::tree_violations()`
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum TreeViolation {
+    /// The root has incoming edges
+    RootHasParents,
+    /// A node other than the root has no incoming edge
+    NoParent,
+    /// A node has this many incoming edges (more than one)
+    SeveralParents(usize),
+    /// A node with one parent can't be reached from the root(s): it lies on or below a cycle,
+    /// or below a node that has `NoParent`
+    Unreachable,
+}
+
+/// Optional identity for edges beyond their terminals.
+///
+/// Keys must be unique within a graph; they let parallel edges
+/// (same start and end) be told apart.
+pub trait EdgeKeyed: DirEdge {
+    fn edge_key(&self) -> u32;
+}
+
+/// Optional named connection points on edge ends, e.g. the "true" and "false"
+/// outputs of a branch node.
+///
+/// An end with no port attaches to the node itself; each named port
+/// takes at most one edge.
+pub trait Ported: DirEdge {
+    fn start_port(&self) -> Option<&str>;
+    fn end_port(&self) -> Option<&str>;
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum GraphChange<N, E> {
     AddNode(N),
     RemoveNode(N, Vec<E>),
@@ -33,7 +333,25 @@ pub enum GraphChange<N, E> {
     AddEdgeWith(E, Option<Id>, Option<Id>),
     RemoveEdge(E),
     InsertNodeAlongEdge(N, E),
-    Failure(&'static str)
+    /// Terminals of the edge before it was flipped
+    ReverseEdge(Id, Id),
+    ReverseAllEdges,
+    /// Node as it was before being replaced, and the id of the node replacing it
+    ReplaceNode(N, Id),
+    /// Node id, and its explicit successor order before the change (empty if it had none)
+    ReorderSuccessors(Id, Vec<Id>),
+    /// Supernode id, and its group before the change (`None` if it had none);
+    /// see `DiGraph::group_nodes()`
+    SetGroup(Id, Option<NodeGroup<N, E>>),
+    /// Node id, and what was kept for it apart from its payload before the change
+    /// (empty if nothing was)
+    SetNodeExtras(Id, NodeExtras),
+    /// Edge terminals, and the edge's tags before the change (empty if it had none)
+    SetEdgeTags(Id, Id, BTreeSet<String>),
+    /// Several changes undone together, in reverse order
+    Batch(Vec<GraphChange<N, E>>),
+    #[serde(skip)]
+    Failure(#[serde(skip)] &'static str)
 }
 impl<N: Nodal, E: DirEdge> GraphChange<N, E> {
     pub(crate) fn try_get_edge(&self) -> Result<E, &'static str> {
@@ -52,10 +370,38 @@ impl<N: Nodal, E: DirEdge> GraphChange<N, E> {
             Self::AddNode(n) => Ok(n.clone()),
             Self::RemoveNode(n, _) => Ok(n.clone()),
             Self::InsertNodeAlongEdge(n, _, ) => Ok(n.clone()),
+            Self::ReplaceNode(n, _) => Ok(n.clone()),
             Self::Failure(reason) => Err(reason),
             _ => Err("Not a node variant.")
         }
     }
+    /// Same as the `kind` of `record()`, without listing nodes and edges
+    pub(crate) fn kind(&self) -> ChangeKind {
+        match self {
+            Self::AddNode(_) => ChangeKind::AddNode,
+            Self::RemoveNode(_, _) => ChangeKind::RemoveNode,
+            Self::AddEdge(_) | Self::AddEdgeWith(_, _, _) => ChangeKind::AddEdge,
+            Self::RemoveEdge(_) => ChangeKind::RemoveEdge,
+            Self::InsertNodeAlongEdge(_, _) => ChangeKind::InsertNodeAlongEdge,
+            Self::ReplaceNode(_, _) => ChangeKind::ReplaceNode,
+            Self::ReverseEdge(_, _) => ChangeKind::ReverseEdge,
+            Self::ReverseAllEdges => ChangeKind::ReverseAllEdges,
+            Self::ReorderSuccessors(_, _) => ChangeKind::ReorderSuccessors,
+            Self::Batch(changes) => Self::main_part(changes).map_or(ChangeKind::Batch, Self::kind),
+            Self::SetGroup(_, _) | Self::SetNodeExtras(_, _) | Self::SetEdgeTags(_, _, _) | Self::Failure(_) => ChangeKind::Batch,
+        }
+    }
+    /// The one other part of a batch that also drops what was kept for the nodes and edges it
+    /// removed (tags and the like), which describes the batch, e.g. a node removal
+    fn main_part(changes: &[Self]) -> Option<&Self> {
+        let is_extras = |change: &&Self| matches!(change, Self::SetNodeExtras(..) | Self::SetEdgeTags(..));
+        let mut others = changes.iter().filter(|change| !is_extras(change));
+        match (others.next(), others.next()) {
+            (Some(change), None) if changes.len() > 1 => Some(change),
+            _ => None,
+        }
+    }
+    #[allow(dead_code)]
     pub(crate) fn try_get_edge_vec(&self) -> Result<Vec<E>, &'static str> {
         match self {
             Self::RemoveNode(_, ev) => Ok(ev.clone()),
@@ -65,9 +411,173 @@ impl<N: Nodal, E: DirEdge> GraphChange<N, E> {
     }
     pub(crate) fn try_get_edge_with_nodes(&self) -> Result<(E, Option<Id>, Option<Id>), &'static str> {
         match self {
-            Self::AddEdgeWith(e, n_in, n_out) => Ok((e.clone(), n_in.clone(), n_out.clone())),
+            Self::AddEdgeWith(e, n_in, n_out) => Ok((e.clone(), *n_in, *n_out)),
             Self::Failure(reason) => Err(reason),
             _ => Err("This method only works with the AddEdgeWith variant.")
         }
     }
+
+    /// Summarizes this change for display; see `ChangeRecord`
+    pub(crate) fn record(&self, timestamp_ms: Option<u64>) -> ChangeRecord {
+        let mut record = ChangeRecord { kind: ChangeKind::Batch, node_ids: vec![], edges: vec![], timestamp_ms };
+        self.fill_record(&mut record);
+        record
+    }
+
+    fn fill_record(&self, record: &mut ChangeRecord) {
+        let kind = match self {
+            Self::AddNode(n) => {
+                record.node_ids.push(n.node_id());
+                ChangeKind::AddNode
+            },
+            Self::RemoveNode(n, ev) => {
+                record.node_ids.push(n.node_id());
+                record.edges.extend(ev.iter().map(|e| e.terminal_ids()));
+                ChangeKind::RemoveNode
+            },
+            Self::AddEdge(e) => {
+                record.edges.push(e.terminal_ids());
+                ChangeKind::AddEdge
+            },
+            Self::AddEdgeWith(e, n_in, n_out) => {
+                record.node_ids.extend(n_in.iter().chain(n_out.iter()));
+                record.edges.push(e.terminal_ids());
+                ChangeKind::AddEdge
+            },
+            Self::RemoveEdge(e) => {
+                record.edges.push(e.terminal_ids());
+                ChangeKind::RemoveEdge
+            },
+            Self::InsertNodeAlongEdge(n, e) => {
+                record.node_ids.push(n.node_id());
+                record.edges.push(e.terminal_ids());
+                ChangeKind::InsertNodeAlongEdge
+            },
+            Self::ReplaceNode(n, new_id) => {
+                record.node_ids.push(n.node_id());
+                if *new_id != n.node_id() {
+                    record.node_ids.push(*new_id);
+                }
+                ChangeKind::ReplaceNode
+            },
+            Self::ReverseEdge(start_id, end_id) => {
+                record.edges.push((*start_id, *end_id));
+                ChangeKind::ReverseEdge
+            },
+            Self::ReverseAllEdges => ChangeKind::ReverseAllEdges,
+            Self::ReorderSuccessors(node_id, _) => {
+                record.node_ids.push(*node_id);
+                ChangeKind::ReorderSuccessors
+            },
+            // Only made within a batch, whose other parts list the nodes and edges
+            Self::SetGroup(_, _) | Self::SetNodeExtras(_, _) | Self::SetEdgeTags(_, _, _) => ChangeKind::Batch,
+            Self::Batch(changes) => {
+                for change in changes.iter() {
+                    change.fill_record(record);
+                }
+                Self::main_part(changes).map_or(ChangeKind::Batch, Self::kind)
+            },
+            // Failures are never registered
+            Self::Failure(_) => ChangeKind::Batch,
+        };
+        record.kind = kind;
+    }
+}
+
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ChangeKind {
+    AddNode,
+    RemoveNode,
+    /// Includes edges added along with new nodes at their terminals
+    AddEdge,
+    RemoveEdge,
+    InsertNodeAlongEdge,
+    /// Node payload swapped, possibly under a new id
+    ReplaceNode,
+    ReverseEdge,
+    ReverseAllEdges,
+    ReorderSuccessors,
+    /// Several changes made (and undone) as one, e.g. by `prune_isolated_nodes()`
+    Batch,
+}
+
+/// Public description of one undoable change, for tools such as an edit-history panel.
+///
+/// See `DiGraph::last_change()` and `DiGraph::history_iter()`.
+#[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ChangeRecord {
+    pub kind: ChangeKind,
+    /// Nodes added, removed or replaced (including by a batch's parts; for a new id, old then new),
+    /// or whose successors were reordered
+    pub node_ids: Vec<Id>,
+    /// (start, end) of edges added, removed, or split; for `ReverseEdge`, the terminals before flipping
+    pub edges: Vec<(Id, Id)>,
+    /// Milliseconds since the Unix epoch when the change was made, where a clock is available
+    pub timestamp_ms: Option<u64>,
+}
+
+/// What a graph keeps for a node apart from its payload, dropped when the node is removed
+/// (so that a node later given the same id starts afresh) and put back if that is undone
+#[derive(PartialEq, Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct NodeExtras {
+    /// See `DiGraph::tag_node()`
+    pub(crate) tags: BTreeSet<String>,
+    /// See `DiGraph::set_successor_order()`; empty if there is none
+    pub(crate) successor_order: Vec<Id>,
+    /// See `DiGraph::set_entry()`
+    pub(crate) is_entry: bool,
+    pub(crate) is_exit: bool,
+    /// See `DiGraph::set_max_out_degree()`
+    pub(crate) max_out_degree: Option<usize>,
+    pub(crate) max_in_degree: Option<usize>,
+}
+
+impl NodeExtras {
+    pub(crate) fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Nodes hidden behind a supernode by `DiGraph::group_nodes()`, kept so that
+/// `DiGraph::ungroup()` can put them back
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct NodeGroup<N, E> {
+    pub(crate) nodes: Vec<N>,
+    pub(crate) edges: Vec<E>,
+    /// (start, end, number of hidden edges) for each edge to or from the supernode
+    pub(crate) edge_counts: Vec<(Id, Id, usize)>,
+}
+
+impl<N: Nodal, E: DirEdge> NodeGroup<N, E> {
+    /// Grouped nodes, ordered by id
+    pub fn nodes(&self) -> &[N] {
+        &self.nodes
+    }
+    /// Every edge with a grouped node as a terminal, as it was when grouped
+    pub fn edges(&self) -> &[E] {
+        &self.edges
+    }
+    /// Ids of the grouped nodes, ascending
+    pub fn member_ids(&self) -> Vec<Id> {
+        self.nodes.iter().map(|node| node.node_id()).collect()
+    }
+    /// How many hidden edges the supernode's edge from `start_id` to `end_id` stands for
+    /// (0 if the group made no such edge)
+    pub fn hidden_edge_count(&self, start_id: Id, end_id: Id) -> usize {
+        self.edge_counts.iter()
+            .find(|&&(start, end, _)| (start, end) == (start_id, end_id))
+            .map_or(0, |&(_, _, count)| count)
+    }
+}
+
+/// A change reversed by `DiGraph::undo()`
+#[derive(PartialEq, Debug, Clone)]
+pub struct UndoneChange<N, E> {
+    /// The change as it was originally made (not its inverse)
+    pub change: GraphChange<N, E>,
+    pub record: ChangeRecord,
 }
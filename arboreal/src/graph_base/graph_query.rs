@@ -0,0 +1,328 @@
+//! Node-edge-node pattern matching, built in code with `Pattern` or parsed from a small
+//! Cypher-like syntax:
+//!
+//! ```text
+//! (a)-[]->(b)<--(c) WHERE out_degree(b) == 0 AND id(a) != 3
+//! ```
+//!
+//! - `(name)` binds a node; `()` matches any node without binding it. A name used twice
+//!   must bind the same node both times; different names may still bind the same node.
+//! - `-[]->` (or `-->`) is an edge to the right, `<-[]-` (or `<--`) an edge to the left.
+//! - Conditions compare integers, `id(x)`, `in_degree(x)`, `out_degree(x)` or `degree(x)`
+//!   with `==`, `!=`, `<`, `<=`, `>` or `>=`, joined by `AND`.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::graph_base::{
+    graph_algo::{never_cancel, report, ProgressFn},
+    graph_components::{GraphError, Id},
+    graph_view::GraphView,
+};
+
+/// Node id bound to each named node of a pattern
+pub type Bindings = BTreeMap<String, Id>;
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Direction {
+    /// From the earlier node to the later one
+    Out,
+    /// From the later node to the earlier one
+    In,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Operand {
+    Literal(i64),
+    Id(String),
+    InDegree(String),
+    OutDegree(String),
+    /// In-degree plus out-degree
+    Degree(String),
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Condition {
+    pub left: Operand,
+    pub comparison: Comparison,
+    pub right: Operand,
+}
+
+impl Condition {
+    pub fn new(left: Operand, comparison: Comparison, right: Operand) -> Self {
+        Self { left, comparison, right }
+    }
+
+    fn holds<G: GraphView + ?Sized>(&self, graph: &G, bindings: &Bindings) -> bool {
+        let (Some(left), Some(right)) = (self.left.value(graph, bindings), self.right.value(graph, bindings)) else {
+            return false;
+        };
+        match self.comparison {
+            Comparison::Eq => left == right,
+            Comparison::Ne => left != right,
+            Comparison::Lt => left < right,
+            Comparison::Le => left <= right,
+            Comparison::Gt => left > right,
+            Comparison::Ge => left >= right,
+        }
+    }
+}
+
+impl Operand {
+    fn variable(&self) -> Option<&str> {
+        match self {
+            Self::Literal(_) => None,
+            Self::Id(name) | Self::InDegree(name) | Self::OutDegree(name) | Self::Degree(name) => Some(name),
+        }
+    }
+
+    fn value<G: GraphView + ?Sized>(&self, graph: &G, bindings: &Bindings) -> Option<i64> {
+        let id = match self.variable() {
+            Some(name) => *bindings.get(name)?,
+            None => 0,
+        };
+        let in_degree = || graph.predecessors(id).len() as i64;
+        let out_degree = || graph.successors(id).len() as i64;
+        Some(match self {
+            Self::Literal(value) => *value,
+            Self::Id(_) => id.into(),
+            Self::InDegree(_) => in_degree(),
+            Self::OutDegree(_) => out_degree(),
+            Self::Degree(_) => in_degree() + out_degree(),
+        })
+    }
+}
+
+/// A chain of nodes joined by edges, plus conditions on the nodes bound.
+///
+/// `Pattern::node("a").to("b").from("").filter(...)` is the same as `(a)-->(b)<--() WHERE ...`;
+/// an empty name matches any node without binding it.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Pattern {
+    /// Name at each position in the chain ("" for anonymous)
+    names: Vec<String>,
+    /// Edge between positions `i` and `i + 1`
+    directions: Vec<Direction>,
+    conditions: Vec<Condition>,
+}
+
+impl Pattern {
+    pub fn node(name: &str) -> Self {
+        Self { names: vec![name.to_string()], directions: vec![], conditions: vec![] }
+    }
+    /// Adds an edge from the last node to a new node `name`
+    pub fn to(self, name: &str) -> Self {
+        self.then(Direction::Out, name)
+    }
+    /// Adds an edge to the last node from a new node `name`
+    pub fn from(self, name: &str) -> Self {
+        self.then(Direction::In, name)
+    }
+    pub fn then(mut self, direction: Direction, name: &str) -> Self {
+        self.directions.push(direction);
+        self.names.push(name.to_string());
+        self
+    }
+    /// Adds a condition; conditions on names not in the pattern never hold
+    pub fn filter(mut self, condition: Condition) -> Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    /// Parses the syntax described in the module docs.
+    ///
+    /// If the text can't be parsed, or a condition names a node not in the pattern, an error is returned.
+    pub fn parse(text: &str) -> Result<Self, GraphError> {
+        let (chain, conditions) = split_keyword(text, "WHERE");
+        let mut pattern = parse_chain(chain.trim())?;
+        if let Some(conditions) = conditions {
+            let mut rest = conditions;
+            loop {
+                let (condition, next) = split_keyword(rest, "AND");
+                pattern = pattern.filter(parse_condition(condition.trim())?);
+                match next {
+                    Some(next) => rest = next,
+                    None => break,
+                }
+            }
+        }
+        let unknown = pattern.conditions.iter()
+            .flat_map(|condition| [condition.left.variable(), condition.right.variable()])
+            .flatten()
+            .any(|name| !pattern.names.iter().any(|known| known == name));
+        if unknown {
+            return Err("Query condition names a node not in the pattern.".into());
+        }
+        Ok(pattern)
+    }
+
+    /// Slot of each position; positions sharing a name share a slot
+    fn slots(&self) -> Vec<usize> {
+        let mut slots = Vec::with_capacity(self.names.len());
+        for (position, name) in self.names.iter().enumerate() {
+            let earlier = self.names[..position].iter().position(|other| !name.is_empty() && other == name);
+            slots.push(earlier.map(|p| slots[p]).unwrap_or(position));
+        }
+        slots
+    }
+}
+
+/// Returns every distinct set of bindings under which `pattern` occurs in `graph`, ascending.
+///
+/// Parallel edges and anonymous nodes never produce repeated bindings.
+pub fn match_pattern<G: GraphView + ?Sized>(graph: &G, pattern: &Pattern) -> Vec<Bindings> {
+    match_pattern_with_progress(graph, pattern, &never_cancel).unwrap()
+}
+
+/// Same as `match_pattern()`, reporting each graph node tried for the pattern's first node
+pub fn match_pattern_with_progress<G: GraphView + ?Sized>(
+    graph: &G,
+    pattern: &Pattern,
+    progress: ProgressFn,
+) -> Result<Vec<Bindings>, GraphError> {
+    trace_span!("match_pattern", nodes = graph.node_count(), pattern_nodes = pattern.names.len());
+    let slots = pattern.slots();
+    let mut bound = vec![None; pattern.names.len()];
+    let mut found = BTreeSet::new();
+    let node_ids = graph.node_ids();
+    for (index, &id) in node_ids.iter().enumerate() {
+        bound[slots[0]] = Some(id);
+        extend_match(graph, pattern, &slots, 0, &mut bound, &mut found);
+        report(progress, index + 1, node_ids.len())?;
+    }
+    Ok(found.into_iter().collect())
+}
+
+fn extend_match<G: GraphView + ?Sized>(
+    graph: &G,
+    pattern: &Pattern,
+    slots: &[usize],
+    position: usize,
+    bound: &mut Vec<Option<Id>>,
+    found: &mut BTreeSet<Bindings>,
+) {
+    if position + 1 == pattern.names.len() {
+        let bindings: Bindings = pattern.names.iter()
+            .zip(slots)
+            .filter(|(name, _)| !name.is_empty())
+            .map(|(name, &slot)| (name.clone(), bound[slot].unwrap()))
+            .collect();
+        if pattern.conditions.iter().all(|condition| condition.holds(graph, &bindings)) {
+            found.insert(bindings);
+        }
+        return;
+    }
+    let current = bound[slots[position]].unwrap();
+    let mut candidates = match pattern.directions[position] {
+        Direction::Out => graph.successors(current).into_owned(),
+        Direction::In => graph.predecessors(current).into_owned(),
+    };
+    candidates.sort_unstable();
+    candidates.dedup();
+    let next_slot = slots[position + 1];
+    match bound[next_slot] {
+        Some(id) => if candidates.binary_search(&id).is_ok() {
+            extend_match(graph, pattern, slots, position + 1, bound, found);
+        },
+        None => {
+            for id in candidates {
+                bound[next_slot] = Some(id);
+                extend_match(graph, pattern, slots, position + 1, bound, found);
+            }
+            bound[next_slot] = None;
+        },
+    }
+}
+
+/// Parses and runs a query; see the module docs for the syntax
+pub fn query<G: GraphView + ?Sized>(graph: &G, text: &str) -> Result<Vec<Bindings>, GraphError> {
+    Ok(match_pattern(graph, &Pattern::parse(text)?))
+}
+
+/// Splits `text` at the first whole-word, case-insensitive `keyword`
+fn split_keyword<'a>(text: &'a str, keyword: &str) -> (&'a str, Option<&'a str>) {
+    let upper = text.to_ascii_uppercase();
+    let mut search_from = 0;
+    while let Some(found) = upper[search_from..].find(keyword) {
+        let start = search_from + found;
+        let end = start + keyword.len();
+        let is_boundary = |index: Option<char>| index.is_none_or(|c| c.is_whitespace() || c == ')');
+        if is_boundary(text[..start].chars().next_back()) && is_boundary(text[end..].chars().next()) {
+            return (&text[..start], Some(&text[end..]));
+        }
+        search_from = end;
+    }
+    (text, None)
+}
+
+fn parse_chain(text: &str) -> Result<Pattern, GraphError> {
+    let (name, mut rest) = parse_node(text)?;
+    let mut pattern = Pattern::node(name);
+    while !rest.is_empty() {
+        let (direction, after_edge) = parse_edge(rest)?;
+        let (name, after_node) = parse_node(after_edge)?;
+        pattern = pattern.then(direction, name);
+        rest = after_node;
+    }
+    Ok(pattern)
+}
+
+/// Parses `(name)` at the start of `text`, returning the name and the trimmed remainder
+fn parse_node(text: &str) -> Result<(&str, &str), GraphError> {
+    let inner = text.trim_start()
+        .strip_prefix('(')
+        .ok_or(GraphError::Invalid("Query expected '(' to start a node."))?;
+    let close = inner.find(')').ok_or(GraphError::Invalid("Query node is missing ')'."))?;
+    let name = inner[..close].trim();
+    if !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return Err("Query node names may only hold letters, digits and '_'.".into());
+    }
+    Ok((name, inner[close + 1..].trim_start()))
+}
+
+fn parse_edge(text: &str) -> Result<(Direction, &str), GraphError> {
+    for (arrow, direction) in [("-[]->", Direction::Out), ("-->", Direction::Out), ("<-[]-", Direction::In), ("<--", Direction::In)] {
+        if let Some(rest) = text.strip_prefix(arrow) {
+            return Ok((direction, rest));
+        }
+    }
+    Err("Query expected an edge ('-[]->' or '<-[]-') between nodes.".into())
+}
+
+fn parse_condition(text: &str) -> Result<Condition, GraphError> {
+    // Two-character operators first, so "<=" isn't read as "<"
+    for (symbol, comparison) in [("==", Comparison::Eq), ("!=", Comparison::Ne), ("<=", Comparison::Le),
+        (">=", Comparison::Ge), ("<", Comparison::Lt), (">", Comparison::Gt)]
+    {
+        if let Some((left, right)) = text.split_once(symbol) {
+            return Ok(Condition::new(parse_operand(left.trim())?, comparison, parse_operand(right.trim())?));
+        }
+    }
+    Err("Query condition needs a comparison (==, !=, <, <=, > or >=).".into())
+}
+
+fn parse_operand(text: &str) -> Result<Operand, GraphError> {
+    if let Ok(value) = text.parse() {
+        return Ok(Operand::Literal(value));
+    }
+    let (function, argument) = text.strip_suffix(')')
+        .and_then(|call| call.split_once('('))
+        .ok_or(GraphError::Invalid("Query operand must be an integer or a call like out_degree(a)."))?;
+    let name = argument.trim().to_string();
+    match function.trim() {
+        "id" => Ok(Operand::Id(name)),
+        "in_degree" => Ok(Operand::InDegree(name)),
+        "out_degree" => Ok(Operand::OutDegree(name)),
+        "degree" => Ok(Operand::Degree(name)),
+        _ => Err("Query functions are id, in_degree, out_degree and degree.".into()),
+    }
+}
@@ -5,13 +5,11 @@ use crate::graph_base::graph_components::*;
 
 type NodeMap<N> = HashMap<Id, N>;
 
-fn edge_index<E: DirEdge>(edges: &Vec<E>, id_in: Id, id_out: Id) -> Option<usize> {
-    for (index, edge) in edges.iter().enumerate() {
-        if edge.start_id() == id_in && edge.end_id() == id_out {
-            return Some(index);
-        }
-    }
-    None
+// Checks that look for edges take any `edges` holding at least those incident on the terminals
+// involved, so that callers with an incidence index need not pass every edge.
+
+fn find_edge<'a, E: DirEdge + 'a>(edges: impl IntoIterator<Item = &'a E>, id_in: Id, id_out: Id) -> Option<&'a E> {
+    edges.into_iter().find(|edge| edge.start_id() == id_in && edge.end_id() == id_out)
 }
 
 fn node_id_present<N: Nodal>(nodes: &NodeMap<N>, id: Id) -> bool {
@@ -27,7 +25,8 @@ pub fn check_add_node<N: Nodal, E: DirEdge>(nodes: &NodeMap<N>, new_node: N) ->
 
 /// Determines in- and out-degree of `id` in `edges`.
 /// If no edge is found with a terminal at this `id`, `(0, 0)` is returned.
-pub fn check_node_degrees<N: Nodal, E: DirEdge>(edges: &Vec<E>, id: Id) -> (usize, usize) {
+#[allow(dead_code)]
+pub fn check_node_degrees<E: DirEdge>(edges: &[E], id: Id) -> (usize, usize) {
     let mut degs = (0, 0);
     for edge in edges.iter() {
         if edge.start_id() == id {
@@ -40,16 +39,16 @@ pub fn check_node_degrees<N: Nodal, E: DirEdge>(edges: &Vec<E>, id: Id) -> (usiz
     degs
 }
 
-pub fn check_remove_node<N: Nodal, E: DirEdge>(nodes: &NodeMap<N>, edges: &Vec<E>, id: Id) -> GraphChange<N, E> {
+pub fn check_remove_node<'a, N: Nodal, E: DirEdge + 'a>(nodes: &NodeMap<N>, edges: impl IntoIterator<Item = &'a E>, id: Id) -> GraphChange<N, E> {
     if !node_id_present(nodes, id) {
         return GraphChange::Failure("Node with this id not found.");
     }
     let node_to_discard = nodes.get(&id)
         .unwrap()
         .clone();
-    let mut edges_to_drop = Vec::with_capacity(nodes.len());
+    let mut edges_to_drop = Vec::new();
     let keep_rule = |e: &E| !(e.start_id() == id || e.end_id() == id);
-    for edge in edges.iter() {
+    for edge in edges {
         if !keep_rule(edge) {
             edges_to_drop.push(edge.clone());
         }
@@ -57,9 +56,21 @@ pub fn check_remove_node<N: Nodal, E: DirEdge>(nodes: &NodeMap<N>, edges: &Vec<E
     GraphChange::RemoveNode(node_to_discard, edges_to_drop)
 }
 
-pub fn check_add_edge<N: Nodal, E: DirEdge>(nodes: &NodeMap<N>, edges: &Vec<E>, new_edge: E) -> GraphChange<N, E> {
+/// Fails if `old_id` is not found, or `new_node` takes a different id that is already in use
+pub fn check_replace_node<N: Nodal, E: DirEdge>(nodes: &NodeMap<N>, old_id: Id, new_node: &N) -> GraphChange<N, E> {
+    let Some(old_node) = nodes.get(&old_id) else {
+        return GraphChange::Failure("Node with this id not found.");
+    };
+    let new_id = new_node.node_id();
+    if new_id != old_id && node_id_present(nodes, new_id) {
+        return GraphChange::Failure("Node with this id already exists.");
+    }
+    GraphChange::ReplaceNode(old_node.clone(), new_id)
+}
+
+pub fn check_add_edge<'a, N: Nodal, E: DirEdge + 'a>(nodes: &NodeMap<N>, edges: impl IntoIterator<Item = &'a E>, new_edge: E) -> GraphChange<N, E> {
     let (id_in, id_out) = new_edge.terminal_ids();
-    if let Some(_index) = edge_index(edges, id_in, id_out) {
+    if find_edge(edges, id_in, id_out).is_some() {
         return GraphChange::Failure("Edge with these terminals already exists.");
     }
     if node_id_present(nodes, id_in) && node_id_present(nodes, id_out)
@@ -69,38 +80,71 @@ pub fn check_add_edge<N: Nodal, E: DirEdge>(nodes: &NodeMap<N>, edges: &Vec<E>,
     GraphChange::Failure("Terminals not found in graph.")
 }
 
-pub fn check_add_edge_with_nodes<N: Nodal, E: DirEdge>(nodes: &NodeMap<N>, edges: &Vec<E>, id_in: Id, id_out: Id) -> GraphChange<N, E> {
-    if let Some(_index) = edge_index(edges, id_in, id_out) {
+/// Same as `check_add_edge()`, but also fails if either of the edge's ports is already connected
+pub fn check_add_ported_edge<'a, N, E, I>(nodes: &NodeMap<N>, edges: I, new_edge: E) -> GraphChange<N, E>
+where
+    N: Nodal,
+    E: Ported + 'a,
+    I: IntoIterator<Item = &'a E> + Clone,
+{
+    let (id_in, id_out) = new_edge.terminal_ids();
+    for edge in edges.clone() {
+        let start_taken = new_edge.start_port().is_some()
+            && edge.start_id() == id_in
+            && edge.start_port() == new_edge.start_port();
+        let end_taken = new_edge.end_port().is_some()
+            && edge.end_id() == id_out
+            && edge.end_port() == new_edge.end_port();
+        if start_taken || end_taken {
+            return GraphChange::Failure("Port already connected.");
+        }
+    }
+    check_add_edge(nodes, edges, new_edge)
+}
+
+pub fn check_add_edge_with_nodes<'a, N: Nodal, E: DirEdge + 'a>(nodes: &NodeMap<N>, edges: impl IntoIterator<Item = &'a E>, id_in: Id, id_out: Id) -> GraphChange<N, E> {
+    if find_edge(edges, id_in, id_out).is_some() {
         return GraphChange::Failure("Edge with these terminals already exists.");
     }
     let new_in = match node_id_present(nodes, id_in) {
         true => None,
         false => Some(id_in)
     };
-    let new_out = match node_id_present(nodes, id_out) {
-        true => None, 
+    // A self-loop on a new node creates it once
+    let new_out = match node_id_present(nodes, id_out) || id_out == id_in {
+        true => None,
         false => Some(id_out)
     };
     let proposed_edge = E::bare(id_in, id_out);
     GraphChange::AddEdgeWith(proposed_edge, new_in, new_out)
 }
 
-pub fn check_remove_edge<N: Nodal, E: DirEdge>(edges: &Vec<E>, id_in: Id, id_out: Id) -> GraphChange<N, E> {
-    if let Some(index) = edge_index(edges, id_in, id_out) {
-        let edge_to_drop = edges[index].clone();
+pub fn check_remove_edge<'a, N: Nodal, E: DirEdge + 'a>(edges: impl IntoIterator<Item = &'a E>, id_in: Id, id_out: Id) -> GraphChange<N, E> {
+    if let Some(edge) = find_edge(edges, id_in, id_out) {
+        let edge_to_drop = edge.clone();
         return GraphChange::RemoveEdge(edge_to_drop);
     }
     GraphChange::Failure("Edge not found in graph.")
 }
 
-pub fn collect_reachable_neighbors(census: &mut Vec<Id>, starting_point: Id, after_neighbor_map: &HashMap<Id, Vec<Id>>) {
-        if census.contains(&starting_point) {
-            return
-        }
-        census.push(starting_point);
-        if let Some(cul_da_sac) = after_neighbor_map.get(&starting_point) {
-            for neighbor in cul_da_sac.iter() {
-                collect_reachable_neighbors(census, *neighbor, after_neighbor_map);
+/// Adds `starting_point` and every id reachable from it through `after_neighbor_map` to `census`,
+/// returning how many ids were added
+pub fn collect_reachable_neighbors(census: &mut IdSet, starting_point: Id, after_neighbor_map: &HashMap<Id, NeighborIds>) -> usize {
+    if !census.insert(starting_point) {
+        return 0;
+    }
+    let mut added = 1;
+    let mut stack = vec![starting_point];
+    while let Some(id) = stack.pop() {
+        let Some(cul_da_sac) = after_neighbor_map.get(&id) else {
+            continue;
+        };
+        for &neighbor in cul_da_sac.iter() {
+            if census.insert(neighbor) {
+                added += 1;
+                stack.push(neighbor);
             }
         }
     }
+    added
+}
@@ -0,0 +1,142 @@
+use std::borrow::Cow;
+
+use crate::graph_base::graph_components::{Id, IdSet};
+
+/// Read-only access to a directed graph's structure.
+///
+/// Every algorithm in `graph_algo` is written against this trait, so they run on
+/// `DiGraph`, `FrozenDiGraph`, `Reversed`, filtered views, or any user type implementing it.
+///
+/// Neighbor lists hold one entry per edge (parallel edges repeat an id), in ascending order
+/// unless the graph defines its own (see `DiGraph::set_successor_order()`).
+pub trait GraphView {
+    type Node;
+
+    /// All node ids, ascending
+    fn node_ids(&self) -> Vec<Id>;
+    fn contains_node(&self, id: Id) -> bool;
+    fn node(&self, id: Id) -> Option<&Self::Node>;
+    /// Ends of edges starting at `id` (empty if `id` is not a node)
+    fn successors(&self, id: Id) -> Cow<'_, [Id]>;
+    /// Starts of edges ending at `id` (empty if `id` is not a node)
+    fn predecessors(&self, id: Id) -> Cow<'_, [Id]>;
+
+    fn node_count(&self) -> usize {
+        self.node_ids().len()
+    }
+
+    /// All (start, end) pairs, ascending
+    fn edge_pairs(&self) -> Vec<(Id, Id)> {
+        let mut edge_pairs = Vec::new();
+        for id in self.node_ids() {
+            for &next in self.successors(id).iter() {
+                edge_pairs.push((id, next));
+            }
+        }
+        edge_pairs
+    }
+}
+
+/// Dyn-compatible, payload-free view of a graph's structure, e.g. to keep graphs with
+/// different node and edge types in one `Vec<Box<dyn AnyGraph>>` for tooling to inspect.
+///
+/// Methods are named after `DiGraph`'s own, so they don't clash with `GraphView`'s.
+pub trait AnyGraph {
+    /// All node ids, ascending
+    fn all_node_ids(&self) -> Vec<Id>;
+    /// All (start, end) pairs, ascending
+    fn all_edge_pairs(&self) -> Vec<(Id, Id)>;
+    /// Ends of edges starting at `node_id`, ascending, or `None` if it is not a node
+    fn successors_sorted(&self, node_id: Id) -> Option<Vec<Id>>;
+    fn has_node(&self, node_id: Id) -> bool;
+}
+
+impl<G: GraphView + ?Sized> GraphView for &G {
+    type Node = G::Node;
+
+    fn node_ids(&self) -> Vec<Id> {
+        (**self).node_ids()
+    }
+    fn contains_node(&self, id: Id) -> bool {
+        (**self).contains_node(id)
+    }
+    fn node(&self, id: Id) -> Option<&Self::Node> {
+        (**self).node(id)
+    }
+    fn successors(&self, id: Id) -> Cow<'_, [Id]> {
+        (**self).successors(id)
+    }
+    fn predecessors(&self, id: Id) -> Cow<'_, [Id]> {
+        (**self).predecessors(id)
+    }
+    fn node_count(&self) -> usize {
+        (**self).node_count()
+    }
+    fn edge_pairs(&self) -> Vec<(Id, Id)> {
+        (**self).edge_pairs()
+    }
+}
+
+/// Borrowed view of a graph with every edge pointing the other way
+#[derive(Debug, Clone, Copy)]
+pub struct Reversed<'a, G: ?Sized>(pub &'a G);
+
+impl<G: GraphView + ?Sized> GraphView for Reversed<'_, G> {
+    type Node = G::Node;
+
+    fn node_ids(&self) -> Vec<Id> {
+        self.0.node_ids()
+    }
+    fn contains_node(&self, id: Id) -> bool {
+        self.0.contains_node(id)
+    }
+    fn node(&self, id: Id) -> Option<&Self::Node> {
+        self.0.node(id)
+    }
+    fn successors(&self, id: Id) -> Cow<'_, [Id]> {
+        self.0.predecessors(id)
+    }
+    fn predecessors(&self, id: Id) -> Cow<'_, [Id]> {
+        self.0.successors(id)
+    }
+    fn node_count(&self) -> usize {
+        self.0.node_count()
+    }
+}
+
+/// Borrowed view of a graph without the nodes in `nodes` (and their edges) or the edges
+/// listed as (start, end) in `edges`, e.g. states that are blocked for now.
+///
+/// Edges are looked up by scanning the list, so keep it short.
+#[derive(Debug, Clone, Copy)]
+pub struct Avoiding<'a, G: ?Sized> {
+    pub graph: &'a G,
+    pub nodes: &'a IdSet,
+    pub edges: &'a [(Id, Id)],
+}
+
+impl<G: GraphView + ?Sized> Avoiding<'_, G> {
+    fn keeps_edge(&self, start: Id, end: Id) -> bool {
+        !self.nodes.contains(start) && !self.nodes.contains(end) && !self.edges.contains(&(start, end))
+    }
+}
+
+impl<G: GraphView + ?Sized> GraphView for Avoiding<'_, G> {
+    type Node = G::Node;
+
+    fn node_ids(&self) -> Vec<Id> {
+        self.graph.node_ids().into_iter().filter(|&id| !self.nodes.contains(id)).collect()
+    }
+    fn contains_node(&self, id: Id) -> bool {
+        !self.nodes.contains(id) && self.graph.contains_node(id)
+    }
+    fn node(&self, id: Id) -> Option<&Self::Node> {
+        self.graph.node(id).filter(|_| !self.nodes.contains(id))
+    }
+    fn successors(&self, id: Id) -> Cow<'_, [Id]> {
+        self.graph.successors(id).iter().copied().filter(|&next| self.keeps_edge(id, next)).collect()
+    }
+    fn predecessors(&self, id: Id) -> Cow<'_, [Id]> {
+        self.graph.predecessors(id).iter().copied().filter(|&previous| self.keeps_edge(previous, id)).collect()
+    }
+}
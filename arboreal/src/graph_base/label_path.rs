@@ -0,0 +1,332 @@
+//! Regular-expression-like patterns over the labels along a path, parsed from text such as
+//!
+//! ```text
+//! open (read | write)* close
+//! ```
+//!
+//! - A label is a run of letters, digits and '_', or any text in double quotes (`"a b"`);
+//!   `.` matches any one label.
+//! - Labels written one after another match in sequence; `|` separates alternatives.
+//! - `*` repeats what it follows zero or more times, `+` one or more times, `?` at most once.
+//! - Parentheses group.
+//!
+//! Edges get their labels from `LabeledEdge`; see `DiGraph::match_label_path()`.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use crate::graph_base::{graph_algo::{never_cancel, report, ProgressFn}, graph_components::{GraphError, Id, IdSet}};
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum LabelPattern {
+    /// One edge with exactly this label
+    Label(String),
+    /// One edge with any label
+    Any,
+    /// Each part in turn (nothing at all when empty)
+    Concat(Vec<LabelPattern>),
+    /// Any one of the parts
+    Alt(Vec<LabelPattern>),
+    /// The inner pattern zero or more times
+    Star(Box<LabelPattern>),
+}
+
+impl LabelPattern {
+    pub fn label(label: &str) -> Self {
+        Self::Label(label.to_string())
+    }
+    /// This pattern, then `next`
+    pub fn then(self, next: Self) -> Self {
+        match self {
+            Self::Concat(mut parts) => {
+                parts.push(next);
+                Self::Concat(parts)
+            },
+            first => Self::Concat(vec![first, next]),
+        }
+    }
+    /// This pattern or `other`
+    pub fn or(self, other: Self) -> Self {
+        match self {
+            Self::Alt(mut parts) => {
+                parts.push(other);
+                Self::Alt(parts)
+            },
+            first => Self::Alt(vec![first, other]),
+        }
+    }
+    /// Zero or more times
+    pub fn star(self) -> Self {
+        Self::Star(Box::new(self))
+    }
+    /// One or more times
+    pub fn plus(self) -> Self {
+        self.clone().then(self.star())
+    }
+    /// At most once
+    pub fn optional(self) -> Self {
+        self.or(Self::Concat(vec![]))
+    }
+
+    /// Parses the syntax described in the module docs.
+    ///
+    /// If the text can't be parsed, an error is returned.
+    pub fn parse(text: &str) -> Result<Self, GraphError> {
+        let tokens = tokenize(text)?;
+        let mut position = 0;
+        let pattern = parse_alt(&tokens, &mut position)?;
+        if position < tokens.len() {
+            return Err("Label pattern has an unmatched ')'.".into());
+        }
+        Ok(pattern)
+    }
+}
+
+#[derive(PartialEq, Debug)]
+enum Token {
+    Label(String),
+    Any,
+    Bar,
+    Star,
+    Plus,
+    Question,
+    Open,
+    Close,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, GraphError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '.' => Token::Any,
+            '|' => Token::Bar,
+            '*' => Token::Star,
+            '+' => Token::Plus,
+            '?' => Token::Question,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '"' => {
+                let label: String = chars.by_ref().take_while(|&c| c != '"').collect();
+                Token::Label(label)
+            },
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut label = c.to_string();
+                while let Some(&next) = chars.peek() && (next.is_alphanumeric() || next == '_') {
+                    label.push(next);
+                    chars.next();
+                }
+                Token::Label(label)
+            },
+            _ => return Err("Label pattern may only hold labels, '.', '|', '*', '+', '?' and parentheses.".into()),
+        };
+        tokens.push(token);
+    }
+    // take_while() drops the closing quote, so an unclosed one only shows in the count
+    if text.chars().filter(|&c| c == '"').count() % 2 == 1 {
+        return Err("Label pattern has an unclosed '\"'.".into());
+    }
+    Ok(tokens)
+}
+
+fn parse_alt(tokens: &[Token], position: &mut usize) -> Result<LabelPattern, GraphError> {
+    let mut alternatives = vec![parse_concat(tokens, position)?];
+    while tokens.get(*position) == Some(&Token::Bar) {
+        *position += 1;
+        alternatives.push(parse_concat(tokens, position)?);
+    }
+    Ok(match alternatives.len() {
+        1 => alternatives.pop().unwrap(),
+        _ => LabelPattern::Alt(alternatives),
+    })
+}
+
+fn parse_concat(tokens: &[Token], position: &mut usize) -> Result<LabelPattern, GraphError> {
+    let mut parts = Vec::new();
+    while let Some(token) = tokens.get(*position) {
+        let mut part = match token {
+            Token::Label(label) => LabelPattern::Label(label.clone()),
+            Token::Any => LabelPattern::Any,
+            Token::Open => {
+                *position += 1;
+                let inner = parse_alt(tokens, position)?;
+                if tokens.get(*position) != Some(&Token::Close) {
+                    return Err("Label pattern is missing ')'.".into());
+                }
+                inner
+            },
+            Token::Bar | Token::Close => break,
+            Token::Star | Token::Plus | Token::Question => {
+                return Err("Label pattern has '*', '+' or '?' with nothing before it.".into());
+            },
+        };
+        *position += 1;
+        loop {
+            part = match tokens.get(*position) {
+                Some(Token::Star) => part.star(),
+                Some(Token::Plus) => part.plus(),
+                Some(Token::Question) => part.optional(),
+                _ => break,
+            };
+            *position += 1;
+        }
+        parts.push(part);
+    }
+    Ok(match parts.len() {
+        1 => parts.pop().unwrap(),
+        _ => LabelPattern::Concat(parts),
+    })
+}
+
+enum Step {
+    Free,
+    Label(String),
+    Any,
+}
+
+/// Thompson automaton for a pattern: state 0 is the start, and `accept` the only final state
+struct Automaton {
+    transitions: Vec<Vec<(Step, usize)>>,
+    accept: usize,
+}
+
+impl Automaton {
+    fn new(pattern: &LabelPattern) -> Self {
+        let mut automaton = Self { transitions: vec![Vec::new()], accept: 0 };
+        automaton.accept = automaton.build(pattern, 0);
+        automaton
+    }
+
+    fn add_state(&mut self) -> usize {
+        self.transitions.push(Vec::new());
+        self.transitions.len() - 1
+    }
+
+    /// Adds states matching `pattern` from `from`, returning the state they end in
+    fn build(&mut self, pattern: &LabelPattern, from: usize) -> usize {
+        match pattern {
+            LabelPattern::Label(label) => {
+                let to = self.add_state();
+                self.transitions[from].push((Step::Label(label.clone()), to));
+                to
+            },
+            LabelPattern::Any => {
+                let to = self.add_state();
+                self.transitions[from].push((Step::Any, to));
+                to
+            },
+            LabelPattern::Concat(parts) => parts.iter().fold(from, |state, part| self.build(part, state)),
+            LabelPattern::Alt(parts) => {
+                let to = self.add_state();
+                for part in parts {
+                    let entry = self.add_state();
+                    self.transitions[from].push((Step::Free, entry));
+                    let exit = self.build(part, entry);
+                    self.transitions[exit].push((Step::Free, to));
+                }
+                to
+            },
+            LabelPattern::Star(inner) => {
+                let hub = self.add_state();
+                self.transitions[from].push((Step::Free, hub));
+                let exit = self.build(inner, hub);
+                self.transitions[exit].push((Step::Free, hub));
+                hub
+            },
+        }
+    }
+
+    /// `state` and every state reachable from it without reading a label
+    fn closure(&self, state: usize) -> Vec<usize> {
+        let mut seen = vec![false; self.transitions.len()];
+        let mut stack = vec![state];
+        let mut closure = Vec::new();
+        seen[state] = true;
+        while let Some(current) = stack.pop() {
+            closure.push(current);
+            for (step, next) in self.transitions[current].iter() {
+                if matches!(step, Step::Free) && !seen[*next] {
+                    seen[*next] = true;
+                    stack.push(*next);
+                }
+            }
+        }
+        closure
+    }
+}
+
+/// For each node where a path from `start` matching `pattern` ends, one such path with the
+/// fewest edges (ids from `start` on), ordered by end id. `edges_from(id)` lists the (end id,
+/// label) of each edge leaving `id`.
+///
+/// Searches the product of the graph and the pattern's automaton breadth-first, so it takes
+/// time proportional to edges times pattern size, however many paths match.
+pub fn match_label_path<F, I, L>(start: Id, pattern: &LabelPattern, edges_from: F) -> Vec<Vec<Id>>
+where
+    F: Fn(Id) -> I,
+    I: IntoIterator<Item = (Id, L)>,
+    L: AsRef<str>,
+{
+    match_label_path_with_progress(start, pattern, edges_from, 0, &never_cancel).unwrap()
+}
+
+/// Same as `match_label_path()`, reporting each (node, pattern state) pair searched, of at most
+/// `node_count` times the number of pattern states
+pub fn match_label_path_with_progress<F, I, L>(
+    start: Id,
+    pattern: &LabelPattern,
+    edges_from: F,
+    node_count: usize,
+    progress: ProgressFn,
+) -> Result<Vec<Vec<Id>>, GraphError>
+where
+    F: Fn(Id) -> I,
+    I: IntoIterator<Item = (Id, L)>,
+    L: AsRef<str>,
+{
+    trace_span!("match_label_path", start);
+    let automaton = Automaton::new(pattern);
+    let total = node_count.saturating_mul(automaton.transitions.len());
+    let mut done = 0;
+    let closures: Vec<Vec<usize>> = (0..automaton.transitions.len()).map(|state| automaton.closure(state)).collect();
+    let mut parents: HashMap<(Id, usize), (Id, usize)> = HashMap::new();
+    let mut ends: BTreeMap<Id, (Id, usize)> = BTreeMap::new();
+    let mut matched = IdSet::new();
+    let mut queue = VecDeque::from([(start, 0)]);
+    parents.insert((start, 0), (start, 0));
+    while let Some((node, state)) = queue.pop_front() {
+        done += 1;
+        report(progress, done, total.max(done))?;
+        if closures[state].contains(&automaton.accept) && matched.insert(node) {
+            ends.insert(node, (node, state));
+        }
+        let edges: Vec<(Id, L)> = edges_from(node).into_iter().collect();
+        for &reachable in closures[state].iter() {
+            for (step, next_state) in automaton.transitions[reachable].iter() {
+                for (next, label) in edges.iter() {
+                    let fits = match step {
+                        Step::Free => false,
+                        Step::Label(wanted) => wanted == label.as_ref(),
+                        Step::Any => true,
+                    };
+                    let key = (*next, *next_state);
+                    if fits && !parents.contains_key(&key) {
+                        parents.insert(key, (node, state));
+                        queue.push_back(key);
+                    }
+                }
+            }
+        }
+    }
+    Ok(ends.into_values()
+        .map(|mut key| {
+            let mut path = vec![key.0];
+            while key != (start, 0) {
+                key = parents[&key];
+                path.push(key.0);
+            }
+            path.reverse();
+            path
+        })
+        .collect())
+}
@@ -0,0 +1,144 @@
+//! Importers for narrative tools, producing a `DiGraph<StoryNode, StoryEdge>`.
+//!
+//! - `from_twee()`: Twee 3 source, as exported by Twine or written by hand
+//! - `from_twine_json()`: Twine stories exported as JSON (Twison layout), with feature "json"
+//! - `from_ink_json()`: compiled Ink runtime JSON, with feature "json"
+//! - `StoryGraph::from_dsl()`: a plain-text sketch of passages and links (`a -> b : "label"`),
+//!   written back by `StoryGraph::to_dsl()`
+//!
+//! Every passage or knot becomes a node, numbered from 0 in the order found.
+//! Links to passages that do not exist are dropped, and several links between
+//! the same two passages become a single edge labeled by the first of them; the
+//! `*_reporting()` variants of the importers also return each `DroppedLink`, and the
+//! others log them as warnings with feature `trace`.
+
+mod dsl;
+mod twine;
+#[cfg(feature = "json")]
+mod ink;
+
+pub use twine::{from_twee, from_twee_reporting};
+#[cfg(feature = "json")]
+pub use twine::{from_twine_json, from_twine_json_reporting};
+#[cfg(feature = "json")]
+pub use ink::{from_ink_json, from_ink_json_reporting, INK_ROOT_NAME};
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::digraph::{ChangeCache, DiGraph};
+use crate::graph_base::graph_components::{DirEdge, GraphError, Id, Nodal, Titled};
+
+#[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct StoryNode {
+    pub id: Id,
+    /// Passage or knot name, unique within the story
+    pub name: String,
+    pub text: String,
+    pub tags: Vec<String>,
+}
+impl Nodal for StoryNode {
+    fn bare(id: Id) -> Self {
+        Self { id, ..Default::default() }
+    }
+    fn node_id(&self) -> Id {
+        self.id
+    }
+    fn change_id(&mut self, new_id: Id) {
+        self.id = new_id;
+    }
+}
+impl Titled for StoryNode {
+    fn title(&self) -> &str {
+        &self.name
+    }
+    fn set_title(&mut self, title: String) {
+        self.name = title;
+    }
+}
+
+#[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct StoryEdge {
+    pub start: Id,
+    pub end: Id,
+    /// Link text shown to the reader, where it differs from the target's name
+    pub label: Option<String>,
+}
+impl DirEdge for StoryEdge {
+    fn bare(start: Id, end: Id) -> Self {
+        Self { start, end, label: None }
+    }
+    fn terminal_ids(&self) -> (Id, Id) {
+        (self.start, self.end)
+    }
+    fn change_start(&mut self, new_start: Id) {
+        self.start = new_start;
+    }
+    fn change_end(&mut self, new_end: Id) {
+        self.end = new_end;
+    }
+}
+
+pub type StoryGraph = DiGraph<StoryNode, StoryEdge>;
+
+/// A link an importer found but left out of the graph: its target is no passage's name,
+/// or the passages it joins were already linked by an earlier link
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct DroppedLink {
+    /// Id of the passage the link is in
+    pub from: Id,
+    /// Name of the passage it leads to
+    pub target: String,
+    pub label: Option<String>,
+}
+
+/// The graph from a `*_reporting()` importer, logging each link it dropped as a warning
+/// with feature `trace`
+fn log_dropped((graph, _dropped): (StoryGraph, Vec<DroppedLink>)) -> StoryGraph {
+    #[cfg(feature = "trace")]
+    for link in _dropped.iter() {
+        tracing::warn!(from = link.from, target = %link.target, "link dropped on import");
+    }
+    graph
+}
+
+/// Collects passages first, then links (which may point forward), by name
+#[derive(Default)]
+struct StoryBuilder {
+    graph: StoryGraph,
+    ids_by_name: HashMap<String, Id>,
+    links: Vec<(Id, String, Option<String>)>,
+}
+
+impl StoryBuilder {
+    fn add_passage(&mut self, name: &str, text: String, tags: Vec<String>) -> Result<Id, GraphError> {
+        if self.ids_by_name.contains_key(name) {
+            return Err("Duplicate passage name.".into());
+        }
+        let id = Id::try_from(self.ids_by_name.len()).map_err(|_| GraphError::IdSpaceExhausted)?;
+        self.graph.insert_node(StoryNode { id, name: name.to_string(), text, tags })?;
+        self.ids_by_name.insert(name.to_string(), id);
+        Ok(id)
+    }
+
+    fn add_link(&mut self, from: Id, target: &str, label: Option<String>) {
+        self.links.push((from, target.to_string(), label));
+    }
+
+    /// The graph, and the links left out of it
+    fn finish(mut self, story_name: Option<String>) -> (StoryGraph, Vec<DroppedLink>) {
+        let mut dropped = Vec::new();
+        for (start, target, label) in std::mem::take(&mut self.links) {
+            // Refused if these passages are already linked
+            let inserted = self.ids_by_name.get(&target)
+                .is_some_and(|&end| self.graph.insert_edge(StoryEdge { start, end, label: label.clone() }).is_ok());
+            if !inserted {
+                dropped.push(DroppedLink { from: start, target, label });
+            }
+        }
+        self.graph.name = story_name;
+        self.graph.clear_history();
+        (self.graph, dropped)
+    }
+}
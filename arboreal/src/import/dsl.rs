@@ -0,0 +1,151 @@
+use crate::graph_base::graph_components::{GraphError, Id};
+use super::{StoryBuilder, StoryEdge, StoryGraph};
+
+impl StoryGraph {
+
+    /// Builds a story graph from a sketch in the text format below, e.g. written in a text
+    /// editor before any passage text exists.
+    ///
+    /// ```text
+    /// # Comment lines and blank lines are skipped
+    /// intro -> choice_a : "Ask about the key"
+    /// intro -> "Dark room"
+    /// epilogue
+    /// ```
+    ///
+    /// Each line is a link, with an optional label after `:`, or a lone passage name. Names
+    /// with spaces or any of `"`, `:`, `#` or `->` are written in double quotes, as are labels
+    /// (which may also be left bare); `\"`, `\\`, `\n` and `\r` escape within quotes.
+    /// Passages have no text or tags.
+    ///
+    /// Only on `StoryGraph`, rather than any `DiGraph`: links name their passages, so names
+    /// must be unique, and labels are optional, which `LabeledEdge` can't express.
+    ///
+    /// If a line is malformed, or links two passages already linked, an error is returned.
+    pub fn from_dsl(source: &str) -> Result<Self, GraphError> {
+        let mut builder = StoryBuilder::default();
+        for line in source.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (start, rest) = parse_name(line)?;
+            let start = passage_id(&mut builder, &start)?;
+            let rest = rest.trim_start();
+            if rest.is_empty() {
+                continue;
+            }
+            let rest = rest.strip_prefix("->")
+                .ok_or(GraphError::Invalid("Expected \"->\" after a passage name."))?;
+            let (end, rest) = parse_name(rest.trim_start())?;
+            let end = passage_id(&mut builder, &end)?;
+            let label = match rest.trim_start() {
+                "" => None,
+                rest => Some(parse_label(rest)?),
+            };
+            // Unlike in Twine and ink, where a passage may well link to another twice
+            if builder.graph.get_edge(start, end).is_some() {
+                return Err("Link between these passages given twice.".into());
+            }
+            builder.graph.insert_edge(StoryEdge { start, end, label })?;
+        }
+        // Links went straight into the graph, so none were dropped
+        Ok(builder.finish(None).0)
+    }
+
+    /// The graph's passage names, links and link labels in the format read by `from_dsl()`:
+    /// each passage's links in the order of their ends' ids, passages by id, with a line
+    /// of its own for any passage no link mentions.
+    ///
+    /// Passage text, tags and the story's name are left out.
+    pub fn to_dsl(&self) -> String {
+        let mut dsl = String::new();
+        for node_id in self.all_node_ids() {
+            let name = quote_if_needed(&self.get_node(node_id).unwrap().name);
+            let successors = self.successors_sorted(node_id).unwrap_or_default();
+            if successors.is_empty() && self.in_degree(node_id) == Some(0) {
+                dsl.push_str(&name);
+                dsl.push('\n');
+            }
+            for end_id in successors {
+                let end = quote_if_needed(&self.get_node(end_id).unwrap().name);
+                dsl.push_str(&format!("{name} -> {end}"));
+                if let Some(label) = &self.get_edge(node_id, end_id).unwrap().label {
+                    dsl.push_str(&format!(" : {}", quote(label)));
+                }
+                dsl.push('\n');
+            }
+        }
+        dsl
+    }
+}
+
+/// Id of the passage named `name`, added (empty) on first mention
+fn passage_id(builder: &mut StoryBuilder, name: &str) -> Result<Id, GraphError> {
+    match builder.ids_by_name.get(name) {
+        Some(&id) => Ok(id),
+        None => builder.add_passage(name, String::new(), Vec::new()),
+    }
+}
+
+/// Splits a quoted or bare name off the front of `text`
+fn parse_name(text: &str) -> Result<(String, &str), GraphError> {
+    if text.starts_with('"') {
+        return parse_quoted(text);
+    }
+    let end = text.char_indices()
+        .find(|&(i, c)| c.is_whitespace() || c == '"' || c == ':' || text[i..].starts_with("->"))
+        .map_or(text.len(), |(i, _)| i);
+    if end == 0 {
+        return Err("Expected a passage name.".into());
+    }
+    Ok((text[..end].to_string(), &text[end..]))
+}
+
+/// Reads `: label` or `: "label"`, which must end the line
+fn parse_label(text: &str) -> Result<String, GraphError> {
+    let label = text.strip_prefix(':')
+        .ok_or(GraphError::Invalid("Expected \":\" before a link label."))?
+        .trim();
+    if !label.starts_with('"') {
+        return Ok(label.to_string());
+    }
+    let (label, rest) = parse_quoted(label)?;
+    if !rest.trim().is_empty() {
+        return Err("Unexpected text after a link label.".into());
+    }
+    Ok(label)
+}
+
+/// Splits a double-quoted string off the front of `text`, unescaping `\"`, `\\`, `\n` and `\r`
+fn parse_quoted(text: &str) -> Result<(String, &str), GraphError> {
+    let mut unquoted = String::new();
+    let mut chars = text.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((unquoted, &text[i + 1..])),
+            '\\' => if let Some((_, escaped)) = chars.next() {
+                unquoted.push(match escaped {
+                    'n' => '\n',
+                    'r' => '\r',
+                    escaped => escaped,
+                });
+            },
+            c => unquoted.push(c),
+        }
+    }
+    Err("Quoted text has no closing quote.".into())
+}
+
+fn quote(text: &str) -> String {
+    let escaped = text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n").replace('\r', "\\r");
+    format!("\"{escaped}\"")
+}
+
+fn quote_if_needed(name: &str) -> String {
+    let bare = !name.is_empty()
+        && !name.starts_with('#')
+        && !name.contains(|c: char| c.is_whitespace() || c == '"' || c == ':' || c == '\\')
+        && !name.contains("->");
+    if bare { name.to_string() } else { quote(name) }
+}
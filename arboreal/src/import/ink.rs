@@ -0,0 +1,81 @@
+use serde_json::{Map, Value};
+
+use crate::graph_base::graph_components::GraphError;
+use super::{DroppedLink, StoryBuilder, StoryGraph};
+
+/// Name given to the content before the first knot
+pub const INK_ROOT_NAME: &str = "ROOT";
+
+/// Holds variable declarations, not story content
+const GLOBAL_DECL: &str = "global decl";
+
+/// Builds a story graph from compiled Ink (the runtime JSON written by inklecate or Inky).
+///
+/// The top-level content becomes node 0, named `INK_ROOT_NAME`, followed by one node per knot
+/// (stitches stay part of their knot). A node's text is all the text in its content; diverts
+/// and tunnels into another knot become edges. Several diverts from one knot into another
+/// make a single edge; the rest are dropped, and `from_ink_json_reporting()` also returns them.
+///
+/// If the JSON is not compiled Ink, an error is returned.
+pub fn from_ink_json(source: &str) -> Result<StoryGraph, GraphError> {
+    from_ink_json_reporting(source).map(super::log_dropped)
+}
+
+/// Same as `from_ink_json()`, also returning the diverts left out of the graph, in the order found
+pub fn from_ink_json_reporting(source: &str) -> Result<(StoryGraph, Vec<DroppedLink>), GraphError> {
+    let story: Value = serde_json::from_str(source)
+        .map_err(|_| GraphError::Invalid("Ink JSON could not be parsed."))?;
+    if story.get("inkVersion").is_none() {
+        return Err("Ink JSON has no inkVersion.".into());
+    }
+    let root = story["root"]
+        .as_array()
+        .ok_or(GraphError::Invalid("Ink JSON has no root container."))?;
+    let knots: Vec<(&String, &Value)> = match root.last().and_then(Value::as_object) {
+        Some(named) => named.iter().filter(|(name, _)| *name != GLOBAL_DECL).collect(),
+        None => Vec::new(),
+    };
+    let mut builder = StoryBuilder::default();
+    let mut contents = vec![(INK_ROOT_NAME, root.first().unwrap_or(&Value::Null))];
+    contents.extend(knots.iter().map(|(name, content)| (name.as_str(), *content)));
+    for (name, content) in contents {
+        let mut text = String::new();
+        let mut targets = Vec::new();
+        collect_content(content, &mut text, &mut targets);
+        let id = builder.add_passage(name, text.trim().to_string(), Vec::new())?;
+        for target in targets {
+            // Paths like "knot.stitch" or "knot.0.c-1" lead into `knot`; relative paths start with '.'
+            let knot = target.split('.').next().unwrap_or_default();
+            if knots.iter().any(|(name, _)| name.as_str() == knot) {
+                builder.add_link(id, knot, None);
+            }
+        }
+    }
+    Ok(builder.finish(None))
+}
+
+fn collect_content(content: &Value, text: &mut String, targets: &mut Vec<String>) {
+    match content {
+        Value::String(entry) if entry == "\n" => text.push('\n'),
+        Value::String(entry) => if let Some(words) = entry.strip_prefix('^') {
+            text.push_str(words);
+        },
+        Value::Array(entries) => for entry in entries {
+            collect_content(entry, text, targets);
+        },
+        Value::Object(fields) => collect_object(fields, text, targets),
+        _ => {},
+    }
+}
+
+fn collect_object(fields: &Map<String, Value>, text: &mut String, targets: &mut Vec<String>) {
+    // Diverts to a variable's value can't be followed statically
+    let is_variable = fields.get("var").and_then(Value::as_bool).unwrap_or(false);
+    for (key, value) in fields {
+        match (key.as_str(), value) {
+            ("->" | "->t->", Value::String(path)) if !is_variable => targets.push(path.clone()),
+            (_, Value::Array(_) | Value::Object(_)) => collect_content(value, text, targets),
+            _ => {},
+        }
+    }
+}
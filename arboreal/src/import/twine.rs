@@ -0,0 +1,157 @@
+use crate::graph_base::graph_components::GraphError;
+use super::{DroppedLink, StoryBuilder, StoryGraph};
+
+/// Passages with these names hold story data rather than story text
+const SPECIAL_PASSAGES: [&str; 2] = ["StoryTitle", "StoryData"];
+
+/// Builds a story graph from Twee 3 source.
+///
+/// Each `:: Name [tags] {metadata}` header starts a passage; its links (`[[Target]]`,
+/// `[[text|Target]]`, `[[text->Target]]`, `[[Target<-text]]`) become edges.
+/// The `StoryTitle` passage names the graph. Links to passages that do not exist, and links
+/// repeating an earlier one between the same passages, are dropped; `from_twee_reporting()`
+/// also returns them.
+///
+/// If a passage header has no name, or two passages share a name, an error is returned.
+pub fn from_twee(source: &str) -> Result<StoryGraph, GraphError> {
+    from_twee_reporting(source).map(super::log_dropped)
+}
+
+/// Same as `from_twee()`, also returning the links left out of the graph, in the order found
+pub fn from_twee_reporting(source: &str) -> Result<(StoryGraph, Vec<DroppedLink>), GraphError> {
+    let mut builder = StoryBuilder::default();
+    let mut story_name = None;
+    let mut passages: Vec<(&str, Vec<String>, Vec<&str>)> = Vec::new();
+    for line in source.lines() {
+        match line.strip_prefix("::") {
+            Some(header) => {
+                let (name, tags) = parse_header(header)?;
+                passages.push((name, tags, Vec::new()));
+            },
+            // Text before the first header is ignored, as in Twine
+            None => if let Some((_, _, body)) = passages.last_mut() {
+                body.push(line);
+            },
+        }
+    }
+    for (name, tags, body) in passages {
+        let text = body.join("\n").trim_end().to_string();
+        if name == "StoryTitle" {
+            story_name = Some(text.trim().to_string());
+        }
+        if SPECIAL_PASSAGES.contains(&name) {
+            continue;
+        }
+        let links = parse_links(&text);
+        let id = builder.add_passage(name, text.clone(), tags)?;
+        for (target, label) in links {
+            builder.add_link(id, &target, label);
+        }
+    }
+    Ok(builder.finish(story_name))
+}
+
+/// Splits `Name [tag1 tag2] {"position":"0,0"}` into the name and its tags
+fn parse_header(header: &str) -> Result<(&str, Vec<String>), GraphError> {
+    let mut rest = header.trim();
+    if rest.ends_with('}') && let Some(open) = rest.rfind('{') {
+        rest = rest[..open].trim_end();
+    }
+    let mut tags = Vec::new();
+    if rest.ends_with(']') && let Some(open) = rest.rfind('[') {
+        tags = rest[open + 1..rest.len() - 1]
+            .split_whitespace()
+            .map(String::from)
+            .collect();
+        rest = rest[..open].trim_end();
+    }
+    if rest.is_empty() {
+        return Err("Twee passage header has no name.".into());
+    }
+    Ok((rest, tags))
+}
+
+/// Returns (target, label) for every `[[...]]` link in `text`, in order
+fn parse_links(text: &str) -> Vec<(String, Option<String>)> {
+    let mut links = Vec::new();
+    let mut rest = text;
+    while let Some(open) = rest.find("[[") {
+        let Some(close) = rest[open..].find("]]") else {
+            break;
+        };
+        let inner = &rest[open + 2..open + close];
+        // Drop setter components, as in [[text|Target][$visited to true]]
+        let inner = inner.split("][").next().unwrap_or(inner);
+        links.push(split_link(inner));
+        rest = &rest[open + close + 2..];
+    }
+    links
+}
+
+fn split_link(inner: &str) -> (String, Option<String>) {
+    let (target, label) = if let Some((label, target)) = inner.split_once('|') {
+        (target, Some(label))
+    } else if let Some((label, target)) = inner.rsplit_once("->") {
+        (target, Some(label))
+    } else if let Some((target, label)) = inner.split_once("<-") {
+        (target, Some(label))
+    } else {
+        (inner, None)
+    };
+    let target = target.trim().to_string();
+    let label = label
+        .map(|label| label.trim().to_string())
+        .filter(|label| *label != target);
+    (target, label)
+}
+
+/// Builds a story graph from a Twine story exported as JSON in the Twison layout
+/// (`{"name": ..., "passages": [{"name", "text", "tags", "links": [{"name", "link"}]}]}`).
+///
+/// Passages without a `links` list have links read from their text, as in `from_twee()`.
+/// Links are dropped as there; `from_twine_json_reporting()` also returns them.
+/// If the JSON does not have this layout, or two passages share a name, an error is returned.
+#[cfg(feature = "json")]
+pub fn from_twine_json(source: &str) -> Result<StoryGraph, GraphError> {
+    from_twine_json_reporting(source).map(super::log_dropped)
+}
+
+/// Same as `from_twine_json()`, also returning the links left out of the graph, in the order found
+#[cfg(feature = "json")]
+pub fn from_twine_json_reporting(source: &str) -> Result<(StoryGraph, Vec<DroppedLink>), GraphError> {
+    use serde_json::Value;
+
+    let story: Value = serde_json::from_str(source)
+        .map_err(|_| GraphError::Invalid("Twine JSON could not be parsed."))?;
+    let passages = story["passages"]
+        .as_array()
+        .ok_or(GraphError::Invalid("Twine JSON has no passages list."))?;
+    let mut builder = StoryBuilder::default();
+    for passage in passages {
+        let name = passage["name"]
+            .as_str()
+            .ok_or(GraphError::Invalid("Twine passage has no name."))?;
+        let text = passage["text"].as_str().unwrap_or_default().to_string();
+        let tags = passage["tags"]
+            .as_array()
+            .map(|tags| tags.iter().filter_map(Value::as_str).map(String::from).collect())
+            .unwrap_or_default();
+        let links = match passage["links"].as_array() {
+            Some(links) => links.iter()
+                .filter_map(|link| {
+                    let target = link["link"].as_str()?.to_string();
+                    let label = link["name"].as_str()
+                        .filter(|label| *label != target)
+                        .map(String::from);
+                    Some((target, label))
+                })
+                .collect(),
+            None => parse_links(&text),
+        };
+        let id = builder.add_passage(name, text, tags)?;
+        for (target, label) in links {
+            builder.add_link(id, &target, label);
+        }
+    }
+    Ok(builder.finish(story["name"].as_str().map(String::from)))
+}
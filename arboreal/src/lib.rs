@@ -1,12 +1,50 @@
+/// Enters a `tracing` debug span (name, then optional fields) until the end of the enclosing
+/// block; expands to nothing without the `trace` feature
+macro_rules! trace_span {
+    ($name:literal $(, $($fields:tt)*)?) => {
+        #[cfg(feature = "trace")]
+        let _span = tracing::debug_span!($name $(, $($fields)*)?).entered();
+    };
+}
+
+// Lets `arboreal_derive` output, which names `::arboreal`, compile in this crate's own tests
+#[cfg(all(test, feature = "derive"))]
+extern crate self as arboreal;
+
 pub mod graph_base;
 pub mod digraph;
+pub mod behavior;
+pub mod eval;
+pub mod fsm;
+pub mod import;
+#[cfg(feature = "mcts")]
+pub mod mcts;
+pub mod named;
+pub mod persistent;
+pub mod property;
+pub mod schedule;
 
 #[cfg(test)]
 mod tests {
     use serde::{Deserialize, Serialize};
 
     use crate::{
-        digraph::{DiGraph, FileIO}, graph_base::graph_components::* 
+        behavior::{Behavior, BehaviorTree, Role, Status},
+        digraph::{BareNodePolicy, CoalesceWindow, CoverageReport, DiGraph, FileIO, IdReuse, GraphArchive, GraphClipboard, MemoryStorage, PasteIds, PathToken, Tree, UniqueEdgeLabels},
+        eval::{Compute, Evaluator},
+        fsm::{StateMachine, Transition},
+        graph_base::{
+            graph_algo,
+            graph_components::*,
+            graph_query::{Bindings, Comparison, Condition, Operand, Pattern},
+            graph_view::{AnyGraph, Avoiding, GraphView, Reversed},
+            label_path::LabelPattern,
+        },
+        import,
+        named::NamedGraph,
+        persistent::PersistentDiGraph,
+        property::{Properties, PropertyGraph, PropertyValue},
+        schedule::ExecutionPlan,
     };
 
     #[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
@@ -21,6 +59,15 @@ mod tests {
         fn node_id(&self) -> Id {
             self.some_id
         }
+        fn change_id(&mut self, new_id: Id) {
+            self.some_id = new_id;
+        }
+    }
+    impl Compute for NodeExample {
+        type Output = u32;
+        fn compute(&mut self, inputs: &[&u32]) -> u32 {
+            self.other_node_stuff as u32 + inputs.iter().copied().sum::<u32>()
+        }
     }
 
     #[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
@@ -45,6 +92,20 @@ mod tests {
             self.vertices.1 = new_end;
         }
     }
+    impl LabeledEdge for EdgeExample {
+        fn label(&self) -> &str {
+            self.other_edge_stuff.as_deref().unwrap_or("")
+        }
+    }
+    // "from..until" in the label, always valid without one
+    impl Temporal for EdgeExample {
+        fn valid_range(&self) -> (u64, u64) {
+            match self.other_edge_stuff.as_deref().and_then(|label| label.split_once("..")) {
+                Some((from, until)) => (from.parse().unwrap(), until.parse().unwrap()),
+                None => (0, u64::MAX),
+            }
+        }
+    }
 
     #[test]
     fn test_auto_edge_drop() {
@@ -89,6 +150,2552 @@ mod tests {
         // std::fs::remove_file(temp_file).unwrap_or(());
     }
 
+    #[test]
+    fn test_storage_round_trip() {
+        let mut storage = MemoryStorage::new();
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,3), (1,3)]);
+        obj.set_metadata("author", "BentPen");
+        obj.save_to_storage(&mut storage, "example").unwrap();
+        assert_eq!(storage.keys(), vec!["example"]);
+        let mut loaded: DiGraph<NodeExample, EdgeExample> = DiGraph::load_from_storage(&storage, "example").unwrap();
+        assert!(loaded == obj);
+        assert_eq!(loaded.metadata("author"), Some("BentPen"));
+        // Adjacency is rebuilt on load, so the graph stays editable
+        assert_eq!(loaded.in_degree(3), Some(2));
+        loaded.insert_edge_with_nodes(3, 4).unwrap();
+        assert!(DiGraph::<NodeExample, EdgeExample>::load_from_storage(&storage, "missing").is_none());
+    }
+
+    #[test]
+    fn test_string_round_trip() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,3)]);
+        obj.name = Some("embedded".to_string());
+        let text = obj.to_ron_string().unwrap();
+        let mut loaded: DiGraph<NodeExample, EdgeExample> = DiGraph::from_ron_str(&text).unwrap();
+        assert!(loaded == obj);
+        loaded.insert_edge_with_nodes(3, 1).unwrap();
+        assert!(DiGraph::<NodeExample, EdgeExample>::from_ron_str("not a graph").is_none());
+        #[cfg(feature = "json")]
+        {
+            let json = obj.to_json_string().unwrap();
+            let from_json: DiGraph<NodeExample, EdgeExample> = DiGraph::from_json_str(&json).unwrap();
+            assert!(from_json == obj);
+            assert_eq!(from_json.out_degree(1), Some(1));
+        }
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_cytoscape_json() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(2,1), (1,2)]);
+        obj.get_edge_mut(1, 2).unwrap().other_edge_stuff = Some("label".to_string());
+        let elements: serde_json::Value = serde_json::from_str(&obj.to_cytoscape_json().unwrap()).unwrap();
+        let elements = elements.as_array().unwrap();
+        assert_eq!(elements.len(), 4);
+        assert_eq!(elements[0]["group"], "nodes");
+        assert_eq!(elements[0]["data"]["id"], "1");
+        assert_eq!(elements[1]["data"]["other_node_stuff"], 255);
+        assert_eq!(elements[2]["group"], "edges");
+        assert_eq!(elements[2]["data"]["id"], "1-2");
+        assert_eq!((&elements[2]["data"]["source"], &elements[2]["data"]["target"]), (&"1".into(), &"2".into()));
+        assert_eq!(elements[2]["data"]["other_edge_stuff"], "label");
+        assert_eq!(elements[3]["data"]["source"], "2");
+    }
+
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    #[test]
+    fn test_compressed_files() {
+        use crate::digraph::Compression;
+
+        let obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,3), (3,1)]);
+        let plain_size = obj.to_ron_string().unwrap().len() as u64;
+        let formats = [
+            #[cfg(feature = "gzip")]
+            (Compression::Gzip, "example_gzip.ron.gz"),
+            #[cfg(feature = "zstd")]
+            (Compression::Zstd, "example_zstd.ron.zst"),
+        ];
+        for (compression, file_name) in formats {
+            obj.save_to_file_compressed(file_name, compression).unwrap();
+            assert!(std::fs::metadata(file_name).unwrap().len() < plain_size);
+            let loaded: DiGraph<NodeExample, EdgeExample> = DiGraph::load_from_file(file_name).unwrap();
+            std::fs::remove_file(file_name).unwrap_or(());
+            assert!(loaded == obj);
+            assert_eq!(loaded.out_degree(3), Some(1));
+        }
+    }
+
+    #[test]
+    fn test_load_structure_only() {
+        let file_name = "example_structure.ron";
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,3), (3,1)]);
+        obj.name = Some("heavy".to_string());
+        obj.get_edge_mut(2, 3).unwrap().other_edge_stuff = Some("payload".to_string());
+        obj.insert_node(NodeExample::bare(9)).unwrap();
+        obj.save_to_file(file_name).unwrap();
+        let structure = DiGraph::load_structure_only(file_name).unwrap();
+        std::fs::remove_file(file_name).unwrap_or(());
+        assert_eq!(structure.name.as_deref(), Some("heavy"));
+        assert_eq!(structure.all_node_ids(), vec![1, 2, 3, 9]);
+        assert_eq!(structure.all_edge_pairs(), obj.all_edge_pairs());
+        assert_eq!(structure.get_edge(2, 3), Some(&BareEdge { start: 2, end: 3 }));
+        assert_eq!(structure.in_degree(1), Some(1));
+
+        // Saved before format v2, with edges in a sequence
+        let file_name = "example_structure_v1.ron";
+        std::fs::write(file_name, "DiGraph(nodes: {1: (some_id: 1, other_node_stuff: 0), 2: (some_id: 2, other_node_stuff: 0)}, \
+            edges: [(vertices: (1, 2), other_edge_stuff: None), (vertices: (2, 1), other_edge_stuff: Some(\"x\"))])").unwrap();
+        let structure = DiGraph::load_structure_only(file_name).unwrap();
+        std::fs::remove_file(file_name).unwrap_or(());
+        assert_eq!(structure.all_edge_pairs(), vec![(1,2), (2,1)]);
+    }
+
+    #[test]
+    fn test_same_topology() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,3)]);
+        let mut other: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(2,3), (1,2)]);
+        other.get_edge_mut(1, 2).unwrap().other_edge_stuff = Some("renamed".to_string());
+        other.name = Some("other".to_string());
+        assert!(obj.same_topology(&other));
+        assert!(obj.explain_difference(&other).is_some());
+        let bare: DiGraph<BareNode, BareEdge> = DiGraph::from_terminal_pairs(vec![(1,2), (2,3)]);
+        assert!(obj.same_topology(&bare));
+        obj.insert_node(NodeExample::bare(4)).unwrap();
+        assert!(!obj.same_topology(&other));
+        other.insert_node(NodeExample::bare(4)).unwrap();
+        other.reverse_edge(2, 3).unwrap();
+        assert!(!obj.same_topology(&other));
+    }
+
+    #[cfg(feature = "schema")]
+    #[test]
+    fn test_schema() {
+        let schema = DiGraph::<BareNode, BareEdge>::schema();
+        let properties = schema.get("properties").unwrap();
+        for field in ["name", "nodes", "edges", "undo_history"] {
+            assert!(properties.get(field).is_some(), "{field}");
+        }
+        assert!(properties.get("neighbors_after").is_none());
+        let required = schema.get("required").unwrap().as_array().unwrap();
+        assert!(required.iter().any(|field| field == "edges"));
+        assert!(!required.iter().any(|field| field == "undo_history"));
+    }
+
+    #[test]
+    fn test_graph_archive() {
+        let mut storage = MemoryStorage::new();
+        let chapter1: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,3)]);
+        let chapter2: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(3,4)]);
+        let mut archive = GraphArchive::new();
+        archive.insert("chapter1", &chapter1).unwrap();
+        archive.insert("chapter2", &chapter2).unwrap();
+        archive.save_to_storage(&mut storage, "story").unwrap();
+
+        let mut loaded = GraphArchive::load_from_storage(&storage, "story").unwrap();
+        assert_eq!(loaded.names().collect::<Vec<_>>(), vec!["chapter1", "chapter2"]);
+        let restored = loaded.get::<NodeExample, EdgeExample>("chapter1").unwrap();
+        assert!(restored == chapter1);
+        assert_eq!(restored.in_degree(3), Some(1));
+        assert!(loaded.get::<NodeExample, EdgeExample>("chapter3").is_none());
+        assert!(loaded.remove("chapter2"));
+        assert_eq!(loaded.len(), 1);
+    }
+
+    #[test]
+    fn test_persisted_history() {
+        let mut storage = MemoryStorage::new();
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,3)]);
+        obj.remove_node(3).unwrap();
+        obj.save_to_storage(&mut storage, "transient").unwrap();
+        obj.set_history_persistence(true);
+        obj.save_to_storage(&mut storage, "persisted").unwrap();
+
+        let mut transient: DiGraph<NodeExample, EdgeExample> = DiGraph::load_from_storage(&storage, "transient").unwrap();
+        assert!(!transient.persists_history());
+        transient.undo().unwrap();
+        assert_eq!(transient.all_node_ids(), vec![1, 2]);
+
+        let mut restored: DiGraph<NodeExample, EdgeExample> = DiGraph::load_from_storage(&storage, "persisted").unwrap();
+        assert!(restored.persists_history());
+        restored.undo().unwrap();
+        assert_eq!(restored.all_edge_pairs(), vec![(1, 2), (2, 3)]);
+    }
+
+    #[test]
+    fn test_change_records() {
+        let mut storage = MemoryStorage::new();
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,3)]);
+        assert!(obj.last_change().is_none());
+        obj.insert_edge_with_nodes(3, 4).unwrap();
+        obj.remove_node(2).unwrap();
+        let last = obj.last_change().unwrap();
+        assert_eq!(last.kind, ChangeKind::RemoveNode);
+        assert_eq!(last.node_ids, vec![2]);
+        assert_eq!(last.edges.len(), 2);
+        assert!(last.timestamp_ms.is_some());
+        let kinds: Vec<ChangeKind> = obj.history_iter().map(|record| record.kind).collect();
+        assert_eq!(kinds, vec![ChangeKind::AddEdge, ChangeKind::RemoveNode]);
+        assert_eq!(obj.history_iter().next().unwrap().node_ids, vec![4]);
+
+        obj.set_history_persistence(true);
+        obj.save_to_storage(&mut storage, "history").unwrap();
+        let mut loaded: DiGraph<NodeExample, EdgeExample> = DiGraph::load_from_storage(&storage, "history").unwrap();
+        assert_eq!(loaded.history_iter().collect::<Vec<_>>(), obj.history_iter().collect::<Vec<_>>());
+        let undone = loaded.undo().unwrap().unwrap();
+        assert_eq!(undone.record.kind, ChangeKind::RemoveNode);
+        assert_eq!(undone.change.try_get_node(), Ok(NodeExample::bare(2)));
+        assert_eq!(loaded.last_change().unwrap().edges, vec![(3, 4)]);
+        assert!(loaded.undo().unwrap().is_some());
+        assert_eq!(loaded.undo(), Ok(None));
+    }
+
+    #[test]
+    fn test_history_states() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2)]);
+        obj.insert_edge_with_nodes(2, 3).unwrap();
+        obj.remove_edge(1, 2).unwrap();
+        obj.reverse_all_edges().unwrap();
+        let edge_history: Vec<Vec<(Id, Id)>> = obj.history_states()
+            .map(|state| state.all_edge_pairs())
+            .collect();
+        assert_eq!(edge_history, vec![vec![(3, 2)], vec![(2, 3)], vec![(1, 2), (2, 3)], vec![(1, 2)]]);
+        let before_insert = obj.state_at(3).unwrap();
+        assert_eq!(before_insert.all_node_ids(), vec![1, 2]);
+        assert_eq!(before_insert.in_degree(2), Some(1));
+        assert!(before_insert.last_change().is_none());
+        assert!(obj.state_at(4).is_none());
+        assert_eq!(obj.all_edge_pairs(), vec![(3, 2)]);
+        // First state (counting back) in which node 3 exists but has no successors
+        let steps_back = obj.history_states().position(|state| state.out_degree(3) == Some(0));
+        assert_eq!(steps_back, Some(1));
+    }
+
+    #[test]
+    fn test_explain_difference() {
+        let obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,3)]);
+        let mut other: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(2,3), (1,2)]);
+        crate::assert_graph_eq!(obj, other);
+        other.insert_edge_with_nodes(3, 4).unwrap();
+        other.get_node_mut(1).unwrap().other_node_stuff = 0;
+        let report = obj.explain_difference(&other).unwrap();
+        assert!(report.contains("Nodes only in right: [4]"));
+        assert!(report.contains("Edges only in right: [3->4]"));
+        assert!(report.contains("Node 1 payload differs"));
+        assert!(!report.contains("left: ["));
+
+        let mut other = obj.clone();
+        other.tag_node(2, "hub").unwrap();
+        other.tag_edge(2, 3, "last").unwrap();
+        other.set_entry(1).unwrap();
+        other.pin_node(3).unwrap();
+        let report = obj.explain_difference(&other).unwrap();
+        assert!(report.contains("Tags of node 2 differ: None vs Some({\"hub\"})"));
+        assert!(report.contains("Tags of edge (2, 3) differ: [] vs [\"last\"]"));
+        assert!(report.contains("Entry and exit differ") && report.contains("Pinned nodes differ"));
+    }
+
+    #[test]
+    fn test_prune_isolated_nodes() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,3), (4,5)]);
+        obj.insert_node(NodeExample::bare(0)).unwrap();
+        obj.remove_edge(4, 5).unwrap();
+        let pruned: Vec<Id> = obj.prune_isolated_nodes_except(&[0]).unwrap()
+            .iter()
+            .map(|node| node.node_id())
+            .collect();
+        assert_eq!(pruned, vec![4, 5]);
+        assert_eq!(obj.all_node_ids(), vec![0, 1, 2, 3]);
+        obj.undo().unwrap();
+        assert_eq!(obj.all_node_ids(), vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(obj.prune_isolated_nodes().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_shortest_paths() {
+        let obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (1,3), (4,3), (3, 5), (5,6), (6,7), (3,7), (8,4), (2,8)]);
+        assert_eq!(obj.shortest_path(1, 7), Some(vec![1, 3, 7]));
+        assert_eq!(obj.shortest_path_bidirectional(8, 7), Some(vec![8, 4, 3, 7]));
+        assert_eq!(obj.shortest_path_bidirectional(7, 1), None);
+        assert_eq!(obj.shortest_path(42, 1), None);
+        for start in obj.all_node_ids() {
+            for end in obj.all_node_ids() {
+                let one_way = obj.shortest_path(start, end).map(|path| path.len());
+                let two_way = obj.shortest_path_bidirectional(start, end).map(|path| path.len());
+                assert_eq!(one_way, two_way, "{start}->{end}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_topological_order() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(5,1), (4,1), (1,2), (3,2)]);
+        assert_eq!(obj.topological_order(), Some(vec![3, 4, 5, 1, 2]));
+        obj.get_node_mut(5).unwrap().other_node_stuff = 0;
+        assert_eq!(obj.topological_order_by(|node| node.other_node_stuff), Some(vec![5, 3, 4, 1, 2]));
+        obj.insert_edge(EdgeExample::bare(2, 5)).unwrap();
+        assert_eq!(obj.topological_order(), None);
+    }
+
+    #[test]
+    fn test_reciprocity() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,1), (2,3), (3,4), (4,3), (4,4)]);
+        assert_eq!(obj.edge_count_between(1, 2), 1);
+        assert_eq!(obj.edge_count_between(3, 2), 0);
+        assert_eq!(obj.edge_count_between(42, 2), 0);
+        assert_eq!(obj.mutual_edges(), vec![(1, 2), (3, 4)]);
+        assert_eq!(obj.reciprocity(), 0.8);
+        obj.remove_edge(2, 3).unwrap();
+        assert_eq!(obj.reciprocity(), 1.0);
+        let empty: DiGraph<NodeExample, EdgeExample> = DiGraph::new();
+        assert_eq!(empty.reciprocity(), 0.0);
+    }
+
+    #[test]
+    fn test_cut_vertices_and_bridges() {
+        let obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,3), (2,4), (4,5), (5,6), (6,4), (3,7), (7,3)]);
+        assert_eq!(obj.articulation_points(), vec![2, 3, 4]);
+        assert_eq!(obj.bridges(), vec![(1, 2), (2, 3), (2, 4)]);
+    }
+
+    #[test]
+    fn test_bipartite_and_coloring() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (3,2), (3,4), (1,4), (5,6)]);
+        assert_eq!(obj.is_bipartite(), Some((vec![1, 3, 5], vec![2, 4, 6])));
+        let colors = obj.color_nodes(2).unwrap();
+        for (start, end) in obj.all_edge_pairs() {
+            assert_ne!(colors[&start], colors[&end]);
+        }
+        obj.insert_edge_with_nodes(2, 4).unwrap();
+        assert_eq!(obj.is_bipartite(), None);
+        assert_eq!(obj.color_nodes(2), None);
+        assert!(obj.color_nodes(3).is_some());
+    }
+
+    #[test]
+    fn test_eulerian_path() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,3), (3,1), (3,4), (4,3)]);
+        assert!(obj.has_eulerian_circuit());
+        assert_eq!(obj.eulerian_path(), Some(vec![(1, 2), (2, 3), (3, 4), (4, 3), (3, 1)]));
+        obj.remove_edge(3, 1).unwrap();
+        assert!(!obj.has_eulerian_circuit());
+        assert_eq!(obj.eulerian_path(), Some(vec![(1, 2), (2, 3), (3, 4), (4, 3)]));
+        obj.insert_edge_with_nodes(5, 6).unwrap();
+        assert_eq!(obj.eulerian_path(), None);
+    }
+
+    #[test]
+    fn test_label_propagation() {
+        // Two triangles joined by a single edge
+        let obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,3), (3,1), (3,4), (4,5), (5,6), (6,4), (7,7)]);
+        let communities = obj.communities_label_propagation(20);
+        assert_eq!(communities[&1], communities[&2]);
+        assert_eq!(communities[&2], communities[&3]);
+        assert_eq!(communities[&4], communities[&5]);
+        assert_eq!(communities[&5], communities[&6]);
+        assert_ne!(communities[&3], communities[&4]);
+        assert_eq!(communities[&1], 0);
+        assert_eq!(communities[&7], 2);
+    }
+
+    #[test]
+    fn test_sampling() {
+        let obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (1,3), (4,3), (3, 5), (5,6), (6,7), (3,7), (8,4), (2,8)]);
+        let ego = obj.ego_graph(3, 1);
+        assert_eq!(ego.all_node_ids(), vec![3, 5, 7]);
+        assert_eq!(ego.all_edge_pairs(), vec![(3, 5), (3, 7)]);
+        let bfs = obj.sample_subgraph_bfs(1, 4);
+        assert_eq!(bfs.all_node_ids(), vec![1, 2, 3, 8]);
+        let mut seed = 7u64;
+        let sample = obj.sample_nodes(5, || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        });
+        assert_eq!(sample.all_node_ids().len(), 5);
+        for (start, end) in sample.all_edge_pairs() {
+            assert!(obj.get_edge(start, end).is_some());
+        }
+    }
+
+    #[test]
+    fn test_reverse_edges() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,1), (2,3)]);
+        obj.get_edge_mut(1, 2).unwrap().other_edge_stuff = Some("one to two".to_string());
+        assert!(obj.reverse_edge(1, 2).is_err());
+        obj.reverse_edge(2, 3).unwrap();
+        assert_eq!(obj.all_edge_pairs(), vec![(1, 2), (2, 1), (3, 2)]);
+        assert_eq!(obj.out_degree(3), Some(1));
+        obj.reverse_all_edges().unwrap();
+        assert_eq!(obj.get_edge(2, 1).unwrap().other_edge_stuff.as_deref(), Some("one to two"));
+        assert_eq!(obj.successors_sorted(2), Some(vec![1, 3]));
+        obj.undo().unwrap();
+        obj.undo().unwrap();
+        assert_eq!(obj.all_edge_pairs(), vec![(1, 2), (2, 1), (2, 3)]);
+        assert_eq!(obj.get_edge(1, 2).unwrap().other_edge_stuff.as_deref(), Some("one to two"));
+    }
+
+    #[test]
+    fn test_graph_views() {
+        let obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (1,3), (4,3), (3, 5), (5,6), (6,7), (3,7), (8,4), (2,8)]);
+        let reversed = Reversed(&obj);
+        assert_eq!(graph_algo::shortest_path(&reversed, 7, 1), Some(vec![7, 3, 1]));
+        assert_eq!(graph_algo::shortest_path(&reversed, 1, 7), None);
+        assert_eq!(reversed.edge_pairs().len(), obj.all_edge_pairs().len());
+        let frozen = obj.freeze();
+        assert_eq!(frozen.shortest_path(8, 7), Some(vec![8, 4, 3, 7]));
+        assert_eq!(graph_algo::bfs_order(&frozen, 3, usize::MAX, 1), vec![3, 5, 7]);
+        assert_eq!(frozen.node(4).map(|node| node.some_id), Some(4));
+        let mut thawed = frozen.thaw();
+        thawed.remove_node(4).unwrap();
+        assert_eq!(thawed.shortest_path(8, 7), None);
+    }
+
+    #[test]
+    fn test_filtered_view() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (1,3), (2,4), (3,4), (4,5)]);
+        obj.get_edge_mut(1, 3).unwrap().other_edge_stuff = Some("locked".to_string());
+        obj.get_node_mut(2).unwrap().other_node_stuff = 0;
+        let unlocked = obj.filtered(|_| true, |edge| edge.other_edge_stuff.is_none());
+        assert_eq!(unlocked.successors(1).to_vec(), vec![2]);
+        assert_eq!(graph_algo::shortest_path(&unlocked, 1, 5), Some(vec![1, 2, 4, 5]));
+        let no_two = obj.filtered(|node| node.other_node_stuff != 0, |_| true);
+        assert_eq!(no_two.node_ids(), vec![1, 3, 4, 5]);
+        assert_eq!(no_two.predecessors(4).to_vec(), vec![3]);
+        assert_eq!(no_two.edge_pairs(), vec![(1, 3), (3, 4), (4, 5)]);
+        let both = obj.filtered(|node| node.other_node_stuff != 0, |edge| edge.other_edge_stuff.is_none());
+        assert_eq!(graph_algo::shortest_path(&both, 1, 5), None);
+        assert_eq!(obj.all_edge_pairs().len(), 5);
+    }
+
+    #[test]
+    fn test_structure_hash() {
+        let obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,3), (1,3)]);
+        let mut shuffled: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,3), (1,2), (2,3)]);
+        assert_eq!(obj.structure_hash(), shuffled.structure_hash());
+        assert_eq!(obj.structure_hash_with_payloads(), shuffled.structure_hash_with_payloads());
+        shuffled.get_node_mut(2).unwrap().other_node_stuff = 0;
+        assert_eq!(obj.structure_hash(), shuffled.structure_hash());
+        assert_ne!(obj.structure_hash_with_payloads(), shuffled.structure_hash_with_payloads());
+        shuffled.remove_edge(1, 3).unwrap();
+        assert_ne!(obj.structure_hash(), shuffled.structure_hash());
+    }
+
+    #[test]
+    fn test_id_allocation() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(0,1), (1,3)]);
+        assert_eq!(obj.next_free_id(), Ok(2));
+        assert_eq!(obj.reserve_ids(2), Ok(vec![2, 4]));
+        assert_eq!(obj.insert_bare_node(), Ok(5));
+        obj.release_ids(&[4]);
+        assert_eq!(obj.next_free_id(), Ok(4));
+        let capacity = obj.remaining_id_capacity();
+        assert_eq!(capacity, ID_MAX as usize + 1 - 5);
+        assert_eq!(obj.reserve_ids(capacity + 1), Err(GraphError::IdSpaceExhausted));
+        assert_eq!(obj.reserve_ids(capacity).map(|ids| ids.len()), Ok(capacity));
+        assert_eq!(obj.remaining_id_capacity(), 0);
+        assert_eq!(obj.insert_bare_node(), Err(GraphError::IdSpaceExhausted));
+    }
+
+    #[test]
+    fn test_successor_order() {
+        let mut storage = MemoryStorage::new();
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (1,3), (1,4), (3,5)]);
+        assert!(obj.set_successor_order(1, vec![4, 2]).is_err());
+        obj.set_successor_order(1, vec![4, 2, 3]).unwrap();
+        assert_eq!(obj.successors(1).to_vec(), vec![4, 2, 3]);
+        obj.move_successor(1, 2, 0).unwrap();
+        assert_eq!(obj.ordered_successors(1), Some(vec![3, 4, 2]));
+        assert_eq!(graph_algo::bfs_order(&obj, 1, usize::MAX, usize::MAX), vec![1, 3, 4, 2, 5]);
+        assert!(obj.move_successor(1, 0, 3).is_err());
+
+        obj.remove_edge(1, 4).unwrap();
+        obj.insert_edge_with_nodes(1, 0).unwrap();
+        assert_eq!(obj.ordered_successors(1), Some(vec![3, 2, 0]));
+        obj.undo().unwrap();
+        obj.undo().unwrap();
+        obj.save_to_storage(&mut storage, "ordered").unwrap();
+        let mut loaded: DiGraph<NodeExample, EdgeExample> = DiGraph::load_from_storage(&storage, "ordered").unwrap();
+        assert_eq!(loaded.ordered_successors(1), Some(vec![3, 4, 2]));
+        loaded.set_successor_order(1, vec![]).unwrap();
+        assert_eq!(loaded.successors(1).to_vec(), vec![2, 3, 4]);
+        loaded.undo().unwrap();
+        assert_eq!(loaded.ordered_successors(1), Some(vec![3, 4, 2]));
+    }
+
+    #[test]
+    fn test_node_handles() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(0,1), (1,2)]);
+        let handle = obj.node_handle(1).unwrap();
+        obj.get_node_by_handle_mut(handle).unwrap().other_node_stuff = 7;
+        assert!(obj.node_handle(42).is_none());
+        obj.remove_node(1).unwrap();
+        assert_eq!(obj.insert_bare_node(), Ok(1));
+        assert!(!obj.is_handle_live(handle));
+        assert_eq!(obj.get_node_by_handle(handle), Err(GraphError::StaleHandle(handle)));
+        let recycled = obj.node_handle(1).unwrap();
+        assert_eq!(recycled.generation, handle.generation + 1);
+        assert_eq!(obj.get_node_by_handle(recycled).unwrap().other_node_stuff, 255);
+        // Undoing the insertion removes the node again
+        obj.undo().unwrap();
+        assert!(obj.resolve_handle(recycled).is_err());
+    }
+
+    #[test]
+    fn test_sorted_neighbors() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,9), (1,4), (1,1), (6,1), (1,2)]);
+        assert_eq!(obj.successors_sorted(1), Some(vec![1, 2, 4, 9]));
+        assert_eq!(obj.predecessors_sorted(1), Some(vec![1, 6]));
+        assert_eq!(obj.out_degree(1), Some(4));
+        obj.remove_edge(1, 4).unwrap();
+        assert_eq!(obj.successors_sorted(1), Some(vec![1, 2, 9]));
+        // Self-loop is dropped once, along with the node
+        obj.remove_node(1).unwrap();
+        assert_eq!(obj.successors_sorted(1), None);
+        assert_eq!(obj.in_degree(1), None);
+        assert_eq!(obj.out_degree(6), Some(0));
+    }
+
+    impl EdgeKeyed for EdgeExample {
+        fn edge_key(&self) -> u32 {
+            ((self.vertices.0 as u32) << 16) | self.vertices.1 as u32
+        }
+    }
+
+    #[test]
+    fn test_edge_keys() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,3)]);
+        let key = obj.get_edge(2, 3).unwrap().edge_key();
+        obj.get_edge_by_key_mut(key).unwrap().other_edge_stuff = Some("keyed".to_string());
+        let removed = obj.remove_edge_by_key(key).unwrap();
+        assert_eq!(removed.other_edge_stuff.as_deref(), Some("keyed"));
+        assert!(obj.get_edge_by_key(key).is_none());
+        assert!(obj.remove_edge_by_key(key).is_err());
+        obj.undo().unwrap();
+        assert_eq!(obj.get_edge_by_key(key), Some(&removed));
+    }
+
+    impl Ported for EdgeExample {
+        fn start_port(&self) -> Option<&str> {
+            self.other_edge_stuff.as_deref()
+        }
+        fn end_port(&self) -> Option<&str> {
+            None
+        }
+    }
+
+    #[test]
+    fn test_ports() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(0,1)]);
+        for id in 2..=4 {
+            obj.insert_node(NodeExample::bare(id)).unwrap();
+        }
+        let branch = |end: Id, port: &str| EdgeExample { vertices: (1, end), other_edge_stuff: Some(port.to_string()) };
+        obj.insert_ported_edge(branch(2, "true")).unwrap();
+        obj.insert_ported_edge(branch(3, "false")).unwrap();
+        assert!(obj.insert_ported_edge(branch(4, "true")).is_err());
+        assert_eq!(obj.successor_via_port(1, "true"), Some(2));
+        assert_eq!(obj.successor_via_port(1, "false"), Some(3));
+        assert_eq!(obj.successor_via_port(1, "maybe"), None);
+        assert!(obj.double_connected_ports().is_empty());
+        obj.insert_edge(branch(4, "true")).unwrap();
+        assert_eq!(obj.double_connected_ports(), vec![(1, "true".to_string(), true)]);
+    }
+
+    impl Behavior for NodeExample {
+        type Context = Vec<Id>;
+
+        fn role(&self) -> Role {
+            match self.other_node_stuff {
+                0 => Role::Sequence,
+                1 => Role::Selector,
+                _ => Role::Leaf,
+            }
+        }
+        fn tick_leaf(&mut self, ctx: &mut Vec<Id>) -> Status {
+            ctx.push(self.some_id);
+            match self.other_node_stuff {
+                2 => Status::Success,
+                3 => Status::Failure,
+                _ => Status::Running,
+            }
+        }
+    }
+
+    #[test]
+    fn test_behavior_tree() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (1,5), (2,3), (2,4)]);
+        for (id, stuff) in [(1, 1), (2, 0), (3, 2), (4, 3), (5, 2)] {
+            obj.get_node_mut(id).unwrap().other_node_stuff = stuff;
+        }
+        let mut tree = BehaviorTree::new(obj, 1).unwrap();
+        let mut log = Vec::new();
+        assert_eq!(tree.tick(&mut log), Status::Success);
+        assert_eq!(log, vec![3, 4, 5]);
+
+        let mut obj = tree.into_graph();
+        obj.get_node_mut(4).unwrap().other_node_stuff = 255;
+        let mut tree = BehaviorTree::new(obj, 1).unwrap();
+        log.clear();
+        assert_eq!(tree.tick(&mut log), Status::Running);
+        assert_eq!(log, vec![3, 4]);
+
+        let mut obj = tree.into_graph();
+        obj.insert_edge_with_nodes(4, 1).unwrap();
+        assert!(BehaviorTree::new(obj, 1).is_err());
+    }
+
+    impl Transition for EdgeExample {
+        type Event = &'static str;
+        type Context = u32;
+
+        fn triggered_by(&self, event: &&'static str) -> bool {
+            self.other_edge_stuff.as_deref() == Some(*event)
+        }
+        fn guard(&self, _event: &&'static str, ctx: &u32) -> bool {
+            *ctx < 3
+        }
+        fn action(&self, _event: &&'static str, ctx: &mut u32) {
+            *ctx += 1;
+        }
+    }
+
+    #[test]
+    fn test_state_machine() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,1), (2,3)]);
+        for ((start, end), event) in [((1, 2), "go"), ((2, 1), "back"), ((2, 3), "go")] {
+            obj.get_edge_mut(start, end).unwrap().other_edge_stuff = Some(event.to_string());
+        }
+        let mut machine = StateMachine::from_graph(obj, 1).unwrap();
+        assert!(!machine.can_fire(&"back"));
+        assert_eq!(machine.fire(&"go"), Ok(2));
+        assert_eq!(machine.fire(&"back"), Ok(1));
+        assert_eq!(machine.fire(&"go"), Ok(2));
+        assert_eq!(*machine.context(), 3);
+        // Guard now refuses every transition
+        assert_eq!(machine.fire(&"go"), Err(GraphError::IllegalTransition { from: 2 }));
+        assert_eq!(machine.current(), 2);
+        *machine.context_mut() = 0;
+        assert_eq!(machine.fire(&"go"), Ok(3));
+        assert_eq!(machine.current_state().some_id, 3);
+        assert!(StateMachine::from_graph(machine.into_graph(), 42).is_err());
+    }
+
+    #[test]
+    fn test_story_import() {
+        let twee = ":: StoryTitle\nThe Cave\n\n:: Start [intro] {\"position\":\"0,0\"}\nYou wake up. [[Look around->Cave]] or [[Sleep]].\n\n:: Cave\nDark. [[Start<-Go back]] [[Nowhere]]\n\n:: Sleep\nThe end.";
+        let story = import::from_twee(twee).unwrap();
+        assert_eq!(story.name.as_deref(), Some("The Cave"));
+        assert_eq!(story.all_node_ids(), vec![0, 1, 2]);
+        let start = story.get_node(0).unwrap();
+        assert_eq!((start.name.as_str(), start.tags.clone()), ("Start", vec!["intro".to_string()]));
+        assert_eq!(story.all_edge_pairs(), vec![(0, 1), (0, 2), (1, 0)]);
+        assert_eq!(story.get_edge(0, 1).unwrap().label.as_deref(), Some("Look around"));
+        assert_eq!(story.get_edge(0, 2).unwrap().label, None);
+        assert!(import::from_twee(":: A\n:: A").is_err());
+        let (story, dropped) = import::from_twee_reporting(":: A\n[[B]] [[Go->B]] [[C]]\n:: B").unwrap();
+        assert_eq!(story.all_edge_pairs(), vec![(0, 1)]);
+        assert_eq!(dropped, vec![
+            import::DroppedLink { from: 0, target: "B".to_string(), label: Some("Go".to_string()) },
+            import::DroppedLink { from: 0, target: "C".to_string(), label: None },
+        ]);
+        #[cfg(feature = "json")]
+        {
+            let twison = r#"{"name": "Tiny", "passages": [
+                {"name": "One", "text": "[[Two]]", "links": [{"name": "Onward", "link": "Two"}]},
+                {"name": "Two", "text": "Back to [[One]]"}]}"#;
+            let story = import::from_twine_json(twison).unwrap();
+            assert_eq!(story.all_edge_pairs(), vec![(0, 1), (1, 0)]);
+            assert_eq!(story.get_edge(0, 1).unwrap().label.as_deref(), Some("Onward"));
+            let ink = r##"{"inkVersion": 21, "root": [["^Hello.", "\n", {"->": "knot_a"}, ["done", {"#n": "g-0"}], null], "done",
+                {"knot_a": ["^In A.", "\n", {"->": "knot_b.stitch"}, null], "knot_b": [{"stitch": ["^In B.", "\n", "end", null]}],
+                "global decl": ["ev", 0, {"VAR=": "x"}, "/ev", "end", null]}], "listDefs": {}}"##;
+            let story = import::from_ink_json(ink).unwrap();
+            assert_eq!(story.get_node(0).unwrap().name, import::INK_ROOT_NAME);
+            assert_eq!(story.node_count(), 3);
+            assert_eq!(story.all_edge_pairs(), vec![(0, 1), (1, 2)]);
+            assert_eq!(story.get_node(2).unwrap().text, "In B.");
+            assert!(import::from_ink_json("{}").is_err());
+        }
+    }
+
+    #[test]
+    fn test_property_graph() {
+        let mut world: PropertyGraph = PropertyGraph::new();
+        let guard = world.add_node("NPC".to_string(), Properties::from([("hp".to_string(), 30.into())])).unwrap();
+        let sword = world.add_node("Item".to_string(), Properties::new()).unwrap();
+        let merchant = world.add_node("NPC".to_string(), Properties::new()).unwrap();
+        world.add_edge(guard, merchant, "talks_to".to_string(), Properties::new()).unwrap();
+        world.add_edge(merchant, sword, "sells".to_string(), Properties::new()).unwrap();
+        assert_eq!(world.nodes_with_label("NPC"), vec![guard, merchant]);
+        assert_eq!(world.edges_with_label("talks_to"), vec![(guard, merchant)]);
+        assert_eq!(world.node_property(guard, "hp").and_then(PropertyValue::as_int), Some(30));
+        assert_eq!(world.set_edge_property(merchant, sword, "price", 12.5), None);
+        assert_eq!(world.nodes_with_property("hp", 30), vec![guard]);
+        // Editing through the inner DiGraph keeps undo working
+        world.remove_node(sword).unwrap();
+        world.undo().unwrap();
+        assert_eq!(world.edge_property(merchant, sword, "price").and_then(PropertyValue::as_float), Some(12.5));
+        let loaded: PropertyGraph = PropertyGraph::from_ron_str(&world.to_ron_string().unwrap()).unwrap();
+        crate::assert_graph_eq!(loaded, world);
+        assert_eq!(loaded.in_degree(merchant), Some(1));
+
+        #[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
+        enum Kind { #[default] Room, Door }
+        let mut house: PropertyGraph<Kind> = PropertyGraph::new();
+        let hall = house.add_node(Kind::Room, Properties::new()).unwrap();
+        house.add_node(Kind::Door, Properties::new()).unwrap();
+        assert_eq!(house.nodes_with_label(&Kind::Room), vec![hall]);
+    }
+
+    #[test]
+    fn test_query() {
+        let obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (1,3), (3,4), (2,4), (4,1), (5,5)]);
+        let binding = |pairs: &[(&str, Id)]| -> Bindings {
+            pairs.iter().map(|&(name, id)| (name.to_string(), id)).collect()
+        };
+        let leaves = obj.query("(a)-[]->(b) WHERE out_degree(b) == 1 and id(a) < 2").unwrap();
+        assert_eq!(leaves, vec![binding(&[("a", 1), ("b", 2)]), binding(&[("a", 1), ("b", 3)])]);
+        // Two-step path into a node with two predecessors
+        assert_eq!(obj.query("(a)-->()-->(c) WHERE in_degree(c) >= 2").unwrap(), vec![binding(&[("a", 1), ("c", 4)])]);
+        assert_eq!(obj.query("(x)-->(x)").unwrap(), vec![binding(&[("x", 5)])]);
+        let pattern = Pattern::node("hub").to("b").from("c")
+            .filter(Condition::new(Operand::Id("c".to_string()), Comparison::Eq, Operand::Literal(2)));
+        assert_eq!(obj.match_pattern(&pattern), obj.query("(hub)-[]->(b)<-[]-(c) WHERE id(c) == 2").unwrap());
+        // Different names may bind the same node
+        assert_eq!(obj.match_pattern(&pattern), vec![binding(&[("b", 4), ("c", 2), ("hub", 2)]), binding(&[("b", 4), ("c", 2), ("hub", 3)])]);
+        assert!(obj.query("(a)->(b)").is_err());
+        assert!(obj.query("(a)-->(b) WHERE degree(z) > 0").is_err());
+    }
+
+    #[test]
+    fn test_subgraph_isomorphisms() {
+        let obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (1,3), (1,4), (2,3), (5,6), (5,7), (5,8), (5,9), (9,5)]);
+        let hub_with_three_leaves: DiGraph<BareNode, BareEdge> = DiGraph::from_terminal_pairs(vec![(0,1), (0,2), (0,3)]);
+        let matches = obj.find_subgraph_isomorphisms(&hub_with_three_leaves);
+        // One mapping per ordering of the leaves: 3! around hub 1, 4 * 3 * 2 around hub 5
+        assert_eq!(matches.len(), 6 + 24);
+        assert!(matches.iter().all(|mapping| mapping[&0] == 1 || mapping[&0] == 5));
+        assert!(matches.iter().all(|mapping| obj.get_edge(mapping[&0], mapping[&3]).is_some()));
+        assert_eq!(obj.find_subgraph_isomorphisms_limited(&hub_with_three_leaves, 7).len(), 7);
+        let two_cycle: DiGraph<BareNode, BareEdge> = DiGraph::from_terminal_pairs(vec![(0,1), (1,0)]);
+        let cycles = obj.find_subgraph_isomorphisms(&two_cycle);
+        assert_eq!(cycles.len(), 2);
+        assert!(cycles.contains(&std::collections::HashMap::from([(0, 5), (1, 9)])));
+        let triangle: DiGraph<BareNode, BareEdge> = DiGraph::from_terminal_pairs(vec![(0,1), (1,2), (2,0)]);
+        assert!(obj.find_subgraph_isomorphisms(&triangle).is_empty());
+    }
+
+    #[test]
+    fn test_rewire() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,3), (2,3), (3,5), (1,4), (6,6)]);
+        obj.get_edge_mut(2, 3).unwrap().other_edge_stuff = Some("kept".to_string());
+        let before = obj.all_edge_pairs();
+        // 1->3 would land on the existing 1->4, so nothing moves
+        let into_replacement = |edge: &EdgeExample| (edge.vertices.1 == 3).then_some((edge.vertices.0, 4));
+        assert!(obj.rewire(into_replacement).is_err());
+        assert!(obj.rewire(|edge| (edge.vertices.1 == 5).then_some((3, 42))).is_err());
+        assert_eq!(obj.all_edge_pairs(), before);
+        obj.remove_edge(1, 4).unwrap();
+        assert_eq!(obj.rewire(into_replacement), Ok(2));
+        assert_eq!(obj.all_edge_pairs(), vec![(1,4), (2,4), (3,5), (6,6)]);
+        assert_eq!(obj.get_edge(2, 4).unwrap().other_edge_stuff.as_deref(), Some("kept"));
+        // Swapping two edges' terminals is fine, as the final set has no duplicates
+        assert_eq!(obj.rewire(|edge| match edge.vertices {
+            (1, 4) => Some((2, 4)),
+            (2, 4) => Some((1, 4)),
+            _ => None,
+        }), Ok(2));
+        assert_eq!(obj.get_edge(1, 4).unwrap().other_edge_stuff.as_deref(), Some("kept"));
+        obj.undo().unwrap();
+        obj.undo().unwrap();
+        assert_eq!(obj.all_edge_pairs(), vec![(1,3), (2,3), (3,5), (6,6)]);
+        assert_eq!(obj.get_edge(2, 3).unwrap().other_edge_stuff.as_deref(), Some("kept"));
+    }
+
+    #[test]
+    fn test_replace_node() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,3), (2,2), (3,1)]);
+        obj.set_successor_order(1, vec![2]).unwrap();
+        let handle = obj.node_handle(2).unwrap();
+        let old = obj.replace_node(2, NodeExample { some_id: 2, other_node_stuff: 7 }).unwrap();
+        assert_eq!(old.other_node_stuff, 255);
+        assert!(obj.is_handle_live(handle));
+        let old = obj.replace_node(2, NodeExample { some_id: 9, other_node_stuff: 8 }).unwrap();
+        assert_eq!(old.other_node_stuff, 7);
+        assert!(obj.get_node(2).is_none());
+        assert_eq!(obj.all_edge_pairs(), vec![(1,9), (3,1), (9,3), (9,9)]);
+        assert_eq!(obj.ordered_successors(1), Some(vec![9]));
+        assert_eq!(obj.last_change().unwrap().node_ids, vec![2, 9]);
+        assert!(obj.replace_node(9, NodeExample::bare(3)).is_err());
+        assert!(obj.replace_node(2, NodeExample::bare(2)).is_err());
+        obj.undo().unwrap();
+        assert_eq!(obj.all_edge_pairs(), vec![(1,2), (2,2), (2,3), (3,1)]);
+        assert_eq!(obj.get_node(2).unwrap().other_node_stuff, 7);
+        obj.undo().unwrap();
+        assert_eq!(obj.get_node(2).unwrap().other_node_stuff, 255);
+    }
+
+    #[test]
+    fn test_tree_shape() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (1,3), (3,4)]);
+        assert!(obj.is_arborescence(1));
+        assert!(!obj.is_arborescence(3));
+        assert!(!obj.is_arborescence(42));
+        assert!(obj.is_forest());
+        obj.insert_edge_with_nodes(5, 6).unwrap();
+        assert!(obj.is_forest());
+        assert_eq!(obj.tree_violations(Some(1)), vec![(5, TreeViolation::NoParent), (6, TreeViolation::Unreachable)]);
+        obj.insert_edge(EdgeExample::bare(2, 4)).unwrap();
+        obj.insert_edge(EdgeExample::bare(6, 5)).unwrap();
+        assert!(!obj.is_forest());
+        assert_eq!(obj.tree_violations(None), vec![(4, TreeViolation::SeveralParents(2)), (5, TreeViolation::Unreachable), (6, TreeViolation::Unreachable)]);
+        let from_five = obj.tree_violations(Some(5));
+        for violation in [(1, TreeViolation::NoParent), (3, TreeViolation::Unreachable), (5, TreeViolation::RootHasParents)] {
+            assert!(from_five.contains(&violation), "{violation:?}");
+        }
+        assert!(DiGraph::<NodeExample, EdgeExample>::new().is_forest());
+    }
+
+    #[test]
+    fn test_lca() {
+        let pairs = vec![(1,2), (1,3), (2,4), (2,5), (4,6), (4,7), (7,8), (3,9), (9,10)];
+        let tree: Tree<NodeExample, EdgeExample> = Tree::from_graph(DiGraph::from_terminal_pairs(pairs), 1).unwrap();
+        assert_eq!(tree.parent(4), Some(2));
+        assert_eq!(tree.parent(1), None);
+        assert_eq!(tree.depth(8), Some(4));
+        assert_eq!(tree.lca(6, 8), Some(4));
+        assert_eq!(tree.lca(8, 5), Some(2));
+        assert_eq!(tree.lca(8, 10), Some(1));
+        assert_eq!(tree.lca(7, 8), Some(7));
+        assert_eq!(tree.lca(3, 3), Some(3));
+        assert_eq!(tree.lca(3, 42), None);
+        let index = tree.build_lca_index();
+        for a in 1..=10 {
+            for b in 1..=10 {
+                assert_eq!(index.lca(a, b), tree.lca(a, b), "{a}, {b}");
+            }
+        }
+        assert_eq!(index.lca(1, 42), None);
+        assert!(Tree::from_graph(tree.into_graph(), 2).is_err());
+
+        let dag: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,3), (1,4), (2,3), (2,4), (0,1), (0,2), (4,5)]);
+        assert_eq!(dag.lowest_common_ancestors(3, 5), vec![1, 2]);
+        assert_eq!(dag.lowest_common_ancestors(4, 5), vec![4]);
+        assert_eq!(dag.lowest_common_ancestors(1, 2), vec![0]);
+        assert!(dag.lowest_common_ancestors(3, 42).is_empty());
+    }
+
+    #[test]
+    fn test_tree_edits() {
+        let pairs = vec![(1,2), (1,3), (2,4), (2,5), (3,6)];
+        let mut tree: Tree<NodeExample, EdgeExample> = Tree::from_graph(DiGraph::from_terminal_pairs(pairs), 1).unwrap();
+        let original = tree.all_edge_pairs();
+        tree.move_subtree(2, 3).unwrap();
+        assert_eq!(tree.children(3), Some(vec![6, 2]));
+        assert_eq!(tree.children(2), Some(vec![4, 5]));
+        assert!(tree.move_subtree(3, 4).is_err());
+        assert!(tree.move_subtree(1, 3).is_err());
+        tree.reparent(2, 4).unwrap();
+        assert_eq!(tree.children(3), Some(vec![6, 4, 5]));
+        assert_eq!(tree.parent(2), Some(4));
+        assert!(tree.is_arborescence(1));
+        tree.insert_child_at(3, 1, NodeExample::bare(7)).unwrap();
+        assert_eq!(tree.children(3), Some(vec![6, 7, 4, 5]));
+        assert!(tree.insert_child_at(3, 5, NodeExample::bare(8)).is_err());
+        assert!(tree.insert_child_at(3, 0, NodeExample::bare(7)).is_err());
+        tree.undo().unwrap();
+        assert_eq!(tree.children(3), Some(vec![6, 4, 5]));
+        tree.undo().unwrap();
+        assert_eq!(tree.children(3), Some(vec![6, 2]));
+        assert_eq!(tree.children(2), Some(vec![4, 5]));
+        tree.undo().unwrap();
+        assert_eq!(tree.all_edge_pairs(), original);
+        assert_eq!(tree.children(1), Some(vec![2, 3]));
+        assert!(tree.undo().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_pinned_nodes() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,3)]);
+        obj.insert_node(NodeExample::bare(4)).unwrap();
+        obj.insert_node(NodeExample::bare(5)).unwrap();
+        obj.pin_node(1).unwrap();
+        obj.pin_node(4).unwrap();
+        assert!(obj.pin_node(42).is_err());
+        assert_eq!(obj.remove_node(1), Err(GraphError::NodePinned(1)));
+        assert_eq!(obj.all_edge_pairs(), vec![(1,2), (2,3)]);
+        assert_eq!(obj.prune_isolated_nodes().unwrap().len(), 1);
+        assert!(obj.get_node(4).is_some());
+        obj.replace_node(1, NodeExample::bare(9)).unwrap();
+        assert_eq!(obj.pinned_node_ids(), vec![4, 9]);
+        let text = obj.to_ron_string().unwrap();
+        let loaded: DiGraph<NodeExample, EdgeExample> = DiGraph::load_from_bytes(text.as_bytes()).unwrap();
+        assert!(loaded.is_pinned(9));
+        obj.undo().unwrap();
+        assert!(obj.is_pinned(1));
+        assert!(obj.unpin_node(1));
+        assert!(!obj.unpin_node(1));
+        obj.remove_node(1).unwrap();
+    }
+
+    #[test]
+    fn test_degree_limits() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (1,3), (4,5)]);
+        assert!(obj.set_max_out_degree(1, 1).is_err());
+        obj.set_max_out_degree(1, 3).unwrap();
+        obj.set_max_in_degree(5, 1).unwrap();
+        obj.insert_edge_with_nodes(1, 6).unwrap();
+        assert_eq!(obj.insert_edge_with_nodes(1, 7), Err(GraphError::OutDegreeLimit { id: 1, max: 3 }));
+        assert!(obj.get_node(7).is_none());
+        assert_eq!(obj.insert_edge(EdgeExample::bare(2, 5)), Err(GraphError::InDegreeLimit { id: 5, max: 1 }));
+        assert!(obj.rewire(|edge| (edge.terminal_ids() == (4, 5)).then_some((1, 4))).is_err());
+        assert_eq!(obj.rewire(|edge| (edge.terminal_ids() == (1, 6)).then_some((1, 4))), Ok(1));
+        obj.replace_node(1, NodeExample::bare(9)).unwrap();
+        assert_eq!(obj.max_out_degree(9), Some(3));
+        obj.clear_degree_limits(9);
+        obj.insert_edge_with_nodes(9, 7).unwrap();
+    }
+
+    #[test]
+    fn test_constraints() {
+        use crate::digraph::{Acyclic, ForbiddenEdges, MaxNodes, SingleSource};
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,3)]);
+        obj.add_constraint(Box::new(Acyclic)).unwrap();
+        obj.add_constraint(Box::new(MaxNodes(4))).unwrap();
+        obj.add_constraint(Box::new(ForbiddenEdges(vec![(1, 3)]))).unwrap();
+        assert!(obj.add_constraint(Box::new(MaxNodes(2))).is_err());
+        let handle = obj.node_handle(2).unwrap();
+        assert_eq!(obj.insert_edge(EdgeExample::bare(3, 1)), Err(GraphError::ConstraintViolated("acyclic")));
+        assert_eq!(obj.insert_edge(EdgeExample::bare(1, 3)), Err(GraphError::ConstraintViolated("forbidden edges")));
+        assert_eq!(obj.reverse_edge(2, 3), Ok(()));
+        obj.insert_edge_with_nodes(3, 4).unwrap();
+        assert_eq!(obj.insert_edge_with_nodes(4, 5), Err(GraphError::ConstraintViolated("max nodes")));
+        assert!(obj.get_node(5).is_none());
+        assert_eq!(obj.all_edge_pairs(), vec![(1,2), (3,2), (3,4)]);
+        assert_eq!(obj.history_iter().count(), 2);
+        assert!(obj.is_handle_live(handle));
+
+        assert!(obj.add_constraint(Box::new(SingleSource)).is_err());
+        obj.remove_constraint("acyclic");
+        obj.remove_constraint("max nodes");
+        assert_eq!(obj.constraint_names(), vec!["forbidden edges"]);
+        obj.undo().unwrap();
+        obj.undo().unwrap();
+        obj.add_constraint(Box::new(SingleSource)).unwrap();
+        assert_eq!(obj.remove_edge(1, 2), Err(GraphError::ConstraintViolated("single source")));
+        assert_eq!(obj.all_edge_pairs(), vec![(1,2), (2,3)]);
+    }
+
+    #[test]
+    fn test_copy_paste() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,3), (3,1), (3,4)]);
+        obj.get_node_mut(2).unwrap().other_node_stuff = 7;
+        obj.get_edge_mut(1, 2).unwrap().other_edge_stuff = Some("kept".to_string());
+        let clipboard = obj.copy_subgraph(&[3, 2, 1, 42]);
+        assert_eq!(clipboard.nodes().len(), 3);
+        assert_eq!(clipboard.edges().len(), 3);
+        let text = ron::to_string(&clipboard).unwrap();
+        let clipboard: GraphClipboard<NodeExample, EdgeExample> = ron::from_str(&text).unwrap();
+
+        let id_map = obj.paste(&clipboard, PasteIds::Auto).unwrap();
+        assert_eq!(id_map, std::collections::HashMap::from([(1, 0), (2, 5), (3, 6)]));
+        assert_eq!(obj.get_node(5).unwrap().other_node_stuff, 7);
+        assert_eq!(obj.get_edge(0, 5).unwrap().other_edge_stuff.as_deref(), Some("kept"));
+        assert_eq!(obj.all_edge_pairs(), vec![(0,5), (1,2), (2,3), (3,1), (3,4), (5,6), (6,0)]);
+        assert!(obj.paste(&clipboard, PasteIds::Offset(4)).is_err());
+        assert!(obj.paste(&clipboard, PasteIds::Offset(Id::MAX)).is_err());
+        assert_eq!(obj.all_node_ids().len(), 7);
+        obj.paste(&clipboard, PasteIds::Offset(10)).unwrap();
+        assert_eq!(obj.successors(13).into_owned(), vec![11]);
+        obj.undo().unwrap();
+        obj.undo().unwrap();
+        assert_eq!(obj.all_node_ids(), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_speculate() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,3), (3,4)]);
+        obj.insert_node(NodeExample::bare(9)).unwrap();
+        let before = obj.all_edge_pairs();
+        {
+            let mut what_if = obj.speculate();
+            assert!(!what_if.is_modified());
+            what_if.remove_node(3).unwrap();
+            assert_eq!(what_if.nodes_unreachable_from(1), vec![4, 9]);
+            assert!(what_if.is_modified());
+        }
+        assert_eq!(obj.all_edge_pairs(), before);
+        assert_eq!(obj.history_iter().count(), 1);
+
+        obj.add_constraint(Box::new(crate::digraph::MaxNodes(5))).unwrap();
+        let mut what_if = obj.speculate();
+        for id in 10..20 {
+            what_if.insert_node(NodeExample::bare(id)).unwrap();
+        }
+        assert_eq!(what_if.commit(), Err(GraphError::ConstraintViolated("max nodes")));
+        assert_eq!(obj.all_node_ids(), vec![1, 2, 3, 4, 9]);
+
+        let mut what_if = obj.speculate();
+        what_if.remove_node(9).unwrap();
+        what_if.insert_edge(EdgeExample::bare(4, 1)).unwrap();
+        what_if.reverse_edge(1, 2).unwrap();
+        what_if.undo().unwrap();
+        what_if.commit().unwrap();
+        assert_eq!(obj.all_edge_pairs(), vec![(1,2), (2,3), (3,4), (4,1)]);
+        assert!(obj.get_node(9).is_none());
+        assert_eq!(obj.history_iter().count(), 2);
+        obj.undo().unwrap();
+        assert_eq!(obj.all_edge_pairs(), before);
+        assert!(obj.get_node(9).is_some());
+
+        // Dirty flags and settings made on the copy are committed, and the base's are kept
+        obj.mark_dirty(1).unwrap();
+        let mut what_if = obj.speculate();
+        what_if.mark_dirty(2).unwrap();
+        what_if.set_id_reuse(IdReuse::Never);
+        what_if.commit().unwrap();
+        assert!(obj.is_dirty(1) && obj.is_dirty(2));
+        assert_eq!(obj.id_reuse(), IdReuse::Never);
+    }
+
+    #[test]
+    fn test_apply_delta() {
+        use crate::digraph::{ApplyError, DeltaConflict, DeltaOp, GraphDelta};
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,3)]);
+        let mut delta = GraphDelta::new();
+        delta.push(DeltaOp::AddNode(NodeExample::bare(4)));
+        delta.push(DeltaOp::AddEdge(EdgeExample::bare(3, 4)));
+        delta.push(DeltaOp::UpdateNode { old: NodeExample::bare(2), new: NodeExample { some_id: 2, other_node_stuff: 7 } });
+        delta.push(DeltaOp::RemoveEdge(EdgeExample::bare(1, 2)));
+        let text = ron::to_string(&delta).unwrap();
+        let delta: GraphDelta<NodeExample, EdgeExample> = ron::from_str(&text).unwrap();
+        obj.apply_delta(&delta).unwrap();
+        assert_eq!(obj.all_edge_pairs(), vec![(2,3), (3,4)]);
+        assert_eq!(obj.get_node(2).unwrap().other_node_stuff, 7);
+
+        // Made against the old state, so the update of node 2 conflicts; the first op is rolled back
+        let mut stale = GraphDelta::new();
+        stale.push(DeltaOp::AddEdge(EdgeExample::bare(4, 1)));
+        stale.push(DeltaOp::RemoveNode(NodeExample::bare(2)));
+        assert_eq!(obj.apply_delta(&stale), Err(ApplyError { index: 1, conflict: DeltaConflict::NodeChanged(2) }));
+        assert_eq!(obj.all_edge_pairs(), vec![(2,3), (3,4)]);
+        // Rolled back exactly: same edge order, and handles to removed nodes stay live (clone() drops the history)
+        let before = obj.clone();
+        let handle = obj.node_handle(3).unwrap();
+        let mut missing = GraphDelta::new();
+        missing.push(DeltaOp::RemoveNode(NodeExample::bare(3)));
+        missing.push(DeltaOp::RemoveNode(NodeExample::bare(9)));
+        assert_eq!(obj.apply_delta(&missing), Err(ApplyError { index: 1, conflict: DeltaConflict::NodeMissing(9) }));
+        assert!(obj.clone() == before);
+        assert!(obj.is_handle_live(handle));
+        assert!(obj.freed_ids().is_empty());
+        obj.pin_node(1).unwrap();
+        let mut pinned = GraphDelta::new();
+        pinned.push(DeltaOp::RemoveNode(NodeExample::bare(1)));
+        assert_eq!(obj.apply_delta(&pinned).unwrap_err().conflict, DeltaConflict::Refused(GraphError::NodePinned(1)));
+        assert_eq!(obj.history_iter().count(), 1);
+        obj.undo().unwrap();
+        assert_eq!(obj.all_edge_pairs(), vec![(1,2), (2,3)]);
+        assert_eq!(obj.get_node(2).unwrap().other_node_stuff, 255);
+    }
+
+    #[cfg(feature = "crdt")]
+    #[test]
+    fn test_merge_concurrent() {
+        let base: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,3)]);
+        let text = base.to_ron_string().unwrap();
+        let mut a: DiGraph<NodeExample, EdgeExample> = DiGraph::load_from_bytes(text.as_bytes()).unwrap();
+        let mut b: DiGraph<NodeExample, EdgeExample> = DiGraph::load_from_bytes(text.as_bytes()).unwrap();
+        assert!(a.merge_concurrent(&b).is_err());
+        a.enable_crdt(1);
+        b.enable_crdt(2);
+        a.remove_node(3).unwrap();
+        a.insert_edge_with_nodes(1, 4).unwrap();
+        b.insert_edge_with_nodes(3, 5).unwrap();
+        b.replace_node(2, NodeExample { some_id: 2, other_node_stuff: 7 }).unwrap();
+        b.remove_edge(1, 2).unwrap();
+        b.undo().unwrap();
+        b.tag_node(3, "end").unwrap();
+        b.tag_edge(2, 3, "last").unwrap();
+        b.set_exit(3).unwrap();
+        // Stamps are saved with the graph
+        let b: DiGraph<NodeExample, EdgeExample> = DiGraph::load_from_bytes(b.to_ron_string().unwrap().as_bytes()).unwrap();
+        let a_before = DiGraph::<NodeExample, EdgeExample>::load_from_bytes(a.to_ron_string().unwrap().as_bytes()).unwrap();
+
+        a.merge_concurrent(&b).unwrap();
+        let mut b = b;
+        b.merge_concurrent(&a_before).unwrap();
+        for obj in [&a, &b] {
+            assert_eq!(obj.all_node_ids(), vec![1, 2, 4, 5]);
+            assert_eq!(obj.all_edge_pairs(), vec![(1,2), (1,4)]);
+            assert_eq!(obj.get_node(2).unwrap().other_node_stuff, 7);
+        }
+        assert!(a.last_change().is_none());
+        a.merge_concurrent(&b).unwrap();
+        assert_eq!(a.all_edge_pairs(), b.all_edge_pairs());
+        // Nothing of the lost node passes to one that takes its id
+        b.insert_edge_with_nodes(2, 3).unwrap();
+        assert_eq!((b.exit(), b.node_tags(3), b.edge_tags(2, 3)), (None, vec![], vec![]));
+    }
+
+    #[test]
+    fn test_propagate_dirty() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,3), (2,3), (3,4), (1,4), (5,6), (6,5), (4,5), (7,1)]);
+        let clean = obj.clone();
+        obj.mark_dirty(2).unwrap();
+        obj.mark_dirty(1).unwrap();
+        assert!(obj.mark_dirty(42).is_err());
+        assert!(obj.is_dirty(1));
+        // Flags are bookkeeping, not data
+        assert!(obj == clean);
+        assert_eq!(obj.propagate_dirty(), vec![1, 2, 3, 4, 5, 6]);
+        assert!(!obj.is_dirty(1));
+        assert!(obj.propagate_dirty().is_empty());
+        obj.mark_dirty(6).unwrap();
+        obj.mark_dirty(7).unwrap();
+        obj.remove_node(7).unwrap();
+        assert_eq!(obj.propagate_dirty(), vec![5, 6]);
+        assert_eq!(graph_algo::downstream_order(&obj, &[4]), vec![4, 5, 6]);
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,3), (2,3), (3,4), (1,4), (5,6), (6,5)]);
+        for id in 1..=6 {
+            obj.get_node_mut(id).unwrap().other_node_stuff = id as u8;
+        }
+        assert_eq!(obj.evaluate(3), Ok(6));
+        assert_eq!(obj.evaluate(4), Ok(11));
+        assert!(obj.evaluate(5).is_err());
+        assert!(obj.evaluate(42).is_err());
+
+        let mut evaluator = Evaluator::new();
+        assert_eq!(evaluator.evaluate(&mut obj, 4), Ok(&11));
+        assert_eq!(evaluator.output(3), Some(&6));
+        assert_eq!(evaluator.output(5), None);
+        obj.get_node_mut(2).unwrap().other_node_stuff = 20;
+        assert_eq!(evaluator.evaluate(&mut obj, 4), Ok(&11));
+        obj.mark_dirty(2).unwrap();
+        assert_eq!(evaluator.evaluate(&mut obj, 4), Ok(&29));
+        obj.get_node_mut(1).unwrap().other_node_stuff = 0;
+        obj.mark_dirty(1).unwrap();
+        assert_eq!(evaluator.evaluate_parallel(&mut obj, 4), Ok(&27));
+        assert_eq!(evaluator.output(3), Some(&23));
+        evaluator.clear();
+        assert_eq!(evaluator.evaluate_parallel(&mut obj, 3), Ok(&23));
+        assert_eq!(evaluator.output(4), None);
+    }
+
+    #[test]
+    fn test_execution_plan() {
+        let obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,3), (2,3), (3,4), (1,4), (5,6)]);
+        let mut plan = ExecutionPlan::from(&obj);
+        assert_eq!(plan.ready(), vec![1, 2, 5]);
+        assert!(plan.complete(3).is_err());
+        assert!(plan.complete(42).is_err());
+        assert_eq!(plan.complete(1), Ok(vec![]));
+        assert!(plan.complete(1).is_err());
+        assert_eq!(plan.complete(2), Ok(vec![3]));
+        assert_eq!(plan.ready(), vec![3, 5]);
+        assert_eq!(plan.complete(5), Ok(vec![6]));
+        assert_eq!(plan.complete(3), Ok(vec![4]));
+        assert_eq!(plan.remaining(), 2);
+        assert!(!plan.is_done());
+        plan.complete(4).unwrap();
+        plan.complete(6).unwrap();
+        assert!(plan.is_done() && !plan.is_stuck());
+
+        let cyclic: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,3), (3,2)]);
+        let mut plan = ExecutionPlan::from(&cyclic);
+        assert_eq!(plan.complete(1), Ok(vec![]));
+        assert!(plan.is_stuck());
+        assert!(plan.is_completed(1) && !plan.is_completed(2));
+    }
+
+    #[test]
+    fn test_progress_and_cancel() {
+        use std::{cell::RefCell, ops::ControlFlow};
+        let obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (1,3), (1,4), (2,3), (5,6), (5,7), (5,8), (5,9), (9,5)]);
+        let hub_with_three_leaves: DiGraph<BareNode, BareEdge> = DiGraph::from_terminal_pairs(vec![(0,1), (0,2), (0,3)]);
+        let seen = RefCell::new(Vec::new());
+        let record = |progress: graph_algo::Progress| {
+            seen.borrow_mut().push(progress);
+            ControlFlow::Continue(())
+        };
+        let matches = obj.find_subgraph_isomorphisms_with_progress(&hub_with_three_leaves, usize::MAX, &record).unwrap();
+        assert_eq!(matches.len(), 30);
+        assert_eq!(seen.borrow().len(), 9);
+        assert_eq!(seen.borrow().last().unwrap().fraction(), 1.0);
+
+        let stop_halfway = |progress: graph_algo::Progress| match progress.fraction() < 0.5 {
+            true => ControlFlow::Continue(()),
+            false => ControlFlow::Break(()),
+        };
+        assert_eq!(obj.find_subgraph_isomorphisms_with_progress(&hub_with_three_leaves, usize::MAX, &stop_halfway), Err(GraphError::Cancelled));
+        assert_eq!(obj.communities_label_propagation_with_progress(0, &stop_halfway), Ok(obj.communities_label_propagation(0)));
+        assert_eq!(obj.communities_label_propagation_with_progress(20, &|_| ControlFlow::Break(())), Err(GraphError::Cancelled));
+        let pattern = Pattern::parse("(a)-->(b)").unwrap();
+        assert_eq!(obj.match_pattern_with_progress(&pattern, &record).unwrap(), obj.match_pattern(&pattern));
+        assert!(obj.match_pattern_with_progress(&pattern, &stop_halfway).is_err());
+
+        seen.borrow_mut().clear();
+        assert_eq!(obj.critical_edges_with_progress(1, 3, &record), Ok(obj.critical_edges(1, 3)));
+        assert_eq!(*seen.borrow(), vec![graph_algo::Progress { done: 1, total: 1 }]);
+        assert_eq!(obj.critical_edges_with_progress(5, 6, &|_| ControlFlow::Break(())), Err(GraphError::Cancelled));
+        assert_eq!(obj.source_to_sink_paths_with_progress(usize::MAX, &record), Ok(obj.source_to_sink_paths(usize::MAX)));
+        assert_eq!(seen.borrow().last().unwrap().fraction(), 1.0);
+        assert_eq!(obj.source_to_sink_paths_with_progress(usize::MAX, &stop_halfway), Err(GraphError::Cancelled));
+    }
+
+    #[test]
+    fn test_edge_lookup_after_removals() {
+        let pairs = vec![(1,2), (2,3), (3,1), (3,3), (1,4), (4,2), (2,1)];
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(pairs.clone());
+        obj.remove_edge(2, 3).unwrap();
+        obj.remove_edge(1, 2).unwrap();
+        for &(start_id, end_id) in pairs.iter().filter(|&&pair| pair != (2,3) && pair != (1,2)) {
+            assert_eq!(obj.get_edge(start_id, end_id).unwrap().vertices, (start_id, end_id));
+        }
+        assert!(obj.get_edge(2, 3).is_none());
+        obj.remove_node(3).unwrap();
+        assert_eq!(obj.all_edge_pairs(), vec![(1,4), (2,1), (4,2)]);
+        assert!(obj.insert_edge(EdgeExample::bare(4, 2)).is_err());
+        obj.undo().unwrap();
+        assert!(obj.get_edge(3, 3).is_some() && obj.get_edge(3, 1).is_some());
+        obj.replace_node(1, NodeExample::bare(9)).unwrap();
+        assert_eq!(obj.all_edge_pairs(), vec![(2,9), (3,3), (3,9), (4,2), (9,4)]);
+        let mut reloaded = DiGraph::<NodeExample, EdgeExample>::load_from_bytes(obj.to_ron_string().unwrap().as_bytes()).unwrap();
+        assert_eq!(reloaded.get_edge(9, 4), obj.get_edge(9, 4));
+        assert_eq!(reloaded.remove_node(9).map(|node| node.some_id), Ok(9));
+        assert_eq!(reloaded.all_edge_pairs(), vec![(3,3), (4,2)]);
+    }
+
+    #[test]
+    fn test_unreachable() {
+        let obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,3), (3,1), (3,4), (5,4), (6,6), (ID_MAX,1)]);
+        assert_eq!(obj.nodes_unreachable_from(1), vec![5, 6, ID_MAX]);
+        assert_eq!(obj.unreachable_count_from(1), 3);
+        assert_eq!(obj.nodes_unreachable_from(ID_MAX), vec![5, 6]);
+        assert_eq!(obj.unreachable_count_from(6), 6);
+        assert_eq!(obj.nodes_unreachable_from(42).len(), 7);
+        assert_eq!(obj.unreachable_count_from(42), 7);
+    }
+
+    #[test]
+    fn test_id_set() {
+        let mut set: IdSet = [9, 3, ID_MAX, 64, 3].into_iter().collect();
+        assert_eq!(set.len(), 4);
+        assert!(set.contains(64) && set.contains(ID_MAX) && !set.contains(65));
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![3, 9, 64, ID_MAX]);
+        assert!(!set.insert(9));
+        assert!(set.remove(9) && !set.remove(9));
+        let other: IdSet = [3, 5, ID_MAX].into_iter().collect();
+        let mut common = set.clone();
+        common.intersect_with(&other);
+        assert_eq!(format!("{common:?}"), format!("{{3, {ID_MAX}}}"));
+        set.union_with(&other);
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![3, 5, 64, ID_MAX]);
+        set.difference_with(&common);
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![5, 64]);
+        assert_eq!(set.len(), 2);
+        set.clear();
+        assert!(set.is_empty() && set == IdSet::default());
+    }
+
+    #[cfg(feature = "arbitrary")]
+    impl<'a> arbitrary::Arbitrary<'a> for NodeExample {
+        fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+            Ok(Self { some_id: u.arbitrary()?, other_node_stuff: u.arbitrary()? })
+        }
+    }
+
+    #[cfg(feature = "arbitrary")]
+    impl<'a> arbitrary::Arbitrary<'a> for EdgeExample {
+        fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+            Ok(Self { vertices: u.arbitrary()?, other_edge_stuff: u.arbitrary()? })
+        }
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn test_arbitrary_graphs() {
+        use arbitrary::{Arbitrary, Unstructured};
+        use crate::digraph::GraphOp;
+        let mut state: u32 = 12345;
+        let mut random_bytes = |count: usize| -> Vec<u8> {
+            (0..count).map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (state >> 16) as u8
+            }).collect()
+        };
+        for _ in 0..200 {
+            let bytes = random_bytes(1024);
+            let mut u = Unstructured::new(&bytes);
+            let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::arbitrary(&mut u).unwrap();
+            let node_ids = obj.all_node_ids();
+            let edge_pairs = obj.all_edge_pairs();
+            assert!(edge_pairs.iter().all(|(start_id, end_id)| node_ids.contains(start_id) && node_ids.contains(end_id)));
+            assert!(edge_pairs.windows(2).all(|pair| pair[0] != pair[1]));
+            assert!(obj.last_change().is_none());
+            let reloaded = DiGraph::<NodeExample, EdgeExample>::load_from_bytes(obj.to_ron_string().unwrap().as_bytes()).unwrap();
+            assert_eq!(reloaded.all_edge_pairs(), edge_pairs);
+
+            let bytes = random_bytes(1024);
+            let mut u = Unstructured::new(&bytes);
+            for _ in 0..60 {
+                let _ = obj.apply_op(GraphOp::arbitrary(&mut u).unwrap());
+            }
+            while obj.undo().unwrap().is_some() {}
+            assert_eq!(obj.all_node_ids(), node_ids);
+            assert_eq!(obj.all_edge_pairs(), edge_pairs);
+        }
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        use crate::digraph::ForbiddenEdges;
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(10,20), (10,30), (20,40), (30,40), (50,50)]);
+        let mut other: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(9,9), (3,1), (7,3), (5,1), (7,5)]);
+        obj.pin_node(40).unwrap();
+        other.pin_node(1).unwrap();
+        other.remove_edge(7, 3).unwrap();
+        other.insert_edge(EdgeExample::bare(7, 3)).unwrap();
+        let id_map = obj.canonicalize().unwrap();
+        assert_eq!(id_map, std::collections::HashMap::from([(10, 0), (20, 1), (30, 2), (40, 3), (50, 4)]));
+        other.canonicalize().unwrap();
+        assert_eq!(obj.all_edge_pairs(), vec![(0,1), (0,2), (1,3), (2,3), (4,4)]);
+        assert_eq!(obj.pinned_node_ids(), vec![3]);
+        assert_eq!(other.undo(), Ok(None));
+        assert_eq!(obj.to_ron_string().unwrap(), other.to_ron_string().unwrap());
+
+        // Payloads come before old ids
+        obj.get_node_mut(2).unwrap().other_node_stuff = 1;
+        obj.canonicalize().unwrap();
+        assert_eq!(obj.get_node(1).unwrap().other_node_stuff, 1);
+        obj.add_constraint(Box::new(ForbiddenEdges(vec![(2, 4)]))).unwrap();
+        obj.insert_edge(EdgeExample::bare(1, 4)).unwrap();
+        obj.set_successor_order(0, vec![2, 1]).unwrap();
+        assert_eq!(obj.canonicalize(), Err(GraphError::ConstraintViolated("forbidden edges")));
+        assert_eq!(obj.ordered_successors(0), Some(vec![2, 1]));
+        assert!(obj.get_edge(1, 4).is_some());
+    }
+
+    #[test]
+    fn test_simplify_chains() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(0,5), (5,2), (2,7), (7,1), (0,3), (3,1), (0,1), (1,4), (4,1), (6,6)]);
+        obj.pin_node(5).unwrap();
+        let combine = |edge_in: &EdgeExample, node: &NodeExample, edge_out: &EdgeExample| {
+            let stuff = [edge_in.other_edge_stuff.clone(), Some(node.some_id.to_string()), edge_out.other_edge_stuff.clone()];
+            EdgeExample { vertices: (0, 0), other_edge_stuff: Some(stuff.into_iter().flatten().collect()) }
+        };
+        assert_eq!(obj.simplify_chains(combine), Ok(vec![2, 4, 7]));
+        assert_eq!(obj.all_edge_pairs(), vec![(0,1), (0,3), (0,5), (1,1), (3,1), (5,1), (6,6)]);
+        assert_eq!(obj.get_edge(5, 1).unwrap().other_edge_stuff.as_deref(), Some("27"));
+        assert_eq!(obj.get_edge(1, 1).unwrap().other_edge_stuff.as_deref(), Some("4"));
+        obj.undo().unwrap();
+        assert_eq!(obj.all_node_ids(), vec![0, 1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(obj.all_edge_pairs().len(), 10);
+
+        // 7 stays, since 0 already has an edge to 1
+        obj.unpin_node(5);
+        assert_eq!(obj.simplify_chains(combine), Ok(vec![2, 4, 5]));
+        assert_eq!(obj.all_edge_pairs(), vec![(0,1), (0,3), (0,7), (1,1), (3,1), (6,6), (7,1)]);
+        assert_eq!(obj.get_edge(0, 7).unwrap().other_edge_stuff.as_deref(), Some("52"));
+    }
+
+    #[test]
+    fn test_group_nodes() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(0,1), (0,2), (1,2), (2,3), (1,3), (3,4), (5,1)]);
+        let original_pairs = obj.all_edge_pairs();
+        assert_eq!(obj.group_nodes(&[1, 2, 3], 4), Err("Node with this id already exists.".into()));
+        obj.pin_node(3).unwrap();
+        assert_eq!(obj.group_nodes(&[1, 2, 3], 10), Err(GraphError::NodePinned(3)));
+        obj.unpin_node(3);
+
+        obj.group_nodes(&[1, 2, 3], 10).unwrap();
+        assert_eq!(obj.all_node_ids(), vec![0, 4, 5, 10]);
+        assert_eq!(obj.all_edge_pairs(), vec![(0,10), (5,10), (10,4)]);
+        let group = obj.group(10).unwrap();
+        assert_eq!(group.member_ids(), vec![1, 2, 3]);
+        assert_eq!(group.hidden_edge_count(0, 10), 2);
+        assert_eq!(group.hidden_edge_count(10, 4), 1);
+        assert_eq!(group.edges().len(), 7);
+        obj.undo().unwrap();
+        assert!(obj.group(10).is_none());
+        assert_eq!(obj.all_edge_pairs(), original_pairs);
+
+        // Nested, under ids already in the group
+        obj.group_nodes(&[1, 2], 2).unwrap();
+        obj.group_nodes(&[2, 3], 3).unwrap();
+        assert_eq!(obj.all_edge_pairs(), vec![(0,3), (3,4), (5,3)]);
+        assert_eq!(obj.group_nodes(&[0], 2), Err("Group with this id already exists.".into()));
+        assert_eq!(obj.ungroup(2), Err("Group with this id not found.".into()));
+        assert_eq!(obj.ungroup(3), Ok(vec![2, 3]));
+        assert_eq!(obj.ungroup(2), Ok(vec![1, 2]));
+        assert_eq!(obj.all_edge_pairs(), original_pairs);
+        obj.undo().unwrap();
+        assert_eq!(obj.group(2).unwrap().hidden_edge_count(0, 2), 2);
+    }
+
+    #[test]
+    fn test_tags() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(0,1), (1,2), (2,0)]);
+        assert_eq!(obj.tag_node(1, "deprecated"), Ok(true));
+        assert_eq!(obj.tag_node(1, "deprecated"), Ok(false));
+        obj.tag_node(1, "core").unwrap();
+        obj.tag_node(2, "deprecated").unwrap();
+        assert!(obj.tag_node(9, "core").is_err());
+        assert_eq!(obj.node_tags(1), vec!["core", "deprecated"]);
+        assert_eq!(obj.nodes_with_tag("deprecated"), vec![1, 2]);
+        obj.tag_edge(1, 2, "hot").unwrap();
+        obj.tag_edge(2, 0, "hot").unwrap();
+        assert!(obj.tag_edge(2, 1, "hot").is_err());
+        assert_eq!(obj.edges_with_tag("hot"), vec![(1,2), (2,0)]);
+        assert_eq!(obj.get_node(1), Some(&NodeExample::bare(1)));
+
+        obj.reverse_edge(1, 2).unwrap();
+        assert_eq!(obj.edge_tags(2, 1), vec!["hot"]);
+        obj.replace_node(2, NodeExample::bare(5)).unwrap();
+        assert_eq!(obj.nodes_with_tag("deprecated"), vec![1, 5]);
+        assert_eq!(obj.edges_with_tag("hot"), vec![(5,0), (5,1)]);
+        obj.remove_node(1).unwrap();
+        assert_eq!(obj.nodes_with_tag("deprecated"), vec![5]);
+        assert_eq!(obj.edges_with_tag("hot"), vec![(5,0)]);
+        obj.undo().unwrap();
+        assert_eq!(obj.node_tags(1), vec!["core", "deprecated"]);
+        assert_eq!(obj.edges_with_tag("hot"), vec![(5,0), (5,1)]);
+
+        let text = obj.to_ron_string().unwrap();
+        let loaded: DiGraph<NodeExample, EdgeExample> = DiGraph::from_ron_str(&text).unwrap();
+        assert_eq!(loaded.edge_tags(5, 1), vec!["hot"]);
+        assert!(obj.untag_node(5, "deprecated") && !obj.untag_node(5, "deprecated"));
+        assert!(obj.untag_edge(5, 1, "hot"));
+        assert_eq!(obj.nodes_with_tag("deprecated"), vec![1]);
+        assert_eq!(obj.edges_with_tag("hot"), vec![(5,0)]);
+    }
+
+    #[test]
+    fn test_tags_dropped_on_removal() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(0,1), (1,2), (2,0)]);
+        obj.tag_node(1, "core").unwrap();
+        obj.tag_edge(1, 2, "hot").unwrap();
+        obj.remove_node(1).unwrap();
+        assert_eq!(obj.last_change().unwrap().kind, ChangeKind::RemoveNode);
+        // A node or edge taking the same id or terminals starts untagged
+        obj.insert_node(NodeExample::bare(1)).unwrap();
+        obj.insert_edge(EdgeExample::bare(1, 2)).unwrap();
+        assert!(obj.node_tags(1).is_empty() && obj.edge_tags(1, 2).is_empty());
+        assert!(obj.nodes_with_tag("core").is_empty());
+        obj.undo().unwrap();
+        obj.undo().unwrap();
+        obj.undo().unwrap();
+        assert_eq!(obj.node_tags(1), vec!["core"]);
+        assert_eq!(obj.edge_tags(1, 2), vec!["hot"]);
+        obj.redo().unwrap();
+        assert!(obj.nodes_with_tag("core").is_empty());
+        obj.undo().unwrap();
+        assert_eq!(obj.edges_with_tag("hot"), vec![(1,2)]);
+
+        // Undoing an addition drops its tags too
+        obj.insert_node(NodeExample::bare(7)).unwrap();
+        obj.tag_node(7, "new").unwrap();
+        obj.undo().unwrap();
+        obj.insert_node(NodeExample::bare(7)).unwrap();
+        assert!(obj.node_tags(7).is_empty());
+
+        // Rewired edges keep their tags, even when swapping terminals
+        obj.tag_edge(0, 1, "a").unwrap();
+        obj.tag_edge(2, 0, "c").unwrap();
+        obj.rewire(|edge| match edge.terminal_ids() {
+            (0, 1) => Some((2, 0)),
+            (2, 0) => Some((0, 1)),
+            _ => None,
+        }).unwrap();
+        assert_eq!((obj.edge_tags(0, 1), obj.edge_tags(2, 0)), (vec!["c"], vec!["a"]));
+        obj.undo().unwrap();
+        assert_eq!((obj.edge_tags(0, 1), obj.edge_tags(2, 0)), (vec!["a"], vec!["c"]));
+
+        // Hidden by a group, not removed
+        obj.tag_node(2, "grouped").unwrap();
+        obj.group_nodes(&[1, 2], 9).unwrap();
+        obj.ungroup(9).unwrap();
+        assert_eq!(obj.node_tags(2), vec!["grouped"]);
+        assert_eq!(obj.edge_tags(1, 2), vec!["hot"]);
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        use crate::digraph::{EditLimits, EditOp};
+        let old: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(0,1), (1,2)]);
+        let mut new: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(0,1), (2,0), (2,3)]);
+        new.get_node_mut(1).unwrap().other_node_stuff = 3;
+        assert_eq!(old.edit_distance(&old, EditLimits::default()), Some((0, vec![])));
+
+        let (distance, ops) = old.edit_distance(&new, EditLimits::default()).unwrap();
+        let steps: Vec<String> = ops.iter().map(|op| op.to_string()).collect();
+        assert_eq!(distance, 5);
+        assert_eq!(steps, vec![
+            "Remove edge 1->2",
+            "Change node 1: (some_id:1,other_node_stuff:255) -> (some_id:1,other_node_stuff:3)",
+            "Insert node 3: (some_id:3,other_node_stuff:255)",
+            "Insert edge 2->0",
+            "Insert edge 2->3",
+        ]);
+        assert_eq!(old.edit_distance(&new, EditLimits { max_distance: 4, ..Default::default() }), None);
+
+        // Renumbering one node beats removing and inserting it
+        let renumbered: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(0,5), (5,2)]);
+        let (distance, ops) = old.edit_distance(&renumbered, EditLimits::default()).unwrap();
+        assert_eq!(distance, 1);
+        assert_eq!(ops, vec![EditOp::ReplaceNode { old: NodeExample::bare(1), new: NodeExample::bare(5) }]);
+        assert_eq!(ops[0].to_string(), "Replace node 1 with node 5: (some_id:5,other_node_stuff:255)");
+        let never = |_| std::ops::ControlFlow::Continue(());
+        assert_eq!(old.edit_distance_with_progress(&new, EditLimits::default(), &never), Ok(old.edit_distance(&new, EditLimits::default())));
+        let cancel = |_| std::ops::ControlFlow::Break(());
+        assert_eq!(old.edit_distance_with_progress(&new, EditLimits::default(), &cancel), Err(GraphError::Cancelled));
+    }
+
+    #[test]
+    fn test_enforce_acyclic() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(0,1), (1,0)]);
+        assert_eq!(obj.enforce_acyclic(true), Err(GraphError::ConstraintViolated("acyclic")));
+        assert!(!obj.is_acyclic_enforced() && obj.cached_topological_order().is_none());
+        obj.remove_edge(1, 0).unwrap();
+        obj.enforce_acyclic(true).unwrap();
+
+        let mut state: u32 = 777;
+        let mut random_id = || {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            ((state >> 16) % 24) as Id
+        };
+        for _ in 0..400 {
+            let (start_id, end_id) = (random_id(), random_id());
+            let edge_pairs = obj.all_edge_pairs();
+            let closes_cycle = start_id == end_id
+                || obj.get_node(start_id).is_some() && obj.get_node(end_id).is_some()
+                    && !obj.nodes_unreachable_from(end_id).contains(&start_id);
+            match obj.insert_edge_with_nodes(start_id, end_id) {
+                Err(GraphError::ConstraintViolated("acyclic")) => {
+                    assert!(closes_cycle);
+                    assert_eq!(obj.all_edge_pairs(), edge_pairs);
+                },
+                result => assert!(!closes_cycle && (result.is_ok() || edge_pairs.contains(&(start_id, end_id)))),
+            }
+            let order = obj.cached_topological_order().unwrap();
+            let position = |id: Id| order.iter().position(|&x| x == id).unwrap();
+            assert!(obj.all_edge_pairs().iter().all(|&(start_id, end_id)| position(start_id) < position(end_id)));
+        }
+        assert!(obj.topological_order().is_some());
+
+        obj.insert_node_along(30, 0, 1).unwrap();
+        obj.insert_edge(EdgeExample::bare(0, 1)).unwrap();
+        assert_eq!(obj.reverse_edge(0, 1), Err(GraphError::ConstraintViolated("acyclic")));
+        obj.undo().unwrap();
+        assert!(obj.is_acyclic_enforced());
+        obj.enforce_acyclic(false).unwrap();
+        obj.insert_edge(EdgeExample::bare(1, 0)).unwrap();
+        assert!(obj.cached_topological_order().is_none());
+    }
+
+    #[test]
+    fn test_topo_index() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(0,1), (1,2), (3,2)]);
+        assert_eq!(obj.topo_index(0), None);
+        assert_eq!(obj.iter_topological().count(), 0);
+        obj.enforce_acyclic(true).unwrap();
+        assert_eq!(obj.iter_topological().collect::<Vec<Id>>(), obj.topological_order().unwrap());
+        obj.insert_edge(EdgeExample::bare(2, 0)).unwrap_err();
+        obj.insert_edge_with_nodes(4, 0).unwrap();
+        obj.insert_edge(EdgeExample::bare(2, 3)).unwrap_err();
+        let index = |id: Id| obj.topo_index(id).unwrap();
+        assert!(index(4) < index(0) && index(0) < index(1) && index(1) < index(2) && index(3) < index(2));
+        assert_eq!(obj.topo_index(9), None);
+
+        for node_id in [0, 1, 3] {
+            obj.remove_node(node_id).unwrap();
+        }
+        assert_eq!(obj.iter_topological().collect::<Vec<Id>>(), vec![4, 2]);
+        obj.insert_edge_with_nodes(2, 5).unwrap();
+        obj.insert_edge(EdgeExample::bare(5, 4)).unwrap();
+        assert_eq!(obj.iter_topological().collect::<Vec<Id>>(), vec![2, 5, 4]);
+        assert_eq!([2, 5, 4].map(|id| obj.topo_index(id)), [Some(0), Some(1), Some(2)]);
+    }
+
+    #[test]
+    fn test_best_first() {
+        let obj: DiGraph<NodeExample, EdgeExample> =
+            DiGraph::from_terminal_pairs(vec![(0,1), (0,2), (1,3), (2,4), (4,5), (6,0)]);
+        let scores = std::collections::HashMap::from([(0, 0.0), (1, 1.0), (2, 5.0), (3, 9.0), (4, 0.5), (5, 2.0), (6, 8.0)]);
+        let score = |id: Id, _: &NodeExample| scores[&id];
+        assert_eq!(obj.best_first(0, score).collect::<Vec<Id>>(), vec![0, 2, 1, 3, 4, 5]);
+        assert_eq!(obj.best_first(0, |_, _| 0.0).collect::<Vec<Id>>(), vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(obj.best_first(2, score).collect::<Vec<Id>>(), vec![2, 4, 5]);
+        assert_eq!(obj.best_first(9, score).count(), 0);
+
+        let scored = std::cell::Cell::new(0);
+        let goal = obj.best_first(6, |id, _| { scored.set(scored.get() + 1); scores[&id] }).find(|&id| id == 2);
+        assert_eq!((goal, scored.get()), (Some(2), 4));
+    }
+
+    #[cfg(feature = "mcts")]
+    struct TreasureHunt {
+        seed: u32,
+    }
+    #[cfg(feature = "mcts")]
+    impl crate::mcts::SearchProblem<NodeExample, EdgeExample> for TreasureHunt {
+        // States form a binary tree: state s leads to 2s+1 and 2s+2, down to the leaves 7..=14
+        fn expand(&mut self, node: &NodeExample) -> Vec<(EdgeExample, NodeExample)> {
+            let state = node.other_node_stuff;
+            match state < 7 {
+                true => [2 * state + 1, 2 * state + 2].map(|next| (EdgeExample::default(), NodeExample { some_id: 0, other_node_stuff: next })).into(),
+                false => Vec::new(),
+            }
+        }
+        fn rollout(&mut self, node: &NodeExample) -> f64 {
+            let mut state = node.other_node_stuff;
+            while state < 7 {
+                self.seed = self.seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                state = 2 * state + 1 + ((self.seed >> 16) & 1) as u8;
+            }
+            (state == 12) as u8 as f64
+        }
+    }
+
+    #[cfg(feature = "mcts")]
+    #[test]
+    fn test_mcts() {
+        use crate::mcts::{MonteCarloTree, SearchProblem};
+
+        let mut problem = TreasureHunt { seed: 7 };
+        let mut search: MonteCarloTree<NodeExample, EdgeExample> = MonteCarloTree::new(NodeExample { some_id: 0, other_node_stuff: 0 });
+        assert_eq!(search.best_child(), None);
+        // The root is rolled out once before being expanded
+        assert_eq!(search.iterate(&mut problem), Ok(vec![0]));
+        assert_eq!(search.iterate(&mut problem), Ok(vec![0, 1]));
+        assert!(search.is_expanded(0) && search.graph().get_edge(0, 2).is_some());
+        assert_eq!(search.expand(0, &mut problem), Ok(Vec::new()));
+
+        search.run(&mut problem, 300).unwrap();
+        let state = |search: &MonteCarloTree<NodeExample, EdgeExample>, id| search.graph().get_node(id).unwrap().other_node_stuff;
+        let best = search.best_child().unwrap();
+        assert_eq!(state(&search, best), 2);
+        assert_eq!(search.stats(0).unwrap().visits, 302);
+        let best_stats = search.stats(best).unwrap();
+        assert!(best_stats.mean() > search.stats(1).unwrap().mean());
+
+        assert!(search.advance(search.root()).is_err());
+        search.advance(best).unwrap();
+        assert_eq!((search.root(), search.stats(best), search.stats(1)), (best, Some(best_stats), None));
+        assert!(search.graph().get_node(0).is_none());
+        assert_eq!(state(&search, search.best_child().unwrap()), 5);
+        assert_eq!(search.select(&problem)[0], best);
+        assert!(problem.maximizing(search.graph().get_node(best).unwrap()));
+    }
+
+    #[test]
+    fn test_entry_and_exit() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (3,2), (2,4)]);
+        assert!(obj.get_source().is_err());
+        let ids = |nodes: Vec<&NodeExample>| nodes.iter().map(|node| node.some_id).collect::<Vec<Id>>();
+        assert_eq!((ids(obj.get_sources()), ids(obj.get_sinks())), (vec![1, 3], vec![4]));
+        assert!(DiGraph::<NodeExample, EdgeExample>::from_terminal_pairs(vec![(1,2), (2,1)]).get_sources().is_empty());
+        assert_eq!((obj.entry(), obj.exit()), (None, None));
+        assert!(obj.set_entry(9).is_err());
+        obj.set_entry(3).unwrap();
+        obj.set_exit(4).unwrap();
+        assert_eq!(obj.get_source().unwrap().some_id, 3);
+
+        let mut loaded: DiGraph<NodeExample, EdgeExample> = DiGraph::from_ron_str(&obj.to_ron_string().unwrap()).unwrap();
+        assert_eq!((loaded.entry(), loaded.exit()), (Some(3), Some(4)));
+
+        loaded.replace_node(3, NodeExample::bare(7)).unwrap();
+        assert_eq!(loaded.entry(), Some(7));
+        loaded.remove_node(4).unwrap();
+        assert_eq!(loaded.exit(), None);
+        loaded.undo().unwrap();
+        assert_eq!(loaded.exit(), Some(4));
+        loaded.set_successor_order(2, vec![4]).unwrap();
+        loaded.tag_node(2, "hub").unwrap();
+        loaded.set_max_out_degree(2, 1).unwrap();
+        loaded.set_exit(2).unwrap();
+        loaded.remove_node(2).unwrap();
+        loaded.insert_node(NodeExample::bare(2)).unwrap();
+        assert_eq!((loaded.exit(), loaded.node_tags(2), loaded.max_out_degree(2)), (None, vec![], None));
+        loaded.undo().unwrap();
+        loaded.undo().unwrap();
+        assert_eq!((loaded.exit(), loaded.node_tags(2), loaded.max_out_degree(2)), (Some(2), vec!["hub"], Some(1)));
+        assert_eq!(loaded.ordered_successors(2), Some(vec![4]));
+        loaded.set_exit(4).unwrap();
+        let id_map = loaded.canonicalize().unwrap();
+        assert_eq!((loaded.entry(), loaded.exit()), (Some(id_map[&7]), Some(id_map[&4])));
+
+        assert_eq!(obj.clear_entry(), Some(3));
+        assert!(obj.get_source().is_err());
+        assert_eq!((obj.clear_exit(), obj.clear_exit()), (Some(4), None));
+        assert!(!obj.to_ron_string().unwrap().contains("entry"));
+    }
+
+    #[test]
+    fn test_distances() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(0,1), (1,2), (0,2), (2,3), (4,0)]);
+        let distances = obj.distances_from(0);
+        assert_eq!(distances, std::collections::HashMap::from([(0, 0), (1, 1), (2, 1), (3, 2)]));
+        assert!(obj.distances_from(9).is_empty());
+        assert_eq!((obj.eccentricity(0), obj.eccentricity(3), obj.eccentricity(9)), (Some(2), Some(0), None));
+        assert_eq!((obj.diameter(), obj.radius()), (3, Some(3)));
+
+        let weight = |edge: &EdgeExample| edge.other_edge_stuff.as_deref().map_or(1.0, |text| text.len() as f64);
+        obj.get_edge_mut(0, 2).unwrap().other_edge_stuff = Some("long".to_string());
+        let weighted = obj.weighted_distances_from(0, weight);
+        assert_eq!((weighted[&2], weighted[&3]), (2.0, 3.0));
+        obj.get_edge_mut(0, 2).unwrap().other_edge_stuff = Some(String::new());
+        assert_eq!(obj.weighted_distances_from(0, weight)[&3], 1.0);
+        assert_eq!(obj.weighted_distances_from(0, |_| -1.0).len(), 1);
+
+        obj.insert_edge_with_nodes(5, 3).unwrap();
+        assert_eq!((obj.diameter(), obj.radius()), (3, None));
+        assert_eq!(DiGraph::<NodeExample, EdgeExample>::new().diameter(), 0);
+    }
+
+    #[test]
+    fn test_path_tokens() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (1,3), (3,4), (3,5), (5,1)]);
+        let token = obj.encode_path(&[1, 3, 5, 1, 2]).unwrap();
+        assert_eq!(token, PathToken { root: 1, choices: vec![1, 1, 0, 0] });
+        assert_eq!(obj.decode_path(&token), Ok(vec![1, 3, 5, 1, 2]));
+        assert_eq!(obj.encode_path(&[4]).map(|token| token.choices.len()), Ok(0));
+        assert!(obj.encode_path(&[]).is_err() && obj.encode_path(&[1, 4]).is_err() && obj.encode_path(&[9]).is_err());
+
+        // Stable under payload edits and edits off the path; choices follow the successor order
+        obj.get_node_mut(3).unwrap().other_node_stuff = 7;
+        obj.insert_edge_with_nodes(4, 6).unwrap();
+        obj.set_successor_order(1, vec![3, 2]).unwrap();
+        assert_eq!(obj.decode_path(&token), Err(GraphError::Invalid("Successor index out of range.")));
+        obj.set_successor_order(1, vec![]).unwrap();
+        let text = ron::to_string(&token).unwrap();
+        assert_eq!(obj.decode_path(&ron::from_str(&text).unwrap()), Ok(vec![1, 3, 5, 1, 2]));
+        obj.remove_node(1).unwrap();
+        assert!(obj.decode_path(&token).is_err());
+    }
+
+    #[test]
+    fn test_named_graph() {
+        let mut obj: NamedGraph<NodeExample, EdgeExample> = NamedGraph::new();
+        obj.insert_edge_between("intro", "chapter1").unwrap();
+        obj.insert_edge_between("chapter1", "chapter1").unwrap();
+        assert_eq!((obj.id("intro"), obj.id("chapter1"), obj.id("chapter2")), (Some(0), Some(1), None));
+        assert!(obj.insert_edge_between("intro", "chapter1").is_err());
+        assert_eq!(obj.remaining_id_capacity(), usize::from(Id::MAX) - 1);
+        let node_id = obj.insert_named("ending", NodeExample { some_id: 40, other_node_stuff: 3 }).unwrap();
+        assert_eq!((node_id, obj.name(2), obj.get_named("ending").unwrap().some_id), (2, Some("ending"), 2));
+        assert!(obj.insert_named("intro", NodeExample::bare(0)).is_err());
+        obj.get_named_mut("ending").unwrap().other_node_stuff = 4;
+        assert_eq!(obj.names().collect::<Vec<&str>>(), vec!["chapter1", "ending", "intro"]);
+
+        obj.rename("chapter1", "chapter one").unwrap();
+        assert!(obj.rename("intro", "ending").is_err() && obj.rename("nobody", "x").is_err());
+        assert_eq!((obj.id("chapter1"), obj.name(1)), (None, Some("chapter one")));
+        assert_eq!(obj.remove_named("intro").unwrap().some_id, 0);
+        assert_eq!((obj.id("intro"), obj.all_edge_pairs()), (None, vec![(1, 1)]));
+        obj.undo().unwrap();
+        assert_eq!((obj.id("intro"), obj.all_edge_pairs()), (Some(0), vec![(0, 1), (1, 1)]));
+
+        let mut loaded: NamedGraph<NodeExample, EdgeExample> = NamedGraph::from_ron_str(&obj.to_ron_string().unwrap()).unwrap();
+        assert!(loaded.names().eq(obj.names()) && loaded.name(1) == Some("chapter one"));
+        loaded.set_name(0, "prologue").unwrap();
+        assert_eq!((loaded.name(0), loaded.id("intro")), (Some("prologue"), None));
+        assert!(loaded.set_name(0, "ending").is_err() && loaded.set_name(9, "x").is_err());
+        assert_eq!(loaded.into_graph().all_node_ids(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_clone() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (1,3), (3,4)]);
+        obj.name = Some("original".to_string());
+        obj.set_metadata("author", "me");
+        obj.pin_node(1).unwrap();
+        obj.tag_node(3, "hub").unwrap();
+        obj.set_successor_order(1, vec![3, 2]).unwrap();
+        obj.remove_node(4).unwrap();
+
+        let copy = obj.clone();
+        assert_eq!((copy.all_edge_pairs(), copy.metadata("author")), (obj.all_edge_pairs(), Some("me")));
+        assert!(copy.is_pinned(1) && copy.node_tags(3) == vec!["hub"] && copy.ordered_successors(1) == Some(vec![3, 2]));
+        assert!(copy.history_iter().next().is_none() && obj.history_iter().next().is_some());
+        assert!(copy == obj);
+
+        let mut data = obj.clone_data();
+        assert_eq!((data.name.as_deref(), data.all_node_ids(), data.all_edge_pairs()), (Some("original"), vec![1, 2, 3], obj.all_edge_pairs()));
+        assert!(data.metadata("author").is_none() && !data.is_pinned(1) && data.node_tags(3).is_empty());
+        assert_eq!(data.ordered_successors(1), Some(vec![2, 3]));
+        data.remove_node(1).unwrap();
+        assert_eq!(obj.all_node_ids(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_history_coalescing() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,3)]);
+        let drag = |obj: &mut DiGraph<NodeExample, EdgeExample>, values: std::ops::Range<u8>| {
+            for value in values {
+                obj.replace_node(2, NodeExample { some_id: 2, other_node_stuff: value }).unwrap();
+            }
+        };
+        drag(&mut obj, 0..10);
+        obj.insert_edge(EdgeExample::bare(1, 3)).unwrap();
+        drag(&mut obj, 10..15);
+        assert_eq!(obj.history_iter().count(), 16);
+
+        let window = CoalesceWindow { max_gap_ms: None, max_changes: 4 };
+        assert_eq!(obj.coalesce_history(window), 10);
+        assert_eq!(obj.history_iter().count(), 6);
+        let mut values = vec![];
+        while obj.undo().unwrap().is_some() {
+            values.push(obj.get_node(2).unwrap().other_node_stuff);
+        }
+        assert_eq!(values, vec![13, 9, 9, 7, 3, 255]);
+        assert_eq!(obj.all_edge_pairs(), vec![(1, 2), (2, 3)]);
+
+        obj.set_history_coalescing(Some(CoalesceWindow::default()));
+        drag(&mut obj, 0..20);
+        obj.set_successor_order(2, vec![3]).unwrap();
+        assert_eq!(obj.history_iter().count(), 2);
+        obj.undo().unwrap();
+        drag(&mut obj, 20..25);
+        assert_eq!(obj.history_iter().count(), 2);
+        obj.undo().unwrap();
+        assert_eq!(obj.get_node(2).unwrap().other_node_stuff, 19);
+        obj.undo().unwrap();
+        assert_eq!(obj.get_node(2).unwrap().other_node_stuff, 255);
+    }
+
+    #[test]
+    fn test_memory_footprint() {
+        let empty: DiGraph<NodeExample, EdgeExample> = DiGraph::new();
+        assert_eq!(empty.memory_footprint().history, 0);
+
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs((0..50).map(|id| (id, id + 1)).collect());
+        let before = obj.memory_footprint();
+        assert!(before.nodes >= 51 * std::mem::size_of::<(Id, NodeExample)>());
+        assert!(before.edges >= 50 * std::mem::size_of::<EdgeExample>() && before.adjacency > 0);
+        assert_eq!(before.history, 0);
+        assert_eq!(before.total(), before.nodes + before.edges + before.adjacency + before.history + before.other);
+        assert!(before.total() > empty.memory_footprint().total());
+
+        obj.remove_node(25).unwrap();
+        obj.tag_node(3, "checkpoint").unwrap();
+        let after = obj.memory_footprint();
+        assert!(after.history > 0 && after.other > before.other);
+    }
+
+    #[test]
+    fn test_as_of() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,3)]);
+        let timed = |start, end, range: &str| EdgeExample { vertices: (start, end), other_edge_stuff: Some(range.to_string()) };
+        obj.insert_edge(timed(1, 3, "10..20")).unwrap();
+        obj.insert_node(NodeExample::bare(4)).unwrap();
+        obj.insert_edge(timed(3, 4, "15..30")).unwrap();
+
+        let at = |t| {
+            let view = obj.as_of(t);
+            let successors: Vec<Vec<Id>> = (1..=4).map(|id| view.successors(id).into_owned()).collect();
+            (view.node_ids().len(), successors)
+        };
+        assert_eq!(at(5), (4, vec![vec![2], vec![3], vec![], vec![]]));
+        assert_eq!(at(10), (4, vec![vec![2, 3], vec![3], vec![], vec![]]));
+        assert_eq!(at(19), (4, vec![vec![2, 3], vec![3], vec![4], vec![]]));
+        assert_eq!(at(20), (4, vec![vec![2], vec![3], vec![4], vec![]]));
+        assert_eq!(at(30), at(5));
+        assert_eq!(graph_algo::bfs_order(&obj.as_of(25), 1, usize::MAX, usize::MAX), vec![1, 2, 3, 4]);
+        assert_eq!(graph_algo::bfs_order(&obj.as_of(12), 3, usize::MAX, usize::MAX), vec![3]);
+    }
+
+    #[test]
+    fn test_shortest_path_avoiding() {
+        let obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,5), (1,3), (3,4), (4,5), (1,6), (6,7), (7,8), (8,5)]);
+        let no_nodes = IdSet::new();
+        assert_eq!(obj.shortest_path_avoiding(1, 5, &no_nodes, &[]), Some(vec![1, 2, 5]));
+        assert_eq!(obj.shortest_path_avoiding(1, 5, &IdSet::from_iter([2]), &[]), Some(vec![1, 3, 4, 5]));
+        assert_eq!(obj.shortest_path_avoiding(1, 5, &IdSet::from_iter([2]), &[(4, 5)]), Some(vec![1, 6, 7, 8, 5]));
+        assert_eq!(obj.shortest_path_avoiding(1, 5, &no_nodes, &[(2, 5), (3, 4), (1, 6)]), None);
+        assert_eq!(obj.shortest_path_avoiding(1, 5, &IdSet::from_iter([5]), &[]), None);
+        assert_eq!(obj.shortest_path_avoiding(1, 1, &no_nodes, &[(1, 2)]), Some(vec![1]));
+
+        let banned = IdSet::from_iter([3]);
+        let view = Avoiding { graph: &obj, nodes: &banned, edges: &[(8, 5)] };
+        assert_eq!(view.predecessors(5).into_owned(), vec![2, 4]);
+        assert_eq!(view.node_count(), 7);
+        assert_eq!(graph_algo::bfs_order(&view, 1, usize::MAX, usize::MAX), vec![1, 2, 6, 5, 7, 8]);
+    }
+
+    #[test]
+    fn test_critical_edges_and_nodes() {
+        // 1 -> 2 -> {3, 4} -> 5 -> 6, and back from 6 to 2
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,3), (2,4), (3,5), (4,5), (5,6), (6,2)]);
+        assert_eq!(obj.critical_edges(1, 6), vec![(1, 2), (5, 6)]);
+        assert_eq!(obj.critical_nodes(1, 6), vec![2, 5]);
+        assert_eq!(obj.critical_edges(3, 4), vec![(3, 5), (5, 6), (6, 2), (2, 4)]);
+        assert_eq!(obj.critical_nodes(3, 4), vec![5, 6, 2]);
+        assert!(obj.critical_edges(6, 1).is_empty() && obj.critical_nodes(6, 1).is_empty());
+        assert!(obj.critical_edges(1, 1).is_empty() && obj.critical_nodes(1, 2).is_empty());
+        assert!(obj.critical_edges(1, 99).is_empty());
+
+        obj.insert_edge_with_nodes(1, 7).unwrap();
+        obj.insert_edge_with_nodes(7, 5).unwrap();
+        assert_eq!(obj.critical_edges(1, 6), vec![(5, 6)]);
+        assert_eq!(obj.critical_nodes(1, 6), vec![5]);
+    }
+
+    #[test]
+    fn test_extend_from_edges() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2)]);
+        obj.remove_edge(1, 2).unwrap();
+        assert_eq!(obj.extend_from_edges(vec![(1,2), (2,3), (3,3), (2,3), (4,1)], false), Err("Edge with these terminals already exists.".into()));
+        assert_eq!(obj.all_edge_pairs(), vec![]);
+        assert_eq!(obj.history_iter().count(), 1);
+
+        assert_eq!(obj.extend_from_edges(vec![(1,2), (2,3), (3,3), (2,3), (4,1)], true), Ok(4));
+        assert_eq!(obj.all_node_ids(), vec![1, 2, 3, 4]);
+        assert_eq!(obj.all_edge_pairs(), vec![(1, 2), (2, 3), (3, 3), (4, 1)]);
+        assert_eq!(obj.extend_from_edges((0..3).map(|id| (id, id + 1)), true), Ok(1));
+        assert_eq!(obj.successors_sorted(0), Some(vec![1]));
+        assert!(obj.history_iter().next().is_none());
+
+        let mut bulk: DiGraph<NodeExample, EdgeExample> = DiGraph::new();
+        bulk.extend_from_edges((0..1000).map(|id| (id, (id * 7 + 3) % 1000)), false).unwrap();
+        let mut single: DiGraph<NodeExample, EdgeExample> = DiGraph::new();
+        for id in 0..1000 {
+            single.insert_edge_with_nodes(id, (id * 7 + 3) % 1000).unwrap();
+        }
+        assert_eq!(bulk.all_edge_pairs(), single.all_edge_pairs());
+        assert_eq!(bulk.predecessors_sorted(3), single.predecessors_sorted(3));
+
+        let mut dag: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2)]);
+        dag.enforce_acyclic(true).unwrap();
+        assert!(dag.extend_from_edges(vec![(2,3), (3,1)], true).is_err());
+        assert_eq!((dag.all_node_ids(), dag.all_edge_pairs()), (vec![1, 2], vec![(1, 2)]));
+        assert_eq!(dag.extend_from_edges(vec![(2,3), (1,3)], true), Ok(2));
+    }
+
+    #[test]
+    fn test_parts() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from(vec![(3,1), (1,2), (3,1), (2,2)]);
+        assert_eq!(obj.all_edge_pairs(), vec![(1, 2), (2, 2), (3, 1)]);
+        obj.get_node_mut(2).unwrap().other_node_stuff = 7;
+
+        let (nodes, edges) = obj.clone_data().into_parts();
+        assert_eq!(nodes.iter().map(|node| node.some_id).collect::<Vec<Id>>(), vec![1, 2, 3]);
+        let rebuilt = DiGraph::from_parts(nodes.clone(), edges.clone()).unwrap();
+        assert_eq!((rebuilt.all_edge_pairs(), rebuilt.get_node(2), rebuilt.predecessors_sorted(1)), (obj.all_edge_pairs(), obj.get_node(2), Some(vec![3])));
+        assert!(rebuilt.history_iter().next().is_none());
+
+        let mut extra_edge = edges.clone();
+        extra_edge.push(EdgeExample::bare(1, 9));
+        assert!(DiGraph::from_parts(nodes.clone(), extra_edge).is_err());
+        extra_edge = edges.clone();
+        extra_edge.push(EdgeExample::bare(1, 2));
+        assert!(DiGraph::from_parts(nodes.clone(), extra_edge).is_err());
+        let mut extra_node = nodes.clone();
+        extra_node.push(NodeExample::bare(3));
+        assert!(DiGraph::from_parts(extra_node, edges.clone()).is_err());
+
+        obj.group_nodes(&[1, 2], 10).unwrap();
+        assert!(<(Vec<NodeExample>, Vec<EdgeExample>)>::try_from(obj.clone_data()).is_ok());
+        assert!(<(Vec<NodeExample>, Vec<EdgeExample>)>::try_from(obj).is_err());
+    }
+
+    #[test]
+    fn test_bare_node_policy() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2)]);
+        assert!(matches!(obj.bare_node_policy(), BareNodePolicy::Allow));
+        obj.set_bare_node_policy(BareNodePolicy::Deny);
+        assert!(obj.insert_edge_with_nodes(2, 3).is_err());
+        assert!(obj.insert_edge_with_nodes(3, 3).is_err());
+        assert!(obj.insert_node_along(5, 1, 2).is_err());
+        assert!(obj.insert_bare_node().is_err());
+        assert!(obj.extend_from_edges(vec![(2, 1), (1, 4)], false).is_err());
+        assert!(obj.group_nodes(&[1, 2], 10).is_err());
+        assert_eq!((obj.all_node_ids(), obj.all_edge_pairs()), (vec![1, 2], vec![(1, 2)]));
+        obj.insert_node(NodeExample::bare(3)).unwrap();
+        obj.insert_edge_with_nodes(2, 3).unwrap();
+        assert_eq!(obj.extend_from_edges(vec![(2, 1), (3, 1)], false), Ok(2));
+
+        obj.set_bare_node_policy(BareNodePolicy::Custom(|id| NodeExample { some_id: 0, other_node_stuff: id as u8 * 2 }));
+        obj.insert_edge_with_nodes(3, 4).unwrap();
+        obj.insert_node_along(5, 1, 2).unwrap();
+        let id = obj.insert_bare_node().unwrap();
+        for node_id in [4, 5, id] {
+            assert_eq!(obj.get_node(node_id), Some(&NodeExample { some_id: node_id, other_node_stuff: node_id as u8 * 2 }));
+        }
+        obj.group_nodes(&[4, 5], 20).unwrap();
+        assert_eq!(obj.get_node(20).unwrap().other_node_stuff, 40);
+        obj.undo().unwrap();
+        assert_eq!(obj.get_node(4).unwrap().other_node_stuff, 8);
+    }
+
+    #[test]
+    fn test_strict_mode() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2)]);
+        assert_eq!(obj.undo(), Ok(None));
+        obj.set_strict(true);
+        assert!(obj.is_strict());
+        assert_eq!(obj.undo(), Err("Nothing to undo.".into()));
+        obj.insert_edge_with_nodes(2, 3).unwrap();
+        assert!(obj.undo().unwrap().is_some());
+        assert!(obj.undo().is_err());
+        // A setting, not data
+        assert!(obj == obj.clone() && !obj.clone().is_strict());
+
+        // Edits that would push the oldest change out of a full history are refused
+        for id in 10..110 {
+            obj.insert_node(NodeExample::bare(id)).unwrap();
+        }
+        assert_eq!(obj.insert_node(NodeExample::bare(110)), Err("Undo history full; clear_undo_history() makes room.".into()));
+        assert!(obj.get_node(110).is_none() && obj.get_node(109).is_some());
+        obj.clear_undo_history();
+        obj.insert_node(NodeExample::bare(110)).unwrap();
+
+        let dir = std::env::temp_dir().join(format!("arboreal_strict_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (missing, corrupt, valid) = (dir.join("missing.ron"), dir.join("corrupt.ron"), dir.join("valid.ron"));
+        std::fs::write(&corrupt, "DiGraph(nodes: {").unwrap();
+        obj.save_to_file(&valid).unwrap();
+        type Graph = DiGraph<NodeExample, EdgeExample>;
+        assert!(Graph::load_or_default_strict(&missing).unwrap().all_node_ids().is_empty());
+        assert!(Graph::load_or_default_strict(&corrupt).is_err_and(|error| error.kind() == std::io::ErrorKind::InvalidData));
+        assert!(Graph::load_or_default(&corrupt).all_node_ids().is_empty());
+        assert_eq!(Graph::load_or_default_strict(&valid).unwrap().all_edge_pairs(), vec![(1, 2)]);
+        // Paths are read and written through a FileStorage at their directory
+        let storage = crate::digraph::FileStorage::new(&dir);
+        assert!(Graph::load_from_storage(&storage, "valid.ron").unwrap() == Graph::load_from_file(&valid).unwrap());
+        assert!(Graph::load_or_default_strict(dir.join("..")).is_err_and(|error| error.kind() == std::io::ErrorKind::InvalidInput));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_mutation_metrics() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,3)]);
+        assert_eq!((obj.mutation_count(), obj.mutation_tally(ChangeKind::AddEdge)), (2, 2));
+        obj.reset_metrics();
+        assert_eq!(obj.metrics(), &Default::default());
+
+        obj.insert_edge_with_nodes(3, 4).unwrap();
+        obj.remove_edge(1, 2).unwrap();
+        obj.insert_edge_with_nodes(1, 2).unwrap();
+        assert!(obj.insert_edge_with_nodes(1, 2).is_err());
+        obj.undo().unwrap();
+        obj.undo().unwrap();
+        obj.remove_edge(3, 4).unwrap();
+        assert_eq!(obj.prune_isolated_nodes().unwrap().len(), 1);
+        assert_eq!((obj.mutation_count(), obj.undo_count()), (5, 2));
+        assert_eq!(obj.mutation_tally(ChangeKind::AddEdge), 2);
+        assert_eq!(obj.mutation_tally(ChangeKind::RemoveEdge), 2);
+        assert_eq!(obj.mutation_tally(ChangeKind::Batch), 1);
+        assert_eq!(obj.mutation_tally(ChangeKind::ReverseEdge), 0);
+
+        let mut speculative = obj.speculate();
+        speculative.insert_edge_with_nodes(4, 5).unwrap();
+        speculative.undo().unwrap();
+        drop(speculative);
+        assert_eq!((obj.mutation_count(), obj.undo_count()), (5, 2));
+        let mut speculative = obj.speculate();
+        speculative.insert_edge_with_nodes(3, 5).unwrap();
+        speculative.reverse_edge(3, 5).unwrap();
+        speculative.commit().unwrap();
+        assert_eq!((obj.mutation_count(), obj.mutation_tally(ChangeKind::ReverseEdge)), (7, 1));
+        assert_eq!(obj.clone().mutation_count(), 0);
+    }
+
+    #[test]
+    fn test_edge_labels() {
+        let labeled = |start, end, label: &str| EdgeExample { vertices: (start, end), other_edge_stuff: Some(label.to_string()) };
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2)]);
+        for id in 3..=5 {
+            obj.insert_node(NodeExample::bare(id)).unwrap();
+        }
+        obj.insert_edge(labeled(1, 3, "a")).unwrap();
+        obj.insert_edge(labeled(1, 4, "b")).unwrap();
+        obj.insert_edge(labeled(4, 1, "a")).unwrap();
+        assert_eq!(obj.successor_by_label(1, "a"), Some(3));
+        assert_eq!(obj.successor_by_label(1, "b"), Some(4));
+        assert_eq!(obj.successor_by_label(1, ""), Some(2));
+        assert_eq!((obj.successor_by_label(1, "c"), obj.successor_by_label(3, "a"), obj.successor_by_label(9, "a")), (None, None, None));
+
+        obj.add_constraint(Box::new(UniqueEdgeLabels)).unwrap();
+        assert_eq!(obj.insert_edge(labeled(1, 5, "a")), Err(GraphError::ConstraintViolated("unique edge labels")));
+        assert!(obj.insert_edge_with_nodes(1, 5).is_err());
+        obj.insert_edge(labeled(1, 5, "c")).unwrap();
+        obj.insert_edge_with_nodes(3, 6).unwrap();
+        assert_eq!(obj.successor_by_label(1, "c"), Some(5));
+
+        obj.remove_constraint("unique edge labels");
+        obj.insert_edge(labeled(1, 1, "a")).unwrap();
+        assert_eq!(obj.successor_by_label(1, "a"), Some(1));
+        assert_eq!(obj.add_constraint(Box::new(UniqueEdgeLabels)), Err(GraphError::ConstraintViolated("unique edge labels")));
+    }
+
+    #[test]
+    fn test_label_paths() {
+        let labeled = |start, end, label: &str| EdgeExample { vertices: (start, end), other_edge_stuff: Some(label.to_string()) };
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::new();
+        for id in 1..=6 {
+            obj.insert_node(NodeExample::bare(id)).unwrap();
+        }
+        // 1 -open-> 2, 2 -read-> 3 -read-> 2, 2 -write-> 4, {2, 4} -close-> 5, 3 -"two words"-> 6
+        for (start, end, label) in [(1, 2, "open"), (2, 3, "read"), (3, 2, "read"), (2, 4, "write"), (2, 5, "close"), (4, 5, "close"), (3, 6, "two words")] {
+            obj.insert_edge(labeled(start, end, label)).unwrap();
+        }
+        let paths = |text: &str| obj.match_label_path(1, &LabelPattern::parse(text).unwrap());
+        assert_eq!(paths("open close"), vec![vec![1, 2, 5]]);
+        assert_eq!(paths("open (read | write)* close"), vec![vec![1, 2, 5]]);
+        assert_eq!(paths("open (read | write)+ close"), vec![vec![1, 2, 4, 5]]);
+        assert_eq!(paths("open read*"), vec![vec![1, 2], vec![1, 2, 3]]);
+        assert_eq!(paths("open write?"), vec![vec![1, 2], vec![1, 2, 4]]);
+        assert_eq!(paths("open read \"two words\""), vec![vec![1, 2, 3, 6]]);
+        assert_eq!(paths("open . ."), vec![vec![1, 2, 3, 2], vec![1, 2, 4, 5], vec![1, 2, 3, 6]]);
+        assert_eq!(paths("()"), vec![vec![1]]);
+        assert!(paths("read").is_empty());
+        assert!(obj.match_label_path(9, &LabelPattern::Concat(vec![])).is_empty());
+        let pattern = LabelPattern::parse("open (read | write)* close").unwrap();
+        let stop = |progress: graph_algo::Progress| match progress.done < 3 {
+            true => std::ops::ControlFlow::Continue(()),
+            false => std::ops::ControlFlow::Break(()),
+        };
+        assert_eq!(obj.match_label_path_with_progress(1, &pattern, &stop), Err(GraphError::Cancelled));
+        assert_eq!(obj.match_label_path_with_progress(1, &pattern, &|_| std::ops::ControlFlow::Continue(())), Ok(vec![vec![1, 2, 5]]));
+
+        let built = LabelPattern::label("open").then(LabelPattern::label("read").or(LabelPattern::label("write")).star()).then(LabelPattern::label("close"));
+        assert_eq!(LabelPattern::parse("open (read | write)* close"), Ok(built));
+        for bad in ["open (read", "open read)", "* open", "open \"read", "open & close"] {
+            assert!(LabelPattern::parse(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_persistent_digraph() {
+        let obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,3), (3,3)]);
+        let first = PersistentDiGraph::from_digraph(&obj);
+        assert_eq!((first.node_count(), first.edge_count(), first.edge_pairs()), (3, 3, obj.all_edge_pairs()));
+
+        let second = first.insert_node(NodeExample::bare(300)).unwrap().insert_edge(EdgeExample::bare(3, 300)).unwrap();
+        let third = second.remove_node(3).unwrap();
+        assert!(first.insert_node(NodeExample::bare(1)).is_err());
+        assert!(first.insert_edge(EdgeExample::bare(1, 2)).is_err());
+        assert!(first.insert_edge(EdgeExample::bare(1, 300)).is_err());
+        assert!(first.remove_edge(2, 1).is_err());
+
+        // Earlier versions are untouched by later edits
+        assert_eq!((first.node_ids(), first.edge_count()), (vec![1, 2, 3], 3));
+        assert_eq!((second.node_ids(), second.edge_pairs()), (vec![1, 2, 3, 300], vec![(1, 2), (2, 3), (3, 3), (3, 300)]));
+        assert_eq!((third.node_ids(), third.edge_pairs(), third.edge_count()), (vec![1, 2, 300], vec![(1, 2)], 1));
+        assert_eq!(third.predecessors(300).as_ref(), &[] as &[Id]);
+        assert_eq!(graph_algo::shortest_path(&second, 1, 300), Some(vec![1, 2, 3, 300]));
+        assert_eq!(graph_algo::shortest_path(&third, 1, 300), None);
+
+        let mut node = NodeExample::bare(2);
+        node.other_node_stuff = 7;
+        let fourth = third.replace_node(node).unwrap().remove_edge(1, 2).unwrap();
+        assert_eq!((first.get_node(2).unwrap().other_node_stuff, fourth.get_node(2).unwrap().other_node_stuff), (255, 7));
+        assert!(fourth.get_edge(1, 2).is_none() && third.get_edge(1, 2).is_some());
+        assert!(first.clone().ptr_eq(&first) && !first.ptr_eq(&second));
+
+        let back = second.to_digraph();
+        assert_eq!((back.all_node_ids(), back.all_edge_pairs()), (second.node_ids(), second.edge_pairs()));
+    }
+
+    #[test]
+    fn test_undo_tree() {
+        use crate::digraph::MaxNodes;
+
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2)]);
+        assert_eq!((obj.redo(), obj.last_change_id()), (Ok(None), None));
+        obj.insert_edge_with_nodes(2, 3).unwrap();
+        let first = obj.last_change_id().unwrap();
+        obj.insert_node_along(4, 2, 3).unwrap();
+        obj.reverse_edge(1, 2).unwrap();
+        let mut node = NodeExample::bare(3);
+        node.other_node_stuff = 7;
+        obj.replace_node(3, node).unwrap();
+        obj.remove_node(4).unwrap();
+        let state = (obj.all_node_ids(), obj.all_edge_pairs(), obj.get_node(3).cloned());
+
+        // Undoing everything and redoing it all comes back to the same state
+        for _ in 0..5 {
+            obj.undo().unwrap();
+        }
+        assert_eq!((obj.all_node_ids(), obj.all_edge_pairs()), (vec![1, 2], vec![(1, 2)]));
+        while obj.redo().unwrap().is_some() {}
+        assert_eq!((obj.all_node_ids(), obj.all_edge_pairs(), obj.get_node(3).cloned()), state);
+        let last = obj.last_change_id().unwrap();
+
+        // A new edit after undoing keeps the undone branch around
+        obj.undo().unwrap();
+        obj.undo().unwrap();
+        let abandoned = obj.redo_branches()[0].0;
+        assert_eq!(obj.redo_branches().len(), 1);
+        obj.insert_node(NodeExample::bare(9)).unwrap();
+        let replacement = obj.last_change_id().unwrap();
+        assert!(obj.redo_branches().is_empty());
+        obj.undo().unwrap();
+        assert_eq!(obj.redo_branches().iter().map(|&(id, _)| id).collect::<Vec<usize>>(), vec![abandoned, replacement]);
+
+        // Switching to the end of the old branch redoes both of its changes
+        obj.switch_branch(last).unwrap();
+        assert_eq!((obj.all_node_ids(), obj.all_edge_pairs(), obj.get_node(3).cloned()), state);
+        obj.switch_branch(replacement).unwrap();
+        assert!(obj.get_node(9).is_some() && obj.get_node(4).is_some());
+        assert_eq!(obj.history_iter().count(), 4);
+        obj.switch_branch(first).unwrap();
+        assert_eq!((obj.all_node_ids(), obj.all_edge_pairs()), (vec![1, 2, 3], vec![(1, 2), (2, 3)]));
+        assert!(obj.switch_branch(99).is_err());
+
+        // Redone changes are checked like new ones
+        obj.switch_branch(replacement).unwrap();
+        obj.undo().unwrap();
+        obj.add_constraint(Box::new(MaxNodes(4))).unwrap();
+        assert_eq!(obj.redo(), Err(GraphError::ConstraintViolated("max nodes")));
+        assert_eq!(obj.redo_branches().len(), 2);
+        obj.set_strict(true);
+        obj.remove_constraint("max nodes");
+        obj.redo().unwrap();
+        assert!(obj.get_node(9).is_some());
+        assert_eq!(obj.redo(), Err("Nothing to redo.".into()));
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_derive() {
+        #[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize, Nodal)]
+        struct Passage {
+            text: String,
+            #[arboreal(id)]
+            id: Id,
+        }
+        #[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize, DirEdge)]
+        struct Choice {
+            #[arboreal(start)]
+            from: Id,
+            #[arboreal(end)]
+            to: Id,
+            weight: u8,
+        }
+
+        let mut obj: DiGraph<Passage, Choice> = DiGraph::from_terminal_pairs(vec![(1,2), (2,3)]);
+        assert_eq!(obj.get_node(2), Some(&Passage { text: String::new(), id: 2 }));
+        assert_eq!(obj.get_edge(1, 2), Some(&Choice { from: 1, to: 2, weight: 0 }));
+        obj.insert_node(Passage { text: "end".to_string(), id: 4 }).unwrap();
+        obj.insert_edge(Choice { from: 3, to: 4, weight: 5 }).unwrap();
+        obj.reverse_edge(1, 2).unwrap();
+        assert_eq!(obj.get_edge(2, 1).map(DirEdge::terminal_ids), Some((2, 1)));
+        assert_eq!(obj.successors_sorted(3), Some(vec![4]));
+    }
+
+    #[test]
+    fn test_any_graph() {
+        let examples: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(2,1), (1,3)]);
+        let mut story = import::StoryGraph::new();
+        story.insert_edge_with_nodes(5, 6).unwrap();
+        let graphs: Vec<Box<dyn AnyGraph>> = vec![Box::new(examples), Box::new(story)];
+
+        let node_ids: Vec<Vec<Id>> = graphs.iter().map(|graph| graph.all_node_ids()).collect();
+        let edge_pairs: Vec<Vec<(Id, Id)>> = graphs.iter().map(|graph| graph.all_edge_pairs()).collect();
+        assert_eq!(node_ids, vec![vec![1, 2, 3], vec![5, 6]]);
+        assert_eq!(edge_pairs, vec![vec![(1, 3), (2, 1)], vec![(5, 6)]]);
+        assert_eq!((graphs[0].successors_sorted(1), graphs[0].successors_sorted(7)), (Some(vec![3]), None));
+        assert!(graphs[1].has_node(6) && !graphs[1].has_node(1));
+    }
+
+    #[test]
+    fn test_id_reuse() {
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(0,1), (1,2), (2,3), (3,4)]);
+        assert_eq!(obj.id_reuse(), IdReuse::Smallest);
+        obj.remove_node(3).unwrap();
+        obj.remove_node(1).unwrap();
+        assert_eq!((obj.freed_ids(), obj.next_free_id()), (&[3, 1][..], Ok(1)));
+
+        obj.set_id_reuse(IdReuse::Lifo);
+        assert_eq!(obj.next_free_id(), Ok(1));
+        assert_eq!(obj.reserve_ids(3), Ok(vec![1, 3, 5]));
+        obj.release_ids(&[1, 3, 5]);
+
+        obj.set_id_reuse(IdReuse::Never);
+        let capacity = obj.remaining_id_capacity();
+        assert_eq!((obj.next_free_id(), capacity), (Ok(5), ID_MAX as usize + 1 - 5));
+        assert_eq!(obj.insert_bare_node(), Ok(5));
+
+        // Undoing frees and takes back ids like any other removal or insertion
+        obj.undo().unwrap();
+        assert_eq!((obj.freed_ids(), obj.next_free_id()), (&[3, 1, 5][..], Ok(6)));
+        obj.undo().unwrap();
+        assert_eq!(obj.freed_ids(), &[3, 5]);
+        obj.set_id_reuse(IdReuse::Lifo);
+        assert_eq!(obj.next_free_id(), Ok(5));
+
+        // Both the setting and the freed ids are saved
+        let text = ron::to_string(&obj).unwrap();
+        let loaded: DiGraph<NodeExample, EdgeExample> = ron::from_str(&text).unwrap();
+        assert_eq!((loaded.id_reuse(), loaded.freed_ids()), (IdReuse::Lifo, &[3, 5][..]));
+    }
+
+    #[test]
+    fn test_source_to_sink_paths() {
+        // Two openings, a loop back from 4 to 2, and an isolated node 9
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,3), (2,4), (4,2), (4,5), (6,4), (3,5)]);
+        obj.insert_node(NodeExample::bare(9)).unwrap();
+        let paths = obj.source_to_sink_paths(usize::MAX);
+        assert_eq!(paths, vec![vec![1, 2, 3, 5], vec![1, 2, 4, 5], vec![6, 4, 2, 3, 5], vec![6, 4, 5], vec![9]]);
+        assert_eq!(obj.source_to_sink_paths(2), paths[..2].to_vec());
+        assert!(obj.source_to_sink_paths(0).is_empty());
+
+        let report = obj.path_coverage(&paths[..2]);
+        assert_eq!(report, CoverageReport {
+            unvisited_nodes: vec![6, 9],
+            unvisited_edges: vec![(4, 2), (6, 4)],
+            node_count: 7,
+            edge_count: 7,
+        });
+        assert_eq!((report.node_fraction(), report.edge_fraction()), (5.0 / 7.0, 5.0 / 7.0));
+        assert!(!report.is_complete() && obj.path_coverage(&paths).is_complete());
+        assert_eq!(obj.path_coverage(&[vec![1, 2, 4, 2, 3, 5], vec![9, 8]]).unvisited_edges, vec![(4, 5), (6, 4)]);
+    }
+
+    #[test]
+    fn test_enumerate_walks() {
+        use crate::graph_base::graph_algo::WalkControl;
+
+        let obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(1,2), (2,1), (2,3), (1,3)]);
+        let mut walks: Vec<Vec<Id>> = Vec::new();
+        let count = obj.enumerate_walks(1, 2, |walk| {
+            walks.push(walk.to_vec());
+            WalkControl::Continue
+        });
+        assert_eq!(count, 4);
+        assert_eq!(walks, vec![vec![1], vec![1, 2], vec![1, 2, 3], vec![1, 3]]);
+
+        walks.clear();
+        obj.enumerate_walks_with_revisits(1, 3, 1, |walk| {
+            walks.push(walk.to_vec());
+            WalkControl::Continue
+        });
+        assert!(walks.contains(&vec![1, 2, 1, 2]) && walks.contains(&vec![1, 2, 1, 3]));
+        assert!(walks.iter().all(|walk| walk.len() <= 4));
+
+        // Pruning skips a walk's extensions; stopping ends the search at once
+        walks.clear();
+        obj.enumerate_walks_with_revisits(1, 3, 1, |walk| {
+            walks.push(walk.to_vec());
+            match walk.last() {
+                Some(2) => WalkControl::Prune,
+                _ => WalkControl::Continue,
+            }
+        });
+        assert_eq!(walks, vec![vec![1], vec![1, 2], vec![1, 3]]);
+        assert_eq!(obj.enumerate_walks(1, 5, |walk| if walk.len() == 3 { WalkControl::Stop } else { WalkControl::Continue }), 3);
+        assert_eq!(obj.enumerate_walks(7, 5, |_| WalkControl::Continue), 0);
+    }
+
+    #[test]
+    fn test_keyed_edges() {
+        let obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(2,3), (1,2)]);
+        let text = obj.to_ron_string().unwrap();
+        assert!(text.contains("format_version: 2"));
+        assert!(text.contains("\"2->3\": ("));
+        assert!(text.find("\"2->3\"") < text.find("\"1->2\""));
+        let loaded: DiGraph<NodeExample, EdgeExample> = DiGraph::from_ron_str(&text).unwrap();
+        assert!(loaded == obj);
+        // Saved before format v2: no version, edges in a plain list
+        let legacy = r#"(
+            name: None,
+            nodes: {1: (some_id: 1, other_node_stuff: 0), 2: (some_id: 2, other_node_stuff: 0)},
+            edges: [(vertices: (1, 2), other_edge_stuff: Some("old"))],
+        )"#;
+        let loaded: DiGraph<NodeExample, EdgeExample> = DiGraph::from_ron_str(legacy).unwrap();
+        assert_eq!(loaded.get_edge(1, 2).unwrap().other_edge_stuff.as_deref(), Some("old"));
+        assert_eq!(loaded.in_degree(2), Some(1));
+        // Hand-edited: key and edge must agree
+        let keyed = |key: &str, version: u8| format!(r#"(
+            format_version: {version},
+            name: None,
+            nodes: {{1: (some_id: 1, other_node_stuff: 0), 2: (some_id: 2, other_node_stuff: 0)}},
+            edges: {{"{key}": (vertices: (1, 2), other_edge_stuff: None)}},
+        )"#);
+        assert!(DiGraph::<NodeExample, EdgeExample>::from_ron_str(&keyed("1->2", 2)).is_some());
+        assert!(DiGraph::<NodeExample, EdgeExample>::from_ron_str(&keyed(" 1 -> 2 ", 2)).is_some());
+        assert!(DiGraph::<NodeExample, EdgeExample>::from_ron_str(&keyed("2->1", 2)).is_none());
+        assert!(DiGraph::<NodeExample, EdgeExample>::from_ron_str(&keyed("1-2", 2)).is_none());
+        assert!(DiGraph::<NodeExample, EdgeExample>::from_ron_str(&keyed("1->2", 3)).is_none());
+        #[cfg(feature = "json")]
+        {
+            let json = obj.to_json_string().unwrap();
+            assert!(json.contains("\"2->3\": {"));
+            let from_json: DiGraph<NodeExample, EdgeExample> = DiGraph::from_json_str(&json).unwrap();
+            assert!(from_json == obj);
+        }
+    }
+
+    #[test]
+    fn test_story_dsl() {
+        let dsl = "# Opening\nintro -> choice_a : \"Ask about the key\"\nintro->\"Dark room\"\n\n\"Dark room\" -> intro : Go back\nepilogue\nchoice_a -> choice_a : \"Say \\\"again\\\"\"\n";
+        let story = import::StoryGraph::from_dsl(dsl).unwrap();
+        assert_eq!(story.all_node_ids(), vec![0, 1, 2, 3]);
+        let names: Vec<_> = story.all_node_ids().into_iter().map(|id| story.get_node(id).unwrap().name.clone()).collect();
+        assert_eq!(names, vec!["intro", "choice_a", "Dark room", "epilogue"]);
+        assert_eq!(story.all_edge_pairs(), vec![(0, 1), (0, 2), (1, 1), (2, 0)]);
+        assert_eq!(story.get_edge(0, 1).unwrap().label.as_deref(), Some("Ask about the key"));
+        assert_eq!(story.get_edge(0, 2).unwrap().label, None);
+        assert_eq!(story.get_edge(2, 0).unwrap().label.as_deref(), Some("Go back"));
+        assert_eq!(story.get_edge(1, 1).unwrap().label.as_deref(), Some("Say \"again\""));
+        let text = story.to_dsl();
+        assert_eq!(text, "intro -> choice_a : \"Ask about the key\"\nintro -> \"Dark room\"\nchoice_a -> choice_a : \"Say \\\"again\\\"\"\n\"Dark room\" -> intro : \"Go back\"\nepilogue\n");
+        assert_eq!(import::StoryGraph::from_dsl(&text).unwrap().to_dsl(), text);
+        assert!(import::StoryGraph::from_dsl("a -> ").is_err());
+        assert!(import::StoryGraph::from_dsl("a => b").is_err());
+        assert!(import::StoryGraph::from_dsl("a -> b : \"open").is_err());
+        assert!(import::StoryGraph::from_dsl("a -> b : \"x\" y").is_err());
+        assert!(import::StoryGraph::from_dsl("a -> b\nb -> c\na -> b : again").is_err());
+
+        // Line breaks in names and labels survive the round trip
+        let mut story = import::StoryGraph::from_dsl("a -> b").unwrap();
+        story.get_node_mut(0).unwrap().name = "Two\nlines".to_string();
+        story.get_edge_mut(0, 1).unwrap().label = Some("Carriage\r\nreturn".to_string());
+        let text = story.to_dsl();
+        assert_eq!(text, "\"Two\\nlines\" -> b : \"Carriage\\r\\nreturn\"\n");
+        let reread = import::StoryGraph::from_dsl(&text).unwrap();
+        assert_eq!(reread.get_node(0).unwrap().name, "Two\nlines");
+        assert_eq!(reread.get_edge(0, 1).unwrap().label.as_deref(), Some("Carriage\r\nreturn"));
+    }
+
+    #[test]
+    fn test_markdown_outline() {
+        type Outline = Tree<import::StoryNode, import::StoryEdge>;
+        let markdown = "Notes before the list\n# Launch plan\n\n- Design\n  * Sketches\n    with #tags\n  * Review\n- Build\n\t1. Code\n\t2) Test\n+ Ship\n";
+        let tree = Outline::from_markdown_outline(markdown).unwrap();
+        let titles: Vec<_> = tree.all_node_ids().into_iter().map(|id| tree.get_node(id).unwrap().name.clone()).collect();
+        assert_eq!(titles, vec!["Launch plan", "Design", "Sketches with #tags", "Review", "Build", "Code", "Test", "Ship"]);
+        assert_eq!((tree.root(), tree.children(0), tree.children(1)), (0, Some(vec![1, 4, 7]), Some(vec![2, 3])));
+        assert_eq!((tree.parent(5), tree.parent(6), tree.depth(2)), (Some(4), Some(4), Some(2)));
+        let text = tree.to_markdown_outline();
+        assert_eq!(text, "# Launch plan\n- Design\n  - Sketches with #tags\n  - Review\n- Build\n  - Code\n  - Test\n- Ship\n");
+        assert!(Outline::from_markdown_outline(&text).unwrap() == tree);
+
+        // Without a heading, the single top-level item is the root
+        let tree = Outline::from_markdown_outline("- Root\n  - A\n    - B\n  - C").unwrap();
+        assert_eq!((tree.children(0), tree.children(1)), (Some(vec![1, 3]), Some(vec![2])));
+        assert_eq!(tree.to_markdown_outline(), "# Root\n- A\n  - B\n- C\n");
+        assert!(Outline::from_markdown_outline("- A\n- B").is_err());
+        assert!(Outline::from_markdown_outline("# A\n- B\n# C").is_err());
+        assert!(Outline::from_markdown_outline("Just prose").is_err());
+        // Text between the heading and the first item continues neither
+        let tree = Outline::from_markdown_outline("# R\n\nintro\n- a\n  more").unwrap();
+        assert_eq!((tree.get_node(0).unwrap().name.as_str(), tree.get_node(1).unwrap().name.as_str()), ("R", "a more"));
+    }
+
+    #[test]
+    fn test_newick() {
+        #[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
+        struct Taxon {
+            id: Id,
+            name: String,
+        }
+        impl Nodal for Taxon {
+            fn bare(id: Id) -> Self {
+                Self { id, ..Default::default() }
+            }
+            fn node_id(&self) -> Id {
+                self.id
+            }
+            fn change_id(&mut self, new_id: Id) {
+                self.id = new_id;
+            }
+        }
+        impl Titled for Taxon {
+            fn title(&self) -> &str {
+                &self.name
+            }
+            fn set_title(&mut self, title: String) {
+                self.name = title;
+            }
+        }
+        #[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
+        struct Branch {
+            ends: (Id, Id),
+            length: Option<f64>,
+        }
+        impl DirEdge for Branch {
+            fn bare(start: Id, end: Id) -> Self {
+                Self { ends: (start, end), length: None }
+            }
+            fn terminal_ids(&self) -> (Id, Id) {
+                self.ends
+            }
+            fn change_start(&mut self, new_start: Id) {
+                self.ends.0 = new_start;
+            }
+            fn change_end(&mut self, new_end: Id) {
+                self.ends.1 = new_end;
+            }
+        }
+        impl Weighted for Branch {
+            fn weight(&self) -> Option<f64> {
+                self.length
+            }
+            fn set_weight(&mut self, weight: Option<f64>) {
+                self.length = weight;
+            }
+        }
+        type Phylogeny = Tree<Taxon, Branch>;
+
+        let newick = "((A:0.1, 'Homo sapiens':0.25)AB:0.05, [outgroup] C:1e-3, 'Bob''s')root:9;";
+        let tree = Phylogeny::from_newick(newick).unwrap();
+        let names: Vec<_> = tree.all_node_ids().into_iter().map(|id| tree.get_node(id).unwrap().name.clone()).collect();
+        assert_eq!(names, vec!["root", "AB", "A", "Homo sapiens", "C", "Bob's"]);
+        assert_eq!((tree.children(0), tree.children(1)), (Some(vec![1, 4, 5]), Some(vec![2, 3])));
+        assert_eq!(tree.get_edge(1, 3).unwrap().weight(), Some(0.25));
+        assert_eq!(tree.get_edge(0, 4).unwrap().weight(), Some(0.001));
+        assert_eq!(tree.get_edge(0, 5).unwrap().weight(), None);
+        let text = tree.to_newick();
+        assert_eq!(text, "((A:0.1,'Homo sapiens':0.25)AB:0.05,C:0.001,'Bob''s')root;");
+        assert!(Phylogeny::from_newick(&text).unwrap() == tree);
+
+        // Unlabeled nodes, and no closing ';'
+        let tree = Phylogeny::from_newick("(,(,))").unwrap();
+        assert_eq!((tree.node_count(), tree.children(2)), (5, Some(vec![3, 4])));
+        assert_eq!(tree.to_newick(), "(,(,));");
+        assert_eq!(Phylogeny::from_newick("A;").unwrap().to_newick(), "A;");
+        for malformed in ["", ";", "(A,B", "A,B;", "(A,B));", "(A:x,B);", "('A,B);", "(A,B); C", "(A[,B);"] {
+            assert!(Phylogeny::from_newick(malformed).is_err(), "{malformed}");
+        }
+    }
+
+    #[test]
+    fn test_refused_edit_leaves_graph_untouched() {
+        use crate::digraph::{MaxNodes, SingleSource};
+        let pairs = vec![(0,1), (0,2), (1,3), (2,3)];
+        let mut obj: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(pairs.clone());
+        let untouched: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(pairs);
+        let handle = obj.node_handle(0).unwrap();
+        obj.add_constraint(Box::new(SingleSource)).unwrap();
+        // Would leave 1 and 2 as sources
+        assert_eq!(obj.remove_node(0), Err(GraphError::ConstraintViolated("single source")));
+        assert!(obj == untouched);
+        assert!(obj.is_handle_live(handle));
+        assert!(obj.freed_ids().is_empty());
+
+        // A refused insertion keeps the reservation of the id it would have taken
+        obj.remove_constraint("single source");
+        obj.add_constraint(Box::new(MaxNodes(4))).unwrap();
+        let reserved = obj.reserve_ids(1).unwrap()[0];
+        assert!(obj.insert_edge_with_nodes(3, reserved).is_err());
+        assert_ne!(obj.next_free_id(), Ok(reserved));
+        assert!(obj.node_handle(reserved).is_none() && obj.freed_ids().is_empty());
+
+        // Refused in DAG mode
+        let mut dag: DiGraph<NodeExample, EdgeExample> = DiGraph::from_terminal_pairs(vec![(0,1), (0,2), (1,3), (2,3)]);
+        dag.enforce_acyclic(true).unwrap();
+        assert_eq!(dag.rewire(|edge| (edge.terminal_ids() == (0, 1)).then_some((3, 0))), Err(GraphError::ConstraintViolated("acyclic")));
+        assert!(dag == untouched);
+    }
+
     #[test]
     fn test_valid() {
         // let mut obj = StateGraph::default();
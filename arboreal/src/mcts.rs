@@ -0,0 +1,246 @@
+//! Monte Carlo tree search, with a `DiGraph` as the search tree (feature `mcts`).
+//!
+//! Nodes are game states and edges are the moves between them. The game itself lives in a
+//! `SearchProblem`, which lists the moves out of a state and plays out random games; the tree
+//! keeps visit counts and rewards per node, picks where to look next by UCT, and grows one
+//! expansion per iteration.
+
+use std::collections::HashMap;
+
+use crate::digraph::DiGraph;
+use crate::graph_base::{graph_algo, graph_components::{DirEdge, GraphError, Id, IdSet, Nodal}};
+
+/// Default weight of exploration against exploitation in UCT: the square root of 2
+pub const DEFAULT_EXPLORATION: f64 = std::f64::consts::SQRT_2;
+
+pub trait SearchProblem<N, E> {
+    /// The moves out of `node`'s state, each as the edge taking it and the state it leads to
+    /// (their ids are filled in by the tree). Empty for a state where the game is over.
+    fn expand(&mut self, node: &N) -> Vec<(E, N)>;
+
+    /// Plays out a game from `node`'s state (typically at random), returning a reward from
+    /// 0.0 (loss) to 1.0 (win)
+    fn rollout(&mut self, node: &N) -> f64;
+
+    /// Whether the player to move in `node`'s state wants high rewards. For two-player games,
+    /// return false on the opponent's turns, so that selection assumes they play their best.
+    fn maximizing(&self, _node: &N) -> bool {
+        true
+    }
+}
+
+/// Visits and total reward backpropagated through a node
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub struct Stats {
+    pub visits: u32,
+    pub total_reward: f64,
+}
+
+impl Stats {
+    /// Average reward (0.0 before the first visit)
+    pub fn mean(&self) -> f64 {
+        match self.visits {
+            0 => 0.0,
+            visits => self.total_reward / visits as f64,
+        }
+    }
+}
+
+pub struct MonteCarloTree<N, E> {
+    graph: DiGraph<N, E>,
+    root: Id,
+    stats: HashMap<Id, Stats>,
+    /// Nodes whose children have been listed (those without any are terminal)
+    expanded: IdSet,
+    exploration: f64,
+    /// Where to start looking for a free id, so that allocating doesn't rescan the used ones
+    next_id: Id,
+}
+
+impl<N: Nodal, E: DirEdge> MonteCarloTree<N, E> {
+
+    /// Starts a search from the state `root`, which keeps its id
+    pub fn new(root: N) -> Self {
+        let root_id = root.node_id();
+        let mut graph = DiGraph::new();
+        graph.insert_node(root).unwrap();
+        Self {
+            graph,
+            root: root_id,
+            stats: HashMap::from([(root_id, Stats::default())]),
+            expanded: IdSet::new(),
+            exploration: DEFAULT_EXPLORATION,
+            next_id: root_id.wrapping_add(1),
+        }
+    }
+
+    pub fn root(&self) -> Id {
+        self.root
+    }
+    pub fn graph(&self) -> &DiGraph<N, E> {
+        &self.graph
+    }
+    pub fn into_graph(self) -> DiGraph<N, E> {
+        self.graph
+    }
+
+    /// Sets the weight `c` of exploration in UCT (`DEFAULT_EXPLORATION` to begin with);
+    /// 0.0 always follows the best average reward
+    pub fn set_exploration(&mut self, c: f64) {
+        self.exploration = c;
+    }
+
+    /// Returns `None` if `node_id` is not found among the nodes
+    pub fn stats(&self, node_id: Id) -> Option<Stats> {
+        self.stats.get(&node_id).copied()
+    }
+
+    /// Whether the moves out of `node_id` have been listed
+    pub fn is_expanded(&self, node_id: Id) -> bool {
+        self.expanded.contains(node_id)
+    }
+
+    /// Ids from the root down to the node the next rollout should start from, each step
+    /// taking the child with the highest UCT value for the player to move (ties go to the
+    /// first in `ordered_successors()` order), until a node not yet expanded or with no moves.
+    /// Unvisited children come before all others.
+    pub fn select<P: SearchProblem<N, E>>(&self, problem: &P) -> Vec<Id> {
+        let mut path = vec![self.root];
+        let mut current = self.root;
+        while self.expanded.contains(current) {
+            let flip = !problem.maximizing(self.graph.get_node(current).unwrap());
+            let parent_visits = self.stats[&current].visits;
+            let best = self.children(current).into_iter()
+                .map(|child_id| (self.uct_value(parent_visits, child_id, flip), child_id))
+                .fold(None, |best: Option<(f64, Id)>, (value, child_id)| match best {
+                    Some((best_value, _)) if best_value >= value => best,
+                    _ => Some((value, child_id)),
+                });
+            let Some((_, child_id)) = best else {
+                break;
+            };
+            path.push(child_id);
+            current = child_id;
+        }
+        path
+    }
+
+    /// Average reward (flipped for a minimizing player) plus the exploration bonus
+    fn uct_value(&self, parent_visits: u32, child_id: Id, flip: bool) -> f64 {
+        let child = self.stats[&child_id];
+        if child.visits == 0 {
+            return f64::INFINITY;
+        }
+        let exploitation = match flip {
+            false => child.mean(),
+            true => 1.0 - child.mean(),
+        };
+        exploitation + self.exploration * ((parent_visits.max(1) as f64).ln() / child.visits as f64).sqrt()
+    }
+
+    fn children(&self, node_id: Id) -> Vec<Id> {
+        self.graph.ordered_successors(node_id).unwrap_or_default()
+    }
+
+    /// Only call with an id known to be left
+    fn allocate_id(&mut self) -> Id {
+        loop {
+            let candidate = self.next_id;
+            self.next_id = self.next_id.wrapping_add(1);
+            if self.graph.get_node(candidate).is_none() {
+                return candidate;
+            }
+        }
+    }
+
+    /// Adds the moves `problem` lists out of `node_id` as its children, returning their new
+    /// ids in order. Expanding a node twice adds nothing the second time.
+    ///
+    /// If `node_id` is not found among the nodes, there are not enough ids left for the moves,
+    /// or a move can't be inserted (e.g. a constraint refuses it), an error is returned.
+    pub fn expand<P: SearchProblem<N, E>>(&mut self, node_id: Id, problem: &mut P) -> Result<Vec<Id>, GraphError> {
+        let node = self.graph.get_node(node_id).ok_or(GraphError::Invalid("Node with this id not found."))?;
+        if self.expanded.contains(node_id) {
+            return Ok(Vec::new());
+        }
+        let moves = problem.expand(node);
+        if moves.len() > self.graph.remaining_id_capacity() {
+            return Err(GraphError::IdSpaceExhausted);
+        }
+        let mut child_ids = Vec::with_capacity(moves.len());
+        for (mut edge, mut child) in moves {
+            let child_id = self.allocate_id();
+            child.change_id(child_id);
+            edge.change_start(node_id);
+            edge.change_end(child_id);
+            self.graph.insert_node(child)?;
+            self.graph.insert_edge(edge)?;
+            self.stats.insert(child_id, Stats::default());
+            child_ids.push(child_id);
+        }
+        self.expanded.insert(node_id);
+        Ok(child_ids)
+    }
+
+    /// Adds one visit and `reward` to every node in `path` (ids not found are skipped)
+    pub fn backpropagate(&mut self, path: &[Id], reward: f64) {
+        for node_id in path {
+            if let Some(stats) = self.stats.get_mut(node_id) {
+                stats.visits += 1;
+                stats.total_reward += reward;
+            }
+        }
+    }
+
+    /// Runs one round of search: selects a node by UCT, expands it if it has been visited
+    /// before (moving on to its first child), rolls out from there, and backpropagates the
+    /// reward. Returns the path that was rewarded.
+    ///
+    /// If the ids run out, an error is returned and nothing is rewarded.
+    pub fn iterate<P: SearchProblem<N, E>>(&mut self, problem: &mut P) -> Result<Vec<Id>, GraphError> {
+        let mut path = self.select(problem);
+        let leaf_id = *path.last().unwrap();
+        if !self.expanded.contains(leaf_id) && self.stats[&leaf_id].visits > 0 {
+            let child_ids = self.expand(leaf_id, problem)?;
+            path.extend(child_ids.first());
+        }
+        let reward = problem.rollout(self.graph.get_node(*path.last().unwrap()).unwrap());
+        self.backpropagate(&path, reward);
+        Ok(path)
+    }
+
+    /// Runs `iterations` rounds of `iterate()`, stopping at the first error
+    pub fn run<P: SearchProblem<N, E>>(&mut self, problem: &mut P, iterations: usize) -> Result<(), GraphError> {
+        trace_span!("mcts_run", iterations);
+        for _ in 0..iterations {
+            self.iterate(problem)?;
+        }
+        Ok(())
+    }
+
+    /// The root's most visited child (the first in `ordered_successors()` order on a tie),
+    /// i.e. the move to play, or `None` if the root has no children yet
+    pub fn best_child(&self) -> Option<Id> {
+        self.children(self.root).into_iter()
+            .fold(None, |best: Option<Id>, child_id| match best {
+                Some(best_id) if self.stats[&best_id].visits >= self.stats[&child_id].visits => best,
+                _ => Some(child_id),
+            })
+    }
+
+    /// Makes the root's child `child_id` the new root (e.g. once its move has been played),
+    /// keeping its subtree and statistics and dropping everything else.
+    ///
+    /// If `child_id` is not a child of the root, an error is returned.
+    pub fn advance(&mut self, child_id: Id) -> Result<(), GraphError> {
+        if !self.children(self.root).contains(&child_id) {
+            return Err("Node is not a child of the root.".into());
+        }
+        let kept = graph_algo::bfs_order(&self.graph, child_id, usize::MAX, usize::MAX);
+        self.graph = self.graph.induced_subgraph(&kept);
+        self.stats.retain(|node_id, _| self.graph.get_node(*node_id).is_some());
+        self.expanded = self.expanded.iter().filter(|&node_id| self.graph.get_node(node_id).is_some()).collect();
+        self.root = child_id;
+        Ok(())
+    }
+}
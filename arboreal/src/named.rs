@@ -0,0 +1,177 @@
+//! Nodes looked up by name: a `DiGraph` facade for code that would rather say `"intro"` than
+//! keep track of ids.
+
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Deref;
+
+use serde::{Deserialize, Serialize};
+
+use crate::digraph::{DiGraph, FileIO};
+use crate::graph_base::graph_components::{DirEdge, GraphError, Id, Nodal, UndoneChange};
+
+/// `DiGraph` whose nodes may each have a unique name, mapped to its id internally.
+///
+/// Derefs to the inner `DiGraph` for all read-only methods. Edits go through `NamedGraph`
+/// methods so that names stay in step. A removed node keeps its name, so that `undo()` brings
+/// both back, until the name or the id is given to another node.
+#[derive(PartialEq, Serialize, Deserialize)]
+#[serde(bound(serialize = "DiGraph<N, E>: Serialize", deserialize = "DiGraph<N, E>: Deserialize<'de>"))]
+pub struct NamedGraph<N, E> {
+    graph: DiGraph<N, E>,
+    ids: BTreeMap<String, Id>,
+    /// Inverse of `ids`
+    #[serde(skip)]
+    names: HashMap<Id, String>,
+}
+
+impl<N: Nodal, E: DirEdge> NamedGraph<N, E> {
+    pub fn new() -> Self {
+        Self::from_graph(DiGraph::new())
+    }
+    /// Wraps `graph`, with no node named yet
+    pub fn from_graph(graph: DiGraph<N, E>) -> Self {
+        Self { graph, ids: BTreeMap::new(), names: HashMap::new() }
+    }
+    pub fn into_graph(self) -> DiGraph<N, E> {
+        self.graph
+    }
+
+    /// Id of the node named `name`, or `None` if there is none
+    pub fn id(&self, name: &str) -> Option<Id> {
+        self.ids.get(name).copied().filter(|&id| self.graph.get_node(id).is_some())
+    }
+
+    /// Name of the node with id `node_id`, or `None` if it has none or is not found
+    pub fn name(&self, node_id: Id) -> Option<&str> {
+        self.graph.get_node(node_id)?;
+        self.names.get(&node_id).map(String::as_str)
+    }
+
+    /// Names in use, in alphabetical order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.ids.iter()
+            .filter(|&(_, &id)| self.graph.get_node(id).is_some())
+            .map(|(name, _)| name.as_str())
+    }
+
+    pub fn get_named(&self, name: &str) -> Option<&N> {
+        self.graph.get_node(self.id(name)?)
+    }
+    pub fn get_named_mut(&mut self, name: &str) -> Option<&mut N> {
+        self.graph.get_node_mut(self.id(name)?)
+    }
+
+    /// Inserts `node` under the smallest free id, named `name`, and returns that id.
+    ///
+    /// If `name` is taken, no id is left, or the graph refuses the node (e.g. a constraint),
+    /// an error is returned.
+    pub fn insert_named<S: Into<String>>(&mut self, name: S, mut node: N) -> Result<Id, GraphError> {
+        let name = name.into();
+        if self.id(&name).is_some() {
+            return Err("Node with this name already exists.".into());
+        }
+        let node_id = self.graph.next_free_id()?;
+        node.change_id(node_id);
+        self.graph.insert_node(node)?;
+        self.bind(name, node_id);
+        Ok(node_id)
+    }
+
+    /// Inserts a bare edge from the node named `start` to the node named `end`, first inserting
+    /// a bare node for each name not in use, as a single undoable change.
+    ///
+    /// If not enough ids are left, or the graph refuses the edit (e.g. the edge exists),
+    /// an error is returned and nothing is inserted.
+    pub fn insert_edge_between(&mut self, start: &str, end: &str) -> Result<(), GraphError> {
+        let mut missing: Vec<&str> = [start, end].into_iter().filter(|name| self.id(name).is_none()).collect();
+        missing.dedup();
+        let new_ids = self.graph.reserve_ids(missing.len())?;
+        let id_of = |name: &str| match missing.iter().position(|&missing_name| missing_name == name) {
+            Some(index) => new_ids[index],
+            None => self.id(name).unwrap(),
+        };
+        let (start_id, end_id) = (id_of(start), id_of(end));
+        if let Err(error) = self.graph.insert_edge_with_nodes(start_id, end_id) {
+            self.graph.release_ids(&new_ids);
+            return Err(error);
+        }
+        for (name, node_id) in missing.into_iter().zip(new_ids) {
+            self.bind(name.to_string(), node_id);
+        }
+        Ok(())
+    }
+
+    /// Removes the node named `name`, with its edges, and returns it.
+    ///
+    /// If there is no such node, or the graph refuses the removal (e.g. the node is pinned),
+    /// an error is returned.
+    pub fn remove_named(&mut self, name: &str) -> Result<N, GraphError> {
+        let node_id = self.id(name).ok_or(GraphError::Invalid("Node with this name not found."))?;
+        self.graph.remove_node(node_id)
+    }
+
+    /// Gives the node named `old_name` the name `new_name` instead.
+    ///
+    /// If there is no node named `old_name`, or `new_name` is taken, an error is returned.
+    pub fn rename(&mut self, old_name: &str, new_name: &str) -> Result<(), GraphError> {
+        let node_id = self.id(old_name).ok_or(GraphError::Invalid("Node with this name not found."))?;
+        if self.id(new_name).is_some() {
+            return Err("Node with this name already exists.".into());
+        }
+        self.bind(new_name.to_string(), node_id);
+        Ok(())
+    }
+
+    /// Names the node with id `node_id` (e.g. one from `from_graph()`), replacing any name it had.
+    ///
+    /// If the node is not found, or `name` is taken by another node, an error is returned.
+    pub fn set_name<S: Into<String>>(&mut self, node_id: Id, name: S) -> Result<(), GraphError> {
+        let name = name.into();
+        if self.graph.get_node(node_id).is_none() {
+            return Err("Node with this id not found.".into());
+        }
+        if self.id(&name).is_some_and(|id| id != node_id) {
+            return Err("Node with this name already exists.".into());
+        }
+        self.bind(name, node_id);
+        Ok(())
+    }
+
+    /// Reverses the most recent edit, and returns it; see `DiGraph::undo()`
+    pub fn undo(&mut self) -> Result<Option<UndoneChange<N, E>>, GraphError> {
+        self.graph.undo()
+    }
+
+    /// Links `name` and `node_id`, unlinking whatever either was linked to before
+    fn bind(&mut self, name: String, node_id: Id) {
+        if let Some(old_id) = self.ids.remove(&name) {
+            self.names.remove(&old_id);
+        }
+        if let Some(old_name) = self.names.remove(&node_id) {
+            self.ids.remove(&old_name);
+        }
+        self.ids.insert(name.clone(), node_id);
+        self.names.insert(node_id, name);
+    }
+}
+
+impl<N: Nodal, E: DirEdge> Default for NamedGraph<N, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<N: Nodal, E: DirEdge> FileIO for NamedGraph<N, E> {
+    fn after_load(&mut self) {
+        self.graph.after_load();
+        self.names = self.ids.iter().map(|(name, &node_id)| (node_id, name.clone())).collect();
+    }
+}
+
+impl<N, E> Deref for NamedGraph<N, E> {
+    type Target = DiGraph<N, E>;
+
+    fn deref(&self) -> &DiGraph<N, E> {
+        &self.graph
+    }
+}
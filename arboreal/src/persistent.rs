@@ -0,0 +1,277 @@
+//! Persistent graph: every edit returns a new version and leaves the old one intact, with
+//! the two sharing everything the edit didn't touch, e.g. for branching undo or planners that
+//! keep many candidate states around.
+//!
+//! Nodes live in a two-level table indexed by id (256 chunks of 256 slots, each behind an
+//! `Arc`), so an edit copies one chunk's pointers and the entries it changes rather than the
+//! whole graph.
+
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use crate::digraph::DiGraph;
+use crate::graph_base::{graph_components::{DirEdge, GraphError, Id, Nodal}, graph_view::GraphView};
+
+const CHUNK_BITS: u32 = 8;
+const CHUNK_LEN: usize = 1 << CHUNK_BITS;
+const CHUNK_COUNT: usize = (Id::MAX as usize + 1) / CHUNK_LEN;
+
+/// A node with its edges out (ascending by end id) and the ids on either side
+struct Entry<N, E> {
+    node: Arc<N>,
+    out_edges: Vec<Arc<E>>,
+    successors: Vec<Id>,
+    predecessors: Vec<Id>,
+}
+
+impl<N, E> Clone for Entry<N, E> {
+    fn clone(&self) -> Self {
+        Self {
+            node: self.node.clone(),
+            out_edges: self.out_edges.clone(),
+            successors: self.successors.clone(),
+            predecessors: self.predecessors.clone(),
+        }
+    }
+}
+
+type Chunk<N, E> = Vec<Option<Arc<Entry<N, E>>>>;
+type Chunks<N, E> = Vec<Option<Arc<Chunk<N, E>>>>;
+
+/// Immutable `DiGraph` counterpart whose edits return new versions; cloning is O(1).
+///
+/// Holds nodes and edges only (no history, tags, constraints, ...), with at most one edge per
+/// (start, end) pair. Implements `GraphView`,
+/// so every algorithm in `graph_algo` runs on any version.
+pub struct PersistentDiGraph<N, E> {
+    chunks: Arc<Chunks<N, E>>,
+    node_count: usize,
+    edge_count: usize,
+}
+
+impl<N, E> Clone for PersistentDiGraph<N, E> {
+    fn clone(&self) -> Self {
+        Self { chunks: self.chunks.clone(), node_count: self.node_count, edge_count: self.edge_count }
+    }
+}
+
+impl<N: Nodal, E: DirEdge> Default for PersistentDiGraph<N, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn split(id: Id) -> (usize, usize) {
+    ((id as usize) >> CHUNK_BITS, (id as usize) & (CHUNK_LEN - 1))
+}
+
+fn insert_sorted(ids: &mut Vec<Id>, id: Id) {
+    let position = ids.partition_point(|&other| other < id);
+    ids.insert(position, id);
+}
+
+fn remove_sorted(ids: &mut Vec<Id>, id: Id) {
+    if let Ok(position) = ids.binary_search(&id) {
+        ids.remove(position);
+    }
+}
+
+impl<N: Nodal, E: DirEdge> PersistentDiGraph<N, E> {
+    pub fn new() -> Self {
+        Self { chunks: Arc::new(vec![None; CHUNK_COUNT]), node_count: 0, edge_count: 0 }
+    }
+
+    /// Copies the nodes and edges of `graph`; parallel edges are kept only once
+    pub fn from_digraph(graph: &DiGraph<N, E>) -> Self {
+        let mut persistent = Self::new();
+        for node_id in graph.all_node_ids() {
+            persistent.set_entry(node_id, Some(Entry {
+                node: Arc::new(graph.get_node(node_id).unwrap().clone()),
+                out_edges: Vec::new(),
+                successors: Vec::new(),
+                predecessors: Vec::new(),
+            }));
+        }
+        let mut edge_pairs = graph.all_edge_pairs();
+        edge_pairs.dedup();
+        for (start_id, end_id) in edge_pairs {
+            persistent.link(graph.get_edge(start_id, end_id).unwrap().clone());
+        }
+        persistent.node_count = graph.node_ids().len();
+        persistent
+    }
+
+    /// Copies this version's nodes and edges into a new `DiGraph`, with an empty history
+    pub fn to_digraph(&self) -> DiGraph<N, E> {
+        let mut nodes = Vec::with_capacity(self.node_count);
+        let mut edges = Vec::with_capacity(self.edge_count);
+        for entry in self.entries() {
+            nodes.push(entry.node.as_ref().clone());
+            edges.extend(entry.out_edges.iter().map(|edge| edge.as_ref().clone()));
+        }
+        DiGraph::from_parts(nodes, edges).unwrap()
+    }
+
+    pub fn get_node(&self, node_id: Id) -> Option<&N> {
+        self.entry(node_id).map(|entry| entry.node.as_ref())
+    }
+
+    pub fn get_edge(&self, start_id: Id, end_id: Id) -> Option<&E> {
+        let out_edges = &self.entry(start_id)?.out_edges;
+        let position = out_edges.binary_search_by_key(&end_id, |edge| edge.end_id()).ok()?;
+        Some(out_edges[position].as_ref())
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edge_count
+    }
+
+    /// Whether the two versions are the same one (e.g. clones of each other with no edit since)
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.chunks, &other.chunks)
+    }
+
+    /// New version with `node` added.
+    ///
+    /// If its id is already in use, an error is returned.
+    pub fn insert_node(&self, node: N) -> Result<Self, GraphError> {
+        let node_id = node.node_id();
+        if self.entry(node_id).is_some() {
+            return Err("Node with this id already exists.".into());
+        }
+        let mut next = self.clone();
+        next.set_entry(node_id, Some(Entry { node: Arc::new(node), out_edges: Vec::new(), successors: Vec::new(), predecessors: Vec::new() }));
+        next.node_count += 1;
+        Ok(next)
+    }
+
+    /// New version with the node of the same id as `node` swapped for it, edges kept.
+    ///
+    /// If no node has that id, an error is returned.
+    pub fn replace_node(&self, node: N) -> Result<Self, GraphError> {
+        let node_id = node.node_id();
+        if self.entry(node_id).is_none() {
+            return Err("Node with this id not found.".into());
+        }
+        let mut next = self.clone();
+        next.entry_mut(node_id).node = Arc::new(node);
+        Ok(next)
+    }
+
+    /// New version without the node with id `node_id` and its edges.
+    ///
+    /// If the node is not found, an error is returned.
+    pub fn remove_node(&self, node_id: Id) -> Result<Self, GraphError> {
+        let entry = self.entry(node_id).ok_or(GraphError::Invalid("Node with this id not found."))?.clone();
+        let mut next = self.clone();
+        for &end_id in entry.successors.iter().filter(|&&end_id| end_id != node_id) {
+            remove_sorted(&mut next.entry_mut(end_id).predecessors, node_id);
+        }
+        for &start_id in entry.predecessors.iter().filter(|&&start_id| start_id != node_id) {
+            let start = next.entry_mut(start_id);
+            start.out_edges.retain(|edge| edge.end_id() != node_id);
+            remove_sorted(&mut start.successors, node_id);
+        }
+        let self_loops = entry.successors.iter().filter(|&&end_id| end_id == node_id).count();
+        next.edge_count -= entry.successors.len() + entry.predecessors.len() - self_loops;
+        next.set_entry(node_id, None);
+        next.node_count -= 1;
+        Ok(next)
+    }
+
+    /// New version with `edge` added.
+    ///
+    /// If either terminal is not found among the nodes, or an edge with these terminals
+    /// already exists, an error is returned.
+    pub fn insert_edge(&self, edge: E) -> Result<Self, GraphError> {
+        let (start_id, end_id) = edge.terminal_ids();
+        if self.entry(start_id).is_none() || self.entry(end_id).is_none() {
+            return Err("Edge terminal not found in graph.".into());
+        }
+        if self.get_edge(start_id, end_id).is_some() {
+            return Err("Edge with these terminals already exists.".into());
+        }
+        let mut next = self.clone();
+        next.link(edge);
+        Ok(next)
+    }
+
+    /// New version without the edge from `start_id` to `end_id`.
+    ///
+    /// If there is no such edge, an error is returned.
+    pub fn remove_edge(&self, start_id: Id, end_id: Id) -> Result<Self, GraphError> {
+        if self.get_edge(start_id, end_id).is_none() {
+            return Err("Edge not found in graph.".into());
+        }
+        let mut next = self.clone();
+        let start = next.entry_mut(start_id);
+        start.out_edges.retain(|edge| edge.end_id() != end_id);
+        remove_sorted(&mut start.successors, end_id);
+        remove_sorted(&mut next.entry_mut(end_id).predecessors, start_id);
+        next.edge_count -= 1;
+        Ok(next)
+    }
+
+    fn entry(&self, node_id: Id) -> Option<&Entry<N, E>> {
+        let (chunk, slot) = split(node_id);
+        self.chunks[chunk].as_ref()?[slot].as_deref()
+    }
+
+    /// Present entries, ascending by id
+    fn entries(&self) -> impl Iterator<Item = &Entry<N, E>> {
+        self.chunks.iter().flatten().flat_map(|chunk| chunk.iter().flatten().map(Arc::as_ref))
+    }
+
+    /// Copies whatever this version still shares on the way to the entry; only call for ids in use
+    fn entry_mut(&mut self, node_id: Id) -> &mut Entry<N, E> {
+        let (chunk, slot) = split(node_id);
+        let chunk = Arc::make_mut(Arc::make_mut(&mut self.chunks)[chunk].as_mut().unwrap());
+        Arc::make_mut(chunk[slot].as_mut().unwrap())
+    }
+
+    fn set_entry(&mut self, node_id: Id, entry: Option<Entry<N, E>>) {
+        let (chunk, slot) = split(node_id);
+        let chunk = Arc::make_mut(&mut self.chunks)[chunk].get_or_insert_with(|| Arc::new(vec![None; CHUNK_LEN]));
+        Arc::make_mut(chunk)[slot] = entry.map(Arc::new);
+    }
+
+    /// Adds `edge`, whose terminals are known to exist and not to be linked yet
+    fn link(&mut self, edge: E) {
+        let (start_id, end_id) = edge.terminal_ids();
+        let start = self.entry_mut(start_id);
+        let position = start.out_edges.partition_point(|other| other.end_id() < end_id);
+        start.out_edges.insert(position, Arc::new(edge));
+        insert_sorted(&mut start.successors, end_id);
+        insert_sorted(&mut self.entry_mut(end_id).predecessors, start_id);
+        self.edge_count += 1;
+    }
+}
+
+impl<N: Nodal, E: DirEdge> GraphView for PersistentDiGraph<N, E> {
+    type Node = N;
+
+    fn node_ids(&self) -> Vec<Id> {
+        self.entries().map(|entry| entry.node.node_id()).collect()
+    }
+    fn contains_node(&self, id: Id) -> bool {
+        self.entry(id).is_some()
+    }
+    fn node(&self, id: Id) -> Option<&N> {
+        self.get_node(id)
+    }
+    fn successors(&self, id: Id) -> Cow<'_, [Id]> {
+        match self.entry(id) {
+            Some(entry) => Cow::Borrowed(&entry.successors),
+            None => Cow::Borrowed(&[]),
+        }
+    }
+    fn predecessors(&self, id: Id) -> Cow<'_, [Id]> {
+        match self.entry(id) {
+            Some(entry) => Cow::Borrowed(&entry.predecessors),
+            None => Cow::Borrowed(&[]),
+        }
+    }
+    fn node_count(&self) -> usize {
+        self.node_count
+    }
+}
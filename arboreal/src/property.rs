@@ -0,0 +1,239 @@
+//! Property graphs: every node and edge carries a label and a bag of typed properties.
+//!
+//! Labels default to `String`, but any serializable type works, e.g. a fieldless enum
+//! (`enum Kind { #[default] Npc, Item, Room }`).
+
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::ops::{Deref, DerefMut};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::digraph::{DiGraph, FileIO};
+use crate::graph_base::graph_components::{DirEdge, GraphError, Id, Nodal};
+
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub enum PropertyValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+impl PropertyValue {
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Self::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+    /// Ints are converted, so `Int(2)` gives `Some(2.0)`
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Self::Float(value) => Some(*value),
+            Self::Int(value) => Some(*value as f64),
+            _ => None,
+        }
+    }
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Self::Text(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl From<bool> for PropertyValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+impl From<i64> for PropertyValue {
+    fn from(value: i64) -> Self {
+        Self::Int(value)
+    }
+}
+impl From<i32> for PropertyValue {
+    fn from(value: i32) -> Self {
+        Self::Int(value.into())
+    }
+}
+impl From<f64> for PropertyValue {
+    fn from(value: f64) -> Self {
+        Self::Float(value)
+    }
+}
+impl From<&str> for PropertyValue {
+    fn from(value: &str) -> Self {
+        Self::Text(value.to_string())
+    }
+}
+impl From<String> for PropertyValue {
+    fn from(value: String) -> Self {
+        Self::Text(value)
+    }
+}
+
+pub type Properties = BTreeMap<String, PropertyValue>;
+
+/// Bounds needed of a label type; implemented for every type that meets them
+pub trait Label: Clone + PartialEq + Serialize + DeserializeOwned + Default {}
+impl<L: Clone + PartialEq + Serialize + DeserializeOwned + Default> Label for L {}
+
+#[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PropertyNode<L = String> {
+    pub id: Id,
+    pub label: L,
+    pub properties: Properties,
+}
+impl<L: Label> Nodal for PropertyNode<L> {
+    fn bare(id: Id) -> Self {
+        Self { id, label: L::default(), properties: Properties::new() }
+    }
+    fn node_id(&self) -> Id {
+        self.id
+    }
+    fn change_id(&mut self, new_id: Id) {
+        self.id = new_id;
+    }
+}
+
+#[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PropertyEdge<L = String> {
+    pub start: Id,
+    pub end: Id,
+    pub label: L,
+    pub properties: Properties,
+}
+impl<L: Label> DirEdge for PropertyEdge<L> {
+    fn bare(start: Id, end: Id) -> Self {
+        Self { start, end, label: L::default(), properties: Properties::new() }
+    }
+    fn terminal_ids(&self) -> (Id, Id) {
+        (self.start, self.end)
+    }
+    fn change_start(&mut self, new_start: Id) {
+        self.start = new_start;
+    }
+    fn change_end(&mut self, new_end: Id) {
+        self.end = new_end;
+    }
+}
+
+/// `DiGraph` of labeled nodes and edges with property bags, plus label and property queries.
+///
+/// Derefs (mutably too) to the inner `DiGraph`, so every other method, undo included, works as usual.
+#[derive(PartialEq, Serialize, Deserialize)]
+#[serde(bound = "L: Label")]
+pub struct PropertyGraph<L = String> {
+    graph: DiGraph<PropertyNode<L>, PropertyEdge<L>>,
+}
+
+impl<L: Label> PropertyGraph<L> {
+    pub fn new() -> Self {
+        Self { graph: DiGraph::new() }
+    }
+    pub fn from_graph(graph: DiGraph<PropertyNode<L>, PropertyEdge<L>>) -> Self {
+        Self { graph }
+    }
+    pub fn into_graph(self) -> DiGraph<PropertyNode<L>, PropertyEdge<L>> {
+        self.graph
+    }
+
+    /// Inserts a node with the lowest unused id, and returns that id
+    pub fn add_node(&mut self, label: L, properties: Properties) -> Result<Id, GraphError> {
+        let id = self.graph.next_free_id()?;
+        self.graph.insert_node(PropertyNode { id, label, properties })?;
+        Ok(id)
+    }
+
+    /// Same as `DiGraph::insert_edge()`, with the edge built from its parts
+    pub fn add_edge(&mut self, start: Id, end: Id, label: L, properties: Properties) -> Result<(), GraphError> {
+        self.graph.insert_edge(PropertyEdge { start, end, label, properties })
+    }
+
+    /// Returns ids (ascending) of nodes whose label equals `label`.
+    ///
+    /// For `String` labels, `label` may be a `&str`.
+    pub fn nodes_with_label<Q: PartialEq + ?Sized>(&self, label: &Q) -> Vec<Id>
+    where
+        L: Borrow<Q>,
+    {
+        self.nodes_where(|node| node.label.borrow() == label)
+    }
+
+    /// Returns (start, end) pairs (ascending) of edges whose label equals `label`
+    pub fn edges_with_label<Q: PartialEq + ?Sized>(&self, label: &Q) -> Vec<(Id, Id)>
+    where
+        L: Borrow<Q>,
+    {
+        self.edges_where(|edge| edge.label.borrow() == label)
+    }
+
+    /// Returns ids (ascending) of nodes whose property `key` equals `value`
+    pub fn nodes_with_property<V: Into<PropertyValue>>(&self, key: &str, value: V) -> Vec<Id> {
+        let value = value.into();
+        self.nodes_where(|node| node.properties.get(key) == Some(&value))
+    }
+
+    pub fn node_property(&self, node_id: Id, key: &str) -> Option<&PropertyValue> {
+        self.graph.get_node(node_id)?.properties.get(key)
+    }
+    /// Sets a node property, returning its previous value
+    pub fn set_node_property<V: Into<PropertyValue>>(&mut self, node_id: Id, key: &str, value: V) -> Option<PropertyValue> {
+        self.graph.get_node_mut(node_id)?.properties.insert(key.to_string(), value.into())
+    }
+
+    pub fn edge_property(&self, start_id: Id, end_id: Id, key: &str) -> Option<&PropertyValue> {
+        self.graph.get_edge(start_id, end_id)?.properties.get(key)
+    }
+    /// Sets an edge property, returning its previous value
+    pub fn set_edge_property<V: Into<PropertyValue>>(&mut self, start_id: Id, end_id: Id, key: &str, value: V) -> Option<PropertyValue> {
+        self.graph.get_edge_mut(start_id, end_id)?.properties.insert(key.to_string(), value.into())
+    }
+
+    fn nodes_where<F: Fn(&PropertyNode<L>) -> bool>(&self, keep: F) -> Vec<Id> {
+        self.graph.all_node_ids()
+            .into_iter()
+            .filter(|&id| self.graph.get_node(id).is_some_and(&keep))
+            .collect()
+    }
+
+    fn edges_where<F: Fn(&PropertyEdge<L>) -> bool>(&self, keep: F) -> Vec<(Id, Id)> {
+        self.graph.all_edge_pairs()
+            .into_iter()
+            .filter(|&(start_id, end_id)| self.graph.get_edge(start_id, end_id).is_some_and(&keep))
+            .collect()
+    }
+}
+
+impl<L: Label> Default for PropertyGraph<L> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<L: Label> FileIO for PropertyGraph<L> {
+    fn after_load(&mut self) {
+        self.graph.after_load();
+    }
+}
+
+impl<L> Deref for PropertyGraph<L> {
+    type Target = DiGraph<PropertyNode<L>, PropertyEdge<L>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.graph
+    }
+}
+impl<L> DerefMut for PropertyGraph<L> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.graph
+    }
+}
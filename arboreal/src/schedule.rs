@@ -0,0 +1,107 @@
+//! Ready-set scheduling of a dependency graph: an edge from `a` to `b` means task `b`
+//! waits for task `a`.
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::graph_base::{
+    graph_components::{GraphError, Id},
+    graph_view::GraphView,
+};
+
+/// Kahn's algorithm, run one step at a time as tasks finish, for driving a job scheduler.
+///
+/// The plan copies the graph's structure, so the graph can change (or go away) while tasks
+/// run without affecting it. Tasks on a cycle, or downstream of one, never become ready;
+/// `is_stuck()` tells when nothing else can run.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct ExecutionPlan {
+    /// Distinct successors of each task
+    dependents: HashMap<Id, Vec<Id>>,
+    /// Predecessors not yet completed, for tasks not yet ready
+    waiting_on: HashMap<Id, usize>,
+    ready: BTreeSet<Id>,
+    completed: BTreeSet<Id>,
+}
+
+impl<G: GraphView + ?Sized> From<&G> for ExecutionPlan {
+    fn from(graph: &G) -> Self {
+        trace_span!("execution_plan", nodes = graph.node_count());
+        let mut dependents = HashMap::with_capacity(graph.node_count());
+        let mut waiting_on = HashMap::new();
+        let mut ready = BTreeSet::new();
+        for node_id in graph.node_ids() {
+            let mut successors = graph.successors(node_id).into_owned();
+            successors.sort_unstable();
+            successors.dedup();
+            dependents.insert(node_id, successors);
+            let mut predecessors = graph.predecessors(node_id).into_owned();
+            predecessors.sort_unstable();
+            predecessors.dedup();
+            match predecessors.len() {
+                0 => {
+                    ready.insert(node_id);
+                },
+                count => {
+                    waiting_on.insert(node_id, count);
+                },
+            }
+        }
+        Self { dependents, waiting_on, ready, completed: BTreeSet::new() }
+    }
+}
+
+impl ExecutionPlan {
+
+    /// Tasks not yet completed whose predecessors have all completed, ascending.
+    /// A task stays ready until passed to `complete()`.
+    pub fn ready(&self) -> Vec<Id> {
+        self.ready.iter().copied().collect()
+    }
+
+    /// Marks the task `node_id` as finished, and returns the tasks that became ready
+    /// because of it, ascending.
+    ///
+    /// If `node_id` is not a ready task (unknown, still waiting, or already completed),
+    /// an error is returned.
+    pub fn complete(&mut self, node_id: Id) -> Result<Vec<Id>, GraphError> {
+        if !self.ready.remove(&node_id) {
+            return Err(match self.completed.contains(&node_id) {
+                true => "Task already completed.".into(),
+                false if self.waiting_on.contains_key(&node_id) => "Task is still waiting on a predecessor.".into(),
+                false => "Node with this id not found.".into(),
+            });
+        }
+        self.completed.insert(node_id);
+        let mut newly_ready = Vec::new();
+        for &dependent in self.dependents[&node_id].iter() {
+            if let Some(count) = self.waiting_on.get_mut(&dependent) {
+                *count -= 1;
+                if *count == 0 {
+                    self.waiting_on.remove(&dependent);
+                    self.ready.insert(dependent);
+                    newly_ready.push(dependent);
+                }
+            }
+        }
+        Ok(newly_ready)
+    }
+
+    pub fn is_completed(&self, node_id: Id) -> bool {
+        self.completed.contains(&node_id)
+    }
+
+    /// Whether every task has completed
+    pub fn is_done(&self) -> bool {
+        self.completed.len() == self.dependents.len()
+    }
+
+    /// Whether tasks remain but none can become ready (they wait on a cycle)
+    pub fn is_stuck(&self) -> bool {
+        self.ready.is_empty() && !self.is_done()
+    }
+
+    /// How many tasks have not completed
+    pub fn remaining(&self) -> usize {
+        self.dependents.len() - self.completed.len()
+    }
+}